@@ -6,7 +6,8 @@ use super::base::{decode_function_data, AbiError};
 use ethers_core::{
     abi::{Detokenize, Function, InvalidOutputType},
     types::{
-        transaction::eip2718::TypedTransaction, Address, BlockId, Bytes, NameOrAddress, TransactionRequest, U256,
+        spoof, transaction::eip2718::TypedTransaction, Address, BlockId, Bytes, NameOrAddress,
+        SimulatedCall, TransactionRequest, U256,
     },
 };
 use ethers_providers::{
@@ -171,6 +172,8 @@ pub struct FunctionCall<B, M, D> {
     pub function: Function,
     /// Optional block number to be used when calculating the transaction's gas and nonce
     pub block: Option<BlockId>,
+    /// Optional state and block overrides used by `call` and `estimate_gas`
+    pub overrides: spoof::CallOverrides,
     pub(crate) client: B,
     pub(crate) datatype: PhantomData<D>,
     pub(crate) _m: PhantomData<M>,
@@ -185,6 +188,7 @@ where
             tx: self.tx.clone(),
             function: self.function.clone(),
             block: self.block,
+            overrides: self.overrides.clone(),
             client: self.client.clone(),
             datatype: self.datatype,
             _m: self._m,
@@ -253,6 +257,22 @@ where
         self.tx.set_nonce(nonce);
         self
     }
+
+    /// Sets the [state override set](https://geth.ethereum.org/docs/rpc/ns-eth#3-object---state-override-set)
+    /// used by `call` and `estimate_gas`.
+    /// Note that not all client implementations will support this as a parameter.
+    pub fn state(mut self, state: spoof::State) -> Self {
+        self.overrides.state = Some(state);
+        self
+    }
+
+    /// Sets the block overrides, e.g. the block number, timestamp or base fee, used by `call` and
+    /// `estimate_gas`.
+    /// Note that not all client implementations will support this as a parameter.
+    pub fn block_overrides(mut self, overrides: spoof::BlockOverrides) -> Self {
+        self.overrides.block = Some(overrides);
+        self
+    }
 }
 
 impl<B, M, D> FunctionCall<B, M, D>
@@ -268,11 +288,13 @@ where
 
     /// Returns the estimated gas cost for the underlying transaction to be executed
     pub async fn estimate_gas(&self) -> Result<U256, ContractError<M>> {
-        self.client
-            .borrow()
-            .estimate_gas(&self.tx, self.block)
-            .await
-            .map_err(ContractError::from_middleware_error)
+        let client = self.client.borrow();
+        if self.overrides.is_empty() {
+            client.estimate_gas(&self.tx, self.block).await
+        } else {
+            client.estimate_gas_with_overrides(&self.tx, self.block, &self.overrides).await
+        }
+        .map_err(ContractError::from_middleware_error)
     }
 
     /// Queries the blockchain via an `eth_call` for the provided transaction.
@@ -285,12 +307,13 @@ where
     ///
     /// Note: this function _does not_ send a transaction from your account
     pub async fn call(&self) -> Result<D, ContractError<M>> {
-        let bytes = self
-            .client
-            .borrow()
-            .call(&self.tx, self.block)
-            .await
-            .map_err(ContractError::from_middleware_error)?;
+        let client = self.client.borrow();
+        let bytes = if self.overrides.is_empty() {
+            client.call(&self.tx, self.block).await
+        } else {
+            client.call_with_overrides(&self.tx, self.block, &self.overrides).await
+        }
+        .map_err(ContractError::from_middleware_error)?;
 
         // decode output
         let data = decode_function_data(&self.function, &bytes, false)?;
@@ -298,6 +321,22 @@ where
         Ok(data)
     }
 
+    /// Decodes the result of this call executed by [`Middleware::simulate`].
+    ///
+    /// Reverts are returned as [`ContractError::Revert`], which can be decoded with
+    /// [`ContractError::decode_revert`].
+    pub fn decode_simulated(&self, result: &SimulatedCall) -> Result<D, ContractError<M>> {
+        if let Some(data) = result.revert_data() {
+            return Err(ContractError::Revert(data.clone()))
+        }
+        if let Some(error) = &result.error {
+            return Err(ContractError::ProviderError {
+                e: ProviderError::CustomError(format!("{} (code {})", error.message, error.code)),
+            })
+        }
+        Ok(decode_function_data(&self.function, &result.return_data, false)?)
+    }
+
     /// Returns an implementer of [`RawCall`] which can be `.await`d to query the blockchain via
     /// `eth_call`, returning the deoded return data.
     ///
@@ -321,12 +360,17 @@ where
     ///
     /// Note: this function _does not_ send a transaction from your account
     pub fn call_raw_bytes(&self) -> CallBuilder<'_, M::Provider> {
-        let call = self.client.borrow().provider().call_raw(&self.tx);
+        let mut call = self.client.borrow().provider().call_raw(&self.tx);
         if let Some(block) = self.block {
-            call.block(block)
-        } else {
-            call
+            call = call.block(block);
+        }
+        if let Some(state) = &self.overrides.state {
+            call = call.state(state);
+        }
+        if let Some(overrides) = &self.overrides.block {
+            call = call.block_overrides(overrides);
         }
+        call
     }
 
     /// Signs and broadcasts the provided transaction
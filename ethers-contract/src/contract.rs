@@ -348,6 +348,7 @@ where
             tx,
             client: self.client.clone(),
             block: None,
+            overrides: Default::default(),
             function: function.to_owned(),
             datatype: PhantomData,
             _m: self._m,
@@ -14,7 +14,10 @@ use ethers_core::{
         NameOrAddress, U256,
     },
 };
-use ethers_providers::{spoof::State, Middleware, PendingTransaction, RawCall};
+use ethers_providers::{
+    spoof::{BlockOverrides, State},
+    Middleware, PendingTransaction, RawCall,
+};
 use std::{fmt, result::Result as StdResult, sync::Arc};
 
 pub use super::contract::Multicall3 as MulticallContract;
@@ -148,6 +151,9 @@ pub struct Multicall<M> {
     /// The state overrides of the Multicall aggregate
     pub state: Option<State>,
 
+    /// The block overrides of the Multicall aggregate
+    pub block_overrides: Option<BlockOverrides>,
+
     /// The internal call vector.
     calls: Vec<Call>,
 }
@@ -162,6 +168,7 @@ impl<M> Clone for Multicall<M> {
             block: self.block,
             calls: self.calls.clone(),
             state: self.state.clone(),
+            block_overrides: self.block_overrides.clone(),
         }
     }
 }
@@ -174,6 +181,7 @@ impl<M> fmt::Debug for Multicall<M> {
             .field("legacy", &self.legacy)
             .field("block", &self.block)
             .field("state", &self.state)
+            .field("block_overrides", &self.block_overrides)
             .field("calls", &self.calls)
             .finish()
     }
@@ -222,6 +230,7 @@ impl<M: Middleware> Multicall<M> {
             legacy: false,
             block: None,
             state: None,
+            block_overrides: None,
             calls: vec![],
             contract,
         })
@@ -272,6 +281,7 @@ impl<M: Middleware> Multicall<M> {
             legacy: false,
             block: None,
             state: None,
+            block_overrides: None,
             calls: vec![],
             contract,
         })
@@ -318,6 +328,13 @@ impl<M: Middleware> Multicall<M> {
         self
     }
 
+    /// Sets the block overrides, e.g. the block number, timestamp or base fee, of the Multicall
+    /// aggregate call.
+    pub fn block_overrides(mut self, overrides: BlockOverrides) -> Self {
+        self.block_overrides = Some(overrides);
+        self
+    }
+
     /// Appends a `call` to the list of calls of the Multicall instance.
     ///
     /// Version specific details:
@@ -634,12 +651,8 @@ impl<M: Middleware> Multicall<M> {
         match self.version {
             // Wrap the return data with `success: true` since version 1 reverts if any call failed
             MulticallVersion::Multicall => {
-                let call = self.as_aggregate();
-                let (_, bytes) = if let Some(state) = &self.state {
-                    ContractCall::call_raw(&call).state(state).await?
-                } else {
-                    ContractCall::call(&call).await?
-                };
+                let call = self.with_overrides(self.as_aggregate());
+                let (_, bytes) = ContractCall::call(&call).await?;
                 self.parse_call_result(
                     bytes
                         .into_iter()
@@ -653,16 +666,19 @@ impl<M: Middleware> Multicall<M> {
                 } else {
                     self.as_aggregate_3()
                 };
-                let results = if let Some(state) = &self.state {
-                    ContractCall::call_raw(&call).state(state).await?
-                } else {
-                    ContractCall::call(&call).await?
-                };
+                let results = ContractCall::call(&self.with_overrides(call)).await?;
                 self.parse_call_result(results.into_iter())
             }
         }
     }
 
+    /// Applies the state and block overrides of the Multicall to the aggregate call
+    fn with_overrides<D: Detokenize>(&self, mut call: ContractCall<M, D>) -> ContractCall<M, D> {
+        call.overrides.state = self.state.clone();
+        call.overrides.block = self.block_overrides.clone();
+        call
+    }
+
     /// For each call and its `return_data`: if `success` is true, parses `return_data` with the
     /// call's function outputs, otherwise returns the bytes in `Err`.
     fn parse_call_result(
@@ -848,4 +864,7 @@ impl<'a, M: Middleware> RawCall<'a> for Multicall<M> {
     fn state(self, state: &'a ethers_providers::spoof::State) -> Self {
         self.state(state.clone())
     }
+    fn block_overrides(self, overrides: &'a BlockOverrides) -> Self {
+        self.block_overrides(overrides.clone())
+    }
 }
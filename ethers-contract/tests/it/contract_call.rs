@@ -1,5 +1,8 @@
 use ethers_contract_derive::abigen;
-use ethers_core::abi::Address;
+use ethers_core::{
+    abi::{Address, Token},
+    types::{spoof, BlockNumber, Bytes, SimulatedCall, SimulatedCallError, SIMULATE_REVERT_CODE},
+};
 use ethers_providers::Provider;
 use std::{
     future::{Future, IntoFuture},
@@ -18,3 +21,51 @@ fn _contract_call_into_future_is_send() {
 
     is_send(contract.cache().into_future());
 }
+
+#[tokio::test]
+async fn contract_call_with_overrides() {
+    abigen!(DsProxyFactory, "./../ethers-middleware/contracts/DSProxyFactory.json");
+    let (provider, mock) = Provider::mocked();
+    let contract = DsProxyFactory::new(Address::zero(), Arc::new(provider));
+
+    let cache = Address::repeat_byte(1);
+    let mut block = spoof::BlockOverrides::default();
+    block.number(100.into());
+    let state = spoof::balance(Address::zero(), 1.into());
+
+    let call = contract.cache().state(state.clone()).block_overrides(block.clone());
+    mock.push::<Bytes, _>(Bytes::from(ethers_core::abi::encode(&[Token::Address(cache)]))).unwrap();
+    assert_eq!(call.call().await.unwrap(), cache);
+    mock.assert_request("eth_call", (&call.tx, BlockNumber::Latest, &state, &block)).unwrap();
+}
+
+#[tokio::test]
+async fn contract_call_decodes_simulated_results() {
+    abigen!(DsProxyFactory, "./../ethers-middleware/contracts/DSProxyFactory.json");
+    let (provider, _) = Provider::mocked();
+    let contract = DsProxyFactory::new(Address::zero(), Arc::new(provider));
+    let call = contract.cache();
+
+    let cache = Address::repeat_byte(1);
+    let result = SimulatedCall {
+        return_data: ethers_core::abi::encode(&[Token::Address(cache)]).into(),
+        status: 1.into(),
+        ..Default::default()
+    };
+    assert_eq!(call.decode_simulated(&result).unwrap(), cache);
+
+    let revert = SimulatedCall {
+        return_data: ethers_core::abi::encode(&[Token::String("nope".into())]).into(),
+        error: Some(SimulatedCallError {
+            code: SIMULATE_REVERT_CODE,
+            message: "execution reverted".into(),
+            data: None,
+        }),
+        ..Default::default()
+    };
+    let mut data = vec![0x08, 0xc3, 0x79, 0xa0];
+    data.extend_from_slice(&revert.return_data);
+    let revert = SimulatedCall { return_data: data.into(), ..revert };
+    let err = call.decode_simulated(&revert).unwrap_err();
+    assert_eq!(err.decode_revert::<String>().as_deref(), Some("nope"));
+}
@@ -309,6 +309,115 @@ impl Block<TxHash> {
     }
 }
 
+#[cfg(not(feature = "celo"))]
+impl<TX: Default> Block<TX> {
+    /// Decodes the header fields of a block from the RLP encoding of its header.
+    ///
+    /// Fields of later forks which are not part of [`Block`] (e.g. `blobGasUsed`) are stored in
+    /// [`Block::other`].
+    fn decode_header(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        let mut block = Self {
+            hash: Some(H256(crate::utils::keccak256(rlp.as_raw()))),
+            parent_hash: rlp.val_at(0)?,
+            uncles_hash: rlp.val_at(1)?,
+            author: Some(rlp.val_at(2)?),
+            state_root: rlp.val_at(3)?,
+            transactions_root: rlp.val_at(4)?,
+            receipts_root: rlp.val_at(5)?,
+            logs_bloom: Some(rlp.val_at(6)?),
+            difficulty: rlp.val_at(7)?,
+            number: Some(rlp.val_at(8)?),
+            gas_limit: rlp.val_at(9)?,
+            gas_used: rlp.val_at(10)?,
+            timestamp: rlp.val_at(11)?,
+            extra_data: rlp.val_at::<Vec<u8>>(12)?.into(),
+            mix_hash: Some(rlp.val_at(13)?),
+            nonce: Some(rlp.val_at(14)?),
+            ..Default::default()
+        };
+
+        let count = rlp.item_count()?;
+        if count > 15 {
+            block.base_fee_per_gas = Some(rlp.val_at(15)?);
+        }
+        if count > 16 {
+            block.withdrawals_root = Some(rlp.val_at(16)?);
+        }
+        if count > 17 {
+            let blob_gas_used: U64 = rlp.val_at(17)?;
+            block.other.insert("blobGasUsed".to_string(), format!("{blob_gas_used:#x}").into());
+        }
+        if count > 18 {
+            let excess_blob_gas: U64 = rlp.val_at(18)?;
+            block.other.insert("excessBlobGas".to_string(), format!("{excess_blob_gas:#x}").into());
+        }
+        if count > 19 {
+            let root: H256 = rlp.val_at(19)?;
+            block.other.insert("parentBeaconBlockRoot".to_string(), format!("{root:?}").into());
+        }
+        if count > 20 {
+            let requests_hash: H256 = rlp.val_at(20)?;
+            block.other.insert("requestsHash".to_string(), format!("{requests_hash:?}").into());
+        }
+        Ok(block)
+    }
+}
+
+#[cfg(not(feature = "celo"))]
+impl Block<TxHash> {
+    /// Decodes a block header from its RLP encoding, as returned by `debug_getRawHeader`.
+    ///
+    /// The block hash is the hash of the encoding; the transactions and uncles are left empty.
+    pub fn decode_raw_header(bytes: &[u8]) -> Result<Self, rlp::DecoderError> {
+        Self::decode_header(&rlp::Rlp::new(bytes))
+    }
+}
+
+#[cfg(not(feature = "celo"))]
+impl Block<Transaction> {
+    /// Decodes a block from its RLP encoding, as returned by `debug_getRawBlock`.
+    ///
+    /// The uncles are only recorded by their hashes. Note that the total difficulty is not part of
+    /// the encoding. Transactions of a type not supported by [`Transaction`], e.g. blob
+    /// transactions, are only recorded by their hash, type and position in the block.
+    pub fn decode_raw(bytes: &[u8]) -> Result<Self, rlp::DecoderError> {
+        let rlp = rlp::Rlp::new(bytes);
+        let mut block = Self::decode_header(&rlp.at(0)?)?;
+
+        let txs = rlp.at(1)?;
+        block.transactions = Vec::with_capacity(txs.item_count()?);
+        for (idx, item) in txs.iter().enumerate() {
+            // typed transactions are wrapped in a byte string
+            let mut tx: Transaction = if item.is_list() {
+                item.as_val()?
+            } else {
+                let data = item.data()?;
+                match data.first() {
+                    Some(0x01 | 0x02) => rlp::Rlp::new(data).as_val()?,
+                    Some(&tx_type) => Transaction {
+                        hash: H256(crate::utils::keccak256(data)),
+                        transaction_type: Some(tx_type.into()),
+                        ..Default::default()
+                    },
+                    None => return Err(rlp::DecoderError::Custom("empty transaction")),
+                }
+            };
+            tx.block_hash = block.hash;
+            tx.block_number = block.number;
+            tx.transaction_index = Some(idx.into());
+            block.transactions.push(tx);
+        }
+
+        block.uncles =
+            rlp.at(2)?.iter().map(|uncle| H256(crate::utils::keccak256(uncle.as_raw()))).collect();
+        if rlp.item_count()? > 3 {
+            block.withdrawals = Some(rlp.list_at(3)?);
+        }
+        block.size = Some(bytes.len().into());
+        Ok(block)
+    }
+}
+
 impl From<Block<Transaction>> for Block<TxHash> {
     fn from(full: Block<Transaction>) -> Self {
         #[cfg(not(feature = "celo"))]
@@ -928,6 +1037,97 @@ mod tests {
               );
         let _block: Block<TxHash> = serde_json::from_value(json).unwrap();
     }
+
+    fn raw_header(number: u64, base_fee: Option<u64>) -> Vec<u8> {
+        let mut header = rlp::RlpStream::new_list(15 + base_fee.is_some() as usize);
+        header
+            .append(&H256::repeat_byte(1))
+            .append(&H256::repeat_byte(2))
+            .append(&Address::repeat_byte(3))
+            .append(&H256::repeat_byte(4))
+            .append(&H256::repeat_byte(5))
+            .append(&H256::repeat_byte(6))
+            .append(&Bloom::zero())
+            .append(&U256::zero())
+            .append(&U64::from(number))
+            .append(&U256::from(30_000_000u64))
+            .append(&U256::from(21_000u64))
+            .append(&U256::from(1_700_000_000u64))
+            .append(&vec![0xffu8])
+            .append(&H256::repeat_byte(7))
+            .append(&crate::types::H64::zero());
+        if let Some(base_fee) = base_fee {
+            header.append(&U256::from(base_fee));
+        }
+        header.out().to_vec()
+    }
+
+    #[test]
+    fn decode_raw_header() {
+        let raw = raw_header(100, Some(7));
+        let block = Block::<TxHash>::decode_raw_header(&raw).unwrap();
+        assert_eq!(block.hash, Some(H256(crate::utils::keccak256(&raw))));
+        assert_eq!(block.parent_hash, H256::repeat_byte(1));
+        assert_eq!(block.author, Some(Address::repeat_byte(3)));
+        assert_eq!(block.number, Some(100u64.into()));
+        assert_eq!(block.gas_limit, 30_000_000u64.into());
+        assert_eq!(block.gas_used, 21_000u64.into());
+        assert_eq!(block.extra_data, Bytes::from(vec![0xff]));
+        assert_eq!(block.mix_hash, Some(H256::repeat_byte(7)));
+        assert_eq!(block.base_fee_per_gas, Some(7u64.into()));
+        assert_eq!(block.withdrawals_root, None);
+    }
+
+    #[test]
+    fn decode_raw_block() {
+        let legacy = hex::decode("f8aa808512ec276caf83010e2b94dac17f958d2ee523a2206206994597c13d831ec780b844a9059cbb000000000000000000000000fdae129ecc2c27d166a3131098bc05d143fa258e0000000000000000000000000000000000000000000000000000000002faf08025a0c81e70f9e49e0d3b854720143e86d172fecc9e76ef8a8666f2fdc017017c5141a01dd3410180f6a6ca3e25ad3058789cd0df3321ed76b5b4dbe0a2bb2dc28ae274").unwrap();
+        let eip1559 = hex::decode("02f86f05418459682f008459682f098301a0cf9411d7c2ab0d4aa26b7d8502f6a7ef6844908495c28084e5225381c001a01a8d7bef47f6155cbdf13d57107fc577fd52880fa2862b1a50d47641f8839419a03279bbf73fde76de83440d04b9d97f3809fec8617d3557ee40ac3e0edc391514").unwrap();
+        let header = raw_header(5, None);
+        let uncle = raw_header(4, None);
+
+        let mut stream = rlp::RlpStream::new_list(3);
+        stream.append_raw(&header, 1);
+        stream.begin_list(2).append_raw(&legacy, 1).append(&eip1559);
+        stream.begin_list(1).append_raw(&uncle, 1);
+        let raw = stream.out();
+
+        let block = Block::<Transaction>::decode_raw(&raw).unwrap();
+        let hash = H256(crate::utils::keccak256(&header));
+        assert_eq!(block.hash, Some(hash));
+        assert_eq!(block.size, Some(raw.len().into()));
+        assert_eq!(block.uncles, vec![H256(crate::utils::keccak256(&uncle))]);
+        assert_eq!(block.withdrawals, None);
+
+        let txs = &block.transactions;
+        assert_eq!(txs.len(), 2);
+        assert_eq!(txs[0].hash, H256(crate::utils::keccak256(&legacy)));
+        assert_eq!(txs[1].hash, H256(crate::utils::keccak256(&eip1559)));
+        assert_eq!(txs[1].transaction_type, Some(2u64.into()));
+        assert_eq!(txs[1].transaction_index, Some(1u64.into()));
+        assert!(txs
+            .iter()
+            .all(|tx| tx.block_hash == Some(hash) && tx.block_number == Some(5u64.into())));
+    }
+
+    #[test]
+    fn decode_raw_block_with_unsupported_tx_type() {
+        // the body of a blob transaction is not decoded
+        let blob = [&[0x03][..], &rlp::encode_list::<u64, u64>(&[1, 2, 3])].concat();
+        let header = raw_header(5, None);
+
+        let mut stream = rlp::RlpStream::new_list(3);
+        stream.append_raw(&header, 1);
+        stream.begin_list(1).append(&blob);
+        stream.begin_list(0);
+        let block = Block::<Transaction>::decode_raw(&stream.out()).unwrap();
+
+        assert_eq!(block.transactions.len(), 1);
+        let tx = &block.transactions[0];
+        assert_eq!(tx.hash, H256(crate::utils::keccak256(&blob)));
+        assert_eq!(tx.transaction_type, Some(3u64.into()));
+        assert_eq!(tx.transaction_index, Some(0u64.into()));
+        assert_eq!(tx.block_number, Some(5u64.into()));
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,163 @@
+//! Types for the `debug` namespace RPC calls
+
+use crate::types::{Block, Bytes, Transaction, H256};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The result of a `debug_storageRangeAt` call
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageRangeResult {
+    /// The storage slots, keyed by the hash of the slot
+    pub storage: BTreeMap<H256, StorageEntry>,
+    /// The hash of the first slot after the range, `None` if the range reached the end
+    pub next_key: Option<H256>,
+}
+
+/// A storage slot returned by `debug_storageRangeAt`
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StorageEntry {
+    /// The slot, `None` if the node does not know the preimage of its hash
+    pub key: Option<H256>,
+    /// The value of the slot
+    pub value: H256,
+}
+
+/// The result of a `debug_accountRange` call
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AccountRangeResult {
+    /// The state root of the block
+    pub root: H256,
+    /// The accounts, keyed by their address or, if the node does not know the preimage, by the
+    /// hash of their address
+    pub accounts: BTreeMap<String, DumpAccount>,
+    /// The key of the first account after the range, `None` if the range reached the end. This
+    /// can be passed as the start key of the next call.
+    ///
+    /// Nodes return the key base64 encoded, it is decoded when deserializing.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "base64_key")]
+    pub next: Option<Bytes>,
+}
+
+/// (De)serializes the `next` key of an [`AccountRangeResult`] as base64, like geth does
+mod base64_key {
+    use crate::types::Bytes;
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(key: &Option<Bytes>, s: S) -> Result<S::Ok, S::Error> {
+        match key {
+            Some(key) => s.serialize_some(&STANDARD.encode(key)),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Bytes>, D::Error> {
+        Option::<String>::deserialize(d)?
+            .map(|key| STANDARD.decode(key).map(Bytes::from).map_err(de::Error::custom))
+            .transpose()
+    }
+}
+
+/// An account returned by `debug_accountRange`
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DumpAccount {
+    /// The balance in wei, as a decimal string
+    pub balance: String,
+    /// The nonce
+    pub nonce: u64,
+    /// The storage root
+    pub root: H256,
+    /// The hash of the code
+    pub code_hash: H256,
+    /// The code, unless it was excluded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<Bytes>,
+    /// The storage, unless it was excluded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage: Option<BTreeMap<H256, String>>,
+    /// The address of the account, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<crate::types::Address>,
+    /// The hash of the address
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<Bytes>,
+}
+
+/// A block which failed validation, returned by `debug_getBadBlocks`
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BadBlock {
+    /// The hash of the block
+    pub hash: H256,
+    /// The block, if the node could decode it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block: Option<Block<Transaction>>,
+    /// The RLP encoding of the block
+    pub rlp: Bytes,
+}
+
+#[cfg(not(feature = "celo"))]
+impl BadBlock {
+    /// Decodes the block from its RLP encoding
+    pub fn decode_block(&self) -> Result<Block<Transaction>, rlp::DecoderError> {
+        Block::decode_raw(&self.rlp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize_storage_range() {
+        let s = r#"{
+            "storage": {
+                "0x290decd9548b62a8d60345a988386fc84ba6bc95484008f6362f93160ef3e563": {
+                    "key": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                    "value": "0x0000000000000000000000000000000000000000000000000000000000000001"
+                },
+                "0xb10e2d527612073b26eecdfd717e6a320cf44b4afac2b0732d9fcbe2b7fa0cf6": {
+                    "key": null,
+                    "value": "0x0000000000000000000000000000000000000000000000000000000000000002"
+                }
+            },
+            "nextKey": null
+        }"#;
+        let range: StorageRangeResult = serde_json::from_str(s).unwrap();
+        assert_eq!(range.storage.len(), 2);
+        assert_eq!(range.next_key, None);
+        assert!(range.storage.values().any(|entry| entry.key == Some(H256::zero())));
+        assert!(range.storage.values().any(|entry| entry.key.is_none()));
+    }
+
+    #[test]
+    fn deserialize_account_range() {
+        let s = r#"{
+            "root": "0x1e35d5f00e5a5d8a2f6c8f5b3d9c2e0c9ba3d0b5b8b4c52b3cd8d7ef0a1c0b4d",
+            "accounts": {
+                "0x0000000000000000000000000000000000000001": {
+                    "balance": "1000000000000000000",
+                    "nonce": 1,
+                    "root": "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421",
+                    "codeHash": "0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470",
+                    "address": "0x0000000000000000000000000000000000000001",
+                    "key": "0x1468288056310c82aa4c01a7e12a10f8111a0560e72b700555479031b86c357d"
+                }
+            },
+            "next": "FGgogFYxDIKqTAGn4SoQ+BEaBWDnK3AFVUeQMbhsNX0="
+        }"#;
+        let range: AccountRangeResult = serde_json::from_str(s).unwrap();
+        let account = &range.accounts["0x0000000000000000000000000000000000000001"];
+        assert_eq!(account.balance, "1000000000000000000");
+        assert_eq!(account.nonce, 1);
+        assert!(account.code.is_none());
+        let next: Bytes =
+            "0x1468288056310c82aa4c01a7e12a10f8111a0560e72b700555479031b86c357d".parse().unwrap();
+        assert_eq!(range.next, Some(next));
+
+        let json = serde_json::to_value(&range).unwrap();
+        assert_eq!(json["next"], "FGgogFYxDIKqTAGn4SoQ+BEaBWDnK3AFVUeQMbhsNX0=");
+        assert_eq!(serde_json::from_value::<AccountRangeResult>(json).unwrap(), range);
+    }
+}
@@ -69,6 +69,17 @@ impl rlp::Encodable for Log {
     }
 }
 
+impl rlp::Decodable for Log {
+    fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        Ok(Self {
+            address: rlp.val_at(0)?,
+            topics: rlp.list_at(1)?,
+            data: rlp.val_at::<Vec<u8>>(2)?.into(),
+            ..Default::default()
+        })
+    }
+}
+
 impl From<Log> for RawLog {
     fn from(val: Log) -> Self {
         (val.topics, val.data.to_vec()).into()
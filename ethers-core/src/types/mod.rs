@@ -71,6 +71,12 @@ pub use proof::*;
 mod fee;
 pub use fee::*;
 
+mod debug;
+pub use debug::*;
+
+mod simulate;
+pub use simulate::*;
+
 mod other;
 pub use other::OtherFields;
 
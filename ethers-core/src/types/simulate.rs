@@ -0,0 +1,229 @@
+//! Types for the `eth_simulateV1` RPC call
+
+use crate::types::{
+    spoof, transaction::eip2718::TypedTransaction, Address, Bytes, Log, H256, U256, U64,
+};
+use serde::{Deserialize, Serialize};
+
+/// The error code of calls which reverted
+pub const SIMULATE_REVERT_CODE: i64 = 3;
+
+/// The input of an `eth_simulateV1` call
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatePayload {
+    /// The blocks to simulate, executed one after another on top of the base block
+    pub block_state_calls: Vec<SimulateBlock>,
+    /// Adds ETH transfers as `Transfer` logs of the `0xeeee…eeee` address
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub trace_transfers: bool,
+    /// Enables the checks of a real block, e.g. nonces, balances and base fees
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub validation: bool,
+    /// Returns full transaction objects instead of hashes
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub return_full_transactions: bool,
+}
+
+impl SimulatePayload {
+    /// Creates a payload simulating the given blocks
+    pub fn new(block_state_calls: Vec<SimulateBlock>) -> Self {
+        Self { block_state_calls, ..Default::default() }
+    }
+
+    /// Appends a block to simulate
+    pub fn block(mut self, block: SimulateBlock) -> Self {
+        self.block_state_calls.push(block);
+        self
+    }
+
+    /// Adds ETH transfers as logs
+    pub fn trace_transfers(mut self, trace_transfers: bool) -> Self {
+        self.trace_transfers = trace_transfers;
+        self
+    }
+
+    /// Enables the checks of a real block
+    pub fn validation(mut self, validation: bool) -> Self {
+        self.validation = validation;
+        self
+    }
+}
+
+/// The calls of a simulated block along with its state and block overrides
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulateBlock {
+    /// Overrides of the block context
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_overrides: Option<spoof::BlockOverrides>,
+    /// Overrides of the state before the calls are executed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_overrides: Option<spoof::State>,
+    /// The calls to execute, in order
+    pub calls: Vec<TypedTransaction>,
+}
+
+impl SimulateBlock {
+    /// Creates a block executing the given calls
+    pub fn new(calls: Vec<TypedTransaction>) -> Self {
+        Self { calls, ..Default::default() }
+    }
+
+    /// Appends a call to the block
+    pub fn call<T: Into<TypedTransaction>>(mut self, tx: T) -> Self {
+        self.calls.push(tx.into());
+        self
+    }
+
+    /// Sets the block overrides
+    pub fn block_overrides(mut self, overrides: spoof::BlockOverrides) -> Self {
+        self.block_overrides = Some(overrides);
+        self
+    }
+
+    /// Sets the state overrides
+    pub fn state_overrides(mut self, state: spoof::State) -> Self {
+        self.state_overrides = Some(state);
+        self
+    }
+}
+
+/// A block returned by `eth_simulateV1`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedBlock {
+    /// Block number
+    pub number: U64,
+    /// Block hash
+    pub hash: H256,
+    /// Block timestamp
+    pub timestamp: U256,
+    /// Block gas limit
+    pub gas_limit: U256,
+    /// Gas used by all calls of the block
+    pub gas_used: U256,
+    /// Block fee recipient
+    #[serde(default)]
+    pub miner: Address,
+    /// Block base fee
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_fee_per_gas: Option<U256>,
+    /// The results of the calls, in the order of the request
+    pub calls: Vec<SimulatedCall>,
+}
+
+/// The result of a call executed by `eth_simulateV1`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedCall {
+    /// The data returned by the call, the revert data if it reverted
+    pub return_data: Bytes,
+    /// The logs emitted by the call
+    #[serde(default)]
+    pub logs: Vec<Log>,
+    /// Gas used by the call
+    pub gas_used: U256,
+    /// `1` if the call succeeded, `0` otherwise
+    pub status: U64,
+    /// The error of a failed call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<SimulatedCallError>,
+}
+
+impl SimulatedCall {
+    /// Returns `true` if the call succeeded
+    pub fn is_success(&self) -> bool {
+        self.status == U64::one()
+    }
+
+    /// Returns the revert data if the call reverted
+    pub fn revert_data(&self) -> Option<&Bytes> {
+        let error = self.error.as_ref()?;
+        if error.code != SIMULATE_REVERT_CODE {
+            return None
+        }
+        Some(error.data.as_ref().unwrap_or(&self.return_data))
+    }
+}
+
+/// The error of a failed call executed by `eth_simulateV1`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SimulatedCallError {
+    /// The error code, [`SIMULATE_REVERT_CODE`] for reverts
+    pub code: i64,
+    /// The error message
+    pub message: String,
+    /// The revert data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Bytes>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::TransactionRequest;
+
+    #[test]
+    fn serialize_payload() {
+        let mut block_overrides = spoof::BlockOverrides::default();
+        block_overrides.number(10.into());
+        let payload = SimulatePayload::default()
+            .block(
+                SimulateBlock::default()
+                    .call(TransactionRequest::new().to(Address::zero()))
+                    .block_overrides(block_overrides)
+                    .state_overrides(spoof::balance(Address::zero(), 1.into())),
+            )
+            .validation(true);
+
+        let value = serde_json::to_value(&payload).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "blockStateCalls": [{
+                    "blockOverrides": { "number": "0xa" },
+                    "stateOverrides": {
+                        "0x0000000000000000000000000000000000000000": { "balance": "0x1" }
+                    },
+                    "calls": [{ "to": "0x0000000000000000000000000000000000000000", "type": "0x00" }]
+                }],
+                "validation": true
+            })
+        );
+    }
+
+    #[test]
+    fn deserialize_simulated_blocks() {
+        let s = r#"[{
+            "number": "0x1",
+            "hash": "0x0000000000000000000000000000000000000000000000000000000000000001",
+            "timestamp": "0x64",
+            "gasLimit": "0x1c9c380",
+            "gasUsed": "0xa410",
+            "miner": "0x0000000000000000000000000000000000000000",
+            "baseFeePerGas": "0x0",
+            "calls": [
+                {
+                    "returnData": "0x0000000000000000000000000000000000000000000000000000000000000001",
+                    "logs": [],
+                    "gasUsed": "0x5208",
+                    "status": "0x1"
+                },
+                {
+                    "returnData": "0x08c379a0000000000000000000000000000000000000000000000000000000000000002000000000000000000000000000000000000000000000000000000000000000046e6f706500000000000000000000000000000000000000000000000000000000",
+                    "logs": [],
+                    "gasUsed": "0x5208",
+                    "status": "0x0",
+                    "error": { "code": 3, "message": "execution reverted: nope" }
+                }
+            ]
+        }]"#;
+        let blocks: Vec<SimulatedBlock> = serde_json::from_str(s).unwrap();
+        let calls = &blocks[0].calls;
+        assert!(calls[0].is_success());
+        assert_eq!(calls[0].revert_data(), None);
+        assert!(!calls[1].is_success());
+        assert_eq!(calls[1].revert_data(), Some(&calls[1].return_data));
+    }
+}
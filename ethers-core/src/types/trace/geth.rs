@@ -209,7 +209,8 @@ pub struct GethDebugTracingCallOptions {
     pub tracing_options: GethDebugTracingOptions,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub state_overrides: Option<spoof::State>,
-    // TODO: Add blockoverrides options
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_overrides: Option<spoof::BlockOverrides>,
 }
 
 /// Provides types and methods for constructing an `eth_call`
@@ -308,6 +309,101 @@ pub mod spoof {
         }
     }
 
+    /// Overrides for the block context of an `eth_call` or `eth_estimateGas`.
+    ///
+    /// Note that not all client implementations support block overrides.
+    #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct BlockOverrides {
+        /// Block number
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub number: Option<U64>,
+        /// Block difficulty
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub difficulty: Option<U256>,
+        /// Block timestamp
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub time: Option<U64>,
+        /// Block gas limit
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub gas_limit: Option<U64>,
+        /// Block fee recipient
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub coinbase: Option<Address>,
+        /// Block `prevrandao`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub random: Option<H256>,
+        /// Block base fee
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub base_fee: Option<U256>,
+    }
+
+    impl BlockOverrides {
+        /// Override the block number
+        pub fn number(&mut self, number: U64) -> &mut Self {
+            self.number = Some(number);
+            self
+        }
+        /// Override the block difficulty
+        pub fn difficulty(&mut self, difficulty: U256) -> &mut Self {
+            self.difficulty = Some(difficulty);
+            self
+        }
+        /// Override the block timestamp
+        pub fn time(&mut self, time: U64) -> &mut Self {
+            self.time = Some(time);
+            self
+        }
+        /// Override the block gas limit
+        pub fn gas_limit(&mut self, gas_limit: U64) -> &mut Self {
+            self.gas_limit = Some(gas_limit);
+            self
+        }
+        /// Override the block fee recipient
+        pub fn coinbase(&mut self, coinbase: Address) -> &mut Self {
+            self.coinbase = Some(coinbase);
+            self
+        }
+        /// Override the block `prevrandao`
+        pub fn random(&mut self, random: H256) -> &mut Self {
+            self.random = Some(random);
+            self
+        }
+        /// Override the block base fee
+        pub fn base_fee(&mut self, base_fee: U256) -> &mut Self {
+            self.base_fee = Some(base_fee);
+            self
+        }
+    }
+
+    /// The state and block overrides of an `eth_call` or `eth_estimateGas`
+    #[derive(Clone, Debug, Default, PartialEq, Eq)]
+    pub struct CallOverrides {
+        /// The state override set
+        pub state: Option<State>,
+        /// The block overrides
+        pub block: Option<BlockOverrides>,
+    }
+
+    impl CallOverrides {
+        /// Returns `true` if neither state nor block overrides are set
+        pub fn is_empty(&self) -> bool {
+            self.state.is_none() && self.block.is_none()
+        }
+
+        /// Sets the state override set
+        pub fn state(mut self, state: State) -> Self {
+            self.state = Some(state);
+            self
+        }
+
+        /// Sets the block overrides
+        pub fn block(mut self, block: BlockOverrides) -> Self {
+            self.block = Some(block);
+            self
+        }
+    }
+
     /// Returns an empty state override set.
     ///
     /// # Examples
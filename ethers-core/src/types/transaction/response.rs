@@ -486,6 +486,38 @@ impl rlp::Encodable for TransactionReceipt {
     }
 }
 
+impl TransactionReceipt {
+    /// Decodes a receipt from its consensus encoding, as returned by `debug_getRawReceipts`.
+    ///
+    /// Only the fields which are part of the encoding are set: the transaction type, the status or
+    /// state root, the cumulative gas used, the bloom and the logs.
+    pub fn decode_raw(bytes: &[u8]) -> Result<Self, DecoderError> {
+        let (transaction_type, body) = match bytes.first() {
+            None => return Err(DecoderError::RlpIsTooShort),
+            // typed receipts are prefixed with the transaction type
+            Some(&ty) if ty <= 0x7f => (ty, &bytes[1..]),
+            Some(_) => (0, bytes),
+        };
+        let rlp = rlp::Rlp::new(body);
+        let first = rlp.at(0)?;
+        // pre-byzantium receipts contain the state root instead of the status
+        let (status, root) = if first.size() == 32 {
+            (None, Some(first.as_val()?))
+        } else {
+            (Some(first.as_val()?), None)
+        };
+        Ok(Self {
+            status,
+            root,
+            cumulative_gas_used: rlp.val_at(1)?,
+            logs_bloom: rlp.val_at(2)?,
+            logs: rlp.list_at(3)?,
+            transaction_type: Some(transaction_type.into()),
+            ..Default::default()
+        })
+    }
+}
+
 // Compares the transaction receipt against another receipt by checking the blocks first and then
 // the transaction index in the block
 impl Ord for TransactionReceipt {
@@ -1165,6 +1197,35 @@ mod tests {
         Transaction::decode(&Rlp::new(&tx.rlp())).unwrap();
     }
 
+    #[test]
+    fn decode_raw_receipts() {
+        let log = Log {
+            address: Address::repeat_byte(1),
+            topics: vec![H256::repeat_byte(2)],
+            data: Bytes::from(vec![3]),
+            ..Default::default()
+        };
+        let mut receipt = rlp::RlpStream::new_list(4);
+        receipt
+            .append(&U64::one())
+            .append(&U256::from(21_000u64))
+            .append(&Bloom::zero())
+            .append_list(std::slice::from_ref(&log));
+        let legacy = receipt.out().to_vec();
+        let mut typed = vec![2u8];
+        typed.extend_from_slice(&legacy);
+
+        let decoded = TransactionReceipt::decode_raw(&legacy).unwrap();
+        assert_eq!(decoded.status, Some(U64::one()));
+        assert_eq!(decoded.cumulative_gas_used, 21_000u64.into());
+        assert_eq!(decoded.logs, vec![log]);
+        assert_eq!(decoded.transaction_type, Some(U64::zero()));
+
+        let decoded = TransactionReceipt::decode_raw(&typed).unwrap();
+        assert_eq!(decoded.transaction_type, Some(2u64.into()));
+        assert_eq!(decoded.root, None);
+    }
+
     #[test]
     #[cfg(feature = "optimism")]
     fn test_rlp_encode_deposited_tx() {
@@ -28,3 +28,14 @@ impl rlp::Encodable for Withdrawal {
         s.append(&self.amount);
     }
 }
+
+impl rlp::Decodable for Withdrawal {
+    fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        Ok(Self {
+            index: rlp.val_at(0)?,
+            validator_index: rlp.val_at(1)?,
+            address: rlp.val_at(2)?,
+            amount: rlp.val_at(3)?,
+        })
+    }
+}
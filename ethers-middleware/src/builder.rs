@@ -1,4 +1,5 @@
 use crate::{
+    gas_oracle::{GasOracle, GasOracleMiddleware},
     NonceManagerMiddleware, SignerMiddleware,
 };
 use ethers_core::types::Address;
@@ -23,10 +24,11 @@ use ethers_signers::Signer;
 ///     let signer = key.parse::<LocalWallet>().unwrap();
 ///     let address = signer.address();
 ///     let escalator = GeometricGasPrice::new(1.125, 60_u64, None::<u64>);
-///     let gas_oracle = GasNow::new();
 ///
-///     let provider = Provider::<Http>::try_from("http://localhost:8545")
-///         .unwrap()
+///     let provider = Provider::<Http>::try_from("http://localhost:8545").unwrap();
+///     let gas_oracle = FeeHistoryOracle::new(provider.clone());
+///
+///     let provider = provider
 ///         .wrap_into(|p| GasEscalatorMiddleware::new(p, escalator, Frequency::PerBlock))
 ///         .gas_oracle(gas_oracle)
 ///         .with_signer(signer)
@@ -39,11 +41,13 @@ use ethers_signers::Signer;
 ///     let address = signer.address();
 ///     let escalator = GeometricGasPrice::new(1.125, 60_u64, None::<u64>);
 ///
-///     let provider = Provider::<Http>::try_from("http://localhost:8545")
-///         .unwrap()
+///     let provider = Provider::<Http>::try_from("http://localhost:8545").unwrap();
+///     let gas_oracle = BlockMedian::new(provider.clone());
+///
+///     let provider = provider
 ///         .wrap_into(|p| GasEscalatorMiddleware::new(p, escalator, Frequency::PerBlock))
 ///         .wrap_into(|p| SignerMiddleware::new(p, signer))
-///         .wrap_into(|p| GasOracleMiddleware::new(p, GasNow::new()))
+///         .wrap_into(|p| GasOracleMiddleware::new(p, gas_oracle))
 ///         .wrap_into(|p| NonceManagerMiddleware::new(p, address)); // Outermost layer
 /// }
 /// ```
@@ -71,6 +75,14 @@ pub trait MiddlewareBuilder: Middleware + Sized + 'static {
     fn nonce_manager(self, address: Address) -> NonceManagerMiddleware<Self> {
         NonceManagerMiddleware::new(self, address)
     }
+
+    /// Wraps `self` inside a [`GasOracleMiddleware`].
+    fn gas_oracle<G>(self, gas_oracle: G) -> GasOracleMiddleware<Self, G>
+    where
+        G: GasOracle,
+    {
+        GasOracleMiddleware::new(self, gas_oracle)
+    }
 }
 
 impl<M> MiddlewareBuilder for M where M: Middleware + Sized + 'static {}
@@ -0,0 +1,377 @@
+mod relay;
+pub use relay::{Relay, RelayError, FLASHBOTS_SIGNATURE_HEADER};
+
+mod request;
+use request::PrivateTransactionParams;
+pub use request::{BundleRequest, SendBundleResponse, SimulatedBundle, SimulatedTransaction};
+
+use async_trait::async_trait;
+use ethers_core::types::{Bytes, TxHash, U64};
+use ethers_providers::{interval, Middleware, MiddlewareError, PendingTransaction, StreamExt};
+use ethers_signers::Signer;
+use thiserror::Error;
+use url::Url;
+
+/// Whether a bundle was included in its target block
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BundleStatus {
+    /// The target block was not mined yet
+    Pending,
+    /// All the transactions of the bundle, including those allowed to revert, are in the target
+    /// block
+    Included,
+    /// The target block was mined without the bundle
+    NotIncluded,
+}
+
+/// Error thrown by the [`BundleMiddleware`]
+#[derive(Debug, Error)]
+pub enum BundleMiddlewareError<M: Middleware> {
+    /// Thrown when an internal middleware errors
+    #[error("{0}")]
+    MiddlewareError(M::Error),
+
+    /// Thrown when the relay errors
+    #[error(transparent)]
+    RelayError(#[from] RelayError),
+
+    /// Thrown when sending, simulating or tracking a bundle without target block
+    #[error("the bundle has no target block")]
+    MissingTargetBlock,
+}
+
+impl<M: Middleware> MiddlewareError for BundleMiddlewareError<M> {
+    type Inner = M::Error;
+
+    fn from_err(src: M::Error) -> Self {
+        BundleMiddlewareError::MiddlewareError(src)
+    }
+
+    fn as_inner(&self) -> Option<&Self::Inner> {
+        match self {
+            BundleMiddlewareError::MiddlewareError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Middleware which submits bundles and private transactions to a Flashbots-style relay instead
+/// of the public mempool.
+///
+/// Signed transactions sent through [`Middleware::send_raw_transaction`] are submitted with
+/// `eth_sendPrivateTransaction`, so that placing the middleware under a
+/// [`SignerMiddleware`](crate::SignerMiddleware) keeps all the signer's transactions private.
+/// Bundles are built with [`BundleRequest`], simulated with `eth_callBundle` and submitted with
+/// `eth_sendBundle`, then tracked on the inner middleware's chain.
+///
+/// ```no_run
+/// use ethers_middleware::{bundle::{BundleMiddleware, BundleRequest, BundleStatus}, SignerMiddleware};
+/// use ethers_providers::{Http, Middleware, Provider};
+/// use ethers_signers::{LocalWallet, Signer};
+///
+/// # async fn foo(bundle: BundleRequest) -> Result<(), Box<dyn std::error::Error>> {
+/// let provider = Provider::<Http>::try_from("http://localhost:8545")?;
+/// // the identity key only authenticates the requests to the relay
+/// let identity = LocalWallet::new(&mut rand::thread_rng());
+/// let client = BundleMiddleware::new(provider, "https://relay.flashbots.net".parse::<url::Url>()?, identity);
+///
+/// let block = client.get_block_number().await?;
+/// let bundle = bundle.set_block(block + 1);
+/// let simulation = client.simulate_bundle(&bundle).await?;
+/// if simulation.first_failure().is_none() {
+///     client.send_bundle(&bundle).await?;
+///     assert_eq!(client.wait_for_bundle(&bundle).await?, BundleStatus::Included);
+/// }
+///
+/// // send the transactions of the wallet privately
+/// let wallet = LocalWallet::new(&mut rand::thread_rng());
+/// let client = SignerMiddleware::new(client, wallet);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct BundleMiddleware<M, S> {
+    inner: M,
+    relay: Relay<S>,
+}
+
+impl<M, S> BundleMiddleware<M, S>
+where
+    M: Middleware,
+    S: Signer,
+{
+    /// Creates a middleware submitting to the relay at `relay_url`, authenticating with `signer`
+    pub fn new(inner: M, relay_url: impl Into<Url>, signer: S) -> Self {
+        Self { inner, relay: Relay::new(relay_url, signer) }
+    }
+
+    /// Returns the client of the relay
+    pub fn relay(&self) -> &Relay<S> {
+        &self.relay
+    }
+
+    /// Simulates the bundle in its target block, on top of its simulation block
+    pub async fn simulate_bundle(
+        &self,
+        bundle: &BundleRequest,
+    ) -> Result<SimulatedBundle, BundleMiddlewareError<M>> {
+        bundle.block().ok_or(BundleMiddlewareError::MissingTargetBlock)?;
+        Ok(self.relay.request("eth_callBundle", [bundle.simulation_params()]).await?)
+    }
+
+    /// Submits the bundle for its target block
+    pub async fn send_bundle(
+        &self,
+        bundle: &BundleRequest,
+    ) -> Result<SendBundleResponse, BundleMiddlewareError<M>> {
+        bundle.block().ok_or(BundleMiddlewareError::MissingTargetBlock)?;
+        Ok(self.relay.request("eth_sendBundle", [bundle]).await?)
+    }
+
+    /// Submits a signed transaction, to be included privately within `max_block_number`, or the
+    /// relay's default number of blocks
+    pub async fn send_private_transaction(
+        &self,
+        tx: Bytes,
+        max_block_number: Option<U64>,
+    ) -> Result<TxHash, BundleMiddlewareError<M>> {
+        let params = PrivateTransactionParams { tx, max_block_number };
+        Ok(self.relay.request("eth_sendPrivateTransaction", [params]).await?)
+    }
+
+    /// Returns whether the bundle was included in its target block
+    pub async fn bundle_status(
+        &self,
+        bundle: &BundleRequest,
+    ) -> Result<BundleStatus, BundleMiddlewareError<M>> {
+        let target = bundle.block().ok_or(BundleMiddlewareError::MissingTargetBlock)?;
+        let Some(block) =
+            self.inner.get_block(target).await.map_err(BundleMiddlewareError::MiddlewareError)?
+        else {
+            return Ok(BundleStatus::Pending)
+        };
+
+        // transactions allowed to revert are still included, only without invalidating the bundle
+        let included =
+            bundle.transaction_hashes().iter().all(|hash| block.transactions.contains(hash));
+        Ok(if included { BundleStatus::Included } else { BundleStatus::NotIncluded })
+    }
+
+    /// Waits until the target block of the bundle is mined, and returns whether the bundle was
+    /// included in it
+    pub async fn wait_for_bundle(
+        &self,
+        bundle: &BundleRequest,
+    ) -> Result<BundleStatus, BundleMiddlewareError<M>> {
+        let mut ticker = interval(self.provider().get_interval());
+        loop {
+            match self.bundle_status(bundle).await? {
+                BundleStatus::Pending => {
+                    ticker.next().await;
+                }
+                status => return Ok(status),
+            }
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<M, S> Middleware for BundleMiddleware<M, S>
+where
+    M: Middleware,
+    S: Signer,
+{
+    type Error = BundleMiddlewareError<M>;
+    type Provider = M::Provider;
+    type Inner = M;
+
+    fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    /// Submits the signed transaction privately with `eth_sendPrivateTransaction`
+    async fn send_raw_transaction<'a>(
+        &'a self,
+        tx: Bytes,
+    ) -> Result<PendingTransaction<'a, Self::Provider>, Self::Error> {
+        let tx_hash = self.send_private_transaction(tx, None).await?;
+        Ok(PendingTransaction::new(tx_hash, self.provider()))
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::SignerMiddleware;
+    use ethers_core::{
+        types::{
+            transaction::eip2718::TypedTransaction, Address, Block, Signature, TransactionRequest,
+            H256,
+        },
+        utils::keccak256,
+    };
+    use ethers_providers::Provider;
+    use ethers_signers::LocalWallet;
+    use serde_json::{json, Value};
+    use std::{
+        str::FromStr,
+        sync::{Arc, Mutex},
+    };
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+    };
+
+    type Requests = Arc<Mutex<Vec<(String, Vec<u8>)>>>;
+
+    /// Serves JSON-RPC requests over HTTP with `respond`, recording their signature header and
+    /// body
+    async fn relay_stub(respond: fn(&str) -> Value) -> (Url, Requests) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap()).parse().unwrap();
+        let requests = Requests::default();
+
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let recorded = recorded.clone();
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(stream);
+                    loop {
+                        let (mut length, mut signature) = (0, String::new());
+                        loop {
+                            let mut line = String::new();
+                            if stream.read_line(&mut line).await.unwrap_or(0) == 0 {
+                                return
+                            }
+                            let line = line.trim_end();
+                            if line.is_empty() {
+                                break
+                            }
+                            let (name, value) = line.split_once(": ").unwrap_or((line, ""));
+                            match name.to_lowercase().as_str() {
+                                "content-length" => length = value.parse().unwrap(),
+                                "x-flashbots-signature" => signature = value.to_string(),
+                                _ => {}
+                            }
+                        }
+
+                        let mut body = vec![0; length];
+                        stream.read_exact(&mut body).await.unwrap();
+                        let request: Value = serde_json::from_slice(&body).unwrap();
+                        recorded.lock().unwrap().push((signature, body));
+
+                        let result = respond(request["method"].as_str().unwrap());
+                        let response =
+                            json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })
+                                .to_string();
+                        let response = format!(
+                            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{response}",
+                            response.len()
+                        );
+                        stream.get_mut().write_all(response.as_bytes()).await.unwrap();
+                    }
+                });
+            }
+        });
+
+        (url, requests)
+    }
+
+    fn respond(method: &str) -> Value {
+        match method {
+            "eth_sendBundle" => json!({ "bundleHash": H256::repeat_byte(0xbb) }),
+            "eth_sendPrivateTransaction" => json!(H256::repeat_byte(0xcc)),
+            _ => Value::Null,
+        }
+    }
+
+    async fn signed(wallet: &LocalWallet, nonce: u64) -> (TypedTransaction, Signature) {
+        let tx: TypedTransaction = TransactionRequest::pay(Address::zero(), 1)
+            .from(wallet.address())
+            .nonce(nonce)
+            .gas(21_000)
+            .gas_price(10)
+            .chain_id(1)
+            .into();
+        let signature = wallet.sign_transaction(&tx).await.unwrap();
+        (tx, signature)
+    }
+
+    #[tokio::test]
+    async fn sends_signed_bundles_and_tracks_inclusion() {
+        let (url, requests) = relay_stub(respond).await;
+        let (provider, mock) = Provider::mocked();
+        let identity = LocalWallet::from_bytes(&[1; 32]).unwrap();
+        let client = BundleMiddleware::new(provider, url, identity.clone());
+
+        let wallet = LocalWallet::from_bytes(&[2; 32]).unwrap().with_chain_id(1u64);
+        let (first, first_sig) = signed(&wallet, 0).await;
+        let (second, second_sig) = signed(&wallet, 1).await;
+        let bundle = BundleRequest::new()
+            .push_transaction(&first, &first_sig)
+            .push_revertible_transaction(&second, &second_sig);
+        assert!(matches!(
+            client.send_bundle(&bundle).await,
+            Err(BundleMiddlewareError::MissingTargetBlock)
+        ));
+
+        let bundle = bundle.set_block(10u64);
+        let response = client.send_bundle(&bundle).await.unwrap();
+        assert_eq!(response.bundle_hash, H256::repeat_byte(0xbb));
+
+        let (signature, body) = requests.lock().unwrap()[0].clone();
+        let request: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(request["method"], "eth_sendBundle");
+        assert_eq!(request["params"][0]["blockNumber"], "0xa");
+        let (address, signature) = signature.split_once(':').unwrap();
+        assert_eq!(Address::from_str(address).unwrap(), identity.address());
+        let hash = format!("{:?}", H256::from(keccak256(&body)));
+        let signer = Signature::from_str(signature).unwrap().recover(hash).unwrap();
+        assert_eq!(signer, identity.address());
+
+        // the target block is pending, then mined without the bundle, then with it
+        mock.push::<Option<Block<TxHash>>, _>(None).unwrap();
+        assert_eq!(client.bundle_status(&bundle).await.unwrap(), BundleStatus::Pending);
+        let block = |transactions| Block::<TxHash> {
+            number: Some(10u64.into()),
+            transactions,
+            ..Default::default()
+        };
+        mock.push(block(vec![first.hash(&second_sig)])).unwrap();
+        assert_eq!(client.bundle_status(&bundle).await.unwrap(), BundleStatus::NotIncluded);
+        // the second transaction is allowed to revert, but not to be dropped
+        mock.push(block(vec![first.hash(&first_sig)])).unwrap();
+        assert_eq!(client.bundle_status(&bundle).await.unwrap(), BundleStatus::NotIncluded);
+        mock.push(block(vec![first.hash(&first_sig), second.hash(&second_sig)])).unwrap();
+        assert_eq!(client.bundle_status(&bundle).await.unwrap(), BundleStatus::Included);
+
+        // a bundle of revertible transactions is not included in an empty block
+        let revertible =
+            BundleRequest::new().push_revertible_transaction(&second, &second_sig).set_block(10u64);
+        mock.push(block(vec![])).unwrap();
+        assert_eq!(client.bundle_status(&revertible).await.unwrap(), BundleStatus::NotIncluded);
+    }
+
+    #[tokio::test]
+    async fn sends_signer_transactions_privately() {
+        let (url, requests) = relay_stub(respond).await;
+        let (provider, _mock) = Provider::mocked();
+        let identity = LocalWallet::from_bytes(&[1; 32]).unwrap();
+        let wallet = LocalWallet::from_bytes(&[2; 32]).unwrap().with_chain_id(1u64);
+        let client =
+            SignerMiddleware::new(BundleMiddleware::new(provider, url, identity), wallet.clone());
+
+        let (tx, signature) = signed(&wallet, 0).await;
+        let pending = client.send_transaction(tx.clone(), None).await.unwrap();
+        assert_eq!(*pending, H256::repeat_byte(0xcc));
+
+        let (_, body) = requests.lock().unwrap()[0].clone();
+        let request: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(request["method"], "eth_sendPrivateTransaction");
+        assert_eq!(request["params"][0], json!({ "tx": tx.rlp_signed(&signature) }));
+    }
+}
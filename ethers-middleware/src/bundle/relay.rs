@@ -0,0 +1,116 @@
+use ethers_core::{types::H256, utils::keccak256};
+use ethers_providers::JsonRpcError;
+use ethers_signers::Signer;
+use reqwest::{header::CONTENT_TYPE, Client};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use thiserror::Error;
+use url::Url;
+
+/// The header authenticating requests to a relay
+pub const FLASHBOTS_SIGNATURE_HEADER: &str = "X-Flashbots-Signature";
+
+/// Error thrown by a [`Relay`]
+#[derive(Debug, Error)]
+pub enum RelayError {
+    /// Thrown when the request to the relay fails
+    #[error(transparent)]
+    HttpError(#[from] reqwest::Error),
+
+    /// Thrown when the request or the response can not be (de)serialized
+    #[error(transparent)]
+    SerdeJson(#[from] serde_json::Error),
+
+    /// Thrown when the relay returns an error
+    #[error(transparent)]
+    JsonRpcError(#[from] JsonRpcError),
+
+    /// Thrown when the request can not be signed
+    #[error("signing failed: {0}")]
+    SignerError(String),
+}
+
+#[derive(Serialize)]
+struct Request<'a, T> {
+    id: u64,
+    jsonrpc: &'static str,
+    method: &'a str,
+    params: T,
+}
+
+// errors first, as a missing `result` would deserialize as a successful `None`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Response<R> {
+    Error { error: JsonRpcError },
+    Success { result: R },
+}
+
+/// A JSON-RPC client of a Flashbots-style relay, which signs the body of every request with the
+/// searcher's identity key in the [`FLASHBOTS_SIGNATURE_HEADER`] header.
+///
+/// The identity key only builds the reputation of the searcher with the relay, it should not hold
+/// funds.
+#[derive(Debug)]
+pub struct Relay<S> {
+    url: Url,
+    signer: S,
+    client: Client,
+    id: AtomicU64,
+}
+
+impl<S: Signer> Relay<S> {
+    /// Creates a client of the relay at `url`, authenticating with `signer`
+    pub fn new(url: impl Into<Url>, signer: S) -> Self {
+        Self { url: url.into(), signer, client: Client::new(), id: AtomicU64::new(1) }
+    }
+
+    /// The URL of the relay
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// The signer of the requests
+    pub fn signer(&self) -> &S {
+        &self.signer
+    }
+
+    /// Returns the value of the [`FLASHBOTS_SIGNATURE_HEADER`] header for `body`: the address of
+    /// the signer and its EIP-191 signature of the hex encoded hash of the body
+    pub async fn sign_body(&self, body: &[u8]) -> Result<String, RelayError> {
+        let hash = format!("{:?}", H256::from(keccak256(body)));
+        let signature = self
+            .signer
+            .sign_message(hash)
+            .await
+            .map_err(|err| RelayError::SignerError(err.to_string()))?;
+        Ok(format!("{:?}:0x{signature}", self.signer.address()))
+    }
+
+    /// Sends the signed JSON-RPC request to the relay
+    pub async fn request<T: Serialize, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: T,
+    ) -> Result<R, RelayError> {
+        let id = self.id.fetch_add(1, Ordering::SeqCst);
+        let body = serde_json::to_vec(&Request { id, jsonrpc: "2.0", method, params })?;
+        let signature = self.sign_body(&body).await?;
+
+        let response = self
+            .client
+            .post(self.url.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .header(FLASHBOTS_SIGNATURE_HEADER, signature)
+            .body(body)
+            .send()
+            .await?
+            .bytes()
+            .await?;
+
+        match serde_json::from_slice(&response)? {
+            Response::Success { result } => Ok(result),
+            Response::Error { error } => Err(error.into()),
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use ethers_core::{
+    types::{
+        serde_helpers::deserialize_stringified_numeric, transaction::eip2718::TypedTransaction,
+        Address, BlockNumber, Bytes, Signature, TxHash, U256, U64,
+    },
+    utils::keccak256,
+};
+use serde::{Deserialize, Serialize};
+
+/// A bundle of signed transactions, to be included atomically and in order in a target block.
+///
+/// ```
+/// use ethers_core::types::{transaction::eip2718::TypedTransaction, Address, TransactionRequest};
+/// use ethers_middleware::bundle::BundleRequest;
+/// use ethers_signers::{LocalWallet, Signer};
+///
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let wallet = LocalWallet::new(&mut rand::thread_rng()).with_chain_id(1u64);
+/// let tx: TypedTransaction =
+///     TransactionRequest::pay(Address::random(), 100).nonce(0).gas(21_000).gas_price(10).chain_id(1).into();
+/// let signature = wallet.sign_transaction(&tx).await?;
+///
+/// let bundle = BundleRequest::new().push_transaction(&tx, &signature).set_block(17_000_000u64);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleRequest {
+    #[serde(rename = "txs")]
+    transactions: Vec<Bytes>,
+    #[serde(rename = "blockNumber", skip_serializing_if = "Option::is_none")]
+    target_block: Option<U64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min_timestamp: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_timestamp: Option<u64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    reverting_tx_hashes: Vec<TxHash>,
+    #[serde(skip)]
+    simulation_block: Option<BlockNumber>,
+    #[serde(skip)]
+    simulation_timestamp: Option<u64>,
+}
+
+impl BundleRequest {
+    /// Creates an empty bundle
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the transaction signed with `signature` to the bundle
+    #[must_use]
+    pub fn push_transaction(self, tx: &TypedTransaction, signature: &Signature) -> Self {
+        self.push_raw_transaction(tx.rlp_signed(signature))
+    }
+
+    /// Adds an RLP encoded signed transaction to the bundle, e.g. a transaction of another sender
+    #[must_use]
+    pub fn push_raw_transaction(mut self, raw: Bytes) -> Self {
+        self.transactions.push(raw);
+        self
+    }
+
+    /// Adds the transaction signed with `signature` to the bundle, allowing it to revert without
+    /// invalidating the bundle
+    #[must_use]
+    pub fn push_revertible_transaction(self, tx: &TypedTransaction, signature: &Signature) -> Self {
+        let mut bundle = self.push_transaction(tx, signature);
+        bundle.reverting_tx_hashes.push(tx.hash(signature));
+        bundle
+    }
+
+    /// Sets the block the bundle must be included in
+    #[must_use]
+    pub fn set_block(mut self, block: impl Into<U64>) -> Self {
+        self.target_block = Some(block.into());
+        self
+    }
+
+    /// Sets the minimum timestamp of the block the bundle is included in
+    #[must_use]
+    pub fn set_min_timestamp(mut self, timestamp: u64) -> Self {
+        self.min_timestamp = Some(timestamp);
+        self
+    }
+
+    /// Sets the maximum timestamp of the block the bundle is included in
+    #[must_use]
+    pub fn set_max_timestamp(mut self, timestamp: u64) -> Self {
+        self.max_timestamp = Some(timestamp);
+        self
+    }
+
+    /// Sets the state block the bundle is simulated on. Defaults to the latest block.
+    #[must_use]
+    pub fn set_simulation_block(mut self, block: impl Into<BlockNumber>) -> Self {
+        self.simulation_block = Some(block.into());
+        self
+    }
+
+    /// Sets the timestamp of the block the bundle is simulated in
+    #[must_use]
+    pub fn set_simulation_timestamp(mut self, timestamp: u64) -> Self {
+        self.simulation_timestamp = Some(timestamp);
+        self
+    }
+
+    /// The signed transactions of the bundle
+    pub fn transactions(&self) -> &[Bytes] {
+        &self.transactions
+    }
+
+    /// The hashes of the transactions of the bundle
+    pub fn transaction_hashes(&self) -> Vec<TxHash> {
+        self.transactions.iter().map(|raw| keccak256(raw).into()).collect()
+    }
+
+    /// The hashes of the transactions allowed to revert
+    pub fn reverting_tx_hashes(&self) -> &[TxHash] {
+        &self.reverting_tx_hashes
+    }
+
+    /// The block the bundle must be included in
+    pub fn block(&self) -> Option<U64> {
+        self.target_block
+    }
+
+    /// Returns the parameters of `eth_callBundle`
+    pub(crate) fn simulation_params(&self) -> CallBundleParams<'_> {
+        CallBundleParams {
+            transactions: &self.transactions,
+            target_block: self.target_block,
+            state_block: self.simulation_block.unwrap_or(BlockNumber::Latest),
+            timestamp: self.simulation_timestamp,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CallBundleParams<'a> {
+    #[serde(rename = "txs")]
+    transactions: &'a [Bytes],
+    #[serde(rename = "blockNumber", skip_serializing_if = "Option::is_none")]
+    target_block: Option<U64>,
+    #[serde(rename = "stateBlockNumber")]
+    state_block: BlockNumber,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamp: Option<u64>,
+}
+
+/// The response of `eth_sendBundle`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendBundleResponse {
+    pub bundle_hash: TxHash,
+}
+
+/// The simulation of a bundle by `eth_callBundle`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedBundle {
+    pub bundle_hash: TxHash,
+    #[serde(deserialize_with = "deserialize_stringified_numeric")]
+    pub coinbase_diff: U256,
+    #[serde(deserialize_with = "deserialize_stringified_numeric")]
+    pub eth_sent_to_coinbase: U256,
+    #[serde(deserialize_with = "deserialize_stringified_numeric")]
+    pub gas_fees: U256,
+    #[serde(deserialize_with = "deserialize_stringified_numeric")]
+    pub bundle_gas_price: U256,
+    pub total_gas_used: u64,
+    pub state_block_number: u64,
+    pub results: Vec<SimulatedTransaction>,
+}
+
+impl SimulatedBundle {
+    /// Returns the first transaction which failed, if any
+    pub fn first_failure(&self) -> Option<&SimulatedTransaction> {
+        self.results.iter().find(|tx| tx.error.is_some())
+    }
+}
+
+/// The simulation of a transaction of a bundle
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedTransaction {
+    pub tx_hash: TxHash,
+    pub from_address: Address,
+    #[serde(default)]
+    pub to_address: Option<Address>,
+    pub gas_used: u64,
+    #[serde(deserialize_with = "deserialize_stringified_numeric")]
+    pub gas_price: U256,
+    #[serde(deserialize_with = "deserialize_stringified_numeric")]
+    pub coinbase_diff: U256,
+    #[serde(default)]
+    pub value: Option<Bytes>,
+    /// The error of the transaction, e.g. `execution reverted`
+    #[serde(default)]
+    pub error: Option<String>,
+    /// The revert reason of the transaction
+    #[serde(default)]
+    pub revert: Option<String>,
+}
+
+/// The parameters of `eth_sendPrivateTransaction`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PrivateTransactionParams {
+    pub tx: Bytes,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_block_number: Option<U64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers_core::types::TransactionRequest;
+    use ethers_signers::{LocalWallet, Signer};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn serializes_bundles() {
+        let wallet = LocalWallet::from_bytes(&[1; 32]).unwrap().with_chain_id(1u64);
+        let tx: TypedTransaction = TransactionRequest::pay(Address::zero(), 1)
+            .nonce(0)
+            .gas(21_000)
+            .gas_price(10)
+            .chain_id(1)
+            .into();
+        let signature = wallet.sign_transaction(&tx).await.unwrap();
+        let raw = tx.rlp_signed(&signature);
+
+        let bundle = BundleRequest::new()
+            .push_raw_transaction(vec![1, 2].into())
+            .push_revertible_transaction(&tx, &signature)
+            .set_block(10u64)
+            .set_min_timestamp(5);
+        assert_eq!(bundle.transaction_hashes()[1], tx.hash(&signature));
+        assert_eq!(
+            json!(bundle),
+            json!({
+                "txs": ["0x0102", raw],
+                "blockNumber": "0xa",
+                "minTimestamp": 5,
+                "revertingTxHashes": [tx.hash(&signature)],
+            })
+        );
+        assert_eq!(
+            json!(bundle.simulation_params()),
+            json!({ "txs": ["0x0102", raw], "blockNumber": "0xa", "stateBlockNumber": "latest" })
+        );
+    }
+
+    #[test]
+    fn deserializes_simulations() {
+        let simulation: SimulatedBundle = serde_json::from_value(json!({
+            "bundleGasPrice": "476190476193",
+            "bundleHash": "0x73b1e258c7a42fd0230b2fd05529c5d4b6fcb66c227783f8bece8aeacdd1db2e",
+            "coinbaseDiff": "20000000000126000",
+            "ethSentToCoinbase": "20000000000000000",
+            "gasFees": "126000",
+            "results": [{
+                "coinbaseDiff": "10000000000063000",
+                "ethSentToCoinbase": "10000000000000000",
+                "fromAddress": "0x02a727155aef8609c9f7f2179b2a1f560b39f5a0",
+                "gasFees": "63000",
+                "gasPrice": "476190476193",
+                "gasUsed": 21000,
+                "toAddress": "0x73625f59cadc5009cb458b751b3e7b6b48c06f2c",
+                "txHash": "0x669b4704a7d993a946cdd6e2f95233f308ce0c4649d2e04944e8299efcaa098a",
+                "value": "0x",
+                "error": "execution reverted",
+                "revert": "not enough"
+            }],
+            "stateBlockNumber": 5221585,
+            "totalGasUsed": 42000
+        }))
+        .unwrap();
+
+        assert_eq!(simulation.coinbase_diff, U256::from(20000000000126000u64));
+        assert_eq!(simulation.first_failure().unwrap().revert.as_deref(), Some("not enough"));
+    }
+}
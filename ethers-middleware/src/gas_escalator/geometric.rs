@@ -7,12 +7,16 @@ use ethers_core::types::U256;
 /// Coefficient defaults to 1.125 (12.5%), the minimum increase for Parity to replace a transaction.
 /// Coefficient can be adjusted, and there is an optional upper limit.
 ///
+/// For EIP-1559 transactions, `max_price` caps the max fee per gas, while the priority fee can be
+/// capped separately with [`GeometricGasPrice::with_max_priority_fee`].
+///
 /// <https://github.com/makerdao/pymaker/blob/master/pymaker/gas.py#L168>
 #[derive(Clone, Debug)]
 pub struct GeometricGasPrice {
     every_secs: u64,
     coefficient: f64,
     max_price: Option<U256>,
+    max_priority_fee: Option<U256>,
 }
 
 impl GeometricGasPrice {
@@ -29,12 +33,18 @@ impl GeometricGasPrice {
             every_secs: every_secs.into(),
             coefficient,
             max_price: max_price.map(Into::into),
+            max_priority_fee: None,
         }
     }
-}
 
-impl GasEscalator for GeometricGasPrice {
-    fn get_gas_price(&self, initial_price: U256, time_elapsed: u64) -> U256 {
+    /// Sets the upper limit of the max priority fee per gas of EIP-1559 transactions
+    #[must_use]
+    pub fn with_max_priority_fee<T: Into<U256>>(mut self, max_priority_fee: T) -> Self {
+        self.max_priority_fee = Some(max_priority_fee.into());
+        self
+    }
+
+    fn escalate(&self, initial_price: U256, time_elapsed: u64, max_price: Option<U256>) -> U256 {
         let mut result = initial_price.as_u64() as f64;
 
         if time_elapsed >= self.every_secs {
@@ -45,13 +55,31 @@ impl GasEscalator for GeometricGasPrice {
         }
 
         let mut result = U256::from(result.ceil() as u64);
-        if let Some(max_price) = self.max_price {
+        if let Some(max_price) = max_price {
             result = std::cmp::min(result, max_price);
         }
         result
     }
 }
 
+impl GasEscalator for GeometricGasPrice {
+    fn get_gas_price(&self, initial_price: U256, time_elapsed: u64) -> U256 {
+        self.escalate(initial_price, time_elapsed, self.max_price)
+    }
+
+    fn get_eip1559_fees(
+        &self,
+        max_fee_per_gas: U256,
+        max_priority_fee_per_gas: U256,
+        time_elapsed: u64,
+    ) -> (U256, U256) {
+        (
+            self.escalate(max_fee_per_gas, time_elapsed, self.max_price),
+            self.escalate(max_priority_fee_per_gas, time_elapsed, self.max_priority_fee),
+        )
+    }
+}
+
 #[cfg(test)]
 // https://github.com/makerdao/pymaker/blob/master/tests/test_gas.py#L165
 mod tests {
@@ -112,4 +140,20 @@ mod tests {
         assert_eq!(normalized(30), 195.3125);
         assert_eq!(normalized(60), 381.469726563);
     }
+
+    #[test]
+    fn eip1559_fees_obey_their_own_max_values() {
+        let oracle = GeometricGasPrice::new(1.125, 60u64, Some(2500)).with_max_priority_fee(150);
+
+        assert_eq!(oracle.get_eip1559_fees(1000.into(), 100.into(), 0), (1000.into(), 100.into()));
+        assert_eq!(oracle.get_eip1559_fees(1000.into(), 100.into(), 60), (1125.into(), 113.into()));
+        assert_eq!(
+            oracle.get_eip1559_fees(1000.into(), 100.into(), 240),
+            (1602.into(), 150.into())
+        );
+        assert_eq!(
+            oracle.get_eip1559_fees(1000.into(), 100.into(), 1200),
+            (2500.into(), 150.into())
+        );
+    }
 }
@@ -7,12 +7,16 @@ use ethers_core::types::U256;
 /// Start with `initial_price`, then increase it by fixed amount `increase_by` every `every_secs`
 /// seconds until the transaction gets confirmed. There is an optional upper limit.
 ///
+/// For EIP-1559 transactions, `max_price` caps the max fee per gas, while the priority fee can be
+/// capped separately with [`LinearGasPrice::with_max_priority_fee`].
+///
 /// <https://github.com/makerdao/pymaker/blob/master/pymaker/gas.py#L129>
 #[derive(Clone, Debug)]
 pub struct LinearGasPrice {
     every_secs: u64,
     increase_by: U256,
     max_price: Option<U256>,
+    max_priority_fee: Option<U256>,
 }
 
 impl LinearGasPrice {
@@ -26,20 +30,44 @@ impl LinearGasPrice {
             every_secs: every_secs.into(),
             increase_by: increase_by.into(),
             max_price: max_price.map(Into::into),
+            max_priority_fee: None,
         }
     }
-}
 
-impl GasEscalator for LinearGasPrice {
-    fn get_gas_price(&self, initial_price: U256, time_elapsed: u64) -> U256 {
+    /// Sets the upper limit of the max priority fee per gas of EIP-1559 transactions
+    #[must_use]
+    pub fn with_max_priority_fee<T: Into<U256>>(mut self, max_priority_fee: T) -> Self {
+        self.max_priority_fee = Some(max_priority_fee.into());
+        self
+    }
+
+    fn escalate(&self, initial_price: U256, time_elapsed: u64, max_price: Option<U256>) -> U256 {
         let mut result = initial_price + self.increase_by * (time_elapsed / self.every_secs);
-        if let Some(max_price) = self.max_price {
+        if let Some(max_price) = max_price {
             result = std::cmp::min(result, max_price);
         }
         result
     }
 }
 
+impl GasEscalator for LinearGasPrice {
+    fn get_gas_price(&self, initial_price: U256, time_elapsed: u64) -> U256 {
+        self.escalate(initial_price, time_elapsed, self.max_price)
+    }
+
+    fn get_eip1559_fees(
+        &self,
+        max_fee_per_gas: U256,
+        max_priority_fee_per_gas: U256,
+        time_elapsed: u64,
+    ) -> (U256, U256) {
+        (
+            self.escalate(max_fee_per_gas, time_elapsed, self.max_price),
+            self.escalate(max_priority_fee_per_gas, time_elapsed, self.max_priority_fee),
+        )
+    }
+}
+
 #[cfg(test)]
 // https://github.com/makerdao/pymaker/blob/master/tests/test_gas.py#L107
 mod tests {
@@ -13,15 +13,13 @@ use std::{pin::Pin, sync::Arc};
 use thiserror::Error;
 use tracing_futures::Instrument;
 
-use ethers_core::types::{
-    transaction::eip2718::TypedTransaction, BlockId, TransactionRequest, TxHash, U256,
-};
+use ethers_core::types::{transaction::eip2718::TypedTransaction, BlockId, TxHash, U256};
 use ethers_providers::{interval, Middleware, MiddlewareError, PendingTransaction, StreamExt};
 
 #[cfg(not(target_arch = "wasm32"))]
 use tokio::spawn;
 
-type ToEscalate = Arc<Mutex<Vec<(TxHash, TransactionRequest, Instant, Option<BlockId>)>>>;
+type ToEscalate = Arc<Mutex<Vec<(TxHash, TypedTransaction, Instant, Option<BlockId>)>>>;
 
 #[cfg(target_arch = "wasm32")]
 type WatcherFuture<'a> = Pin<Box<dyn futures_util::stream::Stream<Item = ()> + 'a>>;
@@ -34,6 +32,52 @@ pub trait GasEscalator: Send + Sync + std::fmt::Debug {
     /// Given the initial gas price and the time elapsed since the transaction's
     /// first broadcast, it returns the new gas price
     fn get_gas_price(&self, initial_price: U256, time_elapsed: u64) -> U256;
+
+    /// Given the initial `max_fee_per_gas` and `max_priority_fee_per_gas` of an EIP-1559
+    /// transaction and the time elapsed since its first broadcast, it returns the new
+    /// `(max_fee_per_gas, max_priority_fee_per_gas)`.
+    ///
+    /// Defaults to escalating both fields with [`GasEscalator::get_gas_price`].
+    fn get_eip1559_fees(
+        &self,
+        max_fee_per_gas: U256,
+        max_priority_fee_per_gas: U256,
+        time_elapsed: u64,
+    ) -> (U256, U256) {
+        (
+            self.get_gas_price(max_fee_per_gas, time_elapsed),
+            self.get_gas_price(max_priority_fee_per_gas, time_elapsed),
+        )
+    }
+}
+
+/// The minimum percentage by which both fees of an EIP-1559 transaction must be bumped for
+/// nodes to accept it as a replacement of the pending one.
+pub const MIN_REPLACEMENT_BUMP_PERCENT: u64 = 10;
+
+/// Returns the escalated `(max_fee_per_gas, max_priority_fee_per_gas)` of an EIP-1559
+/// transaction, or `None` if the escalator's new fees would not be accepted as a replacement,
+/// i.e. if either of them is not at least [`MIN_REPLACEMENT_BUMP_PERCENT`] higher than before.
+///
+/// The priority fee is clamped to the max fee.
+fn escalate_eip1559_fees<E: GasEscalator>(
+    escalator: &E,
+    max_fee_per_gas: U256,
+    max_priority_fee_per_gas: U256,
+    time_elapsed: u64,
+) -> Option<(U256, U256)> {
+    let (new_max_fee, new_priority_fee) =
+        escalator.get_eip1559_fees(max_fee_per_gas, max_priority_fee_per_gas, time_elapsed);
+    let new_priority_fee = std::cmp::min(new_priority_fee, new_max_fee);
+
+    let min_bump = |fee: U256| fee * (100 + MIN_REPLACEMENT_BUMP_PERCENT) / 100;
+    if new_max_fee == max_fee_per_gas ||
+        new_max_fee < min_bump(max_fee_per_gas) ||
+        new_priority_fee < min_bump(max_priority_fee_per_gas)
+    {
+        return None
+    }
+    Some((new_max_fee, new_priority_fee))
 }
 
 /// Error thrown when the GasEscalator interacts with the blockchain
@@ -43,7 +87,7 @@ pub enum GasEscalatorError<M: Middleware> {
     /// Thrown when an internal middleware errors
     MiddlewareError(M::Error),
 
-    #[error("Gas escalation is only supported for EIP2930 or Legacy transactions")]
+    #[error("Gas escalation is only supported for Legacy, EIP2930 or EIP1559 transactions")]
     UnsupportedTxType,
 }
 
@@ -119,13 +163,14 @@ pub(crate) struct GasEscalatorMiddlewareInternal<M> {
 /// use ethers_providers::{Provider, Http};
 /// use ethers_middleware::{
 ///     gas_escalator::{GeometricGasPrice, Frequency, GasEscalatorMiddleware},
-///     gas_oracle::{GasNow, GasCategory, GasOracleMiddleware},
+///     gas_oracle::{FeeHistoryOracle, GasCategory, GasOracleMiddleware},
 /// };
 /// use std::{convert::TryFrom, time::Duration, sync::Arc};
 ///
-/// let provider = Provider::try_from("http://localhost:8545")
+/// let provider = Provider::<Http>::try_from("http://localhost:8545")
 ///     .unwrap()
 ///     .interval(Duration::from_millis(2000u64));
+/// let gas_oracle = FeeHistoryOracle::new(provider.clone()).category(GasCategory::SafeLow);
 ///
 /// let provider = {
 ///     let escalator = GeometricGasPrice::new(5.0, 10u64, None::<u64>);
@@ -133,7 +178,6 @@ pub(crate) struct GasEscalatorMiddlewareInternal<M> {
 /// };
 ///
 /// // ... proceed to wrap it in other middleware
-/// let gas_oracle = GasNow::new().category(GasCategory::SafeLow);
 /// let provider = GasOracleMiddleware::new(provider, gas_oracle);
 /// ```
 #[derive(Debug, Clone)]
@@ -173,19 +217,20 @@ where
         tx: T,
         block: Option<BlockId>,
     ) -> Result<PendingTransaction<'_, M::Provider>, GasEscalatorError<M>> {
-        let tx = tx.into();
+        let mut tx = tx.into();
 
+        // escalate from the fees the transaction is broadcast with, estimated if they are not set
+        self.inner.fill_transaction(&mut tx, block).await.map_err(MiddlewareError::from_err)?;
         let pending_tx = self
             .inner
             .send_transaction(tx.clone(), block)
             .await
             .map_err(MiddlewareError::from_err)?;
 
-        let tx = match tx {
-            TypedTransaction::Legacy(inner) => inner,
-            TypedTransaction::Eip2930(inner) => inner.tx,
-            _ => return Err(GasEscalatorError::UnsupportedTxType),
-        };
+        #[cfg(feature = "optimism")]
+        if let TypedTransaction::OptimismDeposited(_) = tx {
+            return Err(GasEscalatorError::UnsupportedTxType)
+        }
 
         // insert the tx in the pending txs
         let mut lock = self.txs.lock().await;
@@ -303,18 +348,55 @@ impl<M, E> EscalationTask<M, E> {
                     tracing::trace!(tx_hash = ?tx_hash, "checking if exists");
 
                     if receipt.is_none() {
-                        let old_gas_price = replacement_tx.gas_price.expect("gas price must be set");
-                        // Get the new gas price based on how much time passed since the
+                        let time_elapsed = now.duration_since(time).as_secs();
+                        // Get the new fees based on how much time passed since the
                         // tx was last broadcast
-                        let new_gas_price = self
-                            .escalator
-                            .get_gas_price(old_gas_price, now.duration_since(time).as_secs());
+                        let bumped = match replacement_tx {
+                            TypedTransaction::Eip1559(ref mut inner) => {
+                                let old_max_fee =
+                                    inner.max_fee_per_gas.expect("max fee per gas must be set");
+                                let old_priority_fee = inner
+                                    .max_priority_fee_per_gas
+                                    .expect("max priority fee per gas must be set");
+                                match escalate_eip1559_fees(
+                                    &self.escalator,
+                                    old_max_fee,
+                                    old_priority_fee,
+                                    time_elapsed,
+                                ) {
+                                    Some((new_max_fee, new_priority_fee)) => {
+                                        tracing::trace!(
+                                            old_max_fee = ?old_max_fee,
+                                            new_max_fee = ?new_max_fee,
+                                            old_priority_fee = ?old_priority_fee,
+                                            new_priority_fee = ?new_priority_fee,
+                                            "escalating eip1559 fees"
+                                        );
+                                        inner.max_fee_per_gas = Some(new_max_fee);
+                                        inner.max_priority_fee_per_gas = Some(new_priority_fee);
+                                        true
+                                    }
+                                    None => false,
+                                }
+                            }
+                            _ => {
+                                let old_gas_price =
+                                    replacement_tx.gas_price().expect("gas price must be set");
+                                let new_gas_price =
+                                    self.escalator.get_gas_price(old_gas_price, time_elapsed);
+                                tracing::trace!(
+                                    old_gas_price = ?old_gas_price,
+                                    new_gas_price = ?new_gas_price,
+                                    "escalating gas price"
+                                );
+                                replacement_tx.set_gas_price(new_gas_price);
+                                new_gas_price != old_gas_price
+                            }
+                        };
 
-                        let new_txhash = if new_gas_price == old_gas_price {
+                        let new_txhash = if !bumped {
                              tx_hash
                         } else {
-                            // bump the gas price
-                            replacement_tx.gas_price = Some(new_gas_price);
 
                             // the tx hash will be different so we need to update it
                             match self.inner.send_transaction(replacement_tx.clone(), priority).await {
@@ -323,8 +405,6 @@ impl<M, E> EscalationTask<M, E> {
                                     tracing::trace!(
                                         old_tx_hash = ?tx_hash,
                                         new_tx_hash = ?new_tx_hash,
-                                        old_gas_price = ?old_gas_price,
-                                        new_gas_price = ?new_gas_price,
                                         "escalated"
                                     );
                                     new_tx_hash
@@ -358,3 +438,117 @@ impl<M, E> EscalationTask<M, E> {
         }
     }
 }
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::test_utils::{accept_transactions, sent_transactions};
+    use ethers_core::types::{Address, Block, Eip1559TransactionRequest, FeeHistory, TxHash};
+    use ethers_providers::{MockResponse, Provider};
+    use serde_json::{json, Value};
+    use std::time::Duration;
+
+    /// Multiplies the fees by a fixed percentage on every escalation
+    #[derive(Debug)]
+    struct Percent(u64);
+
+    impl GasEscalator for Percent {
+        fn get_gas_price(&self, initial_price: U256, _time_elapsed: u64) -> U256 {
+            initial_price * self.0 / 100
+        }
+    }
+
+    #[test]
+    fn eip1559_fees_respect_min_replacement_bump() {
+        let fees = |escalator: &Percent| escalate_eip1559_fees(escalator, 100.into(), 10.into(), 1);
+
+        assert_eq!(fees(&Percent(100)), None);
+        assert_eq!(fees(&Percent(105)), None);
+        assert_eq!(fees(&Percent(110)), Some((110.into(), 11.into())));
+        assert_eq!(fees(&Percent(200)), Some((200.into(), 20.into())));
+    }
+
+    #[test]
+    fn eip1559_priority_fee_is_clamped_to_max_fee() {
+        let escalator = LinearGasPrice::new(50, 1u64, Some(150));
+        assert_eq!(
+            escalate_eip1559_fees(&escalator, 100.into(), 100.into(), 1),
+            Some((150.into(), 150.into()))
+        );
+        // both fees are capped and cannot be bumped anymore
+        assert_eq!(escalate_eip1559_fees(&escalator, 150.into(), 150.into(), 1), None);
+    }
+
+    fn tx() -> Eip1559TransactionRequest {
+        Eip1559TransactionRequest::new()
+            .from(Address::repeat_byte(1))
+            .to(Address::zero())
+            .gas(21_000)
+            .nonce(0)
+            .chain_id(1)
+    }
+
+    async fn escalated_fees(
+        escalator: Percent,
+        tx: Eip1559TransactionRequest,
+    ) -> Vec<(U256, U256)> {
+        let (provider, mock) = Provider::mocked();
+        accept_transactions(&mock);
+        mock.on("eth_getTransactionReceipt", |_| MockResponse::Value(Value::Null));
+        mock.on("eth_getBlockByNumber", |_| {
+            let block =
+                Block::<TxHash> { base_fee_per_gas: Some(100.into()), ..Default::default() };
+            MockResponse::Value(json!(block))
+        });
+        mock.on("eth_feeHistory", |_| {
+            MockResponse::Value(json!(FeeHistory {
+                base_fee_per_gas: vec![100.into()],
+                gas_used_ratio: vec![0.5],
+                oldest_block: 1.into(),
+                reward: vec![vec![10.into()]],
+            }))
+        });
+
+        let provider = GasEscalatorMiddleware::new(provider, escalator, Frequency::Duration(10));
+        let tx_hash = *provider.send_transaction(tx, None).await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        drop(provider);
+
+        let sent = sent_transactions(&mock);
+        assert_eq!(sent[0].0, tx_hash);
+        sent.into_iter()
+            .map(|(_, tx)| {
+                let tx = tx.as_eip1559_ref().unwrap();
+                (tx.max_fee_per_gas.unwrap(), tx.max_priority_fee_per_gas.unwrap())
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn escalates_eip1559_transactions() {
+        let tx = tx().max_fee_per_gas(100).max_priority_fee_per_gas(10);
+        let fees = escalated_fees(Percent(120), tx).await;
+        assert!(fees.len() > 2, "{fees:?}");
+        assert_eq!(fees[0], (100.into(), 10.into()));
+        assert_eq!(fees[1], (120.into(), 12.into()));
+        assert_eq!(fees[2], (144.into(), 14.into()));
+    }
+
+    #[tokio::test]
+    async fn does_not_rebroadcast_underpriced_replacements() {
+        let tx = tx().max_fee_per_gas(100).max_priority_fee_per_gas(10);
+        let fees = escalated_fees(Percent(105), tx).await;
+        assert_eq!(fees, vec![(100.into(), 10.into())]);
+    }
+
+    #[tokio::test]
+    async fn escalates_estimated_eip1559_fees() {
+        let fees = escalated_fees(Percent(200), tx()).await;
+        assert!(fees.len() > 1, "{fees:?}");
+        let (max_fee, priority_fee) = fees[0];
+        assert!(!max_fee.is_zero() && !priority_fee.is_zero(), "{fees:?}");
+        assert_eq!(fees[1], (max_fee * 2, priority_fee * 2));
+    }
+}
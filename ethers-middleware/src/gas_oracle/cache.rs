@@ -0,0 +1,117 @@
+use super::{GasOracle, Result};
+use async_trait::async_trait;
+use ethers_core::types::U256;
+use futures_util::lock::Mutex;
+use instant::{Duration, Instant};
+use std::{fmt::Debug, future::Future};
+
+/// A gas oracle which caches the estimates of another oracle for a fixed duration.
+///
+/// Concurrent requests for an expired estimate are coalesced into a single request to the inner
+/// oracle.
+///
+/// # Example
+///
+/// ```no_run
+/// use ethers_middleware::gas_oracle::{Cache, GasOracle, ProviderOracle};
+/// use ethers_providers::{Http, Provider};
+/// use std::time::Duration;
+///
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let provider = Provider::<Http>::try_from("http://localhost:8545")?;
+/// let oracle = Cache::new(Duration::from_secs(12), ProviderOracle::new(provider));
+/// // only the first call hits the node
+/// let gas_price = oracle.fetch().await?;
+/// let gas_price = oracle.fetch().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Cache<T> {
+    inner: T,
+    validity: Duration,
+    fee: Cached<U256>,
+    eip1559: Cached<(U256, U256)>,
+}
+
+#[derive(Default, Debug)]
+struct Cached<T>(Mutex<Option<(Instant, T)>>);
+
+impl<T: Clone> Cached<T> {
+    async fn get<F, Fut>(&self, validity: Duration, fetch: F) -> Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        // hold the lock while fetching so that concurrent callers wait for the same request
+        let mut cache = self.0.lock().await;
+        if let Some((last_fetch, ref value)) = *cache {
+            if last_fetch.elapsed() < validity {
+                return Ok(value.clone())
+            }
+        }
+
+        let value = fetch().await?;
+        *cache = Some((Instant::now(), value.clone()));
+        Ok(value)
+    }
+}
+
+impl<T: GasOracle> Cache<T> {
+    /// Creates a new cache of `inner`'s estimates, which are valid for `validity`
+    pub fn new(validity: Duration, inner: T) -> Self {
+        Self { inner, validity, fee: Cached::default(), eip1559: Cached::default() }
+    }
+
+    /// Returns the cached oracle
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<T: GasOracle> GasOracle for Cache<T> {
+    async fn fetch(&self) -> Result<U256> {
+        self.fee.get(self.validity, || self.inner.fetch()).await
+    }
+
+    async fn estimate_eip1559_fees(&self) -> Result<(U256, U256)> {
+        self.eip1559.get(self.validity, || self.inner.estimate_eip1559_fees()).await
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[derive(Debug, Default)]
+    struct CountingOracle(AtomicU64);
+
+    #[async_trait]
+    impl GasOracle for CountingOracle {
+        async fn fetch(&self) -> Result<U256> {
+            Ok(self.0.fetch_add(1, Ordering::SeqCst).into())
+        }
+
+        async fn estimate_eip1559_fees(&self) -> Result<(U256, U256)> {
+            let n = self.0.fetch_add(1, Ordering::SeqCst);
+            Ok((n.into(), n.into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn caches_estimates_until_they_expire() {
+        let oracle = Cache::new(Duration::from_millis(50), CountingOracle::default());
+
+        assert_eq!(oracle.fetch().await.unwrap(), 0.into());
+        assert_eq!(oracle.fetch().await.unwrap(), 0.into());
+        assert_eq!(oracle.estimate_eip1559_fees().await.unwrap(), (1.into(), 1.into()));
+        assert_eq!(oracle.estimate_eip1559_fees().await.unwrap(), (1.into(), 1.into()));
+
+        tokio::time::sleep(std::time::Duration::from_millis(60)).await;
+        assert_eq!(oracle.fetch().await.unwrap(), 2.into());
+    }
+}
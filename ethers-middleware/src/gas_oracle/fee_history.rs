@@ -0,0 +1,163 @@
+use super::{GasCategory, GasOracle, GasOracleError, Result};
+use async_trait::async_trait;
+use ethers_core::{
+    types::{BlockNumber, U256},
+    utils::EIP1559_FEE_ESTIMATION_PAST_BLOCKS,
+};
+use ethers_providers::Middleware;
+use std::fmt::Debug;
+
+/// The default percentile of the priority fees paid in each block.
+pub const DEFAULT_REWARD_PERCENTILE: f64 = 50.0;
+
+/// The default multiplier applied to the next block's base fee to compute the max fee per gas,
+/// which keeps the transaction includable over several blocks of rising base fees.
+pub const DEFAULT_BASE_FEE_MULTIPLIER: u64 = 2;
+
+/// A gas oracle which estimates fees from `eth_feeHistory`.
+///
+/// The priority fee is the average, over the last `block_count` blocks, of the priority fee paid
+/// at the configured percentile of each block, ignoring empty blocks. The max fee is the next
+/// block's base fee times the base fee multiplier, plus the priority fee.
+///
+/// # Example
+///
+/// ```no_run
+/// use ethers_middleware::gas_oracle::{FeeHistoryOracle, GasCategory, GasOracle};
+/// use ethers_providers::{Http, Provider};
+///
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let provider = Provider::<Http>::try_from("http://localhost:8545")?;
+/// let oracle = FeeHistoryOracle::new(provider).block_count(20).category(GasCategory::Fast);
+/// let (max_fee, priority_fee) = oracle.estimate_eip1559_fees().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+#[must_use]
+pub struct FeeHistoryOracle<M> {
+    provider: M,
+    block_count: u64,
+    percentile: f64,
+    base_fee_multiplier: u64,
+}
+
+impl<M: Middleware> FeeHistoryOracle<M> {
+    /// Creates a new oracle which looks at the last
+    /// [`EIP1559_FEE_ESTIMATION_PAST_BLOCKS`] blocks, at the
+    /// [`DEFAULT_REWARD_PERCENTILE`] of each block.
+    pub fn new(provider: M) -> Self {
+        Self {
+            provider,
+            block_count: EIP1559_FEE_ESTIMATION_PAST_BLOCKS,
+            percentile: DEFAULT_REWARD_PERCENTILE,
+            base_fee_multiplier: DEFAULT_BASE_FEE_MULTIPLIER,
+        }
+    }
+
+    /// Sets the number of blocks to look at
+    pub fn block_count(mut self, block_count: u64) -> Self {
+        self.block_count = block_count;
+        self
+    }
+
+    /// Sets the percentile, between 0 and 100, of the priority fees paid in each block
+    pub fn percentile(mut self, percentile: f64) -> Self {
+        self.percentile = percentile;
+        self
+    }
+
+    /// Sets the percentile from a gas category: 10 for [`GasCategory::SafeLow`], 50 for
+    /// [`GasCategory::Standard`], 75 for [`GasCategory::Fast`] and 95 for
+    /// [`GasCategory::Fastest`].
+    pub fn category(self, category: GasCategory) -> Self {
+        let percentile = match category {
+            GasCategory::SafeLow => 10.0,
+            GasCategory::Standard => 50.0,
+            GasCategory::Fast => 75.0,
+            GasCategory::Fastest => 95.0,
+        };
+        self.percentile(percentile)
+    }
+
+    /// Sets the multiplier applied to the next block's base fee
+    pub fn base_fee_multiplier(mut self, multiplier: u64) -> Self {
+        self.base_fee_multiplier = multiplier;
+        self
+    }
+
+    /// Returns the next block's base fee and the priority fee estimate
+    async fn fees(&self) -> Result<(U256, U256)>
+    where
+        M::Error: 'static,
+    {
+        let history = self
+            .provider
+            .fee_history(self.block_count, BlockNumber::Latest, &[self.percentile])
+            .await
+            .map_err(|err| GasOracleError::ProviderError(Box::new(err)))?;
+
+        // `eth_feeHistory` also returns the base fee of the block after the newest one
+        let base_fee = *history.base_fee_per_gas.last().ok_or(GasOracleError::EmptyResponse)?;
+
+        let rewards: Vec<U256> = history
+            .reward
+            .iter()
+            .filter_map(|block| block.first().copied())
+            .filter(|reward| !reward.is_zero())
+            .collect();
+        let priority_fee = if rewards.is_empty() {
+            U256::zero()
+        } else {
+            rewards.iter().fold(U256::zero(), |acc, reward| acc + reward) / rewards.len()
+        };
+
+        Ok((base_fee, priority_fee))
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<M: Middleware> GasOracle for FeeHistoryOracle<M>
+where
+    M::Error: 'static,
+{
+    async fn fetch(&self) -> Result<U256> {
+        let (base_fee, priority_fee) = self.fees().await?;
+        Ok(base_fee + priority_fee)
+    }
+
+    async fn estimate_eip1559_fees(&self) -> Result<(U256, U256)> {
+        let (base_fee, priority_fee) = self.fees().await?;
+        Ok((base_fee * self.base_fee_multiplier + priority_fee, priority_fee))
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use ethers_providers::{MockResponse, Provider};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn averages_rewards_of_non_empty_blocks() {
+        let (provider, mock) = Provider::mocked();
+        mock.on("eth_feeHistory", |params| {
+            assert_eq!(params[2], json!([75.0]));
+            MockResponse::Value(json!({
+                "oldestBlock": "0x10",
+                "baseFeePerGas": ["0x64", "0x6e", "0x78", "0x82"],
+                "gasUsedRatio": [0.5, 0.0, 0.7],
+                "reward": [["0xa"], ["0x0"], ["0x14"]]
+            }))
+        });
+
+        let oracle = FeeHistoryOracle::new(provider).block_count(3).category(GasCategory::Fast);
+        assert_eq!(oracle.fetch().await.unwrap(), U256::from(130 + 15));
+        assert_eq!(
+            oracle.estimate_eip1559_fees().await.unwrap(),
+            (U256::from(2 * 130 + 15), U256::from(15))
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use super::{GasCategory, GasOracle, GasOracleError, Result};
+use async_trait::async_trait;
+use auto_impl::auto_impl;
+use ethers_core::types::U256;
+use std::fmt::Debug;
+
+/// Fee estimates reported by a [`GasFeed`]. Fields which the feed does not report are `None`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FeedEstimate {
+    /// The gas price for Legacy and EIP-2930 transactions
+    pub gas_price: Option<U256>,
+    /// The max fee per gas for EIP-1559 transactions
+    pub max_fee_per_gas: Option<U256>,
+    /// The max priority fee per gas for EIP-1559 transactions
+    pub max_priority_fee_per_gas: Option<U256>,
+}
+
+/// A source of fee estimates outside of the node, e.g. a gas station API or an in-house fee
+/// service.
+///
+/// A feed only reports its estimates. Wrapping it in a [`FeedOracle`] turns it into a
+/// [`GasOracle`] and applies a fee policy on top: which category to use, fee caps and a fallback
+/// oracle for when the feed is unavailable.
+///
+/// # Example
+///
+/// ```
+/// use async_trait::async_trait;
+/// use ethers_core::types::U256;
+/// use ethers_middleware::gas_oracle::{
+///     FeedEstimate, FeedOracle, GasCategory, GasFeed, GasOracle, Result,
+/// };
+///
+/// #[derive(Debug)]
+/// struct FeeService;
+///
+/// #[async_trait]
+/// impl GasFeed for FeeService {
+///     async fn estimate(&self, category: GasCategory) -> Result<FeedEstimate> {
+///         // query the service here
+///         let gwei = U256::exp10(9);
+///         let priority_fee = match category {
+///             GasCategory::Fastest => gwei * 3,
+///             _ => gwei,
+///         };
+///         Ok(FeedEstimate {
+///             gas_price: Some(gwei * 30 + priority_fee),
+///             max_fee_per_gas: Some(gwei * 60 + priority_fee),
+///             max_priority_fee_per_gas: Some(priority_fee),
+///         })
+///     }
+/// }
+///
+/// # async fn foo() -> Result<()> {
+/// let oracle = FeedOracle::new(FeeService)
+///     .category(GasCategory::Fastest)
+///     .max_fee_per_gas(U256::exp10(9) * 50);
+/// let (max_fee, priority_fee) = oracle.estimate_eip1559_fees().await?;
+/// assert_eq!(max_fee, U256::exp10(9) * 50);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[auto_impl(&, Box, Arc)]
+pub trait GasFeed: Send + Sync + Debug {
+    /// Returns the feed's current estimates for `category`.
+    ///
+    /// Feeds which do not distinguish categories should return
+    /// [`GasOracleError::GasCategoryNotSupported`] for the ones they can not serve, and wrap
+    /// their own errors in [`GasOracleError::FeedError`].
+    async fn estimate(&self, category: GasCategory) -> Result<FeedEstimate>;
+}
+
+/// A [`GasOracle`] which gets its estimates from a [`GasFeed`].
+///
+/// All estimates are capped by the configured max fee and max priority fee. If the feed errors or
+/// does not report the requested fees, the fallback oracle is used, if any.
+#[derive(Debug)]
+#[must_use]
+pub struct FeedOracle<F> {
+    feed: F,
+    category: GasCategory,
+    max_fee_per_gas: Option<U256>,
+    max_priority_fee_per_gas: Option<U256>,
+    fallback: Option<Box<dyn GasOracle>>,
+}
+
+impl<F: GasFeed> FeedOracle<F> {
+    /// Creates a new oracle which uses the [`GasCategory::Standard`] estimates of `feed`
+    pub fn new(feed: F) -> Self {
+        Self {
+            feed,
+            category: GasCategory::default(),
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            fallback: None,
+        }
+    }
+
+    /// Sets the gas category to request from the feed
+    pub fn category(mut self, category: GasCategory) -> Self {
+        self.category = category;
+        self
+    }
+
+    /// Caps the gas price and the max fee per gas
+    pub fn max_fee_per_gas<T: Into<U256>>(mut self, max_fee_per_gas: T) -> Self {
+        self.max_fee_per_gas = Some(max_fee_per_gas.into());
+        self
+    }
+
+    /// Caps the max priority fee per gas
+    pub fn max_priority_fee_per_gas<T: Into<U256>>(mut self, max_priority_fee_per_gas: T) -> Self {
+        self.max_priority_fee_per_gas = Some(max_priority_fee_per_gas.into());
+        self
+    }
+
+    /// Sets the oracle to use when the feed errors or does not report the requested fees
+    pub fn fallback<G: GasOracle + 'static>(mut self, fallback: G) -> Self {
+        self.fallback = Some(Box::new(fallback));
+        self
+    }
+
+    /// Returns the feed
+    pub fn feed(&self) -> &F {
+        &self.feed
+    }
+
+    fn cap(fee: U256, max: Option<U256>) -> U256 {
+        max.map_or(fee, |max| std::cmp::min(fee, max))
+    }
+
+    /// Returns the fallback oracle or `err` if there is none
+    fn fallback_or(&self, err: GasOracleError) -> Result<&dyn GasOracle> {
+        match self.fallback {
+            Some(ref fallback) => {
+                tracing::debug!(err = %err, "gas feed unavailable, using fallback oracle");
+                Ok(fallback.as_ref())
+            }
+            None => Err(err),
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<F: GasFeed> GasOracle for FeedOracle<F> {
+    async fn fetch(&self) -> Result<U256> {
+        let gas_price = match self.feed.estimate(self.category).await {
+            Ok(FeedEstimate { gas_price: Some(gas_price), .. }) => gas_price,
+            Ok(_) => self.fallback_or(GasOracleError::EmptyResponse)?.fetch().await?,
+            Err(err) => self.fallback_or(err)?.fetch().await?,
+        };
+        Ok(Self::cap(gas_price, self.max_fee_per_gas))
+    }
+
+    async fn estimate_eip1559_fees(&self) -> Result<(U256, U256)> {
+        let (max_fee, priority_fee) = match self.feed.estimate(self.category).await {
+            Ok(FeedEstimate {
+                max_fee_per_gas: Some(max_fee),
+                max_priority_fee_per_gas: Some(priority_fee),
+                ..
+            }) => (max_fee, priority_fee),
+            Ok(_) => {
+                self.fallback_or(GasOracleError::Eip1559EstimationNotSupported)?
+                    .estimate_eip1559_fees()
+                    .await?
+            }
+            Err(err) => self.fallback_or(err)?.estimate_eip1559_fees().await?,
+        };
+        let max_fee = Self::cap(max_fee, self.max_fee_per_gas);
+        let priority_fee = Self::cap(priority_fee, self.max_priority_fee_per_gas);
+        Ok((max_fee, std::cmp::min(priority_fee, max_fee)))
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct LegacyFeed;
+
+    #[async_trait]
+    impl GasFeed for LegacyFeed {
+        async fn estimate(&self, category: GasCategory) -> Result<FeedEstimate> {
+            match category {
+                GasCategory::Fastest => Err(GasOracleError::GasCategoryNotSupported),
+                _ => Ok(FeedEstimate { gas_price: Some(100.into()), ..Default::default() }),
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    struct FixedOracle;
+
+    #[async_trait]
+    impl GasOracle for FixedOracle {
+        async fn fetch(&self) -> Result<U256> {
+            Ok(50.into())
+        }
+
+        async fn estimate_eip1559_fees(&self) -> Result<(U256, U256)> {
+            Ok((300.into(), 20.into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn applies_caps() {
+        let oracle = FeedOracle::new(LegacyFeed).max_fee_per_gas(80);
+        assert_eq!(oracle.fetch().await.unwrap(), 80.into());
+    }
+
+    #[tokio::test]
+    async fn falls_back_when_feed_is_unavailable() {
+        let oracle = FeedOracle::new(LegacyFeed).category(GasCategory::Fastest);
+        assert!(matches!(oracle.fetch().await, Err(GasOracleError::GasCategoryNotSupported)));
+        assert!(matches!(
+            FeedOracle::new(LegacyFeed).estimate_eip1559_fees().await,
+            Err(GasOracleError::Eip1559EstimationNotSupported)
+        ));
+
+        let oracle = FeedOracle::new(LegacyFeed)
+            .category(GasCategory::Fastest)
+            .max_fee_per_gas(250)
+            .max_priority_fee_per_gas(10)
+            .fallback(FixedOracle);
+        assert_eq!(oracle.fetch().await.unwrap(), 50.into());
+        assert_eq!(oracle.estimate_eip1559_fees().await.unwrap(), (250.into(), 10.into()));
+    }
+}
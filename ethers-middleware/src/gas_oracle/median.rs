@@ -0,0 +1,143 @@
+use super::{median, GasOracle, GasOracleError, Result};
+use async_trait::async_trait;
+use ethers_core::{
+    types::{Transaction, U256},
+    utils::EIP1559_FEE_ESTIMATION_PAST_BLOCKS,
+};
+use ethers_providers::Middleware;
+use futures_util::future::try_join_all;
+use std::fmt::Debug;
+
+/// A gas oracle which uses the median of the fees paid by the transactions of recent blocks.
+///
+/// On EIP-1559 chains, the priority fee is the median of the effective priority fees paid in the
+/// last `block_count` blocks, and the max fee is twice the next block's base fee plus the
+/// priority fee. On other chains, the gas price is the median of the gas prices paid, and EIP-1559
+/// estimation is not supported.
+///
+/// # Example
+///
+/// ```no_run
+/// use ethers_middleware::gas_oracle::{BlockMedian, GasOracle};
+/// use ethers_providers::{Http, Provider};
+///
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let provider = Provider::<Http>::try_from("http://localhost:8545")?;
+/// let oracle = BlockMedian::new(provider).block_count(5);
+/// let gas_price = oracle.fetch().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+#[must_use]
+pub struct BlockMedian<M> {
+    provider: M,
+    block_count: u64,
+}
+
+impl<M: Middleware> BlockMedian<M> {
+    /// Creates a new oracle which looks at the last [`EIP1559_FEE_ESTIMATION_PAST_BLOCKS`]
+    /// blocks
+    pub fn new(provider: M) -> Self {
+        Self { provider, block_count: EIP1559_FEE_ESTIMATION_PAST_BLOCKS }
+    }
+
+    /// Sets the number of blocks to look at
+    pub fn block_count(mut self, block_count: u64) -> Self {
+        self.block_count = block_count;
+        self
+    }
+
+    /// Returns the next block's base fee, if the chain supports EIP-1559, and the median of the
+    /// priority fees, or of the gas prices otherwise
+    async fn sample(&self) -> Result<(Option<U256>, U256)>
+    where
+        M::Error: 'static,
+    {
+        let provider_err = |err: M::Error| GasOracleError::ProviderError(Box::new(err));
+
+        let latest = self.provider.get_block_number().await.map_err(provider_err)?.as_u64();
+        let count = self.block_count.clamp(1, latest + 1);
+        let blocks = try_join_all((0..count).map(|i| self.provider.get_block_with_txs(latest - i)))
+            .await
+            .map_err(provider_err)?;
+        let blocks: Vec<_> = blocks.into_iter().flatten().collect();
+
+        let latest = blocks.first().ok_or(GasOracleError::EmptyResponse)?;
+        #[cfg(not(feature = "celo"))]
+        let next_base_fee = latest.next_block_base_fee();
+        #[cfg(feature = "celo")]
+        let next_base_fee = latest.base_fee_per_gas;
+
+        let fees = blocks
+            .iter()
+            .flat_map(|block| {
+                block.transactions.iter().filter_map(move |tx| paid_fee(tx, block.base_fee_per_gas))
+            })
+            .collect();
+        let fee = median(fees).unwrap_or_default();
+
+        Ok((next_base_fee, fee))
+    }
+}
+
+/// Returns the effective priority fee paid by `tx` in a block with `base_fee`, or its gas price
+/// in a block without base fee.
+fn paid_fee(tx: &Transaction, base_fee: Option<U256>) -> Option<U256> {
+    let Some(base_fee) = base_fee else { return tx.gas_price };
+    let tip = match (tx.max_fee_per_gas, tx.max_priority_fee_per_gas) {
+        (Some(max_fee), Some(priority_fee)) => {
+            std::cmp::min(priority_fee, max_fee.saturating_sub(base_fee))
+        }
+        _ => tx.gas_price?.saturating_sub(base_fee),
+    };
+    Some(tip)
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<M: Middleware> GasOracle for BlockMedian<M>
+where
+    M::Error: 'static,
+{
+    async fn fetch(&self) -> Result<U256> {
+        let (base_fee, fee) = self.sample().await?;
+        Ok(base_fee.unwrap_or_default() + fee)
+    }
+
+    async fn estimate_eip1559_fees(&self) -> Result<(U256, U256)> {
+        let (base_fee, priority_fee) = self.sample().await?;
+        let base_fee = base_fee.ok_or(GasOracleError::Eip1559EstimationNotSupported)?;
+        Ok((base_fee * 2 + priority_fee, priority_fee))
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_paid_fees() {
+        let legacy = Transaction { gas_price: Some(150.into()), ..Default::default() };
+        let eip1559 = Transaction {
+            gas_price: Some(130.into()),
+            max_fee_per_gas: Some(140.into()),
+            max_priority_fee_per_gas: Some(30.into()),
+            ..Default::default()
+        };
+
+        assert_eq!(paid_fee(&legacy, None), Some(150.into()));
+        assert_eq!(paid_fee(&legacy, Some(100.into())), Some(50.into()));
+        assert_eq!(paid_fee(&eip1559, Some(100.into())), Some(30.into()));
+        // the tip is limited by the max fee
+        assert_eq!(paid_fee(&eip1559, Some(120.into())), Some(20.into()));
+    }
+
+    #[test]
+    fn median_of_fees() {
+        assert_eq!(median(vec![]), None);
+        assert_eq!(median(vec![3.into(), 1.into(), 2.into()]), Some(2.into()));
+        assert_eq!(median(vec![4.into(), 1.into(), 2.into(), 3.into()]), Some(2.into()));
+    }
+}
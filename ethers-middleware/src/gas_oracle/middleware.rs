@@ -0,0 +1,191 @@
+use super::{GasOracle, GasOracleError};
+use async_trait::async_trait;
+use ethers_core::types::{transaction::eip2718::TypedTransaction, *};
+use ethers_providers::{Middleware, MiddlewareError as METrait, PendingTransaction};
+use thiserror::Error;
+
+/// Middleware used for fetching gas prices from a [`GasOracle`] instead of `eth_gasPrice`.
+///
+/// Missing fees of sent or filled transactions are set from the [`GasOracle`]: the gas price of
+/// Legacy and EIP-2930 transactions, and the max fee and max priority fee of EIP-1559
+/// transactions. Fees which are already set are left untouched.
+#[derive(Debug)]
+pub struct GasOracleMiddleware<M, G> {
+    inner: M,
+    gas_oracle: G,
+}
+
+impl<M, G> GasOracleMiddleware<M, G>
+where
+    M: Middleware,
+    G: GasOracle,
+{
+    /// Creates a new middleware which uses `gas_oracle` for fee estimation
+    pub fn new(inner: M, gas_oracle: G) -> Self {
+        GasOracleMiddleware { inner, gas_oracle }
+    }
+
+    /// Returns the gas oracle
+    pub fn gas_oracle(&self) -> &G {
+        &self.gas_oracle
+    }
+}
+
+#[derive(Debug, Error)]
+/// Error thrown when the client interacts with the gas oracle middleware.
+pub enum GasOracleMiddlewareError<M: Middleware> {
+    /// Thrown when the gas oracle errors
+    #[error(transparent)]
+    GasOracleError(#[from] GasOracleError),
+
+    /// Thrown when an internal middleware errors
+    #[error("{0}")]
+    MiddlewareError(M::Error),
+
+    /// Thrown when the transaction type does not carry fees the gas oracle can fill
+    #[error("This gas price oracle only works with Legacy, EIP2930 and EIP1559 transactions.")]
+    UnsupportedTxType,
+}
+
+impl<M: Middleware> METrait for GasOracleMiddlewareError<M> {
+    type Inner = M::Error;
+
+    fn from_err(src: M::Error) -> Self {
+        GasOracleMiddlewareError::MiddlewareError(src)
+    }
+
+    fn as_inner(&self) -> Option<&Self::Inner> {
+        match self {
+            GasOracleMiddlewareError::MiddlewareError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<M, G> Middleware for GasOracleMiddleware<M, G>
+where
+    M: Middleware,
+    G: GasOracle,
+{
+    type Error = GasOracleMiddlewareError<M>;
+    type Provider = M::Provider;
+    type Inner = M;
+
+    // OVERRIDEN METHODS
+
+    fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    async fn fill_transaction(
+        &self,
+        tx: &mut TypedTransaction,
+        block: Option<BlockId>,
+    ) -> Result<(), Self::Error> {
+        match tx {
+            TypedTransaction::Legacy(ref mut tx) => {
+                if tx.gas_price.is_none() {
+                    tx.gas_price = Some(self.get_gas_price().await?);
+                }
+            }
+            TypedTransaction::Eip2930(ref mut inner) => {
+                if inner.tx.gas_price.is_none() {
+                    inner.tx.gas_price = Some(self.get_gas_price().await?);
+                }
+            }
+            TypedTransaction::Eip1559(ref mut inner) => {
+                if inner.max_priority_fee_per_gas.is_none() || inner.max_fee_per_gas.is_none() {
+                    let (max_fee_per_gas, max_priority_fee_per_gas) =
+                        self.estimate_eip1559_fees(None).await?;
+                    // keep the fees set by the caller, but never let the tip exceed the max fee
+                    let mfpg = inner.max_fee_per_gas.get_or_insert(max_fee_per_gas);
+                    inner.max_priority_fee_per_gas = Some(
+                        inner
+                            .max_priority_fee_per_gas
+                            .unwrap_or(max_priority_fee_per_gas)
+                            .min(*mfpg),
+                    );
+                }
+            }
+            #[cfg(feature = "optimism")]
+            TypedTransaction::OptimismDeposited(_) => {
+                return Err(GasOracleMiddlewareError::UnsupportedTxType)
+            }
+        };
+
+        self.inner().fill_transaction(tx, block).await.map_err(METrait::from_err)
+    }
+
+    async fn get_gas_price(&self) -> Result<U256, Self::Error> {
+        Ok(self.gas_oracle.fetch().await?)
+    }
+
+    async fn estimate_eip1559_fees(
+        &self,
+        _: Option<fn(U256, Vec<Vec<U256>>) -> (U256, U256)>,
+    ) -> Result<(U256, U256), Self::Error> {
+        Ok(self.gas_oracle.estimate_eip1559_fees().await?)
+    }
+
+    async fn send_transaction<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        tx: T,
+        block: Option<BlockId>,
+    ) -> Result<PendingTransaction<'_, Self::Provider>, Self::Error> {
+        let mut tx = tx.into();
+        self.fill_transaction(&mut tx, block).await?;
+        self.inner.send_transaction(tx, block).await.map_err(METrait::from_err)
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::gas_oracle::Result;
+    use ethers_providers::Provider;
+
+    #[derive(Debug)]
+    struct FixedOracle;
+
+    #[async_trait]
+    impl GasOracle for FixedOracle {
+        async fn fetch(&self) -> Result<U256> {
+            Ok(100.into())
+        }
+
+        async fn estimate_eip1559_fees(&self) -> Result<(U256, U256)> {
+            Ok((200.into(), 3.into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn fills_missing_fees() {
+        let (provider, _mock) = Provider::mocked();
+        let provider = GasOracleMiddleware::new(provider, FixedOracle);
+        let filled = |mut tx: TypedTransaction| async {
+            tx.set_from(Address::zero()).set_gas(21_000).set_nonce(0);
+            provider.fill_transaction(&mut tx, None).await.unwrap();
+            tx
+        };
+
+        let tx = filled(TransactionRequest::new().into()).await;
+        assert_eq!(tx.gas_price(), Some(100.into()));
+
+        let tx = filled(TransactionRequest::new().gas_price(7).into()).await;
+        assert_eq!(tx.gas_price(), Some(7.into()));
+
+        let tx = filled(Eip1559TransactionRequest::new().max_priority_fee_per_gas(5).into()).await;
+        let tx = tx.as_eip1559_ref().unwrap();
+        assert_eq!(tx.max_fee_per_gas, Some(200.into()));
+        assert_eq!(tx.max_priority_fee_per_gas, Some(5.into()));
+
+        // the oracle's tip is clamped to the max fee set by the caller
+        let tx = filled(Eip1559TransactionRequest::new().max_fee_per_gas(2).into()).await;
+        let tx = tx.as_eip1559_ref().unwrap();
+        assert_eq!(tx.max_fee_per_gas, Some(2.into()));
+        assert_eq!(tx.max_priority_fee_per_gas, Some(2.into()));
+    }
+}
@@ -0,0 +1,152 @@
+pub mod cache;
+pub use cache::Cache;
+
+pub mod fee_history;
+pub use fee_history::FeeHistoryOracle;
+
+pub mod feed;
+pub use feed::{FeedEstimate, FeedOracle, GasFeed};
+
+pub mod median;
+pub use median::BlockMedian;
+
+pub mod middleware;
+pub use middleware::{GasOracleMiddleware, GasOracleMiddlewareError};
+
+pub mod provider_oracle;
+pub use provider_oracle::ProviderOracle;
+
+use async_trait::async_trait;
+use auto_impl::auto_impl;
+use ethers_core::types::U256;
+use std::{error::Error, fmt::Debug};
+use thiserror::Error;
+
+pub type Result<T, E = GasOracleError> = std::result::Result<T, E>;
+
+/// Generic [`GasOracle`] gas price categories.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum GasCategory {
+    SafeLow,
+    #[default]
+    Standard,
+    Fast,
+    Fastest,
+}
+
+/// Error thrown by a [`GasOracle`].
+#[derive(Debug, Error)]
+pub enum GasOracleError {
+    /// An internal error in the HTTP request made from the underlying
+    /// gas oracle
+    #[error(transparent)]
+    HttpClientError(#[from] reqwest::Error),
+
+    /// An error decoding JSON response from gas oracle
+    #[error(transparent)]
+    SerdeJsonError(#[from] serde_json::Error),
+
+    /// An internal error thrown when the required gas category is not
+    /// supported by the gas oracle API
+    #[error("gas category not supported")]
+    GasCategoryNotSupported,
+
+    /// Thrown when the gas oracle can not estimate EIP-1559 fees
+    #[error("EIP-1559 gas estimation not supported")]
+    Eip1559EstimationNotSupported,
+
+    /// Thrown when the provider or the feed returned no data to estimate from
+    #[error("empty response from gas oracle")]
+    EmptyResponse,
+
+    /// Thrown when the provider or the feed returned inconsistent data
+    #[error("invalid response from gas oracle: {0}")]
+    InvalidResponse(String),
+
+    /// Error thrown by the provider
+    #[error(transparent)]
+    ProviderError(Box<dyn Error + Send + Sync>),
+
+    /// Error thrown by an external [`GasFeed`]
+    #[error(transparent)]
+    FeedError(Box<dyn Error + Send + Sync>),
+}
+
+/// An Ethereum gas price oracle.
+///
+/// Implementations either estimate fees locally from the chain state, e.g. [`FeeHistoryOracle`]
+/// and [`BlockMedian`], or wrap other oracles, e.g. [`Cache`]. External price services plug in
+/// through [`GasFeed`].
+///
+/// # Example
+///
+/// ```no_run
+/// use ethers_core::types::U256;
+/// use ethers_middleware::gas_oracle::{FeeHistoryOracle, GasOracle};
+/// use ethers_providers::{Http, Provider};
+///
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let provider = Provider::<Http>::try_from("http://localhost:8545")?;
+/// let oracle = FeeHistoryOracle::new(provider).percentile(25.0);
+/// let gas_price = oracle.fetch().await?;
+/// assert!(gas_price > U256::zero());
+/// # Ok(())
+/// # }
+/// ```
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[auto_impl(&, Box, Arc)]
+pub trait GasOracle: Send + Sync + Debug {
+    /// Fetches the current gas price estimate for Legacy and EIP-2930 transactions.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ethers_core::types::U256;
+    /// use ethers_middleware::gas_oracle::{BlockMedian, GasOracle};
+    /// use ethers_providers::{Http, Provider};
+    ///
+    /// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+    /// let provider = Provider::<Http>::try_from("http://localhost:8545")?;
+    /// let oracle = BlockMedian::new(provider);
+    /// let gas_price = oracle.fetch().await?;
+    /// assert!(gas_price > U256::zero());
+    /// # Ok(())
+    /// # }
+    /// ```
+    async fn fetch(&self) -> Result<U256>;
+
+    /// Estimates the fees of EIP-1559 transactions, returning
+    /// `(max_fee_per_gas, max_priority_fee_per_gas)`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ethers_middleware::gas_oracle::{FeeHistoryOracle, GasOracle};
+    /// use ethers_providers::{Http, Provider};
+    ///
+    /// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+    /// let provider = Provider::<Http>::try_from("http://localhost:8545")?;
+    /// let oracle = FeeHistoryOracle::new(provider);
+    /// let (max_fee, priority_fee) = oracle.estimate_eip1559_fees().await?;
+    /// assert!(max_fee >= priority_fee);
+    /// # Ok(())
+    /// # }
+    /// ```
+    async fn estimate_eip1559_fees(&self) -> Result<(U256, U256)>;
+}
+
+/// Returns the median of `values`, rounding down between the two middle values. Returns `None`
+/// if `values` is empty.
+pub(crate) fn median(mut values: Vec<U256>) -> Option<U256> {
+    if values.is_empty() {
+        return None
+    }
+    values.sort_unstable();
+    let mid = values.len() / 2;
+    if values.len() % 2 == 0 {
+        Some((values[mid - 1] + values[mid]) / 2)
+    } else {
+        Some(values[mid])
+    }
+}
@@ -8,11 +8,33 @@
 pub mod gas_escalator;
 pub use gas_escalator::GasEscalatorMiddleware;
 
+/// The [Gas Oracle middleware](crate::gas_oracle::GasOracleMiddleware) is used to fill
+/// transactions' fees from a [gas oracle](crate::gas_oracle::GasOracle) instead of
+/// `eth_gasPrice`.
+pub mod gas_oracle;
+pub use gas_oracle::GasOracleMiddleware;
+
 /// The [Nonce Manager](crate::NonceManagerMiddleware) is used to locally calculate nonces instead
 /// of using eth_getTransactionCount
 pub mod nonce_manager;
 pub use nonce_manager::NonceManagerMiddleware;
 
+/// The [TxManager](crate::tx_manager::TxManager) owns every outbound transaction of a signer:
+/// it queues them by priority, assigns nonces, broadcasts them to multiple endpoints and bumps the
+/// fees of stuck transactions.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tx_manager;
+
+/// The [SimulateBeforeSend](crate::simulate::SimulateBeforeSend) middleware simulates
+/// transactions on the pending block and refuses to send those which revert.
+pub mod simulate;
+pub use simulate::SimulateBeforeSend;
+
+/// The [Bundle middleware](crate::bundle::BundleMiddleware) submits bundles and private
+/// transactions to a Flashbots-style relay.
+pub mod bundle;
+pub use bundle::BundleMiddleware;
+
 /// The [TransformerMiddleware] is used to intercept transactions
 /// and transform them to be sent via various supported transformers, e.g.,
 /// [DSProxy](crate::transformer::DsProxy).
@@ -24,6 +46,11 @@ pub use transformer::TransformerMiddleware;
 pub mod signer;
 pub use signer::SignerMiddleware;
 
+/// The [MultiSignerMiddleware] signs transactions with one of a set of signers, picked from the
+/// transaction's `from` or by a [selection](crate::multi_signer::Selection) strategy.
+pub mod multi_signer;
+pub use multi_signer::MultiSignerMiddleware;
+
 /// The [Policy] is used to ensure transactions comply with the rules configured in the
 /// [`PolicyMiddleware`] before sending them.
 pub mod policy;
@@ -42,6 +69,9 @@ pub use builder::MiddlewareBuilder;
 
 pub use ethers_providers::{Middleware, MiddlewareError};
 
+#[cfg(test)]
+mod test_utils;
+
 // For macro expansions only, not public API.
 // See: [#2235](https://github.com/gakonst/ethers-rs/pull/2235)
 
@@ -0,0 +1,662 @@
+use crate::nonce_manager::{is_nonce_error, AccountNonce};
+use async_trait::async_trait;
+use ethers_core::types::{
+    transaction::eip2718::TypedTransaction, Address, BlockId, BlockNumber, Bytes, Chain, Signature,
+    TransactionRequest, U256,
+};
+use ethers_providers::{Middleware, MiddlewareError, PendingTransaction};
+use ethers_signers::Signer;
+use futures_util::future::try_join_all;
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use thiserror::Error;
+
+/// How a [`MultiSignerMiddleware`] picks the signer of transactions without `from`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Selection {
+    /// Cycles through the signers in the order they were added
+    #[default]
+    RoundRobin,
+    /// Picks the signer with the fewest sent transactions which are not mined yet, the first one
+    /// on ties. This queries the mined transaction count of every signer which already sent a
+    /// transaction.
+    LeastBusy,
+}
+
+#[derive(Debug)]
+struct SignerState<S> {
+    signer: S,
+    /// The nonces of the signer, initialized from its pending transaction count
+    nonce: AccountNonce,
+}
+
+/// Middleware signing transactions with one of a set of signers, keyed by address.
+///
+/// Transactions are signed by the signer matching their `from`. Transactions without `from` are
+/// assigned a signer according to the [`Selection`], and transactions from other addresses are
+/// delegated to the inner middleware.
+///
+/// The nonces of each signer are tracked locally, starting from its pending transaction count.
+/// If broadcasting a transaction fails, its nonce is handed out again. If the node rejects a
+/// transaction because of its nonce, the signer's nonce is resynced and the transaction is resent
+/// once.
+///
+/// # Example
+///
+/// ```no_run
+/// use ethers_core::types::{Address, TransactionRequest};
+/// use ethers_middleware::{multi_signer::Selection, MultiSignerMiddleware};
+/// use ethers_providers::{Http, Middleware, Provider};
+/// use ethers_signers::{LocalWallet, Signer};
+///
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let provider = Provider::<Http>::try_from("http://localhost:8545")?;
+/// let wallets: Vec<LocalWallet> =
+///     (0..50).map(|_| LocalWallet::new(&mut rand::thread_rng()).with_chain_id(1u64)).collect();
+/// let alice = wallets[0].address();
+///
+/// let client = MultiSignerMiddleware::new(provider, wallets).with_selection(Selection::LeastBusy);
+///
+/// // signed by alice
+/// client.send_transaction(TransactionRequest::pay(Address::random(), 100).from(alice), None).await?;
+/// // signed by the least busy wallet
+/// client.send_transaction(TransactionRequest::pay(Address::random(), 100), None).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MultiSignerMiddleware<M, S> {
+    inner: M,
+    signers: Vec<SignerState<S>>,
+    index: HashMap<Address, usize>,
+    selection: Selection,
+    cursor: AtomicUsize,
+}
+
+#[derive(Error, Debug)]
+/// Thrown when an error happens at the [`MultiSignerMiddleware`]
+pub enum MultiSignerMiddlewareError<M: Middleware, S: Signer> {
+    #[error("{0}")]
+    /// Thrown when the internal call to a signer fails
+    SignerError(S::Error),
+
+    #[error("{0}")]
+    /// Thrown when an internal middleware errors
+    MiddlewareError(M::Error),
+
+    /// Thrown when selecting a signer from an empty set
+    #[error("no signers were provided")]
+    NoSigners,
+
+    /// Thrown when managing the nonce of an address which is not one of the signers
+    #[error("{0:?} is not a signer")]
+    UnknownSigner(Address),
+
+    /// Thrown if the signer's chain_id is different than the chain_id of the transaction
+    #[error("specified chain_id is different than the signer's chain_id")]
+    DifferentChainID,
+}
+
+impl<M: Middleware, S: Signer> MiddlewareError for MultiSignerMiddlewareError<M, S> {
+    type Inner = M::Error;
+
+    fn from_err(src: M::Error) -> Self {
+        MultiSignerMiddlewareError::MiddlewareError(src)
+    }
+
+    fn as_inner(&self) -> Option<&Self::Inner> {
+        match self {
+            MultiSignerMiddlewareError::MiddlewareError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl<M, S> MultiSignerMiddleware<M, S>
+where
+    M: Middleware,
+    S: Signer,
+{
+    /// Creates a middleware signing with `signers`. If several signers have the same address, the
+    /// first one is used.
+    pub fn new(inner: M, signers: impl IntoIterator<Item = S>) -> Self {
+        let mut this = Self {
+            inner,
+            signers: Vec::new(),
+            index: HashMap::new(),
+            selection: Selection::default(),
+            cursor: AtomicUsize::new(0),
+        };
+        for signer in signers {
+            this = this.with_signer(signer);
+        }
+        this
+    }
+
+    /// Adds `signer` to the set, unless a signer with the same address is already in it
+    #[must_use]
+    pub fn with_signer(mut self, signer: S) -> Self {
+        let address = signer.address();
+        if !self.index.contains_key(&address) {
+            self.index.insert(address, self.signers.len());
+            self.signers.push(SignerState { signer, nonce: AccountNonce::default() });
+        }
+        self
+    }
+
+    /// Sets how the signer of transactions without `from` is picked
+    #[must_use]
+    pub fn with_selection(mut self, selection: Selection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Returns the addresses of the signers, in the order they were added
+    pub fn addresses(&self) -> Vec<Address> {
+        self.signers.iter().map(|state| state.signer.address()).collect()
+    }
+
+    /// Returns the signer of `address`, if any
+    pub fn signer(&self, address: &Address) -> Option<&S> {
+        self.state(address).map(|state| &state.signer)
+    }
+
+    fn state(&self, address: &Address) -> Option<&SignerState<S>> {
+        self.index.get(address).map(|&i| &self.signers[i])
+    }
+
+    fn known_state(
+        &self,
+        address: Address,
+    ) -> Result<&SignerState<S>, MultiSignerMiddlewareError<M, S>> {
+        self.state(&address).ok_or(MultiSignerMiddlewareError::UnknownSigner(address))
+    }
+
+    /// Returns the next nonce of the signer of `address`, syncing it with its pending transaction
+    /// count the first time
+    pub async fn next_nonce(
+        &self,
+        address: Address,
+    ) -> Result<U256, MultiSignerMiddlewareError<M, S>> {
+        let state = self.known_state(address)?;
+        if !state.nonce.is_initialized() {
+            let _guard = state.nonce.init_guard.lock().await;
+            // another call may have synced it while we were waiting
+            if !state.nonce.is_initialized() {
+                state.nonce.reset(self.pending_count(address).await?);
+            }
+        }
+        Ok(state.nonce.next().into())
+    }
+
+    /// Resets the nonce of the signer of `address` to its pending transaction count, and returns
+    /// it
+    pub async fn resync(&self, address: Address) -> Result<U256, MultiSignerMiddlewareError<M, S>> {
+        let state = self.known_state(address)?;
+        let _guard = state.nonce.init_guard.lock().await;
+        let nonce = self.pending_count(address).await?;
+        state.nonce.reset(nonce);
+        Ok(nonce.into())
+    }
+
+    async fn pending_count(
+        &self,
+        address: Address,
+    ) -> Result<u64, MultiSignerMiddlewareError<M, S>> {
+        let count = self
+            .inner
+            .get_transaction_count(address, Some(BlockNumber::Pending.into()))
+            .await
+            .map_err(MultiSignerMiddlewareError::MiddlewareError)?;
+        Ok(count.as_u64())
+    }
+
+    /// Returns the address of the signer for the next transaction without `from`
+    pub async fn select(&self) -> Result<Address, MultiSignerMiddlewareError<M, S>> {
+        self.pick(true).await
+    }
+
+    /// Returns the address of the signer for the next transaction without `from`, moving the
+    /// round-robin cursor past it if `advance` is set
+    async fn pick(&self, advance: bool) -> Result<Address, MultiSignerMiddlewareError<M, S>> {
+        if self.signers.is_empty() {
+            return Err(MultiSignerMiddlewareError::NoSigners)
+        }
+
+        let index = match self.selection {
+            Selection::RoundRobin if advance => {
+                self.cursor.fetch_add(1, Ordering::SeqCst) % self.signers.len()
+            }
+            Selection::RoundRobin => self.cursor.load(Ordering::SeqCst) % self.signers.len(),
+            Selection::LeastBusy => self.least_busy().await?,
+        };
+        Ok(self.signers[index].signer.address())
+    }
+
+    /// Returns the index of the signer with the fewest unmined transactions. The nonces are read
+    /// without waiting for signers whose nonce is being synced.
+    async fn least_busy(&self) -> Result<usize, MultiSignerMiddlewareError<M, S>> {
+        let mut used = Vec::with_capacity(self.signers.len());
+        for (i, state) in self.signers.iter().enumerate() {
+            if !state.nonce.is_initialized() {
+                // never sent anything through us
+                return Ok(i)
+            }
+            // released nonces are not used by any transaction
+            let (next, released) = state.nonce.snapshot();
+            used.push((i, next - released.len() as u64));
+        }
+
+        let mined = try_join_all(used.iter().map(|&(i, _)| {
+            self.inner.get_transaction_count(
+                self.signers[i].signer.address(),
+                Some(BlockNumber::Latest.into()),
+            )
+        }))
+        .await
+        .map_err(MultiSignerMiddlewareError::MiddlewareError)?;
+
+        let (index, _) = used
+            .iter()
+            .zip(mined)
+            .map(|(&(i, next), mined)| (i, next.saturating_sub(mined.as_u64())))
+            .min_by_key(|&(i, busy)| (busy, i))
+            .expect("there is at least a signer");
+        Ok(index)
+    }
+
+    /// Signs and returns the RLP encoding of the signed transaction, setting its chain id to the
+    /// signer's if it does not have one
+    async fn sign_with(
+        &self,
+        state: &SignerState<S>,
+        mut tx: TypedTransaction,
+    ) -> Result<Bytes, MultiSignerMiddlewareError<M, S>> {
+        let chain_id = state.signer.chain_id();
+        match tx.chain_id() {
+            Some(id) if id.as_u64() != chain_id => {
+                return Err(MultiSignerMiddlewareError::DifferentChainID)
+            }
+            None => {
+                tx.set_chain_id(chain_id);
+            }
+            _ => {}
+        }
+
+        let signature = state
+            .signer
+            .sign_transaction(&tx)
+            .await
+            .map_err(MultiSignerMiddlewareError::SignerError)?;
+        Ok(tx.rlp_signed(&signature))
+    }
+
+    async fn fill_sign_and_send(
+        &self,
+        state: &SignerState<S>,
+        tx: &mut TypedTransaction,
+        block: Option<BlockId>,
+    ) -> Result<PendingTransaction<'_, M::Provider>, MultiSignerMiddlewareError<M, S>> {
+        self.fill_transaction(tx, block).await?;
+        let signed_tx = self.sign_with(state, tx.clone()).await?;
+        self.inner
+            .send_raw_transaction(signed_tx)
+            .await
+            .map_err(MultiSignerMiddlewareError::MiddlewareError)
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<M, S> Middleware for MultiSignerMiddleware<M, S>
+where
+    M: Middleware,
+    S: Signer,
+{
+    type Error = MultiSignerMiddlewareError<M, S>;
+    type Provider = M::Provider;
+    type Inner = M;
+
+    fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    /// `MultiSignerMiddleware` is instantiated with signers.
+    async fn is_signer(&self) -> bool {
+        true
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &TypedTransaction,
+        from: Address,
+    ) -> Result<Signature, Self::Error> {
+        match self.state(&from) {
+            Some(state) => state
+                .signer
+                .sign_transaction(tx)
+                .await
+                .map_err(MultiSignerMiddlewareError::SignerError),
+            None => self
+                .inner
+                .sign_transaction(tx, from)
+                .await
+                .map_err(MultiSignerMiddlewareError::MiddlewareError),
+        }
+    }
+
+    /// Sets `from` to the signer the next transaction without `from` would be sent by if it is
+    /// not set, and fills the chain id of the signer.
+    ///
+    /// The nonce is left unset, as [`send_transaction`](Self::send_transaction) assigns it from
+    /// the signer's tracked nonce.
+    async fn fill_transaction(
+        &self,
+        tx: &mut TypedTransaction,
+        block: Option<BlockId>,
+    ) -> Result<(), Self::Error> {
+        let from = match tx.from() {
+            Some(from) => *from,
+            None => {
+                let from = self.pick(false).await?;
+                tx.set_from(from);
+                from
+            }
+        };
+
+        if let Some(state) = self.state(&from) {
+            if tx.chain_id().is_none() {
+                tx.set_chain_id(state.signer.chain_id());
+            }
+
+            // If a chain_id is matched to a known chain that doesn't support EIP-1559,
+            // automatically change transaction to be Legacy type.
+            if let Some(chain_id) = tx.chain_id() {
+                let chain = Chain::try_from(chain_id.as_u64());
+                if chain.unwrap_or_default().is_legacy() {
+                    if let TypedTransaction::Eip1559(inner) = tx {
+                        let tx_req: TransactionRequest = inner.clone().into();
+                        *tx = TypedTransaction::Legacy(tx_req);
+                    }
+                }
+            }
+        }
+
+        self.inner
+            .fill_transaction(tx, block)
+            .await
+            .map_err(MultiSignerMiddlewareError::MiddlewareError)
+    }
+
+    /// Signs the transaction with the signer of its `from`, selecting one if it is not set, and
+    /// broadcasts it. Transactions from other addresses are sent by the inner middleware.
+    async fn send_transaction<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        tx: T,
+        block: Option<BlockId>,
+    ) -> Result<PendingTransaction<'_, Self::Provider>, Self::Error> {
+        let mut tx = tx.into();
+        let from = match tx.from() {
+            Some(from) => *from,
+            None => {
+                let from = self.select().await?;
+                tx.set_from(from);
+                from
+            }
+        };
+
+        let Some(state) = self.state(&from) else {
+            return self
+                .inner
+                .send_transaction(tx, block)
+                .await
+                .map_err(MultiSignerMiddlewareError::MiddlewareError)
+        };
+
+        let managed = tx.nonce().is_none();
+        if managed {
+            tx.set_nonce(self.next_nonce(from).await?);
+        }
+        let nonce = tx.nonce().copied();
+
+        let err = match self.fill_sign_and_send(state, &mut tx, block).await {
+            Ok(pending) => return Ok(pending),
+            Err(err) => err,
+        };
+
+        // propagate the error if the nonce was set by the caller
+        let Some(nonce) = nonce.filter(|_| managed) else { return Err(err) };
+        if !is_nonce_error(&err) {
+            // the nonce was not used, hand it out again so that no gap is left
+            state.nonce.release(nonce.as_u64());
+            return Err(err)
+        }
+
+        // our counter is out of sync with the node: resync it and re-submit the transaction with
+        // the correct nonce
+        tracing::debug!(?from, err = %err, "resyncing nonce");
+        self.resync(from).await?;
+        tx.set_nonce(self.next_nonce(from).await?);
+        let nonce = *tx.nonce().expect("nonce was set");
+        match self.fill_sign_and_send(state, &mut tx, block).await {
+            Ok(pending) => Ok(pending),
+            Err(err) => {
+                if !is_nonce_error(&err) {
+                    state.nonce.release(nonce.as_u64());
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Signs the message with the signer of `from`, or with the inner middleware if `from` is not
+    /// a signer
+    async fn sign<T: Into<Bytes> + Send + Sync>(
+        &self,
+        data: T,
+        from: &Address,
+    ) -> Result<Signature, Self::Error> {
+        match self.state(from) {
+            Some(state) => state
+                .signer
+                .sign_message(data.into())
+                .await
+                .map_err(MultiSignerMiddlewareError::SignerError),
+            None => self
+                .inner
+                .sign(data, from)
+                .await
+                .map_err(MultiSignerMiddlewareError::MiddlewareError),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{
+        accept_transactions, reject_transactions, sent_transactions, INSUFFICIENT_FUNDS,
+    };
+    use ethers_providers::{MockProvider, MockResponse, Provider};
+    use ethers_signers::LocalWallet;
+    use futures_util::FutureExt;
+    use serde_json::json;
+
+    fn wallet(key: u8) -> LocalWallet {
+        LocalWallet::from_bytes(&[key; 32]).unwrap().with_chain_id(1u64)
+    }
+
+    fn tx() -> TransactionRequest {
+        TransactionRequest::pay(Address::zero(), 1).gas(21_000).gas_price(10)
+    }
+
+    /// Returns the sender and nonce of the signed transactions sent to `mock`
+    fn sent_nonces(mock: &MockProvider) -> Vec<(Address, u64)> {
+        sent_transactions(mock)
+            .into_iter()
+            .filter_map(|(_, tx)| Some((*tx.from()?, tx.nonce()?.as_u64())))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn routes_by_from_with_separate_nonces() {
+        let (alice, bob, carol) = (wallet(1), wallet(2), wallet(3));
+        let (provider, mock) = Provider::mocked();
+        let alice_address = alice.address();
+        mock.on("eth_getTransactionCount", move |params| {
+            let count = if params[0] == json!(alice_address) { 3 } else { 10 };
+            MockResponse::Value(json!(U256::from(count)))
+        });
+        accept_transactions(&mock);
+
+        let client = MultiSignerMiddleware::new(provider, [alice.clone(), bob.clone()]);
+        for from in [bob.address(), alice.address(), bob.address()] {
+            client.send_transaction(tx().from(from), None).await.unwrap();
+        }
+        assert_eq!(
+            sent_nonces(&mock),
+            vec![(bob.address(), 10), (alice.address(), 3), (bob.address(), 11)]
+        );
+
+        // unknown senders are left to the inner middleware
+        client.send_transaction(tx().from(carol.address()), None).await.unwrap();
+        let request: TypedTransaction = tx().from(carol.address()).into();
+        mock.assert_any_request("eth_sendTransaction", [&request]).unwrap();
+        assert!(matches!(
+            client.resync(carol.address()).await,
+            Err(MultiSignerMiddlewareError::UnknownSigner(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn selects_signers_round_robin() {
+        let (alice, bob) = (wallet(1), wallet(2));
+        let (provider, mock) = Provider::mocked();
+        mock.on("eth_getTransactionCount", |_| MockResponse::Value(json!(U256::from(0))));
+        accept_transactions(&mock);
+
+        let client = MultiSignerMiddleware::new(provider, [alice.clone(), bob.clone()]);
+        for _ in 0..3 {
+            client.send_transaction(tx(), None).await.unwrap();
+        }
+        assert_eq!(
+            sent_nonces(&mock),
+            vec![(alice.address(), 0), (bob.address(), 0), (alice.address(), 1)]
+        );
+
+        let client = MultiSignerMiddleware::<_, LocalWallet>::new(client.inner, []);
+        assert!(matches!(client.select().await, Err(MultiSignerMiddlewareError::NoSigners)));
+    }
+
+    #[tokio::test]
+    async fn selects_least_busy_signer() {
+        let (alice, bob) = (wallet(1), wallet(2));
+        let (provider, mock) = Provider::mocked();
+        // nothing gets mined
+        mock.on("eth_getTransactionCount", |_| MockResponse::Value(json!(U256::from(0))));
+        accept_transactions(&mock);
+
+        let client = MultiSignerMiddleware::new(provider, [alice.clone(), bob.clone()])
+            .with_selection(Selection::LeastBusy);
+        for _ in 0..2 {
+            client.send_transaction(tx().from(alice.address()), None).await.unwrap();
+        }
+        for _ in 0..3 {
+            client.send_transaction(tx(), None).await.unwrap();
+        }
+        let senders: Vec<_> = sent_nonces(&mock).into_iter().map(|(from, _)| from).collect();
+        assert_eq!(
+            senders,
+            vec![alice.address(), alice.address(), bob.address(), bob.address(), alice.address()]
+        );
+    }
+
+    #[tokio::test]
+    async fn releases_nonces_of_the_failed_signer_only() {
+        let (alice, bob) = (wallet(1), wallet(2));
+        let (provider, mock) = Provider::mocked();
+        let alice_address = alice.address();
+        mock.on("eth_getTransactionCount", move |params| {
+            let count = if params[0] == json!(alice_address) { 3 } else { 10 };
+            MockResponse::Value(json!(U256::from(count)))
+        });
+        reject_transactions(&mock, &[INSUFFICIENT_FUNDS]);
+
+        let client = MultiSignerMiddleware::new(provider, [alice.clone(), bob.clone()]);
+        client.send_transaction(tx().from(alice.address()), None).await.unwrap_err();
+        for from in [bob.address(), alice.address(), bob.address()] {
+            client.send_transaction(tx().from(from), None).await.unwrap();
+        }
+        assert_eq!(
+            sent_nonces(&mock),
+            vec![
+                (alice.address(), 3),
+                (bob.address(), 10),
+                (alice.address(), 3),
+                (bob.address(), 11)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn fills_gaps_of_released_nonces() {
+        let alice = wallet(1);
+        let (provider, mock) = Provider::mocked();
+        mock.on("eth_getTransactionCount", |_| MockResponse::Value(json!(U256::from(3))));
+        accept_transactions(&mock);
+
+        let client = MultiSignerMiddleware::new(provider, [alice.clone()]);
+        let first = client.next_nonce(alice.address()).await.unwrap();
+        client.next_nonce(alice.address()).await.unwrap();
+        // released below a nonce which is still in use
+        client.known_state(alice.address()).unwrap().nonce.release(first.as_u64());
+        for _ in 0..2 {
+            client.send_transaction(tx(), None).await.unwrap();
+        }
+        assert_eq!(sent_nonces(&mock), vec![(alice.address(), 3), (alice.address(), 5)]);
+    }
+
+    #[tokio::test]
+    async fn selects_without_waiting_for_nonce_syncs() {
+        let (alice, bob) = (wallet(1), wallet(2));
+        let (provider, mock) = Provider::mocked();
+        mock.on("eth_getTransactionCount", |_| MockResponse::Value(json!(U256::from(0))));
+        accept_transactions(&mock);
+
+        let client = MultiSignerMiddleware::new(provider, [alice.clone(), bob.clone()])
+            .with_selection(Selection::LeastBusy);
+        client.send_transaction(tx().from(alice.address()), None).await.unwrap();
+        // as while syncing the nonce of alice with the node
+        let _guard = client.known_state(alice.address()).unwrap().nonce.init_guard.lock().await;
+        let selected = client.select().now_or_never().expect("selection waited").unwrap();
+        assert_eq!(selected, bob.address());
+    }
+
+    #[tokio::test]
+    async fn fills_without_taking_nonces() {
+        let (alice, bob) = (wallet(1), wallet(2));
+        let (provider, mock) = Provider::mocked();
+        mock.on("eth_getTransactionCount", |_| MockResponse::Value(json!(U256::from(0))));
+        accept_transactions(&mock);
+
+        let client = MultiSignerMiddleware::new(provider, [alice.clone(), bob.clone()]);
+        for _ in 0..2 {
+            let mut filled = tx().into();
+            client.fill_transaction(&mut filled, None).await.unwrap();
+            assert_eq!(filled.from(), Some(&alice.address()));
+            assert_eq!(filled.nonce(), None);
+        }
+
+        // filling neither advanced the round-robin cursor nor left nonce gaps
+        for _ in 0..3 {
+            client.send_transaction(tx(), None).await.unwrap();
+        }
+        assert_eq!(
+            sent_nonces(&mock),
+            vec![(alice.address(), 0), (bob.address(), 0), (alice.address(), 1)]
+        );
+    }
+}
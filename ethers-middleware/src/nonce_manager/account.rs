@@ -0,0 +1,125 @@
+use std::{
+    collections::BTreeSet,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+/// The nonce state of a single account, shared by the middlewares which manage nonces locally.
+///
+/// Nonces are handed out from a counter. The nonce of a transaction which is not sent can be
+/// released, and released nonces are handed out again before new ones so that no gap is left.
+#[derive(Debug, Default)]
+pub(crate) struct AccountNonce {
+    /// Serializes syncing the counter with the node
+    pub(crate) init_guard: futures_locks::Mutex<()>,
+    initialized: AtomicBool,
+    /// The next nonce to hand out if there are no released ones
+    nonce: AtomicU64,
+    /// Nonces which were handed out but whose transactions were not sent
+    released: Mutex<BTreeSet<u64>>,
+}
+
+impl AccountNonce {
+    /// Creates an initialized account whose next nonce is `nonce`
+    pub(crate) fn new(nonce: u64) -> Self {
+        let account = Self::default();
+        account.reset(nonce);
+        account
+    }
+
+    /// Returns true once the counter was set, e.g. from the node's transaction count
+    pub(crate) fn is_initialized(&self) -> bool {
+        self.initialized.load(Ordering::SeqCst)
+    }
+
+    /// Returns the nonce following all the nonces handed out
+    pub(crate) fn counter(&self) -> u64 {
+        self.nonce.load(Ordering::SeqCst)
+    }
+
+    /// Returns the nonce handed out next
+    pub(crate) fn peek(&self) -> u64 {
+        let released = self.released.lock().unwrap();
+        released.iter().next().copied().unwrap_or_else(|| self.counter())
+    }
+
+    pub(crate) fn next(&self) -> u64 {
+        let mut released = self.released.lock().unwrap();
+        if let Some(nonce) = released.iter().next().copied() {
+            released.remove(&nonce);
+            return nonce
+        }
+        self.nonce.fetch_add(1, Ordering::SeqCst)
+    }
+
+    /// Hands `nonce` out again, as its transaction was not sent
+    pub(crate) fn release(&self, nonce: u64) {
+        let mut released = self.released.lock().unwrap();
+        if nonce >= self.counter() {
+            // was never handed out, or the counter was reset below it
+            return
+        }
+        released.insert(nonce);
+
+        // roll the counter back over released nonces at the top of the range
+        while let Some(&last) = released.iter().next_back() {
+            if self
+                .nonce
+                .compare_exchange(last + 1, last, Ordering::SeqCst, Ordering::SeqCst)
+                .is_err()
+            {
+                break
+            }
+            released.remove(&last);
+        }
+    }
+
+    /// Sets the next nonce, forgetting released nonces
+    pub(crate) fn reset(&self, nonce: u64) {
+        self.restore(nonce, [])
+    }
+
+    /// Sets the next nonce and the released nonces below it, e.g. as loaded from a store
+    pub(crate) fn restore(&self, nonce: u64, released: impl IntoIterator<Item = u64>) {
+        let mut current = self.released.lock().unwrap();
+        *current = released.into_iter().filter(|released| *released < nonce).collect();
+        self.nonce.store(nonce, Ordering::SeqCst);
+        self.initialized.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns the next nonce and the released nonces, consistently with each other
+    pub(crate) fn snapshot(&self) -> (u64, Vec<u64>) {
+        let released = self.released.lock().unwrap();
+        (self.counter(), released.iter().copied().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn released_nonces_fill_gaps() {
+        let account = AccountNonce::new(5);
+        assert_eq!((account.next(), account.next(), account.next()), (5, 6, 7));
+
+        // a gap is filled by the next nonce handed out
+        account.release(6);
+        assert_eq!(account.peek(), 6);
+        assert_eq!(account.next(), 6);
+        assert_eq!(account.next(), 8);
+
+        // releasing the latest nonces rolls the counter back
+        account.release(7);
+        account.release(8);
+        assert_eq!(account.counter(), 7);
+        assert!(account.released.lock().unwrap().is_empty());
+        assert_eq!(account.next(), 7);
+
+        // nonces which were never handed out are ignored
+        account.release(42);
+        assert_eq!(account.next(), 8);
+    }
+}
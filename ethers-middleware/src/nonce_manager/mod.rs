@@ -0,0 +1,496 @@
+mod account;
+pub(crate) use account::AccountNonce;
+
+mod store;
+#[cfg(not(target_arch = "wasm32"))]
+pub use store::FileNonceStore;
+pub use store::{MemoryNonceStore, NonceStore, NonceStoreError, StoredNonce};
+
+use async_trait::async_trait;
+use ethers_core::types::{transaction::eip2718::TypedTransaction, *};
+use ethers_providers::{Middleware, MiddlewareError, PendingTransaction};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+use thiserror::Error;
+
+#[derive(Debug)]
+/// Middleware used for calculating nonces locally, useful for signing multiple
+/// consecutive transactions without waiting for them to hit the mempool
+///
+/// Nonces are tracked per sender: the `from` of a transaction selects the counter, and
+/// transactions without `from` use the address the manager was created with.
+///
+/// If the node rejects a transaction without adding it to its mempool, e.g. because its sender
+/// can not pay for it, its nonce is handed out again to the next transaction so that no gap is
+/// left. Other errors, such as timeouts, keep the nonce reserved, as the transaction may have been
+/// broadcast. If the node rejects a transaction with a "nonce too low" or "nonce too
+/// high" error, the counter is resynced with the pending transaction count and the transaction is
+/// resent once with the new nonce.
+///
+/// Nonces can be persisted across restarts with a [`NonceStore`].
+///
+/// # Example
+///
+/// ```no_run
+/// use ethers_core::types::{Address, TransactionRequest};
+/// use ethers_middleware::{nonce_manager::FileNonceStore, NonceManagerMiddleware};
+/// use ethers_providers::{Http, Middleware, Provider};
+///
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let provider = Provider::<Http>::try_from("http://localhost:8545")?;
+/// let (alice, bob) = (Address::random(), Address::random());
+///
+/// let provider = NonceManagerMiddleware::new(provider, alice)
+///     .with_store(FileNonceStore::new("nonces.json"));
+///
+/// // both senders get their own nonces
+/// provider.send_transaction(TransactionRequest::pay(bob, 100), None).await?;
+/// provider.send_transaction(TransactionRequest::pay(alice, 100).from(bob), None).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct NonceManagerMiddleware<M> {
+    inner: M,
+    address: Address,
+    accounts: RwLock<HashMap<Address, Arc<AccountNonce>>>,
+    store: Option<Arc<dyn NonceStore>>,
+    /// Serializes writes to the store, so that older nonces never overwrite newer ones
+    persist_guard: futures_locks::Mutex<()>,
+}
+
+impl<M> NonceManagerMiddleware<M>
+where
+    M: Middleware,
+{
+    /// Instantiates the nonce manager with a 0 nonce. The `address` should be the
+    /// address which you'll be sending transactions from
+    pub fn new(inner: M, address: Address) -> Self {
+        Self {
+            inner,
+            address,
+            accounts: Default::default(),
+            store: None,
+            persist_guard: futures_locks::Mutex::new(()),
+        }
+    }
+
+    /// Persists the nonces in `store`, and loads them from it when an address is first managed
+    #[must_use]
+    pub fn with_store<S: NonceStore + 'static>(mut self, store: S) -> Self {
+        self.store = Some(Arc::new(store));
+        self
+    }
+
+    /// Returns the address used for transactions without `from`
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    fn account(&self, address: Address) -> Arc<AccountNonce> {
+        if let Some(account) = self.accounts.read().unwrap().get(&address) {
+            return account.clone()
+        }
+        self.accounts.write().unwrap().entry(address).or_default().clone()
+    }
+
+    /// Returns the next nonce to be used
+    pub fn next(&self) -> U256 {
+        self.next_for(self.address)
+    }
+
+    /// Returns the next nonce to be used by `address`
+    pub fn next_for(&self, address: Address) -> U256 {
+        self.account(address).next().into()
+    }
+
+    /// Hands `nonce` of `address` out again, e.g. because the transaction using it could not be
+    /// broadcast
+    pub fn release(&self, address: Address, nonce: U256) {
+        self.account(address).release(nonce.as_u64());
+    }
+
+    pub async fn initialize_nonce(
+        &self,
+        block: Option<BlockId>,
+    ) -> Result<U256, NonceManagerError<M>> {
+        self.initialize_nonce_for(self.address, block).await
+    }
+
+    /// Initializes the nonce of `address` from the node's transaction count, or from the store if
+    /// its nonce is higher, and returns it
+    pub async fn initialize_nonce_for(
+        &self,
+        address: Address,
+        block: Option<BlockId>,
+    ) -> Result<U256, NonceManagerError<M>> {
+        let account = self.account(address);
+        if account.is_initialized() {
+            // return current nonce
+            return Ok(account.counter().into())
+        }
+
+        let _guard = account.init_guard.lock().await;
+
+        // do this again in case multiple tasks enter this codepath
+        if account.is_initialized() {
+            // return current nonce
+            return Ok(account.counter().into())
+        }
+
+        // initialize the nonce the first time the manager is called
+        let count = self
+            .inner
+            .get_transaction_count(address, block)
+            .await
+            .map_err(NonceManagerError::from_err)?;
+        let stored = match self.store {
+            Some(ref store) => store.load(address).await?.filter(|stored| stored.next > count),
+            None => None,
+        };
+        match stored {
+            Some(stored) => {
+                // released nonces below the count were used since
+                let released = stored.released.iter().filter(|nonce| **nonce >= count);
+                account.restore(stored.next.as_u64(), released.map(|nonce| nonce.as_u64()));
+                Ok(stored.next)
+            }
+            None => {
+                account.reset(count.as_u64());
+                Ok(count)
+            }
+        }
+    } // guard dropped here
+
+    /// Resets the nonce of `address` to its pending transaction count, discarding released
+    /// nonces, and returns it
+    pub async fn resync(&self, address: Address) -> Result<U256, NonceManagerError<M>> {
+        let account = self.account(address);
+        let _guard = account.init_guard.lock().await;
+
+        let nonce = self
+            .inner
+            .get_transaction_count(address, Some(BlockNumber::Pending.into()))
+            .await
+            .map_err(NonceManagerError::from_err)?;
+        account.reset(nonce.as_u64());
+        self.persist(address, &account).await?;
+        Ok(nonce)
+    }
+
+    async fn persist(
+        &self,
+        address: Address,
+        account: &AccountNonce,
+    ) -> Result<(), NonceManagerError<M>> {
+        if let Some(ref store) = self.store {
+            // snapshot the nonces once the previous writes are done, so that this write stores
+            // the latest ones
+            let _guard = self.persist_guard.lock().await;
+            let (next, released) = account.snapshot();
+            let nonce = StoredNonce {
+                next: next.into(),
+                released: released.into_iter().map(Into::into).collect(),
+            };
+            store.store(address, nonce).await?;
+        }
+        Ok(())
+    }
+
+    /// Releases `nonce` of `address` and persists it, so that a restarted manager does not skip
+    /// the nonce
+    async fn release_persisted(&self, address: Address, nonce: U256) {
+        let account = self.account(address);
+        account.release(nonce.as_u64());
+        if let Err(err) = self.persist(address, &account).await {
+            tracing::warn!(?address, err = %err, "failed to persist released nonce");
+        }
+    }
+
+    async fn get_transaction_count_with_manager(
+        &self,
+        address: Address,
+        block: Option<BlockId>,
+    ) -> Result<U256, NonceManagerError<M>> {
+        // initialize the nonce the first time the manager is called
+        self.initialize_nonce_for(address, block).await?;
+
+        let account = self.account(address);
+        let nonce = account.next();
+        if let Err(err) = self.persist(address, &account).await {
+            account.release(nonce);
+            return Err(err)
+        }
+        Ok(nonce.into())
+    }
+}
+
+/// Returns true if the error is the node rejecting a transaction because of its nonce
+pub(crate) fn is_nonce_error(err: &impl std::error::Error) -> bool {
+    let err = err.to_string().to_lowercase();
+    err.contains("nonce too low") || err.contains("nonce too high")
+}
+
+/// Messages of the nodes rejecting a transaction without adding it to their mempool
+const REJECTIONS: [&str; 9] = [
+    "insufficient funds",
+    "intrinsic gas too low",
+    "exceeds block gas limit",
+    "less than block base fee",
+    "max priority fee per gas higher than max fee per gas",
+    "exceeds the configured cap",
+    "invalid sender",
+    "oversized data",
+    "transaction type not supported",
+];
+
+/// Returns true if the error is the node rejecting a transaction, so that its nonce was not used.
+///
+/// Other errors, such as timeouts or "already known", leave it unknown whether the transaction
+/// was broadcast.
+pub(crate) fn is_rejection(err: &impl MiddlewareError) -> bool {
+    let Some(err) = err.as_error_response() else { return false };
+    let message = err.message.to_lowercase();
+    REJECTIONS.iter().any(|rejection| message.contains(rejection))
+}
+
+#[derive(Error, Debug)]
+/// Thrown when an error happens at the Nonce Manager
+pub enum NonceManagerError<M: Middleware> {
+    /// Thrown when the internal middleware errors
+    #[error("{0}")]
+    MiddlewareError(M::Error),
+
+    /// Thrown when the nonce store errors
+    #[error(transparent)]
+    StoreError(#[from] NonceStoreError),
+}
+
+impl<M: Middleware> MiddlewareError for NonceManagerError<M> {
+    type Inner = M::Error;
+
+    fn from_err(src: M::Error) -> Self {
+        NonceManagerError::MiddlewareError(src)
+    }
+
+    fn as_inner(&self) -> Option<&Self::Inner> {
+        match self {
+            NonceManagerError::MiddlewareError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<M> Middleware for NonceManagerMiddleware<M>
+where
+    M: Middleware,
+{
+    type Error = NonceManagerError<M>;
+    type Provider = M::Provider;
+    type Inner = M;
+
+    fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    async fn fill_transaction(
+        &self,
+        tx: &mut TypedTransaction,
+        block: Option<BlockId>,
+    ) -> Result<(), Self::Error> {
+        if tx.nonce().is_none() {
+            let from = tx.from().copied().unwrap_or(self.address);
+            tx.set_nonce(self.get_transaction_count_with_manager(from, block).await?);
+        }
+
+        Ok(self.inner().fill_transaction(tx, block).await.map_err(NonceManagerError::from_err)?)
+    }
+
+    /// Signs and broadcasts the transaction. The optional parameter `block` can be passed so that
+    /// gas cost and nonce calculations take it into account. For simple transactions this can be
+    /// left to `None`.
+    async fn send_transaction<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        tx: T,
+        block: Option<BlockId>,
+    ) -> Result<PendingTransaction<'_, Self::Provider>, Self::Error> {
+        let mut tx = tx.into();
+        let from = tx.from().copied().unwrap_or(self.address);
+
+        let managed = tx.nonce().is_none();
+        if managed {
+            tx.set_nonce(self.get_transaction_count_with_manager(from, block).await?);
+        }
+
+        let err = match self.inner.send_transaction(tx.clone(), block).await {
+            Ok(tx_hash) => return Ok(tx_hash),
+            Err(err) => err,
+        };
+
+        if !managed {
+            // propagate the error, the nonce was set by the caller
+            return Err(MiddlewareError::from_err(err))
+        }
+
+        if !is_nonce_error(&err) {
+            if is_rejection(&err) {
+                // the nonce was not used, hand it out again so that no gap is left
+                self.release_persisted(from, *tx.nonce().expect("nonce was set")).await;
+            }
+            return Err(MiddlewareError::from_err(err))
+        }
+
+        // our counter is out of sync with the node: resync it and re-submit the transaction with
+        // the correct nonce
+        tracing::debug!(?from, err = %err, "resyncing nonce");
+        self.resync(from).await?;
+        tx.set_nonce(self.get_transaction_count_with_manager(from, block).await?);
+        match self.inner.send_transaction(tx.clone(), block).await {
+            Ok(tx_hash) => Ok(tx_hash),
+            Err(err) => {
+                if is_rejection(&err) {
+                    self.release_persisted(from, *tx.nonce().expect("nonce was set")).await;
+                }
+                Err(MiddlewareError::from_err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::test_utils::{
+        accept_transactions, reject_transactions, sent_transactions, INSUFFICIENT_FUNDS,
+    };
+    use ethers_providers::{MockProvider, MockResponse, Provider};
+    use serde_json::json;
+
+    /// Returns the sender and nonce of the transactions sent to `mock`
+    fn sent_nonces(mock: &MockProvider) -> Vec<(Address, u64)> {
+        sent_transactions(mock)
+            .into_iter()
+            .map(|(_, tx)| (*tx.from().unwrap(), tx.nonce().unwrap().as_u64()))
+            .collect()
+    }
+
+    fn tx(from: Address) -> TransactionRequest {
+        TransactionRequest::new().from(from).to(Address::zero()).gas(21_000).gas_price(1)
+    }
+
+    #[tokio::test]
+    async fn tracks_nonces_per_sender() {
+        let (alice, bob) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let (provider, mock) = Provider::mocked();
+        mock.on("eth_getTransactionCount", move |params| {
+            let count = if params[0] == json!(alice) { 3 } else { 10 };
+            MockResponse::Value(json!(U256::from(count)))
+        });
+        accept_transactions(&mock);
+
+        let store = Arc::new(MemoryNonceStore::new());
+        let provider = NonceManagerMiddleware::new(provider, alice).with_store(store.clone());
+        for from in [alice, bob, alice, bob] {
+            provider.send_transaction(tx(from), None).await.unwrap();
+        }
+
+        assert_eq!(sent_nonces(&mock), vec![(alice, 3), (bob, 10), (alice, 4), (bob, 11)]);
+        assert_eq!(
+            store.nonces(),
+            HashMap::from([(alice, StoredNonce::new(5)), (bob, StoredNonce::new(12))])
+        );
+
+        // a new manager continues from the stored nonces if they are ahead of the node
+        let (provider, mock) = Provider::mocked();
+        mock.on("eth_getTransactionCount", |_| MockResponse::Value(json!(U256::from(3))));
+        accept_transactions(&mock);
+        let provider = NonceManagerMiddleware::new(provider, alice).with_store(store);
+        provider.send_transaction(tx(alice), None).await.unwrap();
+        assert_eq!(sent_nonces(&mock), vec![(alice, 5)]);
+    }
+
+    #[tokio::test]
+    async fn recovers_from_failed_broadcasts() {
+        let address = Address::repeat_byte(1);
+        let (provider, mock) = Provider::mocked();
+        mock.on("eth_getTransactionCount", |params| {
+            let count = if params[1] == json!("pending") { 8 } else { 5 };
+            MockResponse::Value(json!(U256::from(count)))
+        });
+        reject_transactions(&mock, &[INSUFFICIENT_FUNDS, "nonce too low"]);
+
+        let provider = NonceManagerMiddleware::new(provider, address);
+        provider.send_transaction(tx(address), None).await.unwrap_err();
+        // the nonce of the failed broadcast is reused, then resynced with the pending count
+        provider.send_transaction(tx(address), None).await.unwrap();
+        provider.send_transaction(tx(address), None).await.unwrap();
+
+        let nonces: Vec<_> = sent_nonces(&mock).into_iter().map(|(_, nonce)| nonce).collect();
+        assert_eq!(nonces, vec![5, 5, 8, 9]);
+    }
+
+    #[tokio::test]
+    async fn persists_released_nonces() {
+        let address = Address::repeat_byte(1);
+        let (provider, mock) = Provider::mocked();
+        mock.on("eth_getTransactionCount", |_| MockResponse::Value(json!(U256::from(5))));
+        reject_transactions(&mock, &[INSUFFICIENT_FUNDS]);
+
+        let store = Arc::new(MemoryNonceStore::new());
+        let provider = NonceManagerMiddleware::new(provider, address).with_store(store.clone());
+        provider.send_transaction(tx(address), None).await.unwrap_err();
+        assert_eq!(store.nonces(), HashMap::from([(address, StoredNonce::new(5))]));
+
+        // a restarted manager does not skip the nonce of the failed broadcast
+        let (provider, mock) = Provider::mocked();
+        mock.on("eth_getTransactionCount", |_| MockResponse::Value(json!(U256::from(5))));
+        accept_transactions(&mock);
+        let provider = NonceManagerMiddleware::new(provider, address).with_store(store);
+        provider.send_transaction(tx(address), None).await.unwrap();
+        assert_eq!(sent_nonces(&mock), vec![(address, 5)]);
+    }
+
+    #[tokio::test]
+    async fn keeps_the_nonce_of_possibly_broadcast_transactions() {
+        let address = Address::repeat_byte(1);
+        let (provider, mock) = Provider::mocked();
+        mock.on("eth_getTransactionCount", |_| MockResponse::Value(json!(U256::from(5))));
+        reject_transactions(&mock, &["already known"]);
+
+        let provider = NonceManagerMiddleware::new(provider, address);
+        provider.send_transaction(tx(address), None).await.unwrap_err();
+        provider.send_transaction(tx(address), None).await.unwrap();
+
+        let nonces: Vec<_> = sent_nonces(&mock).into_iter().map(|(_, nonce)| nonce).collect();
+        assert_eq!(nonces, vec![5, 6]);
+    }
+
+    #[tokio::test]
+    async fn persists_released_nonces_below_the_counter() {
+        let address = Address::repeat_byte(1);
+        let (provider, mock) = Provider::mocked();
+        mock.on("eth_getTransactionCount", |_| MockResponse::Value(json!(U256::from(5))));
+
+        let store = Arc::new(MemoryNonceStore::new());
+        let provider = NonceManagerMiddleware::new(provider, address).with_store(store.clone());
+        let first = provider.get_transaction_count_with_manager(address, None).await.unwrap();
+        provider.get_transaction_count_with_manager(address, None).await.unwrap();
+        provider.release_persisted(address, first).await;
+        let stored = StoredNonce { next: 7.into(), released: vec![5.into()] };
+        assert_eq!(store.nonces(), HashMap::from([(address, stored)]));
+
+        // a restarted manager fills the gap before continuing from the stored nonce
+        let (provider, mock) = Provider::mocked();
+        mock.on("eth_getTransactionCount", |_| MockResponse::Value(json!(U256::from(5))));
+        accept_transactions(&mock);
+        let provider = NonceManagerMiddleware::new(provider, address).with_store(store);
+        for _ in 0..2 {
+            provider.send_transaction(tx(address), None).await.unwrap();
+        }
+        assert_eq!(sent_nonces(&mock), vec![(address, 5), (address, 7)]);
+    }
+}
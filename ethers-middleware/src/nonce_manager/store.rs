@@ -0,0 +1,175 @@
+use async_trait::async_trait;
+use auto_impl::auto_impl;
+use ethers_core::types::{Address, U256};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, error::Error, fmt::Debug, sync::Mutex};
+use thiserror::Error;
+
+/// Error thrown by a [`NonceStore`]
+#[derive(Debug, Error)]
+pub enum NonceStoreError {
+    /// Thrown when reading or writing the store fails
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// Thrown when the stored nonces can not be (de)serialized
+    #[error(transparent)]
+    SerdeJson(#[from] serde_json::Error),
+
+    /// Error thrown by custom stores
+    #[error(transparent)]
+    Other(Box<dyn Error + Send + Sync>),
+}
+
+/// The nonces of an address persisted by a [`NonceStore`]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredNonce {
+    /// The nonce following all the nonces handed out
+    pub next: U256,
+    /// The nonces below `next` which were handed out, but whose transactions were rejected and
+    /// which are handed out again first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub released: Vec<U256>,
+}
+
+impl StoredNonce {
+    /// Creates a stored nonce without released nonces
+    pub fn new(next: impl Into<U256>) -> Self {
+        Self { next: next.into(), released: Vec::new() }
+    }
+}
+
+/// Persists the nonces of a [`NonceManagerMiddleware`](super::NonceManagerMiddleware) across
+/// restarts.
+///
+/// The manager stores the nonces of an address every time it hands one out or one is released,
+/// and loads them when it first manages the address. Loaded nonces are only used if the next
+/// nonce is higher than the node's transaction count, e.g. because the transactions sent before
+/// the restart are still pending.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[auto_impl(&, Box, Arc)]
+pub trait NonceStore: Send + Sync + Debug {
+    /// Returns the stored nonces of `address`, if any
+    async fn load(&self, address: Address) -> Result<Option<StoredNonce>, NonceStoreError>;
+
+    /// Stores the nonces of `address`
+    async fn store(&self, address: Address, nonce: StoredNonce) -> Result<(), NonceStoreError>;
+}
+
+/// A [`NonceStore`] which keeps the nonces in memory.
+///
+/// Mostly useful for tests, or for sharing nonces between managers of the same process.
+#[derive(Debug, Default)]
+pub struct MemoryNonceStore {
+    nonces: Mutex<HashMap<Address, StoredNonce>>,
+}
+
+impl MemoryNonceStore {
+    /// Creates an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the stored nonces
+    pub fn nonces(&self) -> HashMap<Address, StoredNonce> {
+        self.nonces.lock().unwrap().clone()
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl NonceStore for MemoryNonceStore {
+    async fn load(&self, address: Address) -> Result<Option<StoredNonce>, NonceStoreError> {
+        Ok(self.nonces.lock().unwrap().get(&address).cloned())
+    }
+
+    async fn store(&self, address: Address, nonce: StoredNonce) -> Result<(), NonceStoreError> {
+        self.nonces.lock().unwrap().insert(address, nonce);
+        Ok(())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use file::FileNonceStore;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod file {
+    use super::*;
+    use std::{
+        io::ErrorKind,
+        path::{Path, PathBuf},
+    };
+
+    /// A [`NonceStore`] which keeps the nonces in a JSON file, mapping addresses to their
+    /// [`StoredNonce`].
+    ///
+    /// The file is created on the first write, and replaced atomically on every write.
+    #[derive(Debug)]
+    pub struct FileNonceStore {
+        path: PathBuf,
+        lock: tokio::sync::Mutex<()>,
+    }
+
+    impl FileNonceStore {
+        /// Creates a store backed by the file at `path`
+        pub fn new(path: impl Into<PathBuf>) -> Self {
+            Self { path: path.into(), lock: tokio::sync::Mutex::new(()) }
+        }
+
+        /// Returns the path of the file
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+
+        async fn read(&self) -> Result<HashMap<Address, StoredNonce>, NonceStoreError> {
+            match tokio::fs::read(&self.path).await {
+                Ok(content) => Ok(serde_json::from_slice(&content)?),
+                Err(err) if err.kind() == ErrorKind::NotFound => Ok(HashMap::new()),
+                Err(err) => Err(err.into()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl NonceStore for FileNonceStore {
+        async fn load(&self, address: Address) -> Result<Option<StoredNonce>, NonceStoreError> {
+            let _lock = self.lock.lock().await;
+            Ok(self.read().await?.remove(&address))
+        }
+
+        async fn store(&self, address: Address, nonce: StoredNonce) -> Result<(), NonceStoreError> {
+            let _lock = self.lock.lock().await;
+            let mut nonces = self.read().await?;
+            nonces.insert(address, nonce);
+
+            ethers_providers::write_atomic(&self.path, serde_json::to_vec_pretty(&nonces)?).await?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn file_store_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nonces.json");
+
+        let store = FileNonceStore::new(&path);
+        let (a, b) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        assert_eq!(store.load(a).await.unwrap(), None);
+
+        store.store(a, StoredNonce::new(5)).await.unwrap();
+        store.store(b, StoredNonce::new(7)).await.unwrap();
+        let released = StoredNonce { next: 8.into(), released: vec![6.into()] };
+        store.store(a, released.clone()).await.unwrap();
+
+        let store = FileNonceStore::new(&path);
+        assert_eq!(store.load(a).await.unwrap(), Some(released));
+        assert_eq!(store.load(b).await.unwrap(), Some(StoredNonce::new(7)));
+    }
+}
@@ -0,0 +1,260 @@
+use ethers_core::types::{
+    transaction::eip2718::TypedTransaction, Address, BlockId, Selector, U256, U64,
+};
+use ethers_providers::{Middleware, MiddlewareError, PendingTransaction};
+
+use async_trait::async_trait;
+use std::fmt::Debug;
+use thiserror::Error;
+
+mod rules;
+pub use rules::{
+    MaxFee, MaxValue, PinnedChainId, SelectorAllowlist, SpendLimit, ToAllowlist, ToDenylist,
+};
+
+/// Basic trait to ensure that transactions about to be sent follow certain rules.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait Policy: Sync + Send + Debug {
+    type Error: Sync + Send + Debug;
+
+    /// Evaluates the transactions.
+    ///
+    /// Returns Ok with the `tx` or an Err otherwise.
+    async fn ensure_can_send(&self, tx: TypedTransaction) -> Result<TypedTransaction, Self::Error>;
+}
+
+/// A policy that does not restrict anything.
+#[derive(Debug, Clone, Copy)]
+pub struct AllowEverything;
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Policy for AllowEverything {
+    type Error = ();
+
+    async fn ensure_can_send(&self, tx: TypedTransaction) -> Result<TypedTransaction, Self::Error> {
+        Ok(tx)
+    }
+}
+
+/// A policy that rejects all transactions.
+#[derive(Debug, Clone, Copy)]
+pub struct RejectEverything;
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Policy for RejectEverything {
+    type Error = ();
+
+    async fn ensure_can_send(&self, _: TypedTransaction) -> Result<TypedTransaction, Self::Error> {
+        Err(())
+    }
+}
+
+/// The reason a transaction was rejected by one of the built-in policies.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum PolicyViolation {
+    /// The transaction has no recipient, i.e. it deploys a contract
+    #[error("contract deployments are not allowed")]
+    MissingRecipient,
+
+    /// The recipient is not in the allowlist
+    #[error("recipient {0:?} is not allowed")]
+    RecipientNotAllowed(Address),
+
+    /// The recipient is in the denylist
+    #[error("recipient {0:?} is denied")]
+    RecipientDenied(Address),
+
+    /// The called function is not allowed on the recipient. `selector` is `None` when the
+    /// transaction has no calldata.
+    #[error("function {selector:?} is not allowed on {to:?}")]
+    SelectorNotAllowed { to: Address, selector: Option<Selector> },
+
+    /// The transferred value is above the cap
+    #[error("value {value} exceeds the maximum of {max}")]
+    ValueTooHigh { value: U256, max: U256 },
+
+    /// The gas price, or max fee per gas, is above the cap
+    #[error("fee per gas {fee} exceeds the maximum of {max}")]
+    FeeTooHigh { fee: U256, max: U256 },
+
+    /// The transaction does not set its fees, so they can not be checked
+    #[error("the fees of the transaction are not set")]
+    FeeNotSet,
+
+    /// The value would bring the spending of the current window above the limit
+    #[error("spending {value} on top of {spent} exceeds the limit of {limit}")]
+    SpendLimitExceeded { spent: U256, value: U256, limit: U256 },
+
+    /// The transaction is for another chain
+    #[error("chain id {actual} does not match the expected chain id {expected}")]
+    WrongChainId { expected: U64, actual: U64 },
+
+    /// All the alternatives of an [`Or`] policy rejected the transaction
+    #[error("no policy allowed the transaction: {0:?}")]
+    NoneAllowed(Vec<PolicyViolation>),
+
+    /// Rejection by a custom policy
+    #[error("{0}")]
+    Custom(String),
+}
+
+/// Combinators for policies which reject transactions with a [`PolicyViolation`].
+///
+/// ```
+/// use ethers_core::types::Address;
+/// use ethers_middleware::policy::{MaxValue, PolicyExt, ToAllowlist, ToDenylist};
+///
+/// let treasury = Address::random();
+/// let exchanges = [Address::random(), Address::random()];
+///
+/// // anything to the treasury, or small payments to anyone but the exchanges
+/// let policy = ToAllowlist::new([treasury])
+///     .or(ToDenylist::new(exchanges).and(MaxValue::new(1_000_000u64)));
+/// ```
+pub trait PolicyExt: Policy<Error = PolicyViolation> + Sized {
+    /// Returns a policy which requires both `self` and `other` to allow the transaction. The
+    /// transaction returned by `self` is checked by `other`.
+    fn and<P: Policy<Error = PolicyViolation>>(self, other: P) -> And<Self, P> {
+        And(self, other)
+    }
+
+    /// Returns a policy which requires either `self` or `other` to allow the transaction. `other`
+    /// is only evaluated if `self` rejects the transaction.
+    fn or<P: Policy<Error = PolicyViolation>>(self, other: P) -> Or<Self, P> {
+        Or(self, other)
+    }
+}
+
+impl<P: Policy<Error = PolicyViolation>> PolicyExt for P {}
+
+/// A policy which requires both of its policies to allow the transaction, see [`PolicyExt::and`].
+#[derive(Debug, Clone)]
+pub struct And<A, B>(pub A, pub B);
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<A, B> Policy for And<A, B>
+where
+    A: Policy<Error = PolicyViolation>,
+    B: Policy<Error = PolicyViolation>,
+{
+    type Error = PolicyViolation;
+
+    async fn ensure_can_send(&self, tx: TypedTransaction) -> Result<TypedTransaction, Self::Error> {
+        let tx = self.0.ensure_can_send(tx).await?;
+        self.1.ensure_can_send(tx).await
+    }
+}
+
+/// A policy which requires either of its policies to allow the transaction, see
+/// [`PolicyExt::or`].
+#[derive(Debug, Clone)]
+pub struct Or<A, B>(pub A, pub B);
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<A, B> Policy for Or<A, B>
+where
+    A: Policy<Error = PolicyViolation>,
+    B: Policy<Error = PolicyViolation>,
+{
+    type Error = PolicyViolation;
+
+    async fn ensure_can_send(&self, tx: TypedTransaction) -> Result<TypedTransaction, Self::Error> {
+        let first = match self.0.ensure_can_send(tx.clone()).await {
+            Ok(tx) => return Ok(tx),
+            Err(err) => err,
+        };
+        let second = match self.1.ensure_can_send(tx).await {
+            Ok(tx) => return Ok(tx),
+            Err(err) => err,
+        };
+
+        let mut violations = Vec::new();
+        for violation in [first, second] {
+            match violation {
+                PolicyViolation::NoneAllowed(nested) => violations.extend(nested),
+                violation => violations.push(violation),
+            }
+        }
+        Err(PolicyViolation::NoneAllowed(violations))
+    }
+}
+
+/// Middleware used to enforce certain policies for transactions.
+#[derive(Clone, Debug)]
+pub struct PolicyMiddleware<M, P> {
+    pub(crate) inner: M,
+    pub(crate) policy: P,
+}
+
+impl<M, P> PolicyMiddleware<M, P>
+where
+    M: Middleware,
+    P: Policy,
+{
+    /// Creates a new client from the provider and policy.
+    pub fn new(inner: M, policy: P) -> Self {
+        Self { inner, policy }
+    }
+}
+
+#[derive(Error, Debug)]
+/// Error thrown when the client interacts with the policy middleware.
+pub enum PolicyMiddlewareError<M: Middleware, P: Policy> {
+    /// Thrown when the internal policy errors
+    #[error("{0:?}")]
+    PolicyError(P::Error),
+    /// Thrown when an internal middleware errors
+    #[error(transparent)]
+    MiddlewareError(M::Error),
+}
+
+impl<M: Middleware, P: Policy> MiddlewareError for PolicyMiddlewareError<M, P> {
+    type Inner = M::Error;
+
+    fn from_err(src: M::Error) -> Self {
+        PolicyMiddlewareError::MiddlewareError(src)
+    }
+
+    fn as_inner(&self) -> Option<&Self::Inner> {
+        match self {
+            PolicyMiddlewareError::MiddlewareError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<M, P> Middleware for PolicyMiddleware<M, P>
+where
+    M: Middleware,
+    P: Policy,
+{
+    type Error = PolicyMiddlewareError<M, P>;
+    type Provider = M::Provider;
+    type Inner = M;
+
+    fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    /// This ensures the tx complies with the registered policy.
+    /// If so then this simply delegates the transaction to the inner middleware
+    async fn send_transaction<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        tx: T,
+        block: Option<BlockId>,
+    ) -> Result<PendingTransaction<'_, Self::Provider>, Self::Error> {
+        let tx = self
+            .policy
+            .ensure_can_send(tx.into())
+            .await
+            .map_err(PolicyMiddlewareError::PolicyError)?;
+        self.inner.send_transaction(tx, block).await.map_err(PolicyMiddlewareError::MiddlewareError)
+    }
+}
@@ -0,0 +1,491 @@
+use super::{Policy, PolicyViolation};
+use async_trait::async_trait;
+use ethers_core::{
+    abi::{Abi, Function},
+    types::{transaction::eip2718::TypedTransaction, Address, NameOrAddress, Selector, U256, U64},
+};
+use instant::{Duration, Instant};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Mutex,
+};
+
+/// Returns the recipient of the transaction, if it is an address
+fn recipient(tx: &TypedTransaction) -> Result<Address, PolicyViolation> {
+    match tx.to() {
+        Some(NameOrAddress::Address(to)) => Ok(*to),
+        // ENS names are resolved by the inner middlewares, after the policy was evaluated
+        Some(NameOrAddress::Name(name)) => {
+            Err(PolicyViolation::Custom(format!("unresolved ENS name {name}")))
+        }
+        None => Err(PolicyViolation::MissingRecipient),
+    }
+}
+
+/// A policy which only allows transactions to the given addresses.
+///
+/// Contract deployments are rejected.
+#[derive(Debug, Clone, Default)]
+pub struct ToAllowlist {
+    allowed: HashSet<Address>,
+}
+
+impl ToAllowlist {
+    /// Creates a policy allowing transactions to `addresses`
+    pub fn new(addresses: impl IntoIterator<Item = Address>) -> Self {
+        Self { allowed: addresses.into_iter().collect() }
+    }
+
+    /// Allows transactions to `address`
+    #[must_use]
+    pub fn allow(mut self, address: Address) -> Self {
+        self.allowed.insert(address);
+        self
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Policy for ToAllowlist {
+    type Error = PolicyViolation;
+
+    async fn ensure_can_send(&self, tx: TypedTransaction) -> Result<TypedTransaction, Self::Error> {
+        let to = recipient(&tx)?;
+        if !self.allowed.contains(&to) {
+            return Err(PolicyViolation::RecipientNotAllowed(to))
+        }
+        Ok(tx)
+    }
+}
+
+/// A policy which rejects transactions to the given addresses.
+///
+/// Contract deployments are allowed.
+#[derive(Debug, Clone, Default)]
+pub struct ToDenylist {
+    denied: HashSet<Address>,
+}
+
+impl ToDenylist {
+    /// Creates a policy rejecting transactions to `addresses`
+    pub fn new(addresses: impl IntoIterator<Item = Address>) -> Self {
+        Self { denied: addresses.into_iter().collect() }
+    }
+
+    /// Rejects transactions to `address`
+    #[must_use]
+    pub fn deny(mut self, address: Address) -> Self {
+        self.denied.insert(address);
+        self
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Policy for ToDenylist {
+    type Error = PolicyViolation;
+
+    async fn ensure_can_send(&self, tx: TypedTransaction) -> Result<TypedTransaction, Self::Error> {
+        match recipient(&tx) {
+            Ok(to) if self.denied.contains(&to) => Err(PolicyViolation::RecipientDenied(to)),
+            Err(err @ PolicyViolation::Custom(_)) => Err(err),
+            _ => Ok(tx),
+        }
+    }
+}
+
+/// A policy which only allows calling the given functions of the given contracts.
+///
+/// Transactions to other addresses, contract deployments and transactions whose calldata is
+/// shorter than a selector are rejected. Plain transfers, without calldata, are only allowed to the
+/// addresses passed to [`SelectorAllowlist::allow_transfers`].
+///
+/// ```
+/// use ethers_core::{abi::parse_abi, types::Address};
+/// use ethers_middleware::policy::SelectorAllowlist;
+///
+/// let token = Address::random();
+/// let abi = parse_abi(&["function transfer(address to, uint256 amount) returns (bool)"]).unwrap();
+/// let policy = SelectorAllowlist::new().allow_abi(token, &abi);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SelectorAllowlist {
+    allowed: HashMap<Address, HashSet<Selector>>,
+    transfers: HashSet<Address>,
+}
+
+impl SelectorAllowlist {
+    /// Creates a policy which rejects every transaction
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows calling the function with `selector` on `to`
+    #[must_use]
+    pub fn allow(mut self, to: Address, selector: Selector) -> Self {
+        self.allowed.entry(to).or_default().insert(selector);
+        self
+    }
+
+    /// Allows calling `function` on `to`
+    #[must_use]
+    pub fn allow_function(self, to: Address, function: &Function) -> Self {
+        self.allow(to, function.short_signature())
+    }
+
+    /// Allows calling every function of `abi` on `to`
+    #[must_use]
+    pub fn allow_abi(self, to: Address, abi: &Abi) -> Self {
+        abi.functions().fold(self, |policy, function| policy.allow_function(to, function))
+    }
+
+    /// Allows transactions without calldata to `to`
+    #[must_use]
+    pub fn allow_transfers(mut self, to: Address) -> Self {
+        self.transfers.insert(to);
+        self
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Policy for SelectorAllowlist {
+    type Error = PolicyViolation;
+
+    async fn ensure_can_send(&self, tx: TypedTransaction) -> Result<TypedTransaction, Self::Error> {
+        let to = recipient(&tx)?;
+        let data = tx.data().map(|data| data.as_ref()).unwrap_or_default();
+
+        let selector: Option<Selector> =
+            data.get(..4).and_then(|selector| selector.try_into().ok());
+
+        let allowed = match selector {
+            Some(selector) => {
+                self.allowed.get(&to).map_or(false, |allowed| allowed.contains(&selector))
+            }
+            None => data.is_empty() && self.transfers.contains(&to),
+        };
+        if !allowed {
+            return Err(PolicyViolation::SelectorNotAllowed { to, selector })
+        }
+        Ok(tx)
+    }
+}
+
+/// A policy which caps the value transferred by a transaction.
+#[derive(Debug, Clone, Copy)]
+pub struct MaxValue {
+    max: U256,
+}
+
+impl MaxValue {
+    /// Creates a policy rejecting transactions transferring more than `max` wei
+    pub fn new(max: impl Into<U256>) -> Self {
+        Self { max: max.into() }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Policy for MaxValue {
+    type Error = PolicyViolation;
+
+    async fn ensure_can_send(&self, tx: TypedTransaction) -> Result<TypedTransaction, Self::Error> {
+        let value = tx.value().copied().unwrap_or_default();
+        if value > self.max {
+            return Err(PolicyViolation::ValueTooHigh { value, max: self.max })
+        }
+        Ok(tx)
+    }
+}
+
+/// A policy which caps the gas price of Legacy and EIP-2930 transactions, and the max fee per gas
+/// of EIP-1559 transactions.
+///
+/// Policies are evaluated before the inner middlewares fill the transaction, so transactions
+/// without fees are rejected: the fees the inner middlewares would pick can not be checked.
+#[derive(Debug, Clone, Copy)]
+pub struct MaxFee {
+    max: U256,
+}
+
+impl MaxFee {
+    /// Creates a policy rejecting transactions paying more than `max` wei per gas
+    pub fn new(max: impl Into<U256>) -> Self {
+        Self { max: max.into() }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Policy for MaxFee {
+    type Error = PolicyViolation;
+
+    async fn ensure_can_send(&self, tx: TypedTransaction) -> Result<TypedTransaction, Self::Error> {
+        let fee = match tx {
+            TypedTransaction::Eip1559(ref inner) => inner.max_fee_per_gas,
+            _ => tx.gas_price(),
+        }
+        .ok_or(PolicyViolation::FeeNotSet)?;
+        if fee > self.max {
+            return Err(PolicyViolation::FeeTooHigh { fee, max: self.max })
+        }
+        Ok(tx)
+    }
+}
+
+/// A policy which limits the value transferred over a rolling time window, e.g. a day.
+///
+/// The value of every allowed transaction counts towards the limit, whether or not it is
+/// eventually mined. Combined with [`and`](super::PolicyExt::and), the spend limit should be the
+/// last policy, so that it only records transactions the other policies allowed.
+#[derive(Debug)]
+pub struct SpendLimit {
+    limit: U256,
+    window: Duration,
+    spent: Mutex<VecDeque<(Instant, U256)>>,
+}
+
+impl SpendLimit {
+    /// Creates a policy allowing to transfer `limit` wei per `window`
+    pub fn new(limit: impl Into<U256>, window: Duration) -> Self {
+        Self { limit: limit.into(), window, spent: Default::default() }
+    }
+
+    /// Creates a policy allowing to transfer `limit` wei per 24 hours
+    pub fn daily(limit: impl Into<U256>) -> Self {
+        Self::new(limit, Duration::from_secs(24 * 60 * 60))
+    }
+
+    /// Returns the value transferred in the current window
+    pub fn spent(&self) -> U256 {
+        let mut spent = self.spent.lock().unwrap();
+        self.expire(&mut spent);
+        spent.iter().fold(U256::zero(), |total, (_, value)| total.saturating_add(*value))
+    }
+
+    fn expire(&self, spent: &mut VecDeque<(Instant, U256)>) {
+        while spent.front().map_or(false, |(at, _)| at.elapsed() >= self.window) {
+            spent.pop_front();
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Policy for SpendLimit {
+    type Error = PolicyViolation;
+
+    async fn ensure_can_send(&self, tx: TypedTransaction) -> Result<TypedTransaction, Self::Error> {
+        let value = tx.value().copied().unwrap_or_default();
+        let mut spent = self.spent.lock().unwrap();
+        self.expire(&mut spent);
+
+        let total =
+            spent.iter().fold(U256::zero(), |total, (_, value)| total.saturating_add(*value));
+        if total.saturating_add(value) > self.limit {
+            return Err(PolicyViolation::SpendLimitExceeded {
+                spent: total,
+                value,
+                limit: self.limit,
+            })
+        }
+        if !value.is_zero() {
+            spent.push_back((Instant::now(), value));
+        }
+        Ok(tx)
+    }
+}
+
+/// A policy which pins transactions to a chain: it sets the chain id of transactions which have
+/// none, and rejects transactions for other chains.
+#[derive(Debug, Clone, Copy)]
+pub struct PinnedChainId {
+    chain_id: U64,
+}
+
+impl PinnedChainId {
+    /// Creates a policy pinning transactions to `chain_id`
+    pub fn new(chain_id: impl Into<U64>) -> Self {
+        Self { chain_id: chain_id.into() }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Policy for PinnedChainId {
+    type Error = PolicyViolation;
+
+    async fn ensure_can_send(
+        &self,
+        mut tx: TypedTransaction,
+    ) -> Result<TypedTransaction, Self::Error> {
+        match tx.chain_id() {
+            Some(actual) if actual != self.chain_id => {
+                Err(PolicyViolation::WrongChainId { expected: self.chain_id, actual })
+            }
+            Some(_) => Ok(tx),
+            None => {
+                tx.set_chain_id(self.chain_id);
+                Ok(tx)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::policy::PolicyExt;
+    use ethers_core::{
+        abi::parse_abi,
+        types::{Eip1559TransactionRequest, TransactionRequest},
+    };
+
+    fn pay(to: Address, value: u64) -> TypedTransaction {
+        TransactionRequest::pay(to, value).into()
+    }
+
+    #[tokio::test]
+    async fn recipient_lists() {
+        let (a, b) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let deploy: TypedTransaction = TransactionRequest::new().data(vec![1, 2, 3]).into();
+
+        let allowlist = ToAllowlist::new([a]);
+        assert!(allowlist.ensure_can_send(pay(a, 1)).await.is_ok());
+        assert_eq!(
+            allowlist.ensure_can_send(pay(b, 1)).await,
+            Err(PolicyViolation::RecipientNotAllowed(b))
+        );
+        assert_eq!(
+            allowlist.ensure_can_send(deploy.clone()).await,
+            Err(PolicyViolation::MissingRecipient)
+        );
+
+        let denylist = ToDenylist::new([a]);
+        assert_eq!(
+            denylist.ensure_can_send(pay(a, 1)).await,
+            Err(PolicyViolation::RecipientDenied(a))
+        );
+        assert!(denylist.ensure_can_send(pay(b, 1)).await.is_ok());
+        assert!(denylist.ensure_can_send(deploy).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn selector_allowlist() {
+        let (token, other) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let abi = parse_abi(&[
+            "function transfer(address to, uint256 amount) returns (bool)",
+            "function approve(address spender, uint256 amount) returns (bool)",
+        ])
+        .unwrap();
+        let policy = SelectorAllowlist::new().allow_abi(token, &abi).allow_transfers(other);
+        let call = |to, data: Vec<u8>| -> TypedTransaction {
+            TransactionRequest::new().to(to).data(data).into()
+        };
+
+        let transfer = abi.function("transfer").unwrap().short_signature();
+        assert!(policy.ensure_can_send(call(token, transfer.to_vec())).await.is_ok());
+        assert_eq!(
+            policy.ensure_can_send(call(other, transfer.to_vec())).await,
+            Err(PolicyViolation::SelectorNotAllowed { to: other, selector: Some(transfer) })
+        );
+        assert_eq!(
+            policy.ensure_can_send(call(token, vec![1, 2, 3, 4, 5])).await,
+            Err(PolicyViolation::SelectorNotAllowed { to: token, selector: Some([1, 2, 3, 4]) })
+        );
+        assert_eq!(
+            policy.ensure_can_send(call(token, vec![1, 2])).await,
+            Err(PolicyViolation::SelectorNotAllowed { to: token, selector: None })
+        );
+        assert!(policy.ensure_can_send(pay(other, 1)).await.is_ok());
+        assert_eq!(
+            policy.ensure_can_send(pay(token, 1)).await,
+            Err(PolicyViolation::SelectorNotAllowed { to: token, selector: None })
+        );
+    }
+
+    #[tokio::test]
+    async fn caps() {
+        let to = Address::repeat_byte(1);
+        assert!(MaxValue::new(10).ensure_can_send(pay(to, 10)).await.is_ok());
+        assert_eq!(
+            MaxValue::new(10).ensure_can_send(pay(to, 11)).await,
+            Err(PolicyViolation::ValueTooHigh { value: 11.into(), max: 10.into() })
+        );
+
+        let policy = MaxFee::new(100);
+        assert_eq!(policy.ensure_can_send(pay(to, 1)).await, Err(PolicyViolation::FeeNotSet));
+        let legacy = TransactionRequest::pay(to, 1).gas_price(100);
+        assert!(policy.ensure_can_send(legacy.into()).await.is_ok());
+        let eip1559 = Eip1559TransactionRequest::new().to(to).max_fee_per_gas(101);
+        assert_eq!(
+            policy.ensure_can_send(eip1559.into()).await,
+            Err(PolicyViolation::FeeTooHigh { fee: 101.into(), max: 100.into() })
+        );
+    }
+
+    #[tokio::test]
+    async fn spend_limit_window() {
+        let to = Address::repeat_byte(1);
+        let policy = SpendLimit::new(100, Duration::from_millis(50));
+
+        assert!(policy.ensure_can_send(pay(to, 60)).await.is_ok());
+        assert!(policy.ensure_can_send(pay(to, 40)).await.is_ok());
+        assert_eq!(
+            policy.ensure_can_send(pay(to, 1)).await,
+            Err(PolicyViolation::SpendLimitExceeded {
+                spent: 100.into(),
+                value: 1.into(),
+                limit: 100.into()
+            })
+        );
+        assert_eq!(policy.spent(), 100.into());
+
+        tokio::time::sleep(std::time::Duration::from_millis(60)).await;
+        assert_eq!(policy.spent(), 0.into());
+        assert!(policy.ensure_can_send(pay(to, 100)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn pinned_chain_id() {
+        let to = Address::repeat_byte(1);
+        let policy = PinnedChainId::new(1u64);
+
+        let tx = policy.ensure_can_send(pay(to, 1)).await.unwrap();
+        assert_eq!(tx.chain_id(), Some(1u64.into()));
+        let mut other = pay(to, 1);
+        other.set_chain_id(5u64);
+        assert_eq!(
+            policy.ensure_can_send(other).await,
+            Err(PolicyViolation::WrongChainId { expected: 1u64.into(), actual: 5u64.into() })
+        );
+    }
+
+    #[tokio::test]
+    async fn combinators() {
+        let (treasury, exchange, other) =
+            (Address::repeat_byte(1), Address::repeat_byte(2), Address::repeat_byte(3));
+        let policy = ToAllowlist::new([treasury])
+            .or(ToDenylist::new([exchange]).and(MaxValue::new(10)))
+            .and(PinnedChainId::new(1u64));
+
+        assert!(policy.ensure_can_send(pay(treasury, 1000)).await.is_ok());
+        assert!(policy.ensure_can_send(pay(other, 10)).await.is_ok());
+        assert_eq!(
+            policy.ensure_can_send(pay(other, 11)).await,
+            Err(PolicyViolation::NoneAllowed(vec![
+                PolicyViolation::RecipientNotAllowed(other),
+                PolicyViolation::ValueTooHigh { value: 11.into(), max: 10.into() },
+            ]))
+        );
+        assert_eq!(
+            policy.ensure_can_send(pay(exchange, 1)).await,
+            Err(PolicyViolation::NoneAllowed(vec![
+                PolicyViolation::RecipientNotAllowed(exchange),
+                PolicyViolation::RecipientDenied(exchange),
+            ]))
+        );
+    }
+}
@@ -1,6 +1,8 @@
 use ethers_core::types::{
+    spoof,
     transaction::{eip2718::TypedTransaction, eip2930::AccessListWithGasUsed},
-    Address, BlockId, Bytes, Chain, Signature, TransactionRequest, U256,
+    Address, BlockId, Bytes, Chain, Signature, SimulatePayload, SimulatedBlock, TransactionRequest,
+    U256,
 };
 use ethers_providers::{maybe, Middleware, MiddlewareError, PendingTransaction};
 use ethers_signers::Signer;
@@ -354,6 +356,44 @@ where
         let tx = self.set_tx_from_if_none(tx);
         self.inner().call(&tx, block).await.map_err(SignerMiddlewareError::MiddlewareError)
     }
+
+    async fn estimate_gas_with_overrides(
+        &self,
+        tx: &TypedTransaction,
+        block: Option<BlockId>,
+        overrides: &spoof::CallOverrides,
+    ) -> Result<U256, Self::Error> {
+        let tx = self.set_tx_from_if_none(tx);
+        self.inner
+            .estimate_gas_with_overrides(&tx, block, overrides)
+            .await
+            .map_err(SignerMiddlewareError::MiddlewareError)
+    }
+
+    async fn call_with_overrides(
+        &self,
+        tx: &TypedTransaction,
+        block: Option<BlockId>,
+        overrides: &spoof::CallOverrides,
+    ) -> Result<Bytes, Self::Error> {
+        let tx = self.set_tx_from_if_none(tx);
+        self.inner
+            .call_with_overrides(&tx, block, overrides)
+            .await
+            .map_err(SignerMiddlewareError::MiddlewareError)
+    }
+
+    async fn simulate(
+        &self,
+        payload: &SimulatePayload,
+        block: Option<BlockId>,
+    ) -> Result<Vec<SimulatedBlock>, Self::Error> {
+        let mut payload = payload.clone();
+        for call in payload.block_state_calls.iter_mut().flat_map(|block| block.calls.iter_mut()) {
+            *call = self.set_tx_from_if_none(call);
+        }
+        self.inner.simulate(&payload, block).await.map_err(SignerMiddlewareError::MiddlewareError)
+    }
 }
 
 #[cfg(all(test, not(feature = "celo")))]
@@ -0,0 +1,409 @@
+use async_trait::async_trait;
+use ethers_contract::EthError;
+use ethers_core::types::{
+    transaction::eip2718::TypedTransaction, Address, BlockId, BlockNumber, Bytes,
+    GethDebugBuiltInTracerConfig, GethDebugBuiltInTracerType, GethDebugTracerConfig,
+    GethDebugTracerType, GethDebugTracingCallOptions, GethTrace, GethTraceFrame, PreStateConfig,
+    PreStateFrame, I256, U256,
+};
+use ethers_providers::{JsonRpcError, Middleware, MiddlewareError, PendingTransaction};
+use std::{collections::BTreeMap, fmt, sync::Arc};
+use thiserror::Error;
+
+type SkipFn = Arc<dyn Fn(&TypedTransaction) -> bool + Send + Sync>;
+
+/// The outcome of a successful simulation
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Simulation {
+    /// The data returned by the call
+    pub output: Bytes,
+    /// The balance changes caused by the transaction, including the gas paid by the sender. Only
+    /// traced if enabled with [`SimulateBeforeSend::with_balance_changes`].
+    pub balance_changes: BTreeMap<Address, BalanceChange>,
+}
+
+/// The balance of an account before and after a simulated transaction
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BalanceChange {
+    pub before: U256,
+    pub after: U256,
+}
+
+impl BalanceChange {
+    /// Returns the signed difference between the balances
+    pub fn delta(&self) -> I256 {
+        I256::from_raw(self.after).overflowing_sub(I256::from_raw(self.before)).0
+    }
+}
+
+/// Middleware which simulates transactions with `eth_call` on the pending block before sending
+/// them, and refuses to send those which revert.
+///
+/// A copy of each transaction is filled by the inner middlewares and simulated, so that the
+/// simulation runs with the sender, gas and fees that will be broadcast. The copy is filled with
+/// the sender's pending nonce, and the transaction is sent with the nonce of the caller, if any,
+/// so that a nonce manager below only hands out a nonce to the transactions that are sent.
+///
+/// Optionally, the balance changes of the transaction are traced with the `prestateTracer` of
+/// `debug_traceCall`, and transactions decreasing the sender's balance by more than a maximum,
+/// gas included, are refused too.
+///
+/// ```no_run
+/// use ethers_core::types::{Address, TransactionRequest};
+/// use ethers_middleware::simulate::{SimulateBeforeSend, SimulateBeforeSendError};
+/// use ethers_providers::{Http, Middleware, Provider};
+///
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let provider = Provider::<Http>::try_from("http://localhost:8545")?;
+/// let provider = SimulateBeforeSend::new(provider)
+///     .with_max_spend(ethers_core::utils::parse_ether(1)?)
+///     // transactions without calldata can not revert
+///     .with_skip(|tx| tx.data().map_or(true, |data| data.is_empty()));
+///
+/// let tx = TransactionRequest::new().to(Address::random()).data(vec![0xde, 0xad, 0xbe, 0xef]);
+/// match provider.send_transaction(tx, None).await {
+///     Err(SimulateBeforeSendError::Reverted { reason, .. }) => println!("refused: {reason:?}"),
+///     _ => {}
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct SimulateBeforeSend<M> {
+    inner: M,
+    balance_changes: bool,
+    max_spend: Option<U256>,
+    skip: Option<SkipFn>,
+}
+
+impl<M: fmt::Debug> fmt::Debug for SimulateBeforeSend<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimulateBeforeSend")
+            .field("inner", &self.inner)
+            .field("balance_changes", &self.balance_changes)
+            .field("max_spend", &self.max_spend)
+            .field("skip", &self.skip.is_some())
+            .finish()
+    }
+}
+
+impl<M> SimulateBeforeSend<M>
+where
+    M: Middleware,
+{
+    /// Creates a middleware which simulates every sent transaction
+    pub fn new(inner: M) -> Self {
+        Self { inner, balance_changes: false, max_spend: None, skip: None }
+    }
+
+    /// Also traces the balance changes of simulated transactions with the `prestateTracer`. The
+    /// node must support `debug_traceCall`.
+    #[must_use]
+    pub fn with_balance_changes(mut self, balance_changes: bool) -> Self {
+        self.balance_changes = balance_changes;
+        self
+    }
+
+    /// Refuses transactions decreasing the sender's balance by more than `max_spend`, gas
+    /// included. Enables tracing of the balance changes.
+    #[must_use]
+    pub fn with_max_spend<T: Into<U256>>(mut self, max_spend: T) -> Self {
+        self.balance_changes = true;
+        self.max_spend = Some(max_spend.into());
+        self
+    }
+
+    /// Skips the simulation of the transactions for which `skip` returns true
+    #[must_use]
+    pub fn with_skip<F>(mut self, skip: F) -> Self
+    where
+        F: Fn(&TypedTransaction) -> bool + Send + Sync + 'static,
+    {
+        self.skip = Some(Arc::new(skip));
+        self
+    }
+
+    /// Simulates the filled transaction on the pending block
+    pub async fn simulate(
+        &self,
+        tx: &TypedTransaction,
+    ) -> Result<Simulation, SimulateBeforeSendError<M>> {
+        let block = Some(BlockNumber::Pending.into());
+        let output = self.inner.call(tx, block).await.map_err(revert_or_err)?;
+        let mut simulation = Simulation { output, ..Default::default() };
+
+        if self.balance_changes {
+            let mut options = GethDebugTracingCallOptions::default();
+            options.tracing_options.tracer = Some(GethDebugTracerType::BuiltInTracer(
+                GethDebugBuiltInTracerType::PreStateTracer,
+            ));
+            options.tracing_options.tracer_config = Some(GethDebugTracerConfig::BuiltInTracer(
+                GethDebugBuiltInTracerConfig::PreStateTracer(PreStateConfig {
+                    diff_mode: Some(true),
+                }),
+            ));
+
+            let trace = self
+                .inner
+                .debug_trace_call(tx.clone(), block, options)
+                .await
+                .map_err(SimulateBeforeSendError::MiddlewareError)?;
+            let GethTrace::Known(GethTraceFrame::PreStateTracer(PreStateFrame::Diff(diff))) = trace
+            else {
+                return Err(SimulateBeforeSendError::UnexpectedTrace(Box::new(trace)))
+            };
+
+            for (address, post) in diff.post {
+                // accounts whose balance did not change are only in `pre`
+                let Some(after) = post.balance else { continue };
+                let before = diff.pre.get(&address).and_then(|pre| pre.balance).unwrap_or_default();
+                if before != after {
+                    simulation.balance_changes.insert(address, BalanceChange { before, after });
+                }
+            }
+        }
+
+        Ok(simulation)
+    }
+
+    /// Sends the transaction without simulating it
+    pub async fn send_without_simulation<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        tx: T,
+        block: Option<BlockId>,
+    ) -> Result<PendingTransaction<'_, M::Provider>, SimulateBeforeSendError<M>> {
+        self.inner
+            .send_transaction(tx, block)
+            .await
+            .map_err(SimulateBeforeSendError::MiddlewareError)
+    }
+}
+
+/// Error thrown by the [`SimulateBeforeSend`] middleware
+#[derive(Debug, Error)]
+pub enum SimulateBeforeSendError<M: Middleware> {
+    /// Thrown when an internal middleware errors
+    #[error("{0}")]
+    MiddlewareError(M::Error),
+
+    /// Thrown when the simulated transaction reverts. `reason` is decoded from `Error(string)`
+    /// revert data.
+    #[error("transaction reverted: {}", .reason.as_deref().unwrap_or("no reason"))]
+    Reverted { reason: Option<String>, data: Bytes },
+
+    /// Thrown when the transaction decreases the sender's balance by more than the maximum
+    #[error("transaction spends {spent}, more than the maximum of {max}")]
+    SpendTooHigh { spent: U256, max: U256 },
+
+    /// Thrown when `debug_traceCall` does not return a `prestateTracer` diff
+    #[error("unexpected trace: {0:?}")]
+    UnexpectedTrace(Box<GethTrace>),
+}
+
+impl<M: Middleware> MiddlewareError for SimulateBeforeSendError<M> {
+    type Inner = M::Error;
+
+    fn from_err(src: M::Error) -> Self {
+        SimulateBeforeSendError::MiddlewareError(src)
+    }
+
+    fn as_inner(&self) -> Option<&Self::Inner> {
+        match self {
+            SimulateBeforeSendError::MiddlewareError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Converts reverts returned by the node to [`SimulateBeforeSendError::Reverted`]
+fn revert_or_err<M: Middleware>(err: M::Error) -> SimulateBeforeSendError<M> {
+    match err.as_error_response().and_then(JsonRpcError::as_revert_data) {
+        Some(data) => {
+            SimulateBeforeSendError::Reverted { reason: String::decode_with_selector(&data), data }
+        }
+        None => SimulateBeforeSendError::MiddlewareError(err),
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<M> Middleware for SimulateBeforeSend<M>
+where
+    M: Middleware,
+{
+    type Error = SimulateBeforeSendError<M>;
+    type Provider = M::Provider;
+    type Inner = M;
+
+    fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    /// Simulates a filled copy of the transaction, then sends it if it does not revert
+    async fn send_transaction<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        tx: T,
+        block: Option<BlockId>,
+    ) -> Result<PendingTransaction<'_, Self::Provider>, Self::Error> {
+        let mut tx = tx.into();
+        if self.skip.as_ref().map_or(false, |skip| skip(&tx)) {
+            return self.send_without_simulation(tx, block).await
+        }
+
+        let mut simulated = tx.clone();
+        if simulated.nonce().is_none() {
+            // a nonce manager below would hand out a nonce to the copy, and never get it back
+            if let Some(from) = simulated.from().copied().or_else(|| self.default_sender()) {
+                let nonce = self
+                    .inner
+                    .get_transaction_count(from, Some(BlockNumber::Pending.into()))
+                    .await
+                    .map_err(SimulateBeforeSendError::MiddlewareError)?;
+                simulated.set_nonce(nonce);
+            }
+        }
+
+        // filling estimates the gas, which already fails for reverting transactions
+        self.inner.fill_transaction(&mut simulated, block).await.map_err(revert_or_err)?;
+        let simulation = self.simulate(&simulated).await?;
+
+        if let (Some(max), Some(from)) = (self.max_spend, simulated.from()) {
+            if let Some(change) = simulation.balance_changes.get(from) {
+                let spent = change.before.saturating_sub(change.after);
+                if spent > max {
+                    return Err(SimulateBeforeSendError::SpendTooHigh { spent, max })
+                }
+            }
+        }
+
+        // send from the simulated sender, e.g. the one picked by a `MultiSignerMiddleware`
+        if let (None, Some(from)) = (tx.from(), simulated.from()) {
+            tx.set_from(*from);
+        }
+        self.send_without_simulation(tx, block).await
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::{
+        test_utils::{accept_transactions, sent_transactions},
+        NonceManagerMiddleware,
+    };
+    use ethers_core::{
+        abi::AbiEncode,
+        types::{TransactionRequest, TxHash},
+    };
+    use ethers_providers::{MockResponse, Provider};
+    use serde_json::json;
+
+    fn revert(data: &[u8]) -> MockResponse {
+        MockResponse::Error(JsonRpcError {
+            code: 3,
+            message: "execution reverted".to_string(),
+            data: Some(json!(Bytes::from(data.to_vec()))),
+        })
+    }
+
+    fn tx() -> TypedTransaction {
+        TransactionRequest::new()
+            .from(Address::repeat_byte(1))
+            .to(Address::repeat_byte(2))
+            .data(vec![1, 2, 3, 4])
+            .gas(100_000)
+            .gas_price(10)
+            .nonce(0)
+            .into()
+    }
+
+    #[tokio::test]
+    async fn refuses_reverting_transactions() {
+        let (provider, mock) = Provider::mocked();
+        let provider = SimulateBeforeSend::new(provider);
+
+        let mut data = String::selector().to_vec();
+        data.extend("not owner".to_string().encode());
+        let data2 = data.clone();
+        mock.on("eth_call", move |_| revert(&data2));
+
+        let err = provider.send_transaction(tx(), None).await.unwrap_err();
+        let SimulateBeforeSendError::Reverted { reason, data: revert_data } = err else {
+            panic!("{err:?}")
+        };
+        assert_eq!(reason.as_deref(), Some("not owner"));
+        assert_eq!(revert_data.to_vec(), data);
+        mock.assert_any_request("eth_call", (&tx(), "pending")).unwrap();
+        assert!(mock.assert_any_request("eth_sendTransaction", (&tx(),)).is_err());
+    }
+
+    #[tokio::test]
+    async fn sends_simulated_and_skipped_transactions() {
+        let (provider, mock) = Provider::mocked();
+        let provider = SimulateBeforeSend::new(provider)
+            .with_skip(|tx| tx.to_addr() == Some(&Address::repeat_byte(3)));
+        mock.on("eth_call", |_| MockResponse::Value(json!("0x")));
+        mock.on("eth_sendTransaction", |_| MockResponse::Value(json!(TxHash::repeat_byte(7))));
+
+        provider.send_transaction(tx(), None).await.unwrap();
+        mock.assert_any_request("eth_call", (&tx(), "pending")).unwrap();
+
+        let mut skipped = tx();
+        skipped.set_to(Address::repeat_byte(3));
+        provider.send_transaction(skipped.clone(), None).await.unwrap();
+        assert!(mock.assert_any_request("eth_call", (&skipped, "pending")).is_err());
+    }
+
+    #[tokio::test]
+    async fn refuses_transactions_spending_too_much() {
+        let (provider, mock) = Provider::mocked();
+        let provider = SimulateBeforeSend::new(provider).with_max_spend(1000);
+        let from = Address::repeat_byte(1);
+        mock.on("eth_call", |_| MockResponse::Value(json!("0x")));
+        mock.on("debug_traceCall", move |_| {
+            MockResponse::Value(json!({
+                "pre": { format!("{from:?}"): { "balance": "0x1000", "nonce": 0 } },
+                "post": { format!("{from:?}"): { "balance": "0x100", "nonce": 1 } },
+            }))
+        });
+
+        let err = provider.send_transaction(tx(), None).await.unwrap_err();
+        assert!(
+            matches!(err, SimulateBeforeSendError::SpendTooHigh { spent, .. } if spent == U256::from(0xf00)),
+            "{err:?}"
+        );
+
+        let simulation = provider.simulate(&tx()).await.unwrap();
+        let change = simulation.balance_changes[&from];
+        assert_eq!(change.delta(), I256::from(-0xf00));
+    }
+
+    #[tokio::test]
+    async fn leaves_the_nonce_to_nonce_managers() {
+        let (provider, mock) = Provider::mocked();
+        let from = Address::repeat_byte(1);
+        let reverting = Address::repeat_byte(3);
+        mock.on("eth_getTransactionCount", |_| MockResponse::Value(json!(U256::from(5))));
+        mock.on("eth_call", move |params| {
+            if params[0]["to"] == json!(reverting) {
+                revert(&[])
+            } else {
+                MockResponse::Value(json!("0x"))
+            }
+        });
+        accept_transactions(&mock);
+        let provider = SimulateBeforeSend::new(NonceManagerMiddleware::new(provider, from));
+
+        let tx = |to| TransactionRequest::new().from(from).to(to).gas(100_000).gas_price(10);
+        let err = provider.send_transaction(tx(reverting), None).await.unwrap_err();
+        assert!(matches!(err, SimulateBeforeSendError::Reverted { .. }), "{err:?}");
+
+        // the reverted transaction did not take a nonce
+        provider.send_transaction(tx(Address::repeat_byte(2)), None).await.unwrap();
+
+        let nonces: Vec<_> =
+            sent_transactions(&mock).iter().map(|(_, tx)| tx.nonce().unwrap().as_u64()).collect();
+        assert_eq!(nonces, vec![5]);
+    }
+}
@@ -0,0 +1,64 @@
+//! Helpers shared by the tests of the middlewares
+
+use ethers_core::{
+    types::{transaction::eip2718::TypedTransaction, Bytes, TxHash},
+    utils::{keccak256, rlp::Rlp},
+};
+use ethers_providers::{JsonRpcError, MockProvider, MockResponse};
+use serde_json::{json, Value};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+const SEND_METHODS: [&str; 2] = ["eth_sendTransaction", "eth_sendRawTransaction"];
+
+/// The error of a node rejecting a transaction whose sender can not pay for it
+pub(crate) const INSUFFICIENT_FUNDS: &str = "insufficient funds for gas * price + value";
+
+/// Answers `eth_sendTransaction` and `eth_sendRawTransaction` requests with the hash
+/// [`sent_transactions`] reports for them
+pub(crate) fn accept_transactions(mock: &MockProvider) {
+    for method in SEND_METHODS {
+        mock.on(method, move |params| MockResponse::Value(json!(decode(method, params).0)));
+    }
+}
+
+/// Answers the first `eth_sendTransaction` and `eth_sendRawTransaction` requests with JSON-RPC
+/// errors with the messages `errors`, in order, and the later ones like [`accept_transactions`]
+pub(crate) fn reject_transactions(mock: &MockProvider, errors: &[&str]) {
+    let errors: Arc<Mutex<VecDeque<String>>> =
+        Arc::new(Mutex::new(errors.iter().map(|error| error.to_string()).collect()));
+    for method in SEND_METHODS {
+        let errors = errors.clone();
+        mock.on(method, move |params| match errors.lock().unwrap().pop_front() {
+            Some(message) => {
+                MockResponse::Error(JsonRpcError { code: -32000, message, data: None })
+            }
+            None => MockResponse::Value(json!(decode(method, params).0)),
+        });
+    }
+}
+
+/// Returns the hash and the transaction of all `eth_sendTransaction` and `eth_sendRawTransaction`
+/// requests made to `mock`, in order. The `from` of raw transactions is recovered from their
+/// signature, the hash of unsigned transactions is the hash of their JSON encoding.
+pub(crate) fn sent_transactions(mock: &MockProvider) -> Vec<(TxHash, TypedTransaction)> {
+    mock.requests()
+        .into_iter()
+        .filter(|(method, _)| SEND_METHODS.contains(&method.as_str()))
+        .map(|(method, params)| decode(&method, &params))
+        .collect()
+}
+
+fn decode(method: &str, params: &Value) -> (TxHash, TypedTransaction) {
+    if method == "eth_sendRawTransaction" {
+        let raw: Bytes = serde_json::from_value(params[0].clone()).unwrap();
+        let (mut tx, signature) = TypedTransaction::decode_signed(&Rlp::new(&raw)).unwrap();
+        tx.set_from(signature.recover(tx.sighash()).unwrap());
+        (keccak256(&raw).into(), tx)
+    } else {
+        let tx = serde_json::from_value(params[0].clone()).unwrap();
+        (keccak256(serde_json::to_vec(&params[0]).unwrap()).into(), tx)
+    }
+}
@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use ethers_core::types::{
-    transaction::eip2718::TypedTransaction, Block, BlockId, BlockNumber, Bytes, FilterBlockOption,
-    NameOrAddress, Transaction, TransactionReceipt, TxHash, U256,
+    spoof, transaction::eip2718::TypedTransaction, Block, BlockId, BlockNumber, Bytes,
+    FilterBlockOption, NameOrAddress, Transaction, TransactionReceipt, TxHash, U256,
 };
 use std::sync::Arc;
 use thiserror::Error;
@@ -221,6 +221,20 @@ where
         self.inner().call(tx, block).await.map_err(ethers_providers::MiddlewareError::from_err)
     }
 
+    async fn call_with_overrides(
+        &self,
+        tx: &TypedTransaction,
+        block: Option<BlockId>,
+        overrides: &spoof::CallOverrides,
+    ) -> Result<Bytes, Self::Error> {
+        let block = self.normalize_block_id(block).await?;
+
+        self.inner()
+            .call_with_overrides(tx, block, overrides)
+            .await
+            .map_err(ethers_providers::MiddlewareError::from_err)
+    }
+
     async fn get_balance<T: Into<NameOrAddress> + Send + Sync>(
         &self,
         from: T,
@@ -0,0 +1,132 @@
+use super::{UserOperation, UserOperationGasEstimate};
+use ethers_core::types::{Address, Bytes, Log, TransactionReceipt, H256, U256};
+use ethers_providers::{JsonRpcClient, Provider, ProviderError};
+use serde::{Deserialize, Serialize};
+
+/// The receipt of a user operation included by a bundler
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperationReceipt {
+    pub user_op_hash: H256,
+    pub entry_point: Address,
+    pub sender: Address,
+    pub nonce: U256,
+    #[serde(default)]
+    pub paymaster: Option<Address>,
+    pub actual_gas_cost: U256,
+    pub actual_gas_used: U256,
+    /// Whether the call of the operation succeeded
+    pub success: bool,
+    /// The revert data of the call, if it failed
+    #[serde(default)]
+    pub reason: Option<Bytes>,
+    /// The logs emitted by the operation
+    pub logs: Vec<Log>,
+    /// The receipt of the bundle transaction
+    pub receipt: TransactionReceipt,
+}
+
+/// A client of the ERC-4337 bundler RPC methods.
+///
+/// ```no_run
+/// use ethers_middleware::transformer::erc4337::{BundlerClient, UserOperation, ENTRY_POINT_V07};
+/// use ethers_providers::{Http, Provider};
+///
+/// # async fn foo(mut op: UserOperation) -> Result<(), Box<dyn std::error::Error>> {
+/// let bundler = BundlerClient::new(Provider::<Http>::try_from("http://localhost:4337")?);
+///
+/// let estimate = bundler.estimate_user_operation_gas(&op, ENTRY_POINT_V07).await?;
+/// op.set_gas(&estimate);
+/// // sign the operation here
+/// let hash = bundler.send_user_operation(&op, ENTRY_POINT_V07).await?;
+/// let receipt = bundler.get_user_operation_receipt(hash).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct BundlerClient<P> {
+    provider: Provider<P>,
+}
+
+impl<P: JsonRpcClient> BundlerClient<P> {
+    /// Creates a client of the bundler behind `provider`
+    pub fn new(provider: Provider<P>) -> Self {
+        Self { provider }
+    }
+
+    /// Returns the provider of the bundler
+    pub fn provider(&self) -> &Provider<P> {
+        &self.provider
+    }
+
+    /// Submits the signed operation to the bundler's mempool, and returns its `userOpHash`
+    pub async fn send_user_operation(
+        &self,
+        op: &UserOperation,
+        entry_point: Address,
+    ) -> Result<H256, ProviderError> {
+        self.provider.request("eth_sendUserOperation", (op, entry_point)).await
+    }
+
+    /// Estimates the gas limits of the operation. The signature should be a dummy signature of
+    /// the right length, which the account does not reject before checking it.
+    pub async fn estimate_user_operation_gas(
+        &self,
+        op: &UserOperation,
+        entry_point: Address,
+    ) -> Result<UserOperationGasEstimate, ProviderError> {
+        self.provider.request("eth_estimateUserOperationGas", (op, entry_point)).await
+    }
+
+    /// Returns the receipt of the operation with `user_op_hash`, or `None` if it was not included
+    /// yet
+    pub async fn get_user_operation_receipt(
+        &self,
+        user_op_hash: H256,
+    ) -> Result<Option<UserOperationReceipt>, ProviderError> {
+        self.provider.request("eth_getUserOperationReceipt", [user_op_hash]).await
+    }
+
+    /// Returns the entry points supported by the bundler
+    pub async fn supported_entry_points(&self) -> Result<Vec<Address>, ProviderError> {
+        self.provider.request("eth_supportedEntryPoints", ()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformer::erc4337::{UserOperationV07, ENTRY_POINT_V07};
+    use ethers_providers::MockResponse;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn talks_to_bundler() {
+        let (provider, mock) = Provider::mocked();
+        let bundler = BundlerClient::new(provider);
+        let mut op: UserOperation =
+            UserOperationV07 { sender: Address::repeat_byte(1), ..Default::default() }.into();
+        let hash = op.hash(ENTRY_POINT_V07, 1u64);
+
+        mock.on("eth_estimateUserOperationGas", |_| {
+            MockResponse::Value(json!({
+                "preVerificationGas": "0xb000",
+                "verificationGasLimit": "0x10000",
+                "callGasLimit": "0x5000",
+            }))
+        });
+        mock.on("eth_sendUserOperation", move |_| MockResponse::Value(json!(hash)));
+        mock.push::<Option<UserOperationReceipt>, _>(None).unwrap();
+
+        let estimate = bundler.estimate_user_operation_gas(&op, ENTRY_POINT_V07).await.unwrap();
+        op.set_gas(&estimate);
+        let UserOperation::V07(ref inner) = op else { unreachable!() };
+        assert_eq!(inner.call_gas_limit, 0x5000.into());
+        assert_eq!(inner.pre_verification_gas, 0xb000.into());
+
+        assert_eq!(bundler.send_user_operation(&op, ENTRY_POINT_V07).await.unwrap(), hash);
+        mock.assert_any_request("eth_sendUserOperation", (&op, ENTRY_POINT_V07)).unwrap();
+
+        assert_eq!(bundler.get_user_operation_receipt(hash).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,464 @@
+mod bundler;
+pub use bundler::{BundlerClient, UserOperationReceipt};
+
+mod user_operation;
+pub use user_operation::{
+    UserOperation, UserOperationGasEstimate, UserOperationV06, UserOperationV07, ENTRY_POINT_V06,
+    ENTRY_POINT_V07,
+};
+
+use super::{nonce::ProxyNonce, Transformer, TransformerError};
+use async_trait::async_trait;
+use ethers_core::{
+    abi::{self, Token},
+    types::{transaction::eip2718::TypedTransaction, Address, Bytes, U256},
+    utils::id,
+};
+use ethers_signers::{LocalWallet, Signer};
+
+/// The signature of `SimpleAccount`'s function executing a call.
+const EXECUTE: &str = "execute(address,uint256,bytes)";
+
+/// The signatures of the `EntryPoint`'s function executing a bundle of operations.
+const HANDLE_OPS_V06: &str = "handleOps((address,uint256,bytes,bytes,uint256,uint256,uint256,uint256,uint256,bytes,bytes)[],address)";
+const HANDLE_OPS_V07: &str =
+    "handleOps((address,uint256,bytes,bytes,bytes32,uint256,bytes32,bytes,bytes)[],address)";
+
+/// The version of an ERC-4337 `EntryPoint`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntryPointVersion {
+    V06,
+    V07,
+}
+
+impl EntryPointVersion {
+    /// The address of the canonical deployment of the `EntryPoint`
+    pub fn address(&self) -> Address {
+        match self {
+            EntryPointVersion::V06 => ENTRY_POINT_V06,
+            EntryPointVersion::V07 => ENTRY_POINT_V07,
+        }
+    }
+}
+
+/// Returns the calldata of the `EntryPoint`'s `handleOps`, executing `ops` and paying their fees
+/// to `beneficiary`. Fails if `ops` mixes operations of both versions, which no `EntryPoint`
+/// accepts in one batch.
+pub fn handle_ops_data(
+    ops: Vec<UserOperation>,
+    beneficiary: Address,
+) -> Result<Bytes, TransformerError> {
+    let version = ops.first().map(UserOperation::version).unwrap_or(EntryPointVersion::V06);
+    if ops.iter().any(|op| op.version() != version) {
+        return Err(TransformerError::MixedEntryPointVersions)
+    }
+    let selector = match version {
+        EntryPointVersion::V06 => id(HANDLE_OPS_V06),
+        EntryPointVersion::V07 => id(HANDLE_OPS_V07),
+    };
+    let ops = ops.into_iter().map(UserOperation::into_token).collect();
+    let args = abi::encode(&[Token::Array(ops), Token::Address(beneficiary)]);
+    Ok([&selector[..], &args].concat().into())
+}
+
+/// Transforms transactions into user operations of an ERC-4337 smart account, owned by a
+/// [`Signer`].
+///
+/// The call of the transaction is wrapped into the account's `execute(address,uint256,bytes)`,
+/// as implemented by `SimpleAccount` and most ECDSA owned accounts, and the operation is signed
+/// by the owner with [`UserOperation::sign`]. [`UserOperationTransformer::user_operation`] returns
+/// the signed operation, to be sent to a [`BundlerClient`]. As a [`Transformer`], the operation is
+/// instead executed by the sender of the transaction through the `EntryPoint`'s `handleOps`, which
+/// is useful when no bundler is available.
+///
+/// Like the [`SafeTransformer`](super::SafeTransformer), the transformer works offline and tracks
+/// the nonce of the account itself, with the nonce key 0. The nonce of an operation which the
+/// [`TransformerMiddleware`](super::TransformerMiddleware) fails to send, or which is passed to
+/// [`UserOperationTransformer::release`], is used again by the next operation. Since the owner
+/// signs asynchronously, only [`Transformer::transform_async`] transforms transactions.
+///
+/// ```
+/// use ethers_core::types::{Address, TransactionRequest};
+/// use ethers_middleware::transformer::erc4337::{EntryPointVersion, UserOperationTransformer};
+/// use ethers_signers::LocalWallet;
+///
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let owner = LocalWallet::new(&mut rand::thread_rng());
+/// let account = Address::random();
+/// let transformer = UserOperationTransformer::new(account, owner, EntryPointVersion::V07, 1u64, 0);
+///
+/// let tx = TransactionRequest::pay(Address::random(), 100).gas_price(1_000_000_000).into();
+/// let op = transformer.user_operation(&tx).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct UserOperationTransformer<S = LocalWallet> {
+    account: Address,
+    owner: S,
+    version: EntryPointVersion,
+    entry_point: Address,
+    chain_id: U256,
+    nonce: ProxyNonce,
+    factory: Option<(Address, Bytes)>,
+    call_gas_limit: U256,
+    verification_gas_limit: U256,
+    pre_verification_gas: U256,
+    beneficiary: Address,
+}
+
+impl<S: Signer> UserOperationTransformer<S> {
+    /// Creates a transformer for `account`, owned by `owner`, whose next nonce is `nonce`
+    pub fn new(
+        account: Address,
+        owner: S,
+        version: EntryPointVersion,
+        chain_id: impl Into<U256>,
+        nonce: u64,
+    ) -> Self {
+        let beneficiary = owner.address();
+        Self {
+            account,
+            owner,
+            version,
+            entry_point: version.address(),
+            chain_id: chain_id.into(),
+            nonce: ProxyNonce::new(nonce),
+            factory: None,
+            call_gas_limit: 200_000.into(),
+            verification_gas_limit: 150_000.into(),
+            pre_verification_gas: 50_000.into(),
+            beneficiary,
+        }
+    }
+
+    /// Sets the address of the `EntryPoint`, for deployments other than the canonical one
+    #[must_use]
+    pub fn with_entry_point(mut self, entry_point: Address) -> Self {
+        self.entry_point = entry_point;
+        self
+    }
+
+    /// Sets the factory and calldata deploying the account, included in the operation with nonce
+    /// 0
+    #[must_use]
+    pub fn with_factory(mut self, factory: Address, data: Bytes) -> Self {
+        self.factory = Some((factory, data));
+        self
+    }
+
+    /// Sets the gas limits of the operations. Defaults to 200k call gas, 150k verification gas
+    /// and 50k pre-verification gas.
+    #[must_use]
+    pub fn with_gas_limits(
+        mut self,
+        call_gas_limit: impl Into<U256>,
+        verification_gas_limit: impl Into<U256>,
+        pre_verification_gas: impl Into<U256>,
+    ) -> Self {
+        self.call_gas_limit = call_gas_limit.into();
+        self.verification_gas_limit = verification_gas_limit.into();
+        self.pre_verification_gas = pre_verification_gas.into();
+        self
+    }
+
+    /// Sets the receiver of the operations' fees when executed through `handleOps`. Defaults to
+    /// the owner.
+    #[must_use]
+    pub fn with_beneficiary(mut self, beneficiary: Address) -> Self {
+        self.beneficiary = beneficiary;
+        self
+    }
+
+    /// The address of the smart account
+    pub fn account(&self) -> Address {
+        self.account
+    }
+
+    /// The address of the `EntryPoint`
+    pub fn entry_point(&self) -> Address {
+        self.entry_point
+    }
+
+    /// The nonce of the next operation
+    pub fn nonce(&self) -> u64 {
+        self.nonce.peek()
+    }
+
+    /// Sets the nonce of the next operation, e.g. after operations were sent without this
+    /// transformer
+    pub fn set_nonce(&self, nonce: u64) {
+        self.nonce.set(nonce);
+    }
+
+    /// Hands the nonce of `op` out again to the next operation, e.g. after a bundler rejected it
+    pub fn release(&self, op: &UserOperation) {
+        self.nonce.release(op.nonce().low_u64());
+    }
+
+    /// Returns the signed operation executing the call of `tx`, and increments the nonce. The
+    /// fees of the operation are the fees of `tx`, which must be set.
+    pub async fn user_operation(
+        &self,
+        tx: &TypedTransaction,
+    ) -> Result<UserOperation, TransformerError> {
+        let to = *tx.to_addr().ok_or_else(|| TransformerError::MissingField("to".to_string()))?;
+        let value = tx.value().copied().unwrap_or_default();
+        let data = tx.data().cloned().unwrap_or_default();
+        let (max_fee_per_gas, max_priority_fee_per_gas) = match tx {
+            TypedTransaction::Eip1559(inner) => (
+                inner
+                    .max_fee_per_gas
+                    .ok_or_else(|| TransformerError::MissingField("max_fee_per_gas".to_string()))?,
+                inner.max_priority_fee_per_gas.ok_or_else(|| {
+                    TransformerError::MissingField("max_priority_fee_per_gas".to_string())
+                })?,
+            ),
+            _ => {
+                let gas_price = tx
+                    .gas_price()
+                    .ok_or_else(|| TransformerError::MissingField("gas_price".to_string()))?;
+                (gas_price, gas_price)
+            }
+        };
+
+        let call_data: Bytes = [
+            &id(EXECUTE)[..],
+            &abi::encode(&[Token::Address(to), Token::Uint(value), Token::Bytes(data.to_vec())]),
+        ]
+        .concat()
+        .into();
+
+        let nonce = self.nonce.next();
+        let factory = self.factory.as_ref().filter(|_| nonce == 0);
+        let mut op: UserOperation = match self.version {
+            EntryPointVersion::V06 => UserOperationV06 {
+                sender: self.account,
+                nonce: nonce.into(),
+                init_code: factory
+                    .map(|(factory, data)| [factory.as_bytes(), data].concat().into())
+                    .unwrap_or_default(),
+                call_data,
+                call_gas_limit: self.call_gas_limit,
+                verification_gas_limit: self.verification_gas_limit,
+                pre_verification_gas: self.pre_verification_gas,
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+                ..Default::default()
+            }
+            .into(),
+            EntryPointVersion::V07 => UserOperationV07 {
+                sender: self.account,
+                nonce: nonce.into(),
+                factory: factory.map(|(factory, _)| *factory),
+                factory_data: factory.map(|(_, data)| data.clone()),
+                call_data,
+                call_gas_limit: self.call_gas_limit,
+                verification_gas_limit: self.verification_gas_limit,
+                pre_verification_gas: self.pre_verification_gas,
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+                ..Default::default()
+            }
+            .into(),
+        };
+
+        if let Err(err) = op.sign(&self.owner, self.entry_point, self.chain_id).await {
+            self.nonce.release(nonce);
+            return Err(err)
+        }
+        Ok(op)
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<S: Signer> Transformer for UserOperationTransformer<S> {
+    fn transform(&self, _tx: &mut TypedTransaction) -> Result<(), TransformerError> {
+        Err(TransformerError::AsyncOnly)
+    }
+
+    async fn transform_async(&self, tx: &mut TypedTransaction) -> Result<(), TransformerError> {
+        let op = self.user_operation(tx).await?;
+        let nonce = op.nonce().low_u64();
+
+        // the account pays the value, the sender of the transaction only pays the gas, which the
+        // account refunds to the beneficiary
+        let data = handle_ops_data(vec![op], self.beneficiary)?;
+        self.nonce.reserve(&data, nonce);
+        tx.set_data(data);
+        tx.set_to(self.entry_point);
+        tx.set_value(U256::zero());
+
+        Ok(())
+    }
+
+    async fn after_send(&self, tx: &TypedTransaction, sent: bool) {
+        if let Some(data) = tx.data() {
+            self.nonce.finish(data, sent);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers_core::types::{Eip1559TransactionRequest, Signature, TransactionRequest};
+
+    fn transformer(version: EntryPointVersion) -> UserOperationTransformer {
+        let owner = LocalWallet::from_bytes(&[1; 32]).unwrap();
+        UserOperationTransformer::new(Address::repeat_byte(0xac), owner, version, 1u64, 0)
+            .with_factory(Address::repeat_byte(0xfa), vec![1, 2, 3].into())
+    }
+
+    /// The fields of the operations in v0.6 `handleOps` calldata, and its beneficiary
+    fn handle_ops_v06_args(data: &[u8]) -> (Vec<Vec<Token>>, Token) {
+        assert_eq!(&data[..4], &id(HANDLE_OPS_V06)[..]);
+        let op = abi::ParamType::Tuple(vec![
+            abi::ParamType::Address,
+            abi::ParamType::Uint(256),
+            abi::ParamType::Bytes,
+            abi::ParamType::Bytes,
+            abi::ParamType::Uint(256),
+            abi::ParamType::Uint(256),
+            abi::ParamType::Uint(256),
+            abi::ParamType::Uint(256),
+            abi::ParamType::Uint(256),
+            abi::ParamType::Bytes,
+            abi::ParamType::Bytes,
+        ]);
+        let mut args = abi::decode(
+            &[abi::ParamType::Array(Box::new(op)), abi::ParamType::Address],
+            &data[4..],
+        )
+        .unwrap();
+        let beneficiary = args.pop().unwrap();
+        let Some(Token::Array(ops)) = args.pop() else { panic!() };
+        let ops = ops
+            .into_iter()
+            .map(|op| match op {
+                Token::Tuple(fields) => fields,
+                op => panic!("{op:?}"),
+            })
+            .collect();
+        (ops, beneficiary)
+    }
+
+    #[tokio::test]
+    async fn builds_signed_user_operations() {
+        let transformer = transformer(EntryPointVersion::V07);
+        let to = Address::repeat_byte(0x22);
+        let tx = Eip1559TransactionRequest::new()
+            .to(to)
+            .value(100)
+            .data(vec![0xaa])
+            .max_fee_per_gas(30)
+            .max_priority_fee_per_gas(2)
+            .into();
+
+        let op = transformer.user_operation(&tx).await.unwrap();
+        let UserOperation::V07(ref inner) = op else { panic!("{op:?}") };
+        assert_eq!(inner.sender, Address::repeat_byte(0xac));
+        assert_eq!(inner.factory, Some(Address::repeat_byte(0xfa)));
+        assert_eq!((inner.max_fee_per_gas, inner.max_priority_fee_per_gas), (30.into(), 2.into()));
+        assert_eq!(&inner.call_data[..4], &id(EXECUTE)[..]);
+        let args = abi::decode(
+            &[abi::ParamType::Address, abi::ParamType::Uint(256), abi::ParamType::Bytes],
+            &inner.call_data[4..],
+        )
+        .unwrap();
+        assert_eq!(
+            args,
+            vec![Token::Address(to), Token::Uint(100.into()), Token::Bytes(vec![0xaa])]
+        );
+
+        let signature = Signature::try_from(op.signature().as_ref()).unwrap();
+        let hash = op.hash(ENTRY_POINT_V07, 1u64);
+        assert_eq!(signature.recover(hash.as_bytes()).unwrap(), transformer.owner.address());
+
+        // the account is only deployed by the first operation
+        let op = transformer.user_operation(&tx).await.unwrap();
+        assert_eq!(op.nonce(), 1.into());
+        let UserOperation::V07(inner) = op else { panic!() };
+        assert_eq!(inner.factory, None);
+    }
+
+    #[tokio::test]
+    async fn transforms_into_handle_ops() {
+        let transformer = transformer(EntryPointVersion::V06);
+        let mut tx: TypedTransaction =
+            TransactionRequest::pay(Address::repeat_byte(0x22), 100).gas_price(10).into();
+        transformer.transform_async(&mut tx).await.unwrap();
+
+        assert_eq!(tx.to_addr(), Some(&ENTRY_POINT_V06));
+        assert_eq!(tx.value(), Some(&U256::zero()));
+        let (ops, beneficiary) = handle_ops_v06_args(tx.data().unwrap());
+        assert_eq!(beneficiary, Token::Address(transformer.owner.address()));
+        let fields = &ops[0];
+        assert_eq!(fields[0], Token::Address(Address::repeat_byte(0xac)));
+        assert_eq!(
+            fields[2],
+            Token::Bytes([&[0xfa; 20][..], &[1, 2, 3]].concat()),
+            "init code of the first operation"
+        );
+    }
+
+    #[tokio::test]
+    async fn requires_fees() {
+        let transformer = transformer(EntryPointVersion::V06);
+        let tx = TransactionRequest::pay(Address::repeat_byte(0x22), 100).into();
+        assert!(matches!(
+            transformer.user_operation(&tx).await,
+            Err(TransformerError::MissingField(field)) if field == "gas_price"
+        ));
+    }
+
+    #[test]
+    fn rejects_mixed_version_batches() {
+        let ops = vec![UserOperationV06::default().into(), UserOperationV07::default().into()];
+        assert!(matches!(
+            handle_ops_data(ops, Address::zero()),
+            Err(TransformerError::MixedEntryPointVersions)
+        ));
+
+        let ops = vec![UserOperationV07::default().into(), UserOperationV07::default().into()];
+        let data = handle_ops_data(ops, Address::zero()).unwrap();
+        assert_eq!(&data[..4], &id(HANDLE_OPS_V07)[..]);
+    }
+
+    #[tokio::test]
+    async fn reuses_released_nonces() {
+        let transformer = transformer(EntryPointVersion::V07);
+        let tx = TransactionRequest::pay(Address::repeat_byte(0x22), 100).gas_price(10).into();
+
+        let first = transformer.user_operation(&tx).await.unwrap();
+        let second = transformer.user_operation(&tx).await.unwrap();
+        transformer.release(&first);
+        assert_eq!(transformer.nonce(), 0);
+        assert_eq!(transformer.user_operation(&tx).await.unwrap().nonce(), 0.into());
+        assert_eq!(transformer.nonce(), 2);
+
+        transformer.release(&second);
+        assert_eq!(transformer.nonce(), 1);
+    }
+
+    #[tokio::test]
+    async fn reuses_the_nonce_of_unsent_operations() {
+        let transformer = transformer(EntryPointVersion::V06);
+        transformer.set_nonce(5);
+        let transfer: TypedTransaction =
+            TransactionRequest::pay(Address::repeat_byte(0x22), 100).gas_price(10).into();
+
+        let mut tx = transfer.clone();
+        transformer.transform_async(&mut tx).await.unwrap();
+        assert_eq!(transformer.nonce(), 6);
+        transformer.after_send(&tx, false).await;
+        assert_eq!(transformer.nonce(), 5);
+
+        let mut tx = transfer;
+        transformer.transform_async(&mut tx).await.unwrap();
+        let (ops, _) = handle_ops_v06_args(tx.data().unwrap());
+        assert_eq!(ops[0][1], Token::Uint(5.into()));
+        assert_eq!(transformer.nonce(), 6);
+    }
+}
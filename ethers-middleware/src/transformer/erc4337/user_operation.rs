@@ -0,0 +1,468 @@
+use super::EntryPointVersion;
+use crate::transformer::TransformerError;
+use ethers_core::{
+    abi::{self, Token},
+    types::{Address, Bytes, H160, H256, U256},
+    utils::keccak256,
+};
+use ethers_signers::Signer;
+use serde::{Deserialize, Serialize};
+
+/// The address of the v0.6 `EntryPoint` contract, deployed at the same address on most chains.
+pub const ENTRY_POINT_V06: Address = H160([
+    0x5f, 0xf1, 0x37, 0xd4, 0xb0, 0xfd, 0xcd, 0x49, 0xdc, 0xa3, 0x0c, 0x7c, 0xf5, 0x7e, 0x57, 0x8a,
+    0x02, 0x6d, 0x27, 0x89,
+]);
+
+/// The address of the v0.7 `EntryPoint` contract, deployed at the same address on most chains.
+pub const ENTRY_POINT_V07: Address = H160([
+    0x00, 0x00, 0x00, 0x00, 0x71, 0x72, 0x7d, 0xe2, 0x2e, 0x5e, 0x9d, 0x8b, 0xaf, 0x0e, 0xda, 0xc6,
+    0xf3, 0x7d, 0xa0, 0x32,
+]);
+
+/// A user operation of the v0.6 `EntryPoint`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperationV06 {
+    pub sender: Address,
+    pub nonce: U256,
+    /// The factory address and calldata deploying the account, empty if it is deployed
+    pub init_code: Bytes,
+    pub call_data: Bytes,
+    pub call_gas_limit: U256,
+    pub verification_gas_limit: U256,
+    pub pre_verification_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+    /// The paymaster address and data, empty without paymaster
+    pub paymaster_and_data: Bytes,
+    pub signature: Bytes,
+}
+
+impl UserOperationV06 {
+    /// Returns the hash of the operation without its signature
+    fn packed_hash(&self) -> [u8; 32] {
+        keccak256(abi::encode(&[
+            Token::Address(self.sender),
+            Token::Uint(self.nonce),
+            Token::FixedBytes(keccak256(&self.init_code).to_vec()),
+            Token::FixedBytes(keccak256(&self.call_data).to_vec()),
+            Token::Uint(self.call_gas_limit),
+            Token::Uint(self.verification_gas_limit),
+            Token::Uint(self.pre_verification_gas),
+            Token::Uint(self.max_fee_per_gas),
+            Token::Uint(self.max_priority_fee_per_gas),
+            Token::FixedBytes(keccak256(&self.paymaster_and_data).to_vec()),
+        ]))
+    }
+
+    /// Returns the operation as the `UserOperation` tuple of the `EntryPoint`'s `handleOps`
+    pub fn into_token(self) -> Token {
+        Token::Tuple(vec![
+            Token::Address(self.sender),
+            Token::Uint(self.nonce),
+            Token::Bytes(self.init_code.to_vec()),
+            Token::Bytes(self.call_data.to_vec()),
+            Token::Uint(self.call_gas_limit),
+            Token::Uint(self.verification_gas_limit),
+            Token::Uint(self.pre_verification_gas),
+            Token::Uint(self.max_fee_per_gas),
+            Token::Uint(self.max_priority_fee_per_gas),
+            Token::Bytes(self.paymaster_and_data.to_vec()),
+            Token::Bytes(self.signature.to_vec()),
+        ])
+    }
+}
+
+/// A user operation of the v0.7 `EntryPoint`, in the unpacked form used by bundlers
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperationV07 {
+    pub sender: Address,
+    pub nonce: U256,
+    /// The factory deploying the account, `None` if it is deployed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub factory: Option<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub factory_data: Option<Bytes>,
+    pub call_data: Bytes,
+    pub call_gas_limit: U256,
+    pub verification_gas_limit: U256,
+    pub pre_verification_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster: Option<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_verification_gas_limit: Option<U256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_post_op_gas_limit: Option<U256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_data: Option<Bytes>,
+    pub signature: Bytes,
+}
+
+/// Packs two 128 bits values into a word, `high || low`
+fn pack_u128s(high: U256, low: U256) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    ((high << 128) | (low & U256::from(u128::MAX))).to_big_endian(&mut word);
+    word
+}
+
+impl UserOperationV07 {
+    /// Returns `factory || factoryData`, empty without factory
+    pub fn init_code(&self) -> Bytes {
+        match self.factory {
+            Some(factory) => [
+                factory.as_bytes(),
+                self.factory_data.as_ref().map(|data| data.as_ref()).unwrap_or_default(),
+            ]
+            .concat()
+            .into(),
+            None => Bytes::default(),
+        }
+    }
+
+    /// Returns `paymaster || verificationGasLimit || postOpGasLimit || paymasterData`, empty
+    /// without paymaster
+    pub fn paymaster_and_data(&self) -> Bytes {
+        match self.paymaster {
+            Some(paymaster) => {
+                let gas_limits = pack_u128s(
+                    self.paymaster_verification_gas_limit.unwrap_or_default(),
+                    self.paymaster_post_op_gas_limit.unwrap_or_default(),
+                );
+                [
+                    paymaster.as_bytes(),
+                    &gas_limits,
+                    self.paymaster_data.as_ref().map(|data| data.as_ref()).unwrap_or_default(),
+                ]
+                .concat()
+                .into()
+            }
+            None => Bytes::default(),
+        }
+    }
+
+    /// Returns `verificationGasLimit || callGasLimit`
+    pub fn account_gas_limits(&self) -> [u8; 32] {
+        pack_u128s(self.verification_gas_limit, self.call_gas_limit)
+    }
+
+    /// Returns `maxPriorityFeePerGas || maxFeePerGas`
+    pub fn gas_fees(&self) -> [u8; 32] {
+        pack_u128s(self.max_priority_fee_per_gas, self.max_fee_per_gas)
+    }
+
+    /// Returns the hash of the operation without its signature
+    fn packed_hash(&self) -> [u8; 32] {
+        keccak256(abi::encode(&[
+            Token::Address(self.sender),
+            Token::Uint(self.nonce),
+            Token::FixedBytes(keccak256(self.init_code()).to_vec()),
+            Token::FixedBytes(keccak256(&self.call_data).to_vec()),
+            Token::FixedBytes(self.account_gas_limits().to_vec()),
+            Token::Uint(self.pre_verification_gas),
+            Token::FixedBytes(self.gas_fees().to_vec()),
+            Token::FixedBytes(keccak256(self.paymaster_and_data()).to_vec()),
+        ]))
+    }
+
+    /// Returns the operation as the `PackedUserOperation` tuple of the `EntryPoint`'s `handleOps`
+    pub fn into_token(self) -> Token {
+        Token::Tuple(vec![
+            Token::Address(self.sender),
+            Token::Uint(self.nonce),
+            Token::Bytes(self.init_code().to_vec()),
+            Token::Bytes(self.call_data.to_vec()),
+            Token::FixedBytes(self.account_gas_limits().to_vec()),
+            Token::Uint(self.pre_verification_gas),
+            Token::FixedBytes(self.gas_fees().to_vec()),
+            Token::Bytes(self.paymaster_and_data().to_vec()),
+            Token::Bytes(self.signature.to_vec()),
+        ])
+    }
+}
+
+/// A user operation of either version of the `EntryPoint`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum UserOperation {
+    V06(UserOperationV06),
+    V07(UserOperationV07),
+}
+
+impl From<UserOperationV06> for UserOperation {
+    fn from(op: UserOperationV06) -> Self {
+        UserOperation::V06(op)
+    }
+}
+
+impl From<UserOperationV07> for UserOperation {
+    fn from(op: UserOperationV07) -> Self {
+        UserOperation::V07(op)
+    }
+}
+
+impl UserOperation {
+    /// The version of the `EntryPoint` executing the operation
+    pub fn version(&self) -> EntryPointVersion {
+        match self {
+            UserOperation::V06(_) => EntryPointVersion::V06,
+            UserOperation::V07(_) => EntryPointVersion::V07,
+        }
+    }
+
+    /// The smart account sending the operation
+    pub fn sender(&self) -> Address {
+        match self {
+            UserOperation::V06(op) => op.sender,
+            UserOperation::V07(op) => op.sender,
+        }
+    }
+
+    /// The nonce of the operation, whose high 192 bits are the nonce key
+    pub fn nonce(&self) -> U256 {
+        match self {
+            UserOperation::V06(op) => op.nonce,
+            UserOperation::V07(op) => op.nonce,
+        }
+    }
+
+    /// The signature of the operation
+    pub fn signature(&self) -> &Bytes {
+        match self {
+            UserOperation::V06(op) => &op.signature,
+            UserOperation::V07(op) => &op.signature,
+        }
+    }
+
+    /// Sets the signature of the operation
+    pub fn set_signature(&mut self, signature: Bytes) -> &mut Self {
+        match self {
+            UserOperation::V06(op) => op.signature = signature,
+            UserOperation::V07(op) => op.signature = signature,
+        }
+        self
+    }
+
+    /// Sets the gas limits estimated by a bundler
+    pub fn set_gas(&mut self, estimate: &UserOperationGasEstimate) -> &mut Self {
+        match self {
+            UserOperation::V06(op) => {
+                op.call_gas_limit = estimate.call_gas_limit;
+                op.verification_gas_limit = estimate.verification_gas_limit;
+                op.pre_verification_gas = estimate.pre_verification_gas;
+            }
+            UserOperation::V07(op) => {
+                op.call_gas_limit = estimate.call_gas_limit;
+                op.verification_gas_limit = estimate.verification_gas_limit;
+                op.pre_verification_gas = estimate.pre_verification_gas;
+                if op.paymaster.is_some() {
+                    if let Some(limit) = estimate.paymaster_verification_gas_limit {
+                        op.paymaster_verification_gas_limit = Some(limit);
+                    }
+                    if let Some(limit) = estimate.paymaster_post_op_gas_limit {
+                        op.paymaster_post_op_gas_limit = Some(limit);
+                    }
+                }
+            }
+        }
+        self
+    }
+
+    /// Returns the `userOpHash` of the operation for `entry_point` on `chain_id`, which the
+    /// account verifies the signature of
+    pub fn hash(&self, entry_point: Address, chain_id: impl Into<U256>) -> H256 {
+        let packed_hash = match self {
+            UserOperation::V06(op) => op.packed_hash(),
+            UserOperation::V07(op) => op.packed_hash(),
+        };
+        keccak256(abi::encode(&[
+            Token::FixedBytes(packed_hash.to_vec()),
+            Token::Address(entry_point),
+            Token::Uint(chain_id.into()),
+        ]))
+        .into()
+    }
+
+    /// Signs the `userOpHash` as an EIP-191 message with `signer`, the way ECDSA owned accounts
+    /// such as `SimpleAccount` verify it, and sets the signature
+    pub async fn sign<S: Signer>(
+        &mut self,
+        signer: &S,
+        entry_point: Address,
+        chain_id: impl Into<U256>,
+    ) -> Result<&mut Self, TransformerError> {
+        let hash = self.hash(entry_point, chain_id);
+        let signature = signer
+            .sign_message(hash.as_bytes())
+            .await
+            .map_err(|err| TransformerError::SignerError(err.to_string()))?;
+        Ok(self.set_signature(signature.to_vec().into()))
+    }
+
+    /// Returns the operation as a tuple of the `EntryPoint`'s `handleOps`
+    pub fn into_token(self) -> Token {
+        match self {
+            UserOperation::V06(op) => op.into_token(),
+            UserOperation::V07(op) => op.into_token(),
+        }
+    }
+}
+
+/// The gas limits of a user operation estimated by a bundler
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperationGasEstimate {
+    pub pre_verification_gas: U256,
+    pub verification_gas_limit: U256,
+    pub call_gas_limit: U256,
+    /// Only returned for v0.7 operations with a paymaster
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_verification_gas_limit: Option<U256>,
+    /// Only returned for v0.7 operations with a paymaster
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_post_op_gas_limit: Option<U256>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers_signers::LocalWallet;
+    use serde_json::json;
+
+    #[test]
+    fn packs_v07_fields() {
+        let op = UserOperationV07 {
+            factory: Some(Address::repeat_byte(0xfa)),
+            factory_data: Some(vec![1, 2].into()),
+            call_gas_limit: 0x1111.into(),
+            verification_gas_limit: 0x2222.into(),
+            max_fee_per_gas: 0x3333.into(),
+            max_priority_fee_per_gas: 0x4444.into(),
+            paymaster: Some(Address::repeat_byte(0xba)),
+            paymaster_verification_gas_limit: Some(0x5555.into()),
+            paymaster_post_op_gas_limit: Some(0x6666.into()),
+            paymaster_data: Some(vec![3].into()),
+            ..Default::default()
+        };
+
+        assert_eq!(op.init_code().as_ref(), [&[0xfa; 20][..], &[1, 2]].concat());
+        let limits = op.account_gas_limits();
+        assert_eq!(U256::from_big_endian(&limits[..16]), 0x2222.into());
+        assert_eq!(U256::from_big_endian(&limits[16..]), 0x1111.into());
+        let fees = op.gas_fees();
+        assert_eq!(U256::from_big_endian(&fees[..16]), 0x4444.into());
+        assert_eq!(U256::from_big_endian(&fees[16..]), 0x3333.into());
+
+        let paymaster_and_data = op.paymaster_and_data();
+        assert_eq!(paymaster_and_data.len(), 20 + 16 + 16 + 1);
+        assert_eq!(&paymaster_and_data[..20], &[0xba; 20]);
+        assert_eq!(U256::from_big_endian(&paymaster_and_data[20..36]), 0x5555.into());
+        assert_eq!(U256::from_big_endian(&paymaster_and_data[36..52]), 0x6666.into());
+        assert_eq!(paymaster_and_data[52], 3);
+
+        assert!(UserOperationV07::default().init_code().is_empty());
+        assert!(UserOperationV07::default().paymaster_and_data().is_empty());
+    }
+
+    #[test]
+    fn sets_v07_paymaster_gas_limits() {
+        let estimate: UserOperationGasEstimate = serde_json::from_value(json!({
+            "preVerificationGas": "0x100",
+            "verificationGasLimit": "0x200",
+            "callGasLimit": "0x300",
+            "paymasterVerificationGasLimit": "0x400",
+            "paymasterPostOpGasLimit": "0x500",
+        }))
+        .unwrap();
+
+        let mut op: UserOperation =
+            UserOperationV07 { paymaster: Some(Address::repeat_byte(0xba)), ..Default::default() }
+                .into();
+        op.set_gas(&estimate);
+        let UserOperation::V07(ref inner) = op else { panic!("{op:?}") };
+        assert_eq!(
+            (inner.pre_verification_gas, inner.verification_gas_limit, inner.call_gas_limit),
+            (0x100.into(), 0x200.into(), 0x300.into())
+        );
+        assert_eq!(inner.paymaster_verification_gas_limit, Some(0x400.into()));
+        assert_eq!(inner.paymaster_post_op_gas_limit, Some(0x500.into()));
+        let paymaster_and_data = inner.paymaster_and_data();
+        assert_eq!(U256::from_big_endian(&paymaster_and_data[36..52]), 0x500.into());
+
+        // the paymaster limits of operations without paymaster stay unset
+        let mut op: UserOperation = UserOperationV07::default().into();
+        op.set_gas(&estimate);
+        let UserOperation::V07(inner) = op else { panic!() };
+        assert_eq!(inner.paymaster_verification_gas_limit, None);
+        assert_eq!(inner.paymaster_post_op_gas_limit, None);
+    }
+
+    #[test]
+    fn deserializes_both_versions() {
+        let v06: UserOperation =
+            serde_json::from_value(json!(UserOperationV06::default())).unwrap();
+        assert!(matches!(v06, UserOperation::V06(_)));
+        let v07: UserOperation =
+            serde_json::from_value(json!(UserOperationV07::default())).unwrap();
+        assert!(matches!(v07, UserOperation::V07(_)));
+    }
+
+    #[test]
+    fn matches_known_user_op_hashes() {
+        // returned by `getUserOpHash` of the v0.6 `EntryPoint` deployed at this address on chain
+        // 1337, taken from the test vectors of the rundler bundler
+        let entry_point: Address = "0x66a15edcc3b50a663e72f1457ffd49b9ae284ddc".parse().unwrap();
+        let op: UserOperation = UserOperationV06::default().into();
+        assert_eq!(
+            op.hash(entry_point, 1337u64),
+            "0xdca97c3b49558ab360659f6ead939773be8bf26631e61bb17045bb70dc983b2d".parse().unwrap()
+        );
+
+        // computed independently of this implementation, following the v0.7 `UserOperationLib`:
+        // the `accountGasLimits` and `gasFees` words pack two uint128s each, and the paymaster's
+        // gas limits are packed as uint128s between the paymaster and its data. The same
+        // computation reproduces the v0.6 vector above.
+        let op: UserOperation = UserOperationV07 {
+            sender: "0x1306b01bc3e4ad202612d3843387e94737673f53".parse().unwrap(),
+            nonce: 8942.into(),
+            factory: Some("0x6942069420694206942069420694206942069420".parse().unwrap()),
+            factory_data: Some(vec![0xca, 0xfe].into()),
+            call_data: "0x0000000000000000000000000000000000000000080085".parse().unwrap(),
+            call_gas_limit: 10_000.into(),
+            verification_gas_limit: 100_000.into(),
+            pre_verification_gas: 100.into(),
+            max_fee_per_gas: 99_999.into(),
+            max_priority_fee_per_gas: 9_999_999.into(),
+            paymaster: Some("0x0123456789abcdef0123456789abcdef01234567".parse().unwrap()),
+            paymaster_verification_gas_limit: Some(50_000.into()),
+            paymaster_post_op_gas_limit: Some(20_000.into()),
+            paymaster_data: Some(vec![0xbe, 0xef].into()),
+            signature: vec![0xda; 65].into(),
+        }
+        .into();
+        assert_eq!(
+            op.hash(ENTRY_POINT_V07, 1u64),
+            "0x41d91466372bc630bf510cc0d618dbc877b9acbcab046c43f69fd70e82299fc4".parse().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn signs_user_op_hash() {
+        let wallet = LocalWallet::from_bytes(&[1; 32]).unwrap();
+        let mut op: UserOperation =
+            UserOperationV06 { sender: Address::repeat_byte(1), ..Default::default() }.into();
+
+        let hash = op.hash(ENTRY_POINT_V06, 1u64);
+        assert_ne!(hash, op.hash(ENTRY_POINT_V07, 1u64));
+        assert_ne!(hash, op.hash(ENTRY_POINT_V06, 5u64));
+
+        op.sign(&wallet, ENTRY_POINT_V06, 1u64).await.unwrap();
+        // the signature is not part of the hash
+        assert_eq!(op.hash(ENTRY_POINT_V06, 1u64), hash);
+        let signature = ethers_core::types::Signature::try_from(op.signature().as_ref()).unwrap();
+        assert_eq!(signature.recover(hash.as_bytes()).unwrap(), wallet.address());
+    }
+}
@@ -25,6 +25,7 @@ where
 }
 
 #[derive(Error, Debug)]
+/// Errors thrown from the [`TransformerMiddleware`], or when sending a transformed transaction.
 pub enum TransformerMiddlewareError<M: Middleware> {
     #[error(transparent)]
     TransformerError(#[from] TransformerError),
@@ -71,13 +72,59 @@ where
         let mut tx = tx.into();
 
         // construct the appropriate proxy tx.
-        self.transformer.transform(&mut tx)?;
+        self.transformer.transform_async(&mut tx).await?;
 
-        self.fill_transaction(&mut tx, block).await?;
         // send the proxy tx.
-        self.inner
-            .send_transaction(tx, block)
-            .await
-            .map_err(TransformerMiddlewareError::MiddlewareError)
+        let sent = match self.fill_transaction(&mut tx, block).await {
+            Ok(()) => self
+                .inner
+                .send_transaction(tx.clone(), block)
+                .await
+                .map_err(TransformerMiddlewareError::MiddlewareError),
+            Err(err) => Err(err),
+        };
+        self.transformer.after_send(&tx, sent.is_ok()).await;
+        sent
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::test_utils::{reject_transactions, INSUFFICIENT_FUNDS};
+    use ethers_providers::Provider;
+    use std::sync::Mutex;
+
+    /// Leaves transactions as they are and records whether they were sent
+    #[derive(Debug, Default)]
+    struct Recorder {
+        sent: Mutex<Vec<bool>>,
+    }
+
+    #[async_trait]
+    impl Transformer for Recorder {
+        fn transform(&self, _tx: &mut TypedTransaction) -> Result<(), TransformerError> {
+            Ok(())
+        }
+
+        async fn after_send(&self, _tx: &TypedTransaction, sent: bool) {
+            self.sent.lock().unwrap().push(sent);
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_whether_transactions_were_sent() {
+        let (provider, mock) = Provider::mocked();
+        reject_transactions(&mock, &[INSUFFICIENT_FUNDS]);
+        let middleware = TransformerMiddleware::new(provider, Recorder::default());
+
+        let tx = TransactionRequest::pay(Address::zero(), 100)
+            .from(Address::repeat_byte(1))
+            .gas(21_000)
+            .gas_price(1);
+        middleware.send_transaction(tx.clone(), None).await.unwrap_err();
+        middleware.send_transaction(tx, None).await.unwrap();
+        assert_eq!(*middleware.transformer.sent.lock().unwrap(), vec![false, true]);
     }
 }
@@ -1,11 +1,21 @@
 pub mod ds_proxy;
 pub use ds_proxy::DsProxy;
 
+pub mod safe;
+pub use safe::SafeTransformer;
+
+pub mod erc4337;
+pub use erc4337::UserOperationTransformer;
+
 mod middleware;
-pub use middleware::TransformerMiddleware;
+pub use middleware::{TransformerMiddleware, TransformerMiddlewareError};
+
+mod nonce;
 
+use async_trait::async_trait;
 use ethers_contract::AbiError;
 use ethers_core::{abi::ParseError, types::transaction::eip2718::TypedTransaction};
+use ethers_signers::WalletError;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -19,15 +29,42 @@ pub enum TransformerError {
 
     #[error(transparent)]
     AbiError(#[from] AbiError),
+
+    #[error(transparent)]
+    WalletError(#[from] WalletError),
+
+    #[error("signing failed: {0}")]
+    SignerError(String),
+
+    #[error("the user operations of a batch must be of the same EntryPoint version")]
+    MixedEntryPointVersions,
+
+    #[error("the transformer signs asynchronously, use `Transformer::transform_async`")]
+    AsyncOnly,
 }
 
 /// `Transformer` is a trait to be implemented by a proxy wallet, eg. [`DsProxy`], that intends to
 /// intercept a transaction request and transform it into one that is instead sent via the proxy
 /// contract.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
 pub trait Transformer: Send + Sync + std::fmt::Debug {
     /// Transforms a [`transaction request`] into one that can be broadcasted and execute via the
     /// proxy contract.
     ///
     /// [`transaction request`]: struct@ethers_core::types::TransactionRequest
     fn transform(&self, tx: &mut TypedTransaction) -> Result<(), TransformerError>;
+
+    /// Transforms the transaction like [`Transformer::transform`], which it calls by default.
+    /// Used by the [`TransformerMiddleware`], transformers which sign the proxy transaction with
+    /// a [`Signer`](ethers_signers::Signer) override it.
+    async fn transform_async(&self, tx: &mut TypedTransaction) -> Result<(), TransformerError> {
+        self.transform(tx)
+    }
+
+    /// Called by the [`TransformerMiddleware`] with the transformed transaction once it was sent,
+    /// or failed to be sent. Transformers reserving a nonce of the proxy while transforming
+    /// release it here if the transaction was not sent, so callers transforming transactions
+    /// themselves report them here as well.
+    async fn after_send(&self, _tx: &TypedTransaction, _sent: bool) {}
 }
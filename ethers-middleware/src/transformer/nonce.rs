@@ -0,0 +1,61 @@
+use crate::nonce_manager::AccountNonce;
+use ethers_core::{
+    types::{Bytes, H256},
+    utils::keccak256,
+};
+use std::{collections::HashMap, sync::Mutex};
+
+/// The nonce of a proxy account tracked offline by a transformer.
+///
+/// The nonce of a transformed transaction is reserved until the [`TransformerMiddleware`], or the
+/// caller transforming it, reports whether it was sent, and released if it was not. Transformers
+/// release the nonce themselves if transforming fails.
+///
+/// [`TransformerMiddleware`]: super::TransformerMiddleware
+#[derive(Debug)]
+pub(crate) struct ProxyNonce {
+    account: AccountNonce,
+    /// The nonces of the transformed transactions, by hash of their calldata
+    reserved: Mutex<HashMap<H256, u64>>,
+}
+
+impl ProxyNonce {
+    pub(crate) fn new(nonce: u64) -> Self {
+        Self { account: AccountNonce::new(nonce), reserved: Default::default() }
+    }
+
+    /// The nonce handed out next
+    pub(crate) fn peek(&self) -> u64 {
+        self.account.peek()
+    }
+
+    pub(crate) fn next(&self) -> u64 {
+        self.account.next()
+    }
+
+    /// Sets the next nonce, forgetting released and reserved nonces
+    pub(crate) fn set(&self, nonce: u64) {
+        self.reserved.lock().unwrap().clear();
+        self.account.reset(nonce);
+    }
+
+    pub(crate) fn release(&self, nonce: u64) {
+        self.account.release(nonce);
+    }
+
+    /// Records that the transaction with calldata `data` uses `nonce`
+    pub(crate) fn reserve(&self, data: &Bytes, nonce: u64) {
+        self.reserved.lock().unwrap().insert(keccak256(data).into(), nonce);
+    }
+
+    /// Forgets the reservation of the transaction with calldata `data`, releasing its nonce if
+    /// the transaction was not sent
+    pub(crate) fn finish(&self, data: &Bytes, sent: bool) {
+        let Some(nonce) = self.reserved.lock().unwrap().remove(&H256(keccak256(data))) else {
+            return
+        };
+        if !sent {
+            self.release(nonce);
+        }
+    }
+}
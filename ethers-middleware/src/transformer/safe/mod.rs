@@ -0,0 +1,648 @@
+mod multi_send;
+pub use multi_send::{MultiSend, MULTI_SEND_ADDRESS, MULTI_SEND_CALL_ONLY_ADDRESS};
+
+use super::{nonce::ProxyNonce, Transformer, TransformerError, TransformerMiddlewareError};
+use async_trait::async_trait;
+use ethers_core::{
+    abi::{self, Token},
+    types::{
+        transaction::{
+            eip2718::TypedTransaction,
+            eip712::{EIP712Domain, Eip712},
+        },
+        Address, BlockId, Bytes, Signature, TransactionRequest, H256, U256,
+    },
+    utils::{id, keccak256},
+};
+use ethers_providers::{Middleware, PendingTransaction};
+use ethers_signers::{LocalWallet, Signer};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, convert::Infallible};
+
+/// The signature of the Safe's function executing a transaction signed by its owners.
+const EXEC_TRANSACTION: &str =
+    "execTransaction(address,uint256,bytes,uint8,uint256,uint256,uint256,address,address,bytes)";
+
+/// The EIP-712 type of a Safe transaction.
+const SAFE_TX_TYPE: &str = "SafeTx(address to,uint256 value,bytes data,uint8 operation,uint256 safeTxGas,uint256 baseGas,uint256 gasPrice,address gasToken,address refundReceiver,uint256 nonce)";
+
+/// How a Safe executes the call of a [`SafeTx`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "u8", try_from = "u8")]
+pub enum Operation {
+    #[default]
+    Call = 0,
+    /// Executes the code of the target in the context of the Safe, e.g. for [`MultiSend`]
+    DelegateCall = 1,
+}
+
+impl From<Operation> for u8 {
+    fn from(operation: Operation) -> Self {
+        operation as u8
+    }
+}
+
+impl TryFrom<u8> for Operation {
+    type Error = String;
+
+    fn try_from(operation: u8) -> Result<Self, Self::Error> {
+        match operation {
+            0 => Ok(Operation::Call),
+            1 => Ok(Operation::DelegateCall),
+            _ => Err(format!("invalid Safe operation {operation}")),
+        }
+    }
+}
+
+/// A transaction of a Safe (previously Gnosis Safe) multisig, as signed by its owners.
+///
+/// The EIP-712 hash of the transaction is bound to the Safe and its chain, which are not part of
+/// the signed struct but of the domain.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SafeTx {
+    /// The address of the Safe
+    pub safe: Address,
+    pub chain_id: U256,
+    pub to: Address,
+    pub value: U256,
+    pub data: Bytes,
+    pub operation: Operation,
+    /// The gas available to the call, 0 to forward all the gas
+    pub safe_tx_gas: U256,
+    /// The gas refunded to the executor on top of the call's gas, if `gas_price` is non-zero
+    pub base_gas: U256,
+    /// The price of the gas refunded to the executor, 0 for no refund
+    pub gas_price: U256,
+    /// The token of the refund, the zero address for ether
+    pub gas_token: Address,
+    /// The receiver of the refund, the zero address for the executor
+    pub refund_receiver: Address,
+    /// The nonce of the Safe
+    pub nonce: U256,
+}
+
+impl SafeTx {
+    /// Creates a transaction of `safe` calling `to` with `value` and `data`, without refund
+    pub fn new(
+        safe: Address,
+        chain_id: impl Into<U256>,
+        to: Address,
+        value: U256,
+        data: Bytes,
+        nonce: impl Into<U256>,
+    ) -> Self {
+        Self {
+            safe,
+            chain_id: chain_id.into(),
+            to,
+            value,
+            data,
+            nonce: nonce.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the EIP-712 hash of the transaction, which the owners sign
+    pub fn hash(&self) -> H256 {
+        match self.encode_eip712() {
+            Ok(hash) => hash.into(),
+            Err(never) => match never {},
+        }
+    }
+
+    /// Returns the calldata of the Safe's `execTransaction` with the packed `signatures`
+    pub fn exec_transaction_data(&self, signatures: Bytes) -> Bytes {
+        let args = abi::encode(&[
+            Token::Address(self.to),
+            Token::Uint(self.value),
+            Token::Bytes(self.data.to_vec()),
+            Token::Uint((self.operation as u8).into()),
+            Token::Uint(self.safe_tx_gas),
+            Token::Uint(self.base_gas),
+            Token::Uint(self.gas_price),
+            Token::Address(self.gas_token),
+            Token::Address(self.refund_receiver),
+            Token::Bytes(signatures.to_vec()),
+        ]);
+        [&id(EXEC_TRANSACTION)[..], &args].concat().into()
+    }
+}
+
+impl Eip712 for SafeTx {
+    type Error = Infallible;
+
+    fn domain(&self) -> Result<EIP712Domain, Self::Error> {
+        Ok(EIP712Domain {
+            chain_id: Some(self.chain_id),
+            verifying_contract: Some(self.safe),
+            ..Default::default()
+        })
+    }
+
+    fn type_hash() -> Result<[u8; 32], Self::Error> {
+        Ok(keccak256(SAFE_TX_TYPE))
+    }
+
+    fn struct_hash(&self) -> Result<[u8; 32], Self::Error> {
+        Ok(keccak256(abi::encode(&[
+            Token::FixedBytes(Self::type_hash()?.to_vec()),
+            Token::Address(self.to),
+            Token::Uint(self.value),
+            Token::FixedBytes(keccak256(&self.data).to_vec()),
+            Token::Uint((self.operation as u8).into()),
+            Token::Uint(self.safe_tx_gas),
+            Token::Uint(self.base_gas),
+            Token::Uint(self.gas_price),
+            Token::Address(self.gas_token),
+            Token::Address(self.refund_receiver),
+            Token::Uint(self.nonce),
+        ])))
+    }
+}
+
+/// Packs owners' signatures the way a Safe expects them: sorted by owner, as `r || s || v`.
+pub fn pack_signatures(signatures: &BTreeMap<Address, Signature>) -> Bytes {
+    signatures.values().flat_map(|signature| signature.to_vec()).collect::<Vec<_>>().into()
+}
+
+/// A [`SafeTx`] and the signatures of its owners, collected without the Safe transaction
+/// service.
+///
+/// A proposal can be serialized to JSON and passed from owner to owner, each adding their
+/// signature with [`SafeProposal::sign`], until enough owners signed it to reach the Safe's
+/// threshold. Anyone can then send the [`SafeProposal::transaction`] executing it.
+///
+/// ```
+/// use ethers_core::types::{Address, U256};
+/// use ethers_middleware::transformer::safe::{SafeProposal, SafeTx};
+/// use ethers_signers::{LocalWallet, Signer};
+///
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let (alice, bob) = (LocalWallet::new(&mut rand::thread_rng()), LocalWallet::new(&mut rand::thread_rng()));
+/// let safe = Address::random();
+/// let payment = SafeTx::new(safe, 1u64, Address::random(), U256::exp10(18), Default::default(), 0u64);
+///
+/// let mut proposal = SafeProposal::new(payment);
+/// proposal.sign(&alice).await?;
+/// let json = serde_json::to_string(&proposal)?;
+///
+/// // later, somewhere else
+/// let mut proposal: SafeProposal = serde_json::from_str(&json)?;
+/// proposal.sign(&bob).await?;
+/// let tx = proposal.transaction();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SafeProposal {
+    pub tx: SafeTx,
+    /// The signatures of the owners, by owner
+    pub signatures: BTreeMap<Address, Signature>,
+}
+
+impl SafeProposal {
+    /// Creates a proposal without signatures
+    pub fn new(tx: SafeTx) -> Self {
+        Self { tx, signatures: BTreeMap::new() }
+    }
+
+    /// Signs the transaction with `signer`
+    pub async fn sign<S: Signer>(&mut self, signer: &S) -> Result<(), TransformerError> {
+        let signature = signer
+            .sign_typed_data(&self.tx)
+            .await
+            .map_err(|err| TransformerError::SignerError(err.to_string()))?;
+        self.signatures.insert(signer.address(), signature);
+        Ok(())
+    }
+
+    /// Adds the signature of `owner`, collected elsewhere. Fails if the signature is not the
+    /// owner's signature of the transaction.
+    pub fn add_signature(
+        &mut self,
+        owner: Address,
+        signature: Signature,
+    ) -> Result<(), TransformerError> {
+        let signer = signature
+            .recover(self.tx.hash())
+            .map_err(|err| TransformerError::SignerError(err.to_string()))?;
+        if signer != owner {
+            return Err(TransformerError::SignerError(format!(
+                "signature of {signer:?} instead of {owner:?}"
+            )))
+        }
+        self.signatures.insert(owner, signature);
+        Ok(())
+    }
+
+    /// Returns the packed signatures of the owners
+    pub fn packed_signatures(&self) -> Bytes {
+        pack_signatures(&self.signatures)
+    }
+
+    /// Returns the transaction executing the proposal on the Safe
+    pub fn transaction(&self) -> TransactionRequest {
+        TransactionRequest::new()
+            .to(self.tx.safe)
+            .data(self.tx.exec_transaction_data(self.packed_signatures()))
+            .chain_id(self.tx.chain_id.low_u64())
+    }
+}
+
+/// Transforms transactions into `execTransaction` calls of a Safe, signed by its owners.
+///
+/// The transformer works fully offline: it tracks the nonce of the Safe itself, starting from the
+/// nonce it was created with, and signs with the signers of at least as many owners as the
+/// Safe's threshold. The nonce of a transaction which the [`TransformerMiddleware`] fails to send
+/// is used again by the next one. The transformed transaction can be sent from any account, which
+/// pays its gas. Owners without access to the same machine can sign through a [`SafeProposal`]
+/// instead.
+///
+/// The owners sign asynchronously, so the transformer only implements
+/// [`Transformer::transform_async`]. Transactions transformed without the
+/// [`TransformerMiddleware`] keep their Safe nonce reserved until they are reported to
+/// [`Transformer::after_send`].
+///
+/// ```
+/// use ethers_core::types::{Address, TransactionRequest};
+/// use ethers_middleware::transformer::{SafeTransformer, TransformerMiddleware};
+/// use ethers_providers::{Http, Middleware, Provider};
+/// use ethers_signers::LocalWallet;
+///
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let provider = Provider::<Http>::try_from("http://localhost:8545")?;
+/// let owners = vec![LocalWallet::new(&mut rand::thread_rng()), LocalWallet::new(&mut rand::thread_rng())];
+/// let safe = SafeTransformer::new(Address::random(), 1u64, 0u64, owners);
+/// let client = TransformerMiddleware::new(provider, safe);
+///
+/// let tx = TransactionRequest::pay(Address::random(), 100).from(Address::random());
+/// client.send_transaction(tx, None).await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`TransformerMiddleware`]: super::TransformerMiddleware
+#[derive(Debug)]
+pub struct SafeTransformer<S = LocalWallet> {
+    address: Address,
+    chain_id: U256,
+    nonce: ProxyNonce,
+    owners: Vec<S>,
+    multi_send: Address,
+}
+
+impl<S: Signer> SafeTransformer<S> {
+    /// Creates a transformer for the Safe at `address`, whose next nonce is `nonce`
+    pub fn new(address: Address, chain_id: impl Into<U256>, nonce: u64, owners: Vec<S>) -> Self {
+        Self {
+            address,
+            chain_id: chain_id.into(),
+            nonce: ProxyNonce::new(nonce),
+            owners,
+            multi_send: MULTI_SEND_CALL_ONLY_ADDRESS,
+        }
+    }
+
+    /// Sets the address of the `MultiSend` contract used by [`SafeTransformer::batch`]. Defaults
+    /// to [`MULTI_SEND_CALL_ONLY_ADDRESS`].
+    #[must_use]
+    pub fn with_multi_send(mut self, multi_send: Address) -> Self {
+        self.multi_send = multi_send;
+        self
+    }
+
+    /// The address of the Safe
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// The nonce of the next transaction of the Safe
+    pub fn nonce(&self) -> u64 {
+        self.nonce.peek()
+    }
+
+    /// Sets the nonce of the next transaction of the Safe, e.g. after transactions were executed
+    /// without this transformer
+    pub fn set_nonce(&self, nonce: u64) {
+        self.nonce.set(nonce);
+    }
+
+    /// Returns the next [`SafeTx`] calling `to`, and increments the nonce
+    pub fn safe_tx(&self, to: Address, value: U256, data: Bytes) -> SafeTx {
+        let nonce = self.nonce.next();
+        SafeTx::new(self.address, self.chain_id, to, value, data, nonce)
+    }
+
+    /// Signs `tx` with the owners' signers, and returns the calldata executing it
+    pub async fn sign(&self, tx: &SafeTx) -> Result<Bytes, TransformerError> {
+        let mut signatures = BTreeMap::new();
+        for owner in &self.owners {
+            let signature = owner
+                .sign_typed_data(tx)
+                .await
+                .map_err(|err| TransformerError::SignerError(err.to_string()))?;
+            signatures.insert(owner.address(), signature);
+        }
+        Ok(tx.exec_transaction_data(pack_signatures(&signatures)))
+    }
+
+    /// Returns a transaction executing all `calls` atomically through the Safe, with a
+    /// `MultiSend` delegate call.
+    ///
+    /// The returned transaction already calls the Safe, and must not be sent through the
+    /// [`TransformerMiddleware`](super::TransformerMiddleware), which would wrap it into another
+    /// `execTransaction`. Its Safe nonce is reserved until it is reported to
+    /// [`Transformer::after_send`], which [`SafeTransformer::send_batch`] does.
+    pub async fn batch(
+        &self,
+        calls: impl IntoIterator<Item = TypedTransaction>,
+    ) -> Result<TypedTransaction, TransformerError> {
+        let mut multi_send = MultiSend::new(self.multi_send);
+        for call in calls {
+            multi_send = multi_send.add_transaction(&call)?;
+        }
+        let mut safe_tx = self.safe_tx(self.multi_send, U256::zero(), multi_send.encode());
+        safe_tx.operation = Operation::DelegateCall;
+
+        let data = self.sign_new(&safe_tx).await?;
+        self.nonce.reserve(&data, safe_tx.nonce.as_u64());
+        let mut tx: TypedTransaction = TransactionRequest::new().into();
+        tx.set_to(self.address).set_data(data);
+        Ok(tx)
+    }
+
+    /// Sends the [`SafeTransformer::batch`] of `calls` with `client`, releasing its Safe nonce if
+    /// it could not be sent
+    pub async fn send_batch<'a, M: Middleware>(
+        &self,
+        client: &'a M,
+        calls: impl IntoIterator<Item = TypedTransaction>,
+        block: Option<BlockId>,
+    ) -> Result<PendingTransaction<'a, M::Provider>, TransformerMiddlewareError<M>> {
+        let tx = self.batch(calls).await?;
+        let sent = client
+            .send_transaction(tx.clone(), block)
+            .await
+            .map_err(TransformerMiddlewareError::MiddlewareError);
+        self.after_send(&tx, sent.is_ok()).await;
+        sent
+    }
+
+    /// Signs `tx`, returned by [`SafeTransformer::safe_tx`], releasing its nonce if signing fails
+    async fn sign_new(&self, tx: &SafeTx) -> Result<Bytes, TransformerError> {
+        let signed = self.sign(tx).await;
+        if signed.is_err() {
+            self.nonce.release(tx.nonce.as_u64());
+        }
+        signed
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<S: Signer> Transformer for SafeTransformer<S> {
+    fn transform(&self, _tx: &mut TypedTransaction) -> Result<(), TransformerError> {
+        Err(TransformerError::AsyncOnly)
+    }
+
+    async fn transform_async(&self, tx: &mut TypedTransaction) -> Result<(), TransformerError> {
+        // the target address cannot be None.
+        let to = *tx.to_addr().ok_or_else(|| TransformerError::MissingField("to".to_string()))?;
+        let value = tx.value().copied().unwrap_or_default();
+        let data = tx.data().cloned().unwrap_or_default();
+
+        // the Safe pays the value, the sender of the transaction only pays the gas
+        let safe_tx = self.safe_tx(to, value, data);
+        let data = self.sign_new(&safe_tx).await?;
+        self.nonce.reserve(&data, safe_tx.nonce.as_u64());
+        tx.set_data(data);
+        tx.set_to(self.address);
+        tx.set_value(U256::zero());
+
+        Ok(())
+    }
+
+    async fn after_send(&self, tx: &TypedTransaction, sent: bool) {
+        if let Some(data) = tx.data() {
+            self.nonce.finish(data, sent);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{reject_transactions, sent_transactions, INSUFFICIENT_FUNDS};
+    use ethers_core::types::transaction::eip712::TypedData;
+    use ethers_providers::{MockResponse, Provider};
+    use serde_json::json;
+
+    fn wallet(key: u8) -> LocalWallet {
+        LocalWallet::from_bytes(&[key; 32]).unwrap()
+    }
+
+    #[test]
+    fn hash_matches_typed_data() {
+        let tx = SafeTx {
+            safe: Address::repeat_byte(0x11),
+            chain_id: 5.into(),
+            to: Address::repeat_byte(0x22),
+            value: 1000.into(),
+            data: vec![0xde, 0xad, 0xbe, 0xef].into(),
+            operation: Operation::DelegateCall,
+            safe_tx_gas: 1.into(),
+            base_gas: 2.into(),
+            gas_price: 3.into(),
+            gas_token: Address::repeat_byte(0x33),
+            refund_receiver: Address::repeat_byte(0x44),
+            nonce: 7.into(),
+        };
+
+        let typed_data: TypedData = serde_json::from_value(json!({
+            "types": {
+                "EIP712Domain": [
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" },
+                ],
+                "SafeTx": [
+                    { "name": "to", "type": "address" },
+                    { "name": "value", "type": "uint256" },
+                    { "name": "data", "type": "bytes" },
+                    { "name": "operation", "type": "uint8" },
+                    { "name": "safeTxGas", "type": "uint256" },
+                    { "name": "baseGas", "type": "uint256" },
+                    { "name": "gasPrice", "type": "uint256" },
+                    { "name": "gasToken", "type": "address" },
+                    { "name": "refundReceiver", "type": "address" },
+                    { "name": "nonce", "type": "uint256" },
+                ],
+            },
+            "primaryType": "SafeTx",
+            "domain": { "chainId": 5, "verifyingContract": tx.safe },
+            "message": {
+                "to": tx.to,
+                "value": "1000",
+                "data": "0xdeadbeef",
+                "operation": 1,
+                "safeTxGas": "1",
+                "baseGas": "2",
+                "gasPrice": "3",
+                "gasToken": tx.gas_token,
+                "refundReceiver": tx.refund_receiver,
+                "nonce": "7",
+            },
+        }))
+        .unwrap();
+
+        assert_eq!(tx.hash(), H256(typed_data.encode_eip712().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn proposals_collect_sorted_signatures() {
+        let owners = [wallet(1), wallet(2), wallet(3)];
+        let tx = SafeTx::new(
+            Address::repeat_byte(0x11),
+            1u64,
+            Address::repeat_byte(0x22),
+            100.into(),
+            Default::default(),
+            0u64,
+        );
+
+        let mut proposal = SafeProposal::new(tx.clone());
+        for owner in owners.iter().rev() {
+            proposal.sign(owner).await.unwrap();
+        }
+        let proposal: SafeProposal =
+            serde_json::from_str(&serde_json::to_string(&proposal).unwrap()).unwrap();
+
+        let mut addresses: Vec<_> = owners.iter().map(|owner| owner.address()).collect();
+        addresses.sort();
+        let packed = proposal.packed_signatures();
+        assert_eq!(packed.len(), 3 * 65);
+        for (owner, signature) in addresses.iter().zip(packed.chunks(65)) {
+            let signature = Signature::try_from(signature).unwrap();
+            assert!(signature.v == 27 || signature.v == 28);
+            assert_eq!(signature.recover(tx.hash()).unwrap(), *owner);
+        }
+
+        let mut other = SafeProposal::new(tx);
+        let signature = proposal.signatures[&addresses[0]];
+        assert!(other.add_signature(addresses[1], signature).is_err());
+        other.add_signature(addresses[0], signature).unwrap();
+
+        let exec = proposal.transaction();
+        assert_eq!(exec.to, Some(Address::repeat_byte(0x11).into()));
+        assert_eq!(&exec.data.unwrap()[..4], &id(EXEC_TRANSACTION)[..]);
+    }
+
+    /// Decodes the arguments of the `execTransaction` call of `tx`
+    fn exec_transaction_args(tx: &TypedTransaction) -> Vec<Token> {
+        let data = tx.data().unwrap();
+        assert_eq!(&data[..4], &id(EXEC_TRANSACTION)[..]);
+        abi::decode(
+            &[
+                abi::ParamType::Address,
+                abi::ParamType::Uint(256),
+                abi::ParamType::Bytes,
+                abi::ParamType::Uint(8),
+                abi::ParamType::Uint(256),
+                abi::ParamType::Uint(256),
+                abi::ParamType::Uint(256),
+                abi::ParamType::Address,
+                abi::ParamType::Address,
+                abi::ParamType::Bytes,
+            ],
+            &data[4..],
+        )
+        .unwrap()
+    }
+
+    /// Recovers the owner whose signature of `signed` comes first in the `execTransaction` of `tx`
+    fn first_signer(tx: &TypedTransaction, signed: &SafeTx) -> Address {
+        let signatures = exec_transaction_args(tx)[9].clone().into_bytes().unwrap();
+        Signature::try_from(&signatures[..65]).unwrap().recover(signed.hash()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn transforms_into_exec_transaction() {
+        let safe = Address::repeat_byte(0x11);
+        let transformer = SafeTransformer::new(safe, 1u64, 5, vec![wallet(1), wallet(2)]);
+        let to = Address::repeat_byte(0x22);
+
+        let mut tx: TypedTransaction = TransactionRequest::pay(to, 100).data(vec![1, 2, 3]).into();
+        assert!(matches!(transformer.transform(&mut tx), Err(TransformerError::AsyncOnly)));
+        transformer.transform_async(&mut tx).await.unwrap();
+        assert_eq!(tx.to_addr(), Some(&safe));
+        assert_eq!(tx.value(), Some(&U256::zero()));
+        assert_eq!(transformer.nonce(), 6);
+
+        let tokens = exec_transaction_args(&tx);
+        assert_eq!(tokens[0], Token::Address(to));
+        assert_eq!(tokens[1], Token::Uint(100.into()));
+        assert_eq!(tokens[2], Token::Bytes(vec![1, 2, 3]));
+        let signatures = tokens[9].clone().into_bytes().unwrap();
+        assert_eq!(signatures.len(), 2 * 65);
+
+        let signed = SafeTx::new(safe, 1u64, to, 100.into(), vec![1, 2, 3].into(), 5u64);
+        assert_eq!(first_signer(&tx, &signed), wallet(1).address().min(wallet(2).address()));
+    }
+
+    #[tokio::test]
+    async fn reuses_the_safe_nonce_of_unsent_transactions() {
+        let safe = Address::repeat_byte(0x11);
+        let transformer = SafeTransformer::new(safe, 1u64, 5, vec![wallet(1)]);
+        let to = Address::repeat_byte(0x22);
+        let transform = || async {
+            let mut tx: TypedTransaction = TransactionRequest::pay(to, 100).into();
+            transformer.transform_async(&mut tx).await.unwrap();
+            tx
+        };
+
+        let unsent = transform().await;
+        assert_eq!(transformer.nonce(), 6);
+        transformer.after_send(&unsent, false).await;
+        assert_eq!(transformer.nonce(), 5);
+
+        let sent = transform().await;
+        transformer.after_send(&sent, true).await;
+        assert_eq!(transformer.nonce(), 6);
+        let signed = SafeTx::new(safe, 1u64, to, 100.into(), Default::default(), 5u64);
+        assert_eq!(first_signer(&sent, &signed), wallet(1).address());
+    }
+
+    #[tokio::test]
+    async fn reuses_the_nonce_of_unsent_batches() {
+        let (provider, mock) = Provider::mocked();
+        reject_transactions(&mock, &[INSUFFICIENT_FUNDS]);
+        mock.on("eth_gasPrice", |_| MockResponse::Value(json!(U256::one())));
+        mock.on("eth_estimateGas", |_| MockResponse::Value(json!(U256::from(100_000))));
+        let safe = Address::repeat_byte(0x11);
+        let transformer = SafeTransformer::new(safe, 1u64, 5, vec![wallet(1)]);
+        let call: TypedTransaction =
+            TransactionRequest::pay(Address::repeat_byte(0x22), 100).into();
+
+        // a batch which is never sent
+        let tx = transformer.batch([call.clone()]).await.unwrap();
+        assert_eq!(transformer.nonce(), 6);
+        transformer.after_send(&tx, false).await;
+        assert_eq!(transformer.nonce(), 5);
+
+        // a batch whose broadcast fails
+        transformer.send_batch(&provider, [call.clone()], None).await.unwrap_err();
+        assert_eq!(transformer.nonce(), 5);
+
+        transformer.send_batch(&provider, [call], None).await.unwrap();
+        assert_eq!(transformer.nonce(), 6);
+
+        // the batch is sent as is, and signed again with the same nonce after the failed broadcast
+        let sent = sent_transactions(&mock);
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0].1.data(), sent[1].1.data());
+        let (_, sent) = &sent[1];
+        assert_eq!(sent.to_addr(), Some(&safe));
+        let tokens = abi::decode(&[abi::ParamType::Address], &sent.data().unwrap()[4..]).unwrap();
+        assert_eq!(tokens[0], Token::Address(MULTI_SEND_CALL_ONLY_ADDRESS));
+    }
+}
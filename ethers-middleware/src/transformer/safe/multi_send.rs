@@ -0,0 +1,105 @@
+use super::Operation;
+use crate::transformer::TransformerError;
+use ethers_core::{
+    abi::{self, Token},
+    types::{transaction::eip2718::TypedTransaction, Address, Bytes, H160, U256},
+    utils::id,
+};
+
+/// The address of the v1.3.0 `MultiSendCallOnly` contract, deployed at the same address on most
+/// chains. It rejects batched delegate calls.
+pub const MULTI_SEND_CALL_ONLY_ADDRESS: Address = H160([
+    0x40, 0xa2, 0xac, 0xcb, 0xd9, 0x2b, 0xca, 0x93, 0x8b, 0x02, 0x01, 0x0e, 0x17, 0xa5, 0xb8, 0x92,
+    0x9b, 0x49, 0x13, 0x0d,
+]);
+
+/// The address of the v1.3.0 `MultiSend` contract, deployed at the same address on most chains.
+pub const MULTI_SEND_ADDRESS: Address = H160([
+    0xa2, 0x38, 0xcb, 0xeb, 0x14, 0x2c, 0x10, 0xef, 0x7a, 0xd8, 0x44, 0x2c, 0x6d, 0x1f, 0x9e, 0x89,
+    0xe0, 0x7e, 0x77, 0x61,
+]);
+
+/// A batch of calls executed atomically by a Safe, through a delegate call to a `MultiSend`
+/// contract.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MultiSend {
+    address: Address,
+    calls: Vec<(Operation, Address, U256, Bytes)>,
+}
+
+impl MultiSend {
+    /// Creates an empty batch for the `MultiSend` contract at `address`
+    pub fn new(address: Address) -> Self {
+        Self { address, calls: Vec::new() }
+    }
+
+    /// The address of the `MultiSend` contract
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// Adds a call to the batch
+    #[must_use]
+    pub fn add(mut self, operation: Operation, to: Address, value: U256, data: Bytes) -> Self {
+        self.calls.push((operation, to, value, data));
+        self
+    }
+
+    /// Adds the call of `tx` to the batch
+    pub fn add_transaction(self, tx: &TypedTransaction) -> Result<Self, TransformerError> {
+        let to = *tx.to_addr().ok_or_else(|| TransformerError::MissingField("to".to_string()))?;
+        let value = tx.value().copied().unwrap_or_default();
+        let data = tx.data().cloned().unwrap_or_default();
+        Ok(self.add(Operation::Call, to, value, data))
+    }
+
+    /// Returns the calldata of `multiSend(bytes)` executing the batch
+    pub fn encode(&self) -> Bytes {
+        // every call is packed as `operation || to || value || data length || data`
+        let mut transactions = Vec::new();
+        for (operation, to, value, data) in self.calls.iter() {
+            transactions.push(*operation as u8);
+            transactions.extend_from_slice(to.as_bytes());
+            let mut word = [0u8; 32];
+            value.to_big_endian(&mut word);
+            transactions.extend_from_slice(&word);
+            U256::from(data.len()).to_big_endian(&mut word);
+            transactions.extend_from_slice(&word);
+            transactions.extend_from_slice(data);
+        }
+
+        let args = abi::encode(&[Token::Bytes(transactions)]);
+        [&id("multiSend(bytes)")[..], &args].concat().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers_core::types::TransactionRequest;
+
+    #[test]
+    fn encodes_packed_calls() {
+        let (a, b) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+        let batch = MultiSend::new(MULTI_SEND_CALL_ONLY_ADDRESS)
+            .add_transaction(&TransactionRequest::pay(a, 1).into())
+            .unwrap()
+            .add(Operation::Call, b, U256::zero(), vec![0x12, 0x34].into());
+        let encoded = batch.encode();
+
+        assert_eq!(&encoded[..4], &id("multiSend(bytes)")[..]);
+        let tokens = abi::decode(&[abi::ParamType::Bytes], &encoded[4..]).unwrap();
+        let packed = tokens[0].clone().into_bytes().unwrap();
+        assert_eq!(packed.len(), 2 * (1 + 20 + 32 + 32) + 2);
+
+        assert_eq!(packed[0], 0);
+        assert_eq!(&packed[1..21], a.as_bytes());
+        assert_eq!(U256::from_big_endian(&packed[21..53]), 1.into());
+        assert_eq!(U256::from_big_endian(&packed[53..85]), 0.into());
+
+        let second = &packed[85..];
+        assert_eq!(&second[1..21], b.as_bytes());
+        assert_eq!(U256::from_big_endian(&second[53..85]), 2.into());
+        assert_eq!(&second[85..], &[0x12, 0x34]);
+    }
+}
@@ -0,0 +1,1105 @@
+use crate::{
+    gas_escalator::MIN_REPLACEMENT_BUMP_PERCENT,
+    nonce_manager::{is_nonce_error, is_rejection},
+};
+use async_trait::async_trait;
+use ethers_core::{
+    types::{
+        transaction::eip2718::TypedTransaction, Address, BlockId, BlockNumber, Bytes,
+        TransactionReceipt, TransactionRequest, TxHash, U256,
+    },
+    utils::keccak256,
+};
+use ethers_providers::{
+    interval, JsonRpcClient, Middleware, MiddlewareError, PendingTransaction, Provider,
+    ProviderError, StreamExt,
+};
+use futures_channel::{mpsc, oneshot};
+use futures_util::{lock::Mutex, select_biased, stream::Stream, FutureExt};
+use instant::{Duration, Instant};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BinaryHeap},
+    fmt::Debug,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use thiserror::Error;
+use tracing_futures::Instrument;
+
+/// A node to which the [`TxManager`] also broadcasts its signed transactions.
+#[async_trait]
+pub trait Broadcaster: Send + Sync + Debug {
+    /// Broadcasts the signed, RLP encoded transaction
+    async fn send_raw_transaction(&self, tx: Bytes) -> Result<TxHash, ProviderError>;
+}
+
+#[async_trait]
+impl<P: JsonRpcClient> Broadcaster for Provider<P> {
+    async fn send_raw_transaction(&self, tx: Bytes) -> Result<TxHash, ProviderError> {
+        self.request("eth_sendRawTransaction", [tx]).await
+    }
+}
+
+/// The priority of a transaction in the [`TxManager`]'s queue. Higher priority transactions are
+/// assigned nonces first, transactions of the same priority are sent in submission order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// The identifier of a transaction submitted to a [`TxManager`]
+pub type TxId = u64;
+
+/// A lifecycle update of a transaction submitted to a [`TxManager`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TxStatus {
+    /// The transaction is waiting in the queue for a nonce
+    Queued,
+    /// The transaction was assigned `nonce` and broadcast
+    Broadcast { nonce: U256, tx_hash: TxHash },
+    /// The transaction was replaced by a copy with higher fees
+    FeeBumped { old_hash: TxHash, new_hash: TxHash },
+    /// The transaction is being replaced by a 0-value self-transfer
+    Cancelling { tx_hash: TxHash },
+    /// The transaction, or one of its fee-bumped copies, was mined
+    Mined(Box<TransactionReceipt>),
+    /// The cancellation of the transaction was mined
+    Cancelled(Box<TransactionReceipt>),
+    /// The nonce of the transaction was used by a transaction which the manager did not send
+    Dropped,
+    /// The transaction could not be signed or broadcast, and its nonce was not used
+    Failed(String),
+}
+
+impl TxStatus {
+    /// Returns true if the transaction will not receive further updates
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            TxStatus::Mined(_) | TxStatus::Cancelled(_) | TxStatus::Dropped | TxStatus::Failed(_)
+        )
+    }
+}
+
+/// The stream of [`TxStatus`] updates of a transaction submitted to a [`TxManager`]. The stream
+/// ends after a final status.
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct TxHandle {
+    id: TxId,
+    status: mpsc::UnboundedReceiver<TxStatus>,
+}
+
+impl TxHandle {
+    /// Returns the identifier of the transaction
+    pub fn id(&self) -> TxId {
+        self.id
+    }
+}
+
+impl Stream for TxHandle {
+    type Item = TxStatus;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.status).poll_next(cx)
+    }
+}
+
+/// Configuration of a [`TxManager`]
+#[derive(Clone, Debug)]
+pub struct TxManagerConfig {
+    interval: Duration,
+    bump_after: Duration,
+    bump_percent: u64,
+    max_fee_per_gas: Option<U256>,
+    max_in_flight: usize,
+    endpoints: Vec<Arc<dyn Broadcaster>>,
+}
+
+impl Default for TxManagerConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(3),
+            bump_after: Duration::from_secs(60),
+            bump_percent: 12,
+            max_fee_per_gas: None,
+            max_in_flight: 16,
+            endpoints: Vec::new(),
+        }
+    }
+}
+
+impl TxManagerConfig {
+    /// Sets how often in-flight transactions are checked, rebroadcast and bumped
+    #[must_use]
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets how long a transaction may stay unmined before its fees are bumped
+    #[must_use]
+    pub fn with_bump_after(mut self, bump_after: Duration) -> Self {
+        self.bump_after = bump_after;
+        self
+    }
+
+    /// Sets the percentage by which fees are bumped. Values below
+    /// [`MIN_REPLACEMENT_BUMP_PERCENT`] are raised to it, as nodes would reject the replacement.
+    #[must_use]
+    pub fn with_bump_percent(mut self, bump_percent: u64) -> Self {
+        self.bump_percent = bump_percent.max(MIN_REPLACEMENT_BUMP_PERCENT);
+        self
+    }
+
+    /// Caps the gas price and the max fee per gas of bumped transactions
+    #[must_use]
+    pub fn with_max_fee_per_gas<T: Into<U256>>(mut self, max_fee_per_gas: T) -> Self {
+        self.max_fee_per_gas = Some(max_fee_per_gas.into());
+        self
+    }
+
+    /// Sets the maximum number of unmined transactions. Queued transactions wait for in-flight
+    /// ones to be mined past that number.
+    #[must_use]
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = max_in_flight.max(1);
+        self
+    }
+
+    /// Adds a node to which signed transactions are also broadcast and rebroadcast
+    #[must_use]
+    pub fn with_endpoint<B: Broadcaster + 'static>(mut self, endpoint: B) -> Self {
+        self.endpoints.push(Arc::new(endpoint));
+        self
+    }
+}
+
+/// Error thrown by the [`TxManager`]
+#[derive(Debug, Error)]
+pub enum TxManagerError<M: Middleware> {
+    /// Thrown when an internal middleware errors
+    #[error("{0}")]
+    MiddlewareError(M::Error),
+
+    /// Thrown when cancelling a nonce which has no unmined transaction
+    #[error("no in-flight transaction with nonce {0}")]
+    NotInFlight(U256),
+
+    /// Thrown when the configured max fee does not allow a valid replacement of the transaction
+    /// with this nonce
+    #[error("the max fee does not allow replacing the transaction with nonce {0}")]
+    MaxFeeReached(U256),
+
+    /// Thrown when the transaction could not be broadcast
+    #[error("transaction failed: {0}")]
+    TransactionFailed(String),
+
+    /// Thrown when every node rejected the transaction without adding it to its mempool
+    #[error("transaction rejected: {0}")]
+    Rejected(String),
+
+    /// Thrown when the background task has stopped
+    #[error("the transaction manager has shut down")]
+    ShutDown,
+}
+
+impl<M: Middleware> MiddlewareError for TxManagerError<M> {
+    type Inner = M::Error;
+
+    fn from_err(src: M::Error) -> Self {
+        TxManagerError::MiddlewareError(src)
+    }
+
+    fn as_inner(&self) -> Option<&Self::Inner> {
+        match self {
+            TxManagerError::MiddlewareError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Queued {
+    id: TxId,
+    priority: Priority,
+    tx: TypedTransaction,
+    status: mpsc::UnboundedSender<TxStatus>,
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    // highest priority first, then lowest id
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority.cmp(&other.priority).then_with(|| other.id.cmp(&self.id))
+    }
+}
+
+#[derive(Debug)]
+struct InFlight {
+    id: TxId,
+    /// The latest version of the transaction, with its current fees
+    tx: TypedTransaction,
+    raw: Bytes,
+    /// The hashes of all versions of the transaction, the latest last
+    hashes: Vec<TxHash>,
+    last_bump: Instant,
+    /// The hashes of the cancellations replacing the transaction
+    cancellations: Vec<TxHash>,
+    status: mpsc::UnboundedSender<TxStatus>,
+}
+
+impl InFlight {
+    fn notify(&self, status: TxStatus) {
+        // the handle may have been dropped
+        let _ = self.status.unbounded_send(status);
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    next_id: TxId,
+    nonce: Option<U256>,
+    queue: BinaryHeap<Queued>,
+    in_flight: BTreeMap<U256, InFlight>,
+}
+
+#[derive(Debug)]
+struct TxManagerInner<M> {
+    inner: Arc<M>,
+    address: Address,
+    config: TxManagerConfig,
+    /// Never held across requests to the nodes
+    state: Mutex<State>,
+    /// Serializes the checks of in-flight transactions and cancellations, so that a fee bump
+    /// and a cancellation never replace the same transaction at once
+    replacing: Mutex<()>,
+    wake: mpsc::UnboundedSender<()>,
+}
+
+/// A middleware which owns every transaction sent from `address`.
+///
+/// Transactions are queued by [`Priority`], assigned nonces and signed by the inner middleware
+/// (e.g. a [`SignerMiddleware`](crate::SignerMiddleware)), then broadcast to the inner
+/// middleware and to the configured extra endpoints. A background task rebroadcasts unmined
+/// transactions, bumps their fees once they are stuck for longer than the configured duration,
+/// and reports every step on the transaction's [`TxHandle`] status stream.
+///
+/// Like the [`GasEscalatorMiddleware`](crate::GasEscalatorMiddleware), the background task is
+/// shared by all clones of the manager and stops when the last one is dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// use ethers_core::types::TransactionRequest;
+/// use ethers_middleware::{
+///     tx_manager::{Priority, TxManager, TxManagerConfig, TxStatus},
+///     SignerMiddleware,
+/// };
+/// use ethers_providers::{Http, Provider, StreamExt};
+/// use ethers_signers::{LocalWallet, Signer};
+/// use std::time::Duration;
+///
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let provider = Provider::<Http>::try_from("http://localhost:8545")?;
+/// let backup = Provider::<Http>::try_from("http://localhost:8546")?;
+/// let wallet: LocalWallet = "380eb0f3d505f087e438eca80bc4df9a7faa24f868e69fc0440261a0fc0567dc"
+///     .parse::<LocalWallet>()?
+///     .with_chain_id(1u64);
+/// let address = wallet.address();
+///
+/// let config = TxManagerConfig::default()
+///     .with_bump_after(Duration::from_secs(30))
+///     .with_endpoint(backup);
+/// let manager = TxManager::new(SignerMiddleware::new(provider, wallet), address, config);
+///
+/// let tx = TransactionRequest::pay(address, 100);
+/// let mut handle = manager.submit(tx, Priority::High).await?;
+/// while let Some(status) = handle.next().await {
+///     if let TxStatus::Broadcast { nonce, .. } = status {
+///         // changed our mind
+///         manager.cancel(nonce).await?;
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TxManager<M> {
+    inner: Arc<TxManagerInner<M>>,
+    _background: Arc<oneshot::Sender<()>>,
+}
+
+impl<M> TxManager<M>
+where
+    M: Middleware + 'static,
+{
+    /// Creates the manager of the transactions of `address`, which must be signable by `inner`,
+    /// and spawns its background task
+    pub fn new(inner: M, address: Address, config: TxManagerConfig) -> Self {
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let (wake_tx, wake_rx) = mpsc::unbounded();
+
+        let inner = Arc::new(TxManagerInner {
+            inner: Arc::new(inner),
+            address,
+            config,
+            state: Mutex::new(State::default()),
+            replacing: Mutex::new(()),
+            wake: wake_tx,
+        });
+
+        tokio::spawn(
+            inner.clone().run(wake_rx, shutdown_rx).instrument(tracing::trace_span!("tx-manager")),
+        );
+
+        Self { inner, _background: Arc::new(shutdown_tx) }
+    }
+
+    /// Returns the address whose transactions are managed
+    pub fn address(&self) -> Address {
+        self.inner.address
+    }
+
+    /// Queues `tx` and returns the stream of its status updates
+    pub async fn submit<T: Into<TypedTransaction>>(
+        &self,
+        tx: T,
+        priority: Priority,
+    ) -> Result<TxHandle, TxManagerError<M>> {
+        let (status_tx, status_rx) = mpsc::unbounded();
+        let mut tx = tx.into();
+        tx.set_from(self.inner.address);
+
+        let id = {
+            let mut state = self.inner.state.lock().await;
+            let id = state.next_id;
+            state.next_id += 1;
+            let _ = status_tx.unbounded_send(TxStatus::Queued);
+            state.queue.push(Queued { id, priority, tx, status: status_tx });
+            id
+        };
+        self.inner.wake.unbounded_send(()).map_err(|_| TxManagerError::ShutDown)?;
+
+        Ok(TxHandle { id, status: status_rx })
+    }
+
+    /// Replaces the unmined transaction with `nonce` by a 0-value transfer to self with higher
+    /// fees, and returns the hash of the cancellation. The transaction's status stream reports
+    /// [`TxStatus::Cancelled`] once the cancellation is mined.
+    pub async fn cancel(&self, nonce: U256) -> Result<TxHash, TxManagerError<M>> {
+        let _replacing = self.inner.replacing.lock().await;
+        let tx = {
+            let state = self.inner.state.lock().await;
+            state.in_flight.get(&nonce).ok_or(TxManagerError::NotInFlight(nonce))?.tx.clone()
+        };
+
+        let mut cancellation: TypedTransaction = match tx {
+            TypedTransaction::Eip1559(ref inner) => {
+                let mut cancellation = inner.clone();
+                cancellation.value = None;
+                cancellation.data = None;
+                cancellation.access_list = Default::default();
+                cancellation.into()
+            }
+            _ => {
+                let mut cancellation = TransactionRequest::new();
+                cancellation.gas_price = tx.gas_price();
+                cancellation.chain_id = tx.chain_id().map(|id| id.as_u64().into());
+                cancellation.into()
+            }
+        };
+        cancellation
+            .set_from(self.inner.address)
+            .set_to(self.inner.address)
+            .set_gas(21_000)
+            .set_nonce(nonce);
+        if !self.inner.bump_fees(&mut cancellation) {
+            return Err(TxManagerError::MaxFeeReached(nonce))
+        }
+
+        let (raw, tx_hash) = self.inner.sign(&cancellation).await?;
+        self.inner.broadcast(raw.clone()).await?;
+
+        tracing::debug!(?nonce, ?tx_hash, "cancelling transaction");
+        let mut state = self.inner.state.lock().await;
+        // only checks of in-flight transactions remove them, which can not run concurrently
+        let in_flight = state.in_flight.get_mut(&nonce).expect("nonce is in flight");
+        in_flight.tx = cancellation;
+        in_flight.raw = raw;
+        in_flight.hashes.push(tx_hash);
+        in_flight.last_bump = Instant::now();
+        in_flight.cancellations.push(tx_hash);
+        in_flight.notify(TxStatus::Cancelling { tx_hash });
+        Ok(tx_hash)
+    }
+
+    /// Returns the number of queued and of in-flight transactions
+    pub async fn pending(&self) -> (usize, usize) {
+        let state = self.inner.state.lock().await;
+        (state.queue.len(), state.in_flight.len())
+    }
+}
+
+impl<M> TxManagerInner<M>
+where
+    M: Middleware + 'static,
+{
+    async fn run(
+        self: Arc<Self>,
+        wake: mpsc::UnboundedReceiver<()>,
+        shutdown: oneshot::Receiver<()>,
+    ) {
+        let mut wake = wake.fuse();
+        let mut shutdown = shutdown.fuse();
+        let mut ticker = interval(self.config.interval).fuse();
+
+        loop {
+            let tick = select_biased! {
+                _ = shutdown => {
+                    tracing::debug!("shutting down transaction manager, middleware has gone away");
+                    return
+                }
+                _ = wake.next() => false,
+                _ = ticker.next() => true,
+            };
+
+            if tick {
+                if let Err(err) = self.check_in_flight().await {
+                    tracing::warn!(err = %err, "failed to check in-flight transactions");
+                }
+            }
+            self.send_queued().await;
+        }
+    }
+
+    /// Assigns nonces to queued transactions, signs and broadcasts them.
+    ///
+    /// Only the background task sends queued transactions, so the nonce does not change while the
+    /// state is unlocked.
+    async fn send_queued(&self) {
+        loop {
+            let (queued, nonce) = {
+                let mut state = self.state.lock().await;
+                if state.in_flight.len() >= self.config.max_in_flight {
+                    return
+                }
+                let Some(queued) = state.queue.pop() else { return };
+                (queued, state.nonce)
+            };
+            let Queued { id, priority, tx: queued_tx, status } = queued;
+
+            let nonce = match nonce {
+                Some(nonce) => nonce,
+                None => match self.pending_nonce().await {
+                    Ok(nonce) => nonce,
+                    Err(err) => {
+                        let _ = status.unbounded_send(TxStatus::Failed(err.to_string()));
+                        continue
+                    }
+                },
+            };
+            let mut tx = queued_tx.clone();
+            tx.set_nonce(nonce);
+
+            let sent = async {
+                self.inner
+                    .fill_transaction(&mut tx, None)
+                    .await
+                    .map_err(TxManagerError::from_err)?;
+                let (raw, tx_hash) = self.sign(&tx).await?;
+                match self.broadcast(raw.clone()).await {
+                    Ok(()) => {}
+                    // a node may have accepted the transaction, so its nonce stays reserved. The
+                    // checks of in-flight transactions look it up by its hash and rebroadcast it
+                    Err(TxManagerError::TransactionFailed(err)) => {
+                        tracing::debug!(id, ?nonce, err = %err, "broadcast may have failed")
+                    }
+                    Err(err) => return Err(err),
+                }
+                Ok::<_, TxManagerError<M>>((raw, tx_hash))
+            };
+            let sent = sent.await;
+            let mut state = self.state.lock().await;
+            match sent {
+                Ok((raw, tx_hash)) => {
+                    tracing::debug!(id, ?nonce, ?tx_hash, "broadcast transaction");
+                    state.nonce = Some(nonce + 1);
+                    let in_flight = InFlight {
+                        id,
+                        tx,
+                        raw,
+                        hashes: vec![tx_hash],
+                        last_bump: Instant::now(),
+                        cancellations: Vec::new(),
+                        status,
+                    };
+                    in_flight.notify(TxStatus::Broadcast { nonce, tx_hash });
+                    state.in_flight.insert(nonce, in_flight);
+                }
+                Err(err) if is_nonce_error(&err) => {
+                    // resync the nonce and retry the transaction on the next run, as its nonce
+                    // was not used
+                    tracing::debug!(id, err = %err, "nonce out of sync, requeueing transaction");
+                    state.nonce = None;
+                    state.queue.push(Queued { id, priority, tx: queued_tx, status });
+                    return
+                }
+                Err(err) => {
+                    tracing::debug!(id, err = %err, "failed to send transaction");
+                    let _ = status.unbounded_send(TxStatus::Failed(err.to_string()));
+                }
+            }
+        }
+    }
+
+    /// Reports mined and dropped transactions, and rebroadcasts or fee-bumps the others
+    async fn check_in_flight(&self) -> Result<(), TxManagerError<M>> {
+        let _replacing = self.replacing.lock().await;
+        let in_flight: Vec<_> = {
+            let state = self.state.lock().await;
+            state
+                .in_flight
+                .iter()
+                .map(|(nonce, in_flight)| (*nonce, in_flight.hashes.clone()))
+                .collect()
+        };
+        if in_flight.is_empty() {
+            return Ok(())
+        }
+
+        let mined_nonce = self
+            .inner
+            .get_transaction_count(self.address, Some(BlockNumber::Latest.into()))
+            .await
+            .map_err(TxManagerError::from_err)?;
+
+        // in-flight transactions are only removed and replaced below, or by cancellations which
+        // can not run concurrently, so they are still there after every request
+        for (nonce, hashes) in in_flight {
+            let mut receipt = None;
+            for tx_hash in hashes.iter().rev() {
+                receipt = self
+                    .inner
+                    .get_transaction_receipt(*tx_hash)
+                    .await
+                    .map_err(TxManagerError::from_err)?;
+                if receipt.is_some() {
+                    break
+                }
+            }
+
+            if let Some(receipt) = receipt {
+                let mut state = self.state.lock().await;
+                let in_flight = state.in_flight.remove(&nonce).expect("nonce is in flight");
+                tracing::debug!(id = in_flight.id, tx_hash = ?receipt.transaction_hash, "mined");
+                let cancelled = in_flight.cancellations.contains(&receipt.transaction_hash);
+                in_flight.notify(if cancelled {
+                    TxStatus::Cancelled(Box::new(receipt))
+                } else {
+                    TxStatus::Mined(Box::new(receipt))
+                });
+                continue
+            }
+
+            if nonce < mined_nonce {
+                // the nonce was used by a transaction which we did not send
+                let mut state = self.state.lock().await;
+                let in_flight = state.in_flight.remove(&nonce).expect("nonce is in flight");
+                tracing::debug!(id = in_flight.id, ?nonce, "dropped");
+                in_flight.notify(TxStatus::Dropped);
+                continue
+            }
+
+            let (tx, raw, due) = {
+                let state = self.state.lock().await;
+                let in_flight = &state.in_flight[&nonce];
+                let due = in_flight.last_bump.elapsed() >= self.config.bump_after;
+                (in_flight.tx.clone(), in_flight.raw.clone(), due)
+            };
+
+            if due {
+                let mut bumped = tx;
+                if self.bump_fees(&mut bumped) {
+                    let (raw, new_hash) = self.sign(&bumped).await?;
+                    match self.broadcast(raw.clone()).await {
+                        Ok(()) => {
+                            let mut state = self.state.lock().await;
+                            let in_flight =
+                                state.in_flight.get_mut(&nonce).expect("nonce is in flight");
+                            let old_hash = *in_flight.hashes.last().expect("at least one hash");
+                            tracing::debug!(?old_hash, ?new_hash, "bumped fees");
+                            in_flight.tx = bumped;
+                            in_flight.raw = raw;
+                            in_flight.hashes.push(new_hash);
+                            if !in_flight.cancellations.is_empty() {
+                                // the bumped transaction is a cancellation as well
+                                in_flight.cancellations.push(new_hash);
+                            }
+                            in_flight.last_bump = Instant::now();
+                            in_flight.notify(TxStatus::FeeBumped { old_hash, new_hash });
+                        }
+                        // the transaction may have just been mined, which will be picked up by
+                        // the next check
+                        Err(err) => tracing::debug!(err = %err, "failed to bump fees"),
+                    }
+                    continue
+                }
+            }
+
+            // keep the transaction in the mempools
+            if let Err(err) = self.broadcast(raw).await {
+                tracing::trace!(err = %err, "failed to rebroadcast transaction");
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn pending_nonce(&self) -> Result<U256, TxManagerError<M>> {
+        self.inner
+            .get_transaction_count(self.address, Some(BlockNumber::Pending.into()))
+            .await
+            .map_err(TxManagerError::from_err)
+    }
+
+    async fn sign(&self, tx: &TypedTransaction) -> Result<(Bytes, TxHash), TxManagerError<M>> {
+        let signature = self
+            .inner
+            .sign_transaction(tx, self.address)
+            .await
+            .map_err(TxManagerError::from_err)?;
+        let raw = tx.rlp_signed(&signature);
+        let tx_hash = keccak256(&raw).into();
+        Ok((raw, tx_hash))
+    }
+
+    /// Broadcasts the transaction to the inner middleware and to the extra endpoints. Succeeds if
+    /// any of them accepted it, or already knew it, and fails with [`TxManagerError::Rejected`]
+    /// if all of them rejected it without adding it to their mempool.
+    async fn broadcast(&self, raw: Bytes) -> Result<(), TxManagerError<M>> {
+        let mut result = match self.inner.send_raw_transaction(raw.clone()).await {
+            Ok(_) => Ok(()),
+            Err(err) if is_known_error(&err) => Ok(()),
+            Err(err) if is_rejection(&err) || is_nonce_error(&err) => {
+                Err(TxManagerError::Rejected(err.to_string()))
+            }
+            Err(err) => Err(TxManagerError::TransactionFailed(err.to_string())),
+        };
+
+        for endpoint in self.config.endpoints.iter() {
+            match endpoint.send_raw_transaction(raw.clone()).await {
+                Ok(_) => result = Ok(()),
+                Err(err) if is_known_error(&err) => result = Ok(()),
+                Err(err) => {
+                    tracing::debug!(?endpoint, err = %err, "endpoint rejected transaction");
+                    // the transaction may have reached the endpoint's mempool
+                    if !is_rejection(&err) && !is_nonce_error(&err) {
+                        if let Err(TxManagerError::Rejected(err)) = result {
+                            result = Err(TxManagerError::TransactionFailed(err));
+                        }
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Bumps the fees of `tx` by the configured percentage, capped by the configured max fee.
+    /// Returns false if the cap does not allow a valid replacement.
+    fn bump_fees(&self, tx: &mut TypedTransaction) -> bool {
+        let bump = |fee: U256| fee * (100 + self.config.bump_percent) / 100 + 1;
+        let min_bump = |fee: U256| fee * (100 + MIN_REPLACEMENT_BUMP_PERCENT) / 100;
+        let cap = |fee: U256| self.config.max_fee_per_gas.map_or(fee, |max| fee.min(max));
+
+        match tx {
+            TypedTransaction::Eip1559(ref mut inner) => {
+                let max_fee = inner.max_fee_per_gas.unwrap_or_default();
+                let priority_fee = inner.max_priority_fee_per_gas.unwrap_or_default();
+                let new_max_fee = cap(bump(max_fee));
+                let new_priority_fee = bump(priority_fee).min(new_max_fee);
+                if new_max_fee < min_bump(max_fee) || new_priority_fee < min_bump(priority_fee) {
+                    return false
+                }
+                inner.max_fee_per_gas = Some(new_max_fee);
+                inner.max_priority_fee_per_gas = Some(new_priority_fee);
+            }
+            _ => {
+                let gas_price = tx.gas_price().unwrap_or_default();
+                let new_gas_price = cap(bump(gas_price));
+                if new_gas_price < min_bump(gas_price) {
+                    return false
+                }
+                tx.set_gas_price(new_gas_price);
+            }
+        }
+        true
+    }
+}
+
+/// Returns true if the error is the node rejecting a transaction which it already has
+fn is_known_error(err: &impl std::error::Error) -> bool {
+    let err = err.to_string().to_lowercase();
+    err.contains("already known") || err.contains("known transaction")
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<M> Middleware for TxManager<M>
+where
+    M: Middleware + 'static,
+{
+    type Error = TxManagerError<M>;
+    type Provider = M::Provider;
+    type Inner = M;
+
+    fn inner(&self) -> &M {
+        &self.inner.inner
+    }
+
+    /// Submits the transaction with [`Priority::Normal`] and waits until it is broadcast.
+    ///
+    /// The returned [`PendingTransaction`] tracks the first broadcast version of the transaction;
+    /// use [`TxManager::submit`] to follow fee bumps.
+    async fn send_transaction<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        tx: T,
+        _: Option<BlockId>,
+    ) -> Result<PendingTransaction<'_, Self::Provider>, Self::Error> {
+        let mut handle = self.submit(tx, Priority::Normal).await?;
+        while let Some(status) = handle.next().await {
+            match status {
+                TxStatus::Broadcast { tx_hash, .. } => {
+                    return Ok(PendingTransaction::new(tx_hash, self.provider()))
+                }
+                TxStatus::Failed(err) => return Err(TxManagerError::TransactionFailed(err)),
+                _ => {}
+            }
+        }
+        Err(TxManagerError::ShutDown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_utils::{
+            accept_transactions, reject_transactions, sent_transactions, INSUFFICIENT_FUNDS,
+        },
+        SignerMiddleware,
+    };
+    use ethers_core::types::{Eip1559TransactionRequest, U64};
+    use ethers_providers::{JsonRpcError, MockProvider, MockResponse};
+    use ethers_signers::{LocalWallet, Signer};
+    use serde_json::{json, Value};
+    use std::sync::{
+        atomic::{AtomicBool, Ordering as AtomicOrdering},
+        Mutex as StdMutex,
+    };
+
+    #[test]
+    fn queue_orders_by_priority_then_submission() {
+        let queued = |id, priority| Queued {
+            id,
+            priority,
+            tx: TransactionRequest::new().into(),
+            status: mpsc::unbounded().0,
+        };
+        let mut queue = BinaryHeap::from(vec![
+            queued(0, Priority::Normal),
+            queued(1, Priority::Low),
+            queued(2, Priority::High),
+            queued(3, Priority::Normal),
+        ]);
+        let order: Vec<_> = std::iter::from_fn(|| queue.pop().map(|q| q.id)).collect();
+        assert_eq!(order, vec![2, 0, 3, 1]);
+    }
+
+    /// A mocked chain which accepts broadcast transactions and mines the ones in `mined`
+    struct Chain {
+        mock: MockProvider,
+        mined: Arc<StdMutex<Vec<TxHash>>>,
+    }
+
+    impl Chain {
+        /// The hashes and JSON encodings of the broadcast transactions
+        fn sent(&self) -> Vec<(TxHash, Value)> {
+            sent_transactions(&self.mock)
+                .into_iter()
+                .map(|(tx_hash, tx)| (tx_hash, serde_json::to_value(tx).unwrap()))
+                .collect()
+        }
+    }
+
+    fn chain() -> (Provider<MockProvider>, Chain) {
+        chain_with(|_| {})
+    }
+
+    /// Creates a chain whose handlers come after the ones registered by `setup`
+    fn chain_with(setup: impl FnOnce(&MockProvider)) -> (Provider<MockProvider>, Chain) {
+        let (provider, mock) = Provider::mocked();
+        let mined = Arc::new(StdMutex::new(Vec::<TxHash>::new()));
+
+        setup(&mock);
+        mock.on("eth_getTransactionCount", |_| MockResponse::Value(json!(U256::from(7))));
+        accept_transactions(&mock);
+        let (mock2, mined2) = (mock.clone(), mined.clone());
+        mock.on("eth_getTransactionReceipt", move |params| {
+            let tx_hash: TxHash = serde_json::from_value(params[0].clone()).unwrap();
+            if !mined2.lock().unwrap().contains(&tx_hash) {
+                return MockResponse::Value(Value::Null)
+            }
+            let sent = sent_transactions(&mock2);
+            let (_, tx) = sent.iter().find(|(hash, _)| *hash == tx_hash).unwrap();
+            let receipt = TransactionReceipt {
+                transaction_hash: tx_hash,
+                block_number: Some(U64::from(1)),
+                to: tx.to_addr().copied(),
+                ..Default::default()
+            };
+            MockResponse::Value(json!(receipt))
+        });
+
+        (provider, Chain { mock, mined })
+    }
+
+    fn manager(provider: Provider<MockProvider>) -> TxManager<impl Middleware> {
+        let wallet = LocalWallet::new(&mut rand::thread_rng()).with_chain_id(1u64);
+        let address = wallet.address();
+        let config = TxManagerConfig::default()
+            .with_interval(Duration::from_millis(10))
+            .with_bump_after(Duration::from_millis(30));
+        TxManager::new(SignerMiddleware::new(provider, wallet), address, config)
+    }
+
+    #[tokio::test]
+    async fn broadcasts_bumps_and_reports_mined_transactions() {
+        let (provider, chain) = chain();
+        let manager = manager(provider);
+
+        let tx = Eip1559TransactionRequest::new()
+            .to(Address::zero())
+            .gas(21_000)
+            .max_fee_per_gas(100)
+            .max_priority_fee_per_gas(10);
+        let mut handle = manager.submit(tx, Priority::Normal).await.unwrap();
+
+        assert_eq!(handle.next().await, Some(TxStatus::Queued));
+        let Some(TxStatus::Broadcast { nonce, tx_hash }) = handle.next().await else { panic!() };
+        assert_eq!(nonce, 7.into());
+
+        let Some(TxStatus::FeeBumped { old_hash, new_hash }) = handle.next().await else {
+            panic!()
+        };
+        assert_eq!(old_hash, tx_hash);
+        let bumped = chain.sent().into_iter().find(|(h, _)| *h == new_hash).unwrap().1;
+        assert_eq!(bumped["maxFeePerGas"], json!("0x71"));
+        assert_eq!(bumped["maxPriorityFeePerGas"], json!("0xc"));
+        assert_eq!(bumped["nonce"], json!("0x7"));
+
+        chain.mined.lock().unwrap().push(tx_hash);
+        let status = loop {
+            match handle.next().await.unwrap() {
+                TxStatus::FeeBumped { .. } => continue,
+                status => break status,
+            }
+        };
+        let TxStatus::Mined(receipt) = status else { panic!("{status:?}") };
+        assert_eq!(receipt.transaction_hash, tx_hash);
+        assert_eq!(handle.next().await, None);
+        assert_eq!(manager.pending().await, (0, 0));
+        chain
+            .mock
+            .assert_any_request("eth_getTransactionCount", (manager.address(), "pending"))
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn cancels_in_flight_transactions() {
+        let (provider, chain) = chain();
+        let manager = manager(provider);
+
+        let tx = TransactionRequest::pay(Address::zero(), 1000).gas(50_000).gas_price(100);
+        let mut handle = manager.submit(tx, Priority::High).await.unwrap();
+        assert_eq!(handle.next().await, Some(TxStatus::Queued));
+        let Some(TxStatus::Broadcast { nonce, .. }) = handle.next().await else { panic!() };
+
+        let cancellation = manager.cancel(nonce).await.unwrap();
+        let sent = chain.sent().pop().unwrap();
+        assert_eq!(sent.0, cancellation);
+        assert_eq!(sent.1["to"], json!(manager.address()));
+        assert_eq!(sent.1["value"], json!("0x0"));
+        assert_eq!(sent.1["gasPrice"], json!("0x71"));
+        assert_eq!(sent.1["nonce"], json!("0x7"));
+
+        chain.mined.lock().unwrap().push(cancellation);
+        let status = loop {
+            match handle.next().await.unwrap() {
+                TxStatus::Cancelling { .. } | TxStatus::FeeBumped { .. } => continue,
+                status => break status,
+            }
+        };
+        assert!(matches!(status, TxStatus::Cancelled(_)), "{status:?}");
+        assert!(matches!(manager.cancel(nonce).await, Err(TxManagerError::NotInFlight(_))));
+    }
+
+    #[tokio::test]
+    async fn reports_mined_self_transfers_which_were_being_cancelled() {
+        let (provider, chain) = chain();
+        let manager = manager(provider);
+
+        let tx = TransactionRequest::pay(manager.address(), 1000).gas(21_000).gas_price(100);
+        let mut handle = manager.submit(tx, Priority::High).await.unwrap();
+        assert_eq!(handle.next().await, Some(TxStatus::Queued));
+        let Some(TxStatus::Broadcast { nonce, tx_hash }) = handle.next().await else { panic!() };
+        manager.cancel(nonce).await.unwrap();
+
+        // the original transaction wins the race against its cancellation
+        chain.mined.lock().unwrap().push(tx_hash);
+        let status = loop {
+            match handle.next().await.unwrap() {
+                TxStatus::Cancelling { .. } | TxStatus::FeeBumped { .. } => continue,
+                status => break status,
+            }
+        };
+        assert!(
+            matches!(status, TxStatus::Mined(ref receipt) if receipt.transaction_hash == tx_hash)
+        );
+    }
+
+    #[tokio::test]
+    async fn refuses_underpriced_cancellations() {
+        let (provider, _chain) = chain();
+        let wallet = LocalWallet::new(&mut rand::thread_rng()).with_chain_id(1u64);
+        let address = wallet.address();
+        let config = TxManagerConfig::default()
+            .with_interval(Duration::from_millis(10))
+            .with_max_fee_per_gas(100);
+        let manager = TxManager::new(SignerMiddleware::new(provider, wallet), address, config);
+
+        let tx = TransactionRequest::pay(Address::zero(), 1000).gas(50_000).gas_price(100);
+        let mut handle = manager.submit(tx, Priority::Normal).await.unwrap();
+        assert_eq!(handle.next().await, Some(TxStatus::Queued));
+        let Some(TxStatus::Broadcast { nonce, .. }) = handle.next().await else { panic!() };
+
+        let err = manager.cancel(nonce).await.unwrap_err();
+        assert!(matches!(err, TxManagerError::MaxFeeReached(n) if n == nonce), "{err}");
+    }
+
+    #[tokio::test]
+    async fn requeues_transactions_rejected_for_their_nonce() {
+        let (provider, chain) = chain_with(|mock| {
+            // the pending count moves on after the first broadcast is rejected
+            let mut calls = 0;
+            mock.on("eth_getTransactionCount", move |_| {
+                calls += 1;
+                MockResponse::Value(json!(U256::from(if calls == 1 { 7 } else { 8 })))
+            });
+            let rejected = AtomicBool::new(false);
+            mock.on_match(
+                "eth_sendRawTransaction",
+                move |_| !rejected.swap(true, AtomicOrdering::SeqCst),
+                |_| {
+                    MockResponse::Error(JsonRpcError {
+                        code: -32000,
+                        message: "nonce too low".to_string(),
+                        data: None,
+                    })
+                },
+            );
+        });
+        let manager = manager(provider);
+
+        let mut handle = manager
+            .submit(
+                TransactionRequest::pay(Address::zero(), 1).gas(21_000).gas_price(10),
+                Priority::Normal,
+            )
+            .await
+            .unwrap();
+        assert_eq!(handle.next().await, Some(TxStatus::Queued));
+        let Some(TxStatus::Broadcast { nonce, tx_hash }) = handle.next().await else { panic!() };
+        assert_eq!(nonce, 8.into());
+
+        let sent: Vec<_> = sent_transactions(&chain.mock)
+            .into_iter()
+            .map(|(_, tx)| tx.nonce().unwrap().as_u64())
+            .collect();
+        assert_eq!(sent, vec![7, 8]);
+        assert_eq!(chain.sent().last().unwrap().0, tx_hash);
+    }
+
+    /// Submits two transfers and returns their handles with their status after being queued
+    async fn submit_two(
+        manager: &TxManager<impl Middleware + 'static>,
+    ) -> Vec<(TxHandle, TxStatus)> {
+        let mut handles = Vec::new();
+        for _ in 0..2 {
+            let tx = TransactionRequest::pay(Address::zero(), 1).gas(21_000).gas_price(10);
+            handles.push(manager.submit(tx, Priority::Normal).await.unwrap());
+        }
+        let mut sent = Vec::new();
+        for mut handle in handles {
+            assert_eq!(handle.next().await, Some(TxStatus::Queued));
+            let status = handle.next().await.unwrap();
+            sent.push((handle, status));
+        }
+        sent
+    }
+
+    #[tokio::test]
+    async fn reuses_the_nonce_of_rejected_transactions() {
+        let (provider, _chain) =
+            chain_with(|mock| reject_transactions(mock, &[INSUFFICIENT_FUNDS]));
+        let manager = manager(provider);
+
+        let sent = submit_two(&manager).await;
+        assert!(matches!(sent[0].1, TxStatus::Failed(_)), "{:?}", sent[0].1);
+        assert!(matches!(sent[1].1, TxStatus::Broadcast { nonce, .. } if nonce == 7.into()));
+    }
+
+    #[tokio::test]
+    async fn keeps_the_nonce_of_transactions_which_may_have_been_broadcast() {
+        let (provider, chain) =
+            chain_with(|mock| reject_transactions(mock, &["request timed out"]));
+        let manager = manager(provider);
+
+        let mut sent = submit_two(&manager).await;
+        assert!(matches!(sent[1].1, TxStatus::Broadcast { nonce, .. } if nonce == 8.into()));
+        let (ref mut handle, TxStatus::Broadcast { nonce, tx_hash }) = sent[0] else {
+            panic!("{:?}", sent[0].1)
+        };
+        assert_eq!(nonce, 7.into());
+
+        // the transaction is tracked by its hash
+        chain.mined.lock().unwrap().push(tx_hash);
+        let status = loop {
+            match handle.next().await.unwrap() {
+                TxStatus::FeeBumped { .. } => continue,
+                status => break status,
+            }
+        };
+        assert!(
+            matches!(status, TxStatus::Mined(ref receipt) if receipt.transaction_hash == tx_hash)
+        );
+    }
+}
@@ -2,7 +2,7 @@ use ethers_core::{rand::thread_rng, types::U64};
 use ethers_middleware::{
     builder::MiddlewareBuilder,
     gas_escalator::{Frequency, GasEscalatorMiddleware, GeometricGasPrice},
-    gas_oracle::{GasNow, GasOracleMiddleware},
+    gas_oracle::{FeeHistoryOracle, GasOracleMiddleware},
     nonce_manager::NonceManagerMiddleware,
     signer::SignerMiddleware,
 };
@@ -16,10 +16,11 @@ async fn build_raw_middleware_stack() {
     let signer = LocalWallet::new(&mut thread_rng());
     let address = signer.address();
     let escalator = GeometricGasPrice::new(1.125, 60u64, None::<u64>);
+    let gas_oracle = FeeHistoryOracle::new(provider.clone());
 
     let provider = provider
         .wrap_into(|p| GasEscalatorMiddleware::new(p, escalator, Frequency::PerBlock))
-        .wrap_into(|p| GasOracleMiddleware::new(p, GasNow::new()))
+        .wrap_into(|p| GasOracleMiddleware::new(p, gas_oracle))
         .wrap_into(|p| SignerMiddleware::new(p, signer))
         .wrap_into(|p| NonceManagerMiddleware::new(p, address));
 
@@ -43,7 +44,7 @@ async fn build_declarative_middleware_stack() {
     let signer = LocalWallet::new(&mut thread_rng());
     let address = signer.address();
     let escalator = GeometricGasPrice::new(1.125, 60u64, None::<u64>);
-    let gas_oracle = GasNow::new();
+    let gas_oracle = FeeHistoryOracle::new(provider.clone());
 
     let provider = provider
         .wrap_into(|p| GasEscalatorMiddleware::new(p, escalator, Frequency::PerBlock))
@@ -3,7 +3,12 @@ use ethers_core::{
     types::*,
     utils::{parse_ether, Anvil},
 };
+use ethers_middleware::gas_oracle::{
+    BlockMedian, Cache, FeeHistoryOracle, GasCategory, GasOracle, GasOracleError,
+    GasOracleMiddleware, ProviderOracle, Result,
+};
 use ethers_providers::{Http, Middleware, Provider};
+use std::time::Duration;
 
 #[derive(Debug)]
 struct FakeGasOracle {
@@ -61,59 +66,35 @@ async fn provider_oracle() {
 }
 
 #[tokio::test]
-async fn blocknative() {
-    let gas_now_oracle = BlockNative::default();
-    let gas_price = gas_now_oracle.fetch().await.unwrap();
-    assert!(gas_price > U256::zero());
-}
+async fn fee_history_oracle() {
+    let anvil = Anvil::new().spawn();
+    let provider = Provider::<Http>::try_from(anvil.endpoint()).unwrap();
 
-#[tokio::test]
-#[ignore = "ETHGasStation is shutting down: https://twitter.com/ETHGasStation/status/1597341610777317376"]
-#[allow(deprecated)]
-async fn eth_gas_station() {
-    let eth_gas_station_oracle = ethers_middleware::gas_oracle::EthGasStation::default();
-    let gas_price = eth_gas_station_oracle.fetch().await.unwrap();
-    assert!(gas_price > U256::zero());
+    let oracle = FeeHistoryOracle::new(provider).category(GasCategory::Fast);
+    let (max_fee, priority_fee) = oracle.estimate_eip1559_fees().await.unwrap();
+    assert!(max_fee > priority_fee);
+    assert!(oracle.fetch().await.unwrap() > U256::zero());
 }
 
 #[tokio::test]
-#[ignore = "Etherchain / beaconcha.in's `gasPriceOracle` API currently returns 404: https://www.etherchain.org/api/gasPriceOracle"]
-async fn etherchain() {
-    let etherchain_oracle = Etherchain::default();
-    let gas_price = etherchain_oracle.fetch().await.unwrap();
-    assert!(gas_price > U256::zero());
-}
+async fn block_median() {
+    let anvil = Anvil::new().spawn();
+    let provider = Provider::<Http>::try_from(anvil.endpoint()).unwrap();
 
-#[tokio::test]
-async fn etherscan() {
-    let chain = Chain::Mainnet;
-    let etherscan_client = Client::new_from_opt_env(chain).unwrap();
-
-    // initialize and fetch gas estimates from Etherscan
-    // since etherscan does not support `fastest` category, we expect an error
-    let etherscan_oracle = Etherscan::new(etherscan_client.clone()).category(GasCategory::Fastest);
-    let error = etherscan_oracle.fetch().await.unwrap_err();
-    assert!(matches!(error, GasOracleError::GasCategoryNotSupported));
-
-    // but fetching the `standard` gas price should work fine
-    let etherscan_oracle = Etherscan::new(etherscan_client).category(GasCategory::SafeLow);
-
-    let gas_price = etherscan_oracle.fetch().await.unwrap();
-    assert!(gas_price > U256::zero());
-    let ten_ethers = parse_ether(10).unwrap();
-    assert!(gas_price < ten_ethers, "gas calculation is wrong (too high)");
+    let oracle = BlockMedian::new(provider);
+    let (max_fee, priority_fee) = oracle.estimate_eip1559_fees().await.unwrap();
+    assert!(max_fee > priority_fee);
+    let gas_price = oracle.fetch().await.unwrap();
+    assert!(gas_price < parse_ether(1).unwrap(), "gas calculation is wrong (too high)");
 }
 
 #[tokio::test]
-async fn gas_now() {
-    let gas_now_oracle = GasNow::default();
-    let gas_price = gas_now_oracle.fetch().await.unwrap();
-    assert!(gas_price > U256::zero());
-}
+async fn cached_provider_oracle() {
+    let anvil = Anvil::new().spawn();
+    let provider = Provider::<Http>::try_from(anvil.endpoint()).unwrap();
 
-#[tokio::test]
-async fn polygon() {
-    let polygon_oracle = Polygon::default();
-    let gas_price = polygon_oracle.fetch().await.unwrap();
-    assert!(gas_price > U256::zero());
+    let oracle = Cache::new(Duration::from_secs(60), ProviderOracle::new(provider.clone()));
+    let gas_price = oracle.fetch().await.unwrap();
+    assert_eq!(gas_price, provider.get_gas_price().await.unwrap());
+    assert_eq!(oracle.fetch().await.unwrap(), gas_price);
 }
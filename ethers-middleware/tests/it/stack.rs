@@ -1,7 +1,7 @@
 use ethers_core::{rand::thread_rng, types::TransactionRequest, utils::Anvil};
 use ethers_middleware::{
     gas_escalator::{Frequency, GasEscalatorMiddleware, GeometricGasPrice},
-    gas_oracle::{GasCategory, GasNow, GasOracleMiddleware},
+    gas_oracle::{FeeHistoryOracle, GasCategory, GasOracleMiddleware},
     nonce_manager::NonceManagerMiddleware,
     signer::SignerMiddleware,
 };
@@ -14,7 +14,7 @@ async fn mock_with_middleware() {
     let (provider, mock) = Provider::mocked();
 
     // add a bunch of middlewares
-    let gas_oracle = GasNow::new().category(GasCategory::SafeLow);
+    let gas_oracle = FeeHistoryOracle::new(provider.clone()).category(GasCategory::SafeLow);
     let signer = LocalWallet::new(&mut thread_rng());
     let address = signer.address();
     let escalator = GeometricGasPrice::new(1.125, 60u64, None::<u64>);
@@ -50,7 +50,6 @@ async fn mock_with_middleware() {
 #[tokio::test]
 async fn can_stack_middlewares() {
     let anvil = Anvil::new().block_time(5u64).spawn();
-    let gas_oracle = GasNow::new().category(GasCategory::SafeLow);
     let signer: LocalWallet = anvil.keys()[0].clone().into();
     let address = signer.address();
 
@@ -58,6 +57,7 @@ async fn can_stack_middlewares() {
     let provider = Arc::new(Provider::<Http>::try_from(anvil.endpoint()).unwrap());
     let chain_id = provider.get_chainid().await.unwrap().as_u64();
     let signer = signer.with_chain_id(chain_id);
+    let gas_oracle = FeeHistoryOracle::new(provider.clone()).category(GasCategory::SafeLow);
 
     // the Gas Price escalator middleware is the first middleware above the provider,
     // so that it receives the transaction last, after all the other middleware
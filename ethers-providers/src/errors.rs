@@ -175,6 +175,19 @@ pub enum ProviderError {
     /// Signer is not available to this provider.
     #[error("Attempted to sign a transaction with no available signer. Hint: did you mean to use a SignerMiddleware?")]
     SignerUnavailable,
+
+    /// A mined transaction was removed from the chain by a reorg
+    #[error("transaction {0:?} was removed from the chain by a reorg")]
+    TransactionReorged(ethers_core::types::TxHash),
+
+    /// A pending transaction was removed from the mempool without being mined
+    #[error("transaction {tx_hash:?} was {reason}")]
+    TransactionDropped {
+        /// The hash of the dropped transaction
+        tx_hash: ethers_core::types::TxHash,
+        /// Why the transaction was dropped
+        reason: crate::DropReason,
+    },
 }
 
 impl RpcError for ProviderError {
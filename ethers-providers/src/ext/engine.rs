@@ -0,0 +1,443 @@
+//! Typed methods of the Engine API, which consensus clients use to drive execution clients
+//!
+//! The Engine API is served on a separate, JWT authenticated port. Use [`Http::new_with_jwt`] to
+//! connect to it, which issues a fresh token for every request.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use ethers_core::types::H256;
+//! use ethers_providers::{engine::ForkchoiceState, EngineApi, Http, JwtAuth, JwtKey, Provider};
+//!
+//! # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+//! let key = JwtKey::from_hex("0000000000000000000000000000000000000000000000000000000000000000")?;
+//! let http = Http::new_with_jwt("http://localhost:8551".parse::<url::Url>()?, JwtAuth::new(key, None, None));
+//! let provider = Provider::new(http);
+//!
+//! let head = H256::zero();
+//! let state = ForkchoiceState { head_block_hash: head, safe_block_hash: head, finalized_block_hash: head };
+//! let updated = provider.fork_choice_updated_v1(state, None).await?;
+//! assert!(updated.payload_status.status.is_valid());
+//! # Ok(()) }
+//! ```
+//!
+//! [`Http::new_with_jwt`]: crate::Http::new_with_jwt
+
+use crate::{Middleware, ProviderError};
+use async_trait::async_trait;
+use ethers_core::types::{Address, Bloom, Bytes, Withdrawal, H256, H64, U256, U64};
+use serde::{Deserialize, Serialize};
+
+/// The identifier of a payload build process, returned by `engine_forkchoiceUpdated`
+pub type PayloadId = H64;
+
+/// An execution payload as of the Paris fork
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionPayloadV1 {
+    /// Hash of the parent block
+    pub parent_hash: H256,
+    /// The fee recipient of the block
+    pub fee_recipient: Address,
+    /// State root after the execution of the block
+    pub state_root: H256,
+    /// Root of the receipts trie
+    pub receipts_root: H256,
+    /// Bloom of the logs of the block
+    pub logs_bloom: Bloom,
+    /// The randomness provided by the beacon chain
+    pub prev_randao: H256,
+    /// Block number
+    pub block_number: U64,
+    /// Block gas limit
+    pub gas_limit: U64,
+    /// Gas used by the block
+    pub gas_used: U64,
+    /// Block timestamp
+    pub timestamp: U64,
+    /// Extra data
+    pub extra_data: Bytes,
+    /// Base fee per unit of gas
+    pub base_fee_per_gas: U256,
+    /// Hash of the block
+    pub block_hash: H256,
+    /// The encoded transactions of the block
+    pub transactions: Vec<Bytes>,
+}
+
+/// An execution payload as of the Shanghai fork
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionPayloadV2 {
+    /// The fields of the previous payload version
+    #[serde(flatten)]
+    pub payload_inner: ExecutionPayloadV1,
+    /// The withdrawals of the block, `None` for payloads before Shanghai
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub withdrawals: Option<Vec<Withdrawal>>,
+}
+
+/// An execution payload as of the Cancun fork
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionPayloadV3 {
+    /// The fields of the previous payload version
+    #[serde(flatten)]
+    pub payload_inner: ExecutionPayloadV2,
+    /// Blob gas used by the block
+    pub blob_gas_used: U64,
+    /// Excess blob gas of the block
+    pub excess_blob_gas: U64,
+}
+
+/// The payload returned by `engine_getPayloadV2`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionPayloadEnvelopeV2 {
+    /// The payload, which has no withdrawals before Shanghai
+    pub execution_payload: ExecutionPayloadV2,
+    /// The fees paid to the fee recipient, in wei
+    pub block_value: U256,
+}
+
+/// The payload returned by `engine_getPayloadV3`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionPayloadEnvelopeV3 {
+    /// The payload
+    pub execution_payload: ExecutionPayloadV3,
+    /// The fees paid to the fee recipient, in wei
+    pub block_value: U256,
+    /// The blobs of the blob transactions of the payload
+    pub blobs_bundle: BlobsBundleV1,
+    /// Whether the execution client suggests building the block locally instead of using a
+    /// builder
+    pub should_override_builder: bool,
+}
+
+/// The payload returned by `engine_getPayloadV4`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionPayloadEnvelopeV4 {
+    /// The fields of the previous envelope version
+    #[serde(flatten)]
+    pub envelope_inner: ExecutionPayloadEnvelopeV3,
+    /// The execution layer requests of the payload, as defined by EIP-7685
+    pub execution_requests: Vec<Bytes>,
+}
+
+/// The blobs, commitments and proofs of the blob transactions of a payload
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobsBundleV1 {
+    /// The KZG commitments of the blobs
+    pub commitments: Vec<Bytes>,
+    /// The KZG proofs of the blobs
+    pub proofs: Vec<Bytes>,
+    /// The blobs
+    pub blobs: Vec<Bytes>,
+}
+
+/// The attributes of a payload to build, passed to `engine_forkchoiceUpdated`.
+///
+/// `withdrawals` is required as of `V2` and `parent_beacon_block_root` as of `V3`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PayloadAttributes {
+    /// The timestamp of the payload
+    pub timestamp: U64,
+    /// The randomness of the payload
+    pub prev_randao: H256,
+    /// The fee recipient of the payload
+    pub suggested_fee_recipient: Address,
+    /// The withdrawals of the payload
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub withdrawals: Option<Vec<Withdrawal>>,
+    /// The root of the parent beacon block
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_beacon_block_root: Option<H256>,
+}
+
+/// The heads of the chain as seen by the consensus client
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForkchoiceState {
+    /// Hash of the head block
+    pub head_block_hash: H256,
+    /// Hash of the safe block
+    pub safe_block_hash: H256,
+    /// Hash of the finalized block
+    pub finalized_block_hash: H256,
+}
+
+/// The result of `engine_forkchoiceUpdated`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForkchoiceUpdated {
+    /// The status of the new head
+    pub payload_status: PayloadStatus,
+    /// The identifier of the payload build process, if payload attributes were given
+    pub payload_id: Option<PayloadId>,
+}
+
+/// The result of `engine_newPayload`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PayloadStatus {
+    /// The validation status
+    pub status: PayloadValidationStatus,
+    /// The hash of the most recent valid block in the branch of the payload
+    pub latest_valid_hash: Option<H256>,
+    /// The reason the payload is invalid
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validation_error: Option<String>,
+}
+
+/// The validation status of a payload
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PayloadValidationStatus {
+    /// The payload is valid
+    Valid,
+    /// The payload is invalid
+    Invalid,
+    /// The execution client is syncing and could not validate the payload
+    Syncing,
+    /// The payload extends a side chain and was not validated
+    Accepted,
+    /// The block hash of the payload does not match its contents
+    InvalidBlockHash,
+}
+
+impl PayloadValidationStatus {
+    /// Returns `true` if the payload is valid
+    pub fn is_valid(&self) -> bool {
+        matches!(self, PayloadValidationStatus::Valid)
+    }
+
+    /// Returns `true` if the payload is invalid
+    pub fn is_invalid(&self) -> bool {
+        matches!(self, PayloadValidationStatus::Invalid | PayloadValidationStatus::InvalidBlockHash)
+    }
+}
+
+/// Typed methods of the Engine API, implemented for all [`Middleware`]s.
+///
+/// The requests are sent by the [`Provider`](crate::Provider) of the middleware stack, so they
+/// bypass the middlewares.
+///
+/// Ref: <https://github.com/ethereum/execution-apis/tree/main/src/engine>
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait EngineApi: Middleware {
+    /// Sends a payload of the Paris fork to the execution client to validate and import
+    async fn new_payload_v1(
+        &self,
+        payload: ExecutionPayloadV1,
+    ) -> Result<PayloadStatus, ProviderError> {
+        self.provider().request("engine_newPayloadV1", [payload]).await
+    }
+
+    /// Sends a payload of the Shanghai fork to the execution client to validate and import
+    async fn new_payload_v2(
+        &self,
+        payload: ExecutionPayloadV2,
+    ) -> Result<PayloadStatus, ProviderError> {
+        self.provider().request("engine_newPayloadV2", [payload]).await
+    }
+
+    /// Sends a payload of the Cancun fork to the execution client to validate and import, along
+    /// with the versioned hashes of its blobs and the root of the parent beacon block
+    async fn new_payload_v3(
+        &self,
+        payload: ExecutionPayloadV3,
+        versioned_hashes: Vec<H256>,
+        parent_beacon_block_root: H256,
+    ) -> Result<PayloadStatus, ProviderError> {
+        let params = (payload, versioned_hashes, parent_beacon_block_root);
+        self.provider().request("engine_newPayloadV3", params).await
+    }
+
+    /// Sends a payload of the Prague fork to the execution client to validate and import, along
+    /// with the versioned hashes of its blobs, the root of the parent beacon block and its
+    /// execution layer requests
+    async fn new_payload_v4(
+        &self,
+        payload: ExecutionPayloadV3,
+        versioned_hashes: Vec<H256>,
+        parent_beacon_block_root: H256,
+        execution_requests: Vec<Bytes>,
+    ) -> Result<PayloadStatus, ProviderError> {
+        let params = (payload, versioned_hashes, parent_beacon_block_root, execution_requests);
+        self.provider().request("engine_newPayloadV4", params).await
+    }
+
+    /// Updates the heads of the chain and starts building a payload if attributes are given
+    async fn fork_choice_updated_v1(
+        &self,
+        state: ForkchoiceState,
+        attributes: Option<PayloadAttributes>,
+    ) -> Result<ForkchoiceUpdated, ProviderError> {
+        self.provider().request("engine_forkchoiceUpdatedV1", (state, attributes)).await
+    }
+
+    /// Updates the heads of the chain and starts building a payload with withdrawals if
+    /// attributes are given
+    async fn fork_choice_updated_v2(
+        &self,
+        state: ForkchoiceState,
+        attributes: Option<PayloadAttributes>,
+    ) -> Result<ForkchoiceUpdated, ProviderError> {
+        self.provider().request("engine_forkchoiceUpdatedV2", (state, attributes)).await
+    }
+
+    /// Updates the heads of the chain and starts building a payload with withdrawals and a
+    /// parent beacon block root if attributes are given
+    async fn fork_choice_updated_v3(
+        &self,
+        state: ForkchoiceState,
+        attributes: Option<PayloadAttributes>,
+    ) -> Result<ForkchoiceUpdated, ProviderError> {
+        self.provider().request("engine_forkchoiceUpdatedV3", (state, attributes)).await
+    }
+
+    /// Returns the payload built by the given build process
+    async fn get_payload_v1(&self, id: PayloadId) -> Result<ExecutionPayloadV1, ProviderError> {
+        self.provider().request("engine_getPayloadV1", [id]).await
+    }
+
+    /// Returns the payload built by the given build process along with its value
+    async fn get_payload_v2(
+        &self,
+        id: PayloadId,
+    ) -> Result<ExecutionPayloadEnvelopeV2, ProviderError> {
+        self.provider().request("engine_getPayloadV2", [id]).await
+    }
+
+    /// Returns the payload built by the given build process along with its value and blobs
+    async fn get_payload_v3(
+        &self,
+        id: PayloadId,
+    ) -> Result<ExecutionPayloadEnvelopeV3, ProviderError> {
+        self.provider().request("engine_getPayloadV3", [id]).await
+    }
+
+    /// Returns the payload built by the given build process along with its value, blobs and
+    /// execution layer requests
+    async fn get_payload_v4(
+        &self,
+        id: PayloadId,
+    ) -> Result<ExecutionPayloadEnvelopeV4, ProviderError> {
+        self.provider().request("engine_getPayloadV4", [id]).await
+    }
+
+    /// Exchanges the supported Engine API methods with the execution client, returning the
+    /// methods it supports
+    async fn exchange_capabilities(
+        &self,
+        capabilities: Vec<String>,
+    ) -> Result<Vec<String>, ProviderError> {
+        self.provider().request("engine_exchangeCapabilities", [capabilities]).await
+    }
+}
+
+impl<M: Middleware> EngineApi for M {}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::{MockResponse, Provider};
+
+    #[test]
+    fn serde_execution_payload_v3() {
+        let s = r#"{
+            "parentHash": "0x0000000000000000000000000000000000000000000000000000000000000001",
+            "feeRecipient": "0x0000000000000000000000000000000000000002",
+            "stateRoot": "0x0000000000000000000000000000000000000000000000000000000000000003",
+            "receiptsRoot": "0x0000000000000000000000000000000000000000000000000000000000000004",
+            "logsBloom": "0x00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+            "prevRandao": "0x0000000000000000000000000000000000000000000000000000000000000005",
+            "blockNumber": "0x10",
+            "gasLimit": "0x1c9c380",
+            "gasUsed": "0x0",
+            "timestamp": "0x64",
+            "extraData": "0x",
+            "baseFeePerGas": "0x7",
+            "blockHash": "0x0000000000000000000000000000000000000000000000000000000000000006",
+            "transactions": ["0x02"],
+            "withdrawals": [{ "index": "0x1", "validatorIndex": "0x2", "address": "0x0000000000000000000000000000000000000003", "amount": "0x4" }],
+            "blobGasUsed": "0x20000",
+            "excessBlobGas": "0x0"
+        }"#;
+        let payload: ExecutionPayloadV3 = serde_json::from_str(s).unwrap();
+        assert_eq!(payload.payload_inner.payload_inner.block_number, 16.into());
+        assert_eq!(payload.payload_inner.withdrawals.as_ref().unwrap()[0].amount, 4.into());
+        assert_eq!(payload.blob_gas_used, 0x20000.into());
+
+        let value = serde_json::to_value(&payload).unwrap();
+        assert_eq!(value, serde_json::from_str::<serde_json::Value>(s).unwrap());
+    }
+
+    #[test]
+    fn serde_pre_shanghai_envelope_v2() {
+        let s = r#"{
+            "executionPayload": {
+                "parentHash": "0x0000000000000000000000000000000000000000000000000000000000000001",
+                "feeRecipient": "0x0000000000000000000000000000000000000002",
+                "stateRoot": "0x0000000000000000000000000000000000000000000000000000000000000003",
+                "receiptsRoot": "0x0000000000000000000000000000000000000000000000000000000000000004",
+                "logsBloom": "0x00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+                "prevRandao": "0x0000000000000000000000000000000000000000000000000000000000000005",
+                "blockNumber": "0x10",
+                "gasLimit": "0x1c9c380",
+                "gasUsed": "0x0",
+                "timestamp": "0x64",
+                "extraData": "0x",
+                "baseFeePerGas": "0x7",
+                "blockHash": "0x0000000000000000000000000000000000000000000000000000000000000006",
+                "transactions": []
+            },
+            "blockValue": "0x1"
+        }"#;
+        let envelope: ExecutionPayloadEnvelopeV2 = serde_json::from_str(s).unwrap();
+        assert_eq!(envelope.execution_payload.withdrawals, None);
+        assert_eq!(envelope.block_value, 1.into());
+
+        let value = serde_json::to_value(&envelope).unwrap();
+        assert_eq!(value, serde_json::from_str::<serde_json::Value>(s).unwrap());
+    }
+
+    #[tokio::test]
+    async fn fork_choice_updated() {
+        let (provider, mock) = Provider::mocked();
+        mock.on("engine_forkchoiceUpdatedV3", |_| {
+            MockResponse::Value(serde_json::json!({
+                "payloadStatus": {
+                    "status": "VALID",
+                    "latestValidHash": "0x0000000000000000000000000000000000000000000000000000000000000001",
+                    "validationError": null
+                },
+                "payloadId": "0x0000000000000001"
+            }))
+        });
+
+        let head = H256::from_low_u64_be(1);
+        let state = ForkchoiceState {
+            head_block_hash: head,
+            safe_block_hash: head,
+            finalized_block_hash: head,
+        };
+        let attributes = PayloadAttributes {
+            timestamp: 100.into(),
+            withdrawals: Some(vec![]),
+            parent_beacon_block_root: Some(H256::zero()),
+            ..Default::default()
+        };
+        let updated =
+            provider.fork_choice_updated_v3(state, Some(attributes.clone())).await.unwrap();
+        assert!(updated.payload_status.status.is_valid());
+        assert_eq!(updated.payload_id, Some(H64::from_low_u64_be(1)));
+
+        mock.assert_any_request("engine_forkchoiceUpdatedV3", (state, Some(attributes))).unwrap();
+    }
+}
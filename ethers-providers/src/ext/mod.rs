@@ -7,6 +7,10 @@ pub use ens::*;
 
 pub mod erc;
 
+/// Types and methods of the Engine API
+pub mod engine;
+pub use engine::EngineApi;
+
 #[cfg(feature = "dev-rpc")]
 pub mod dev_rpc;
 #[cfg(feature = "dev-rpc")]
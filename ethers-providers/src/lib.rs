@@ -15,6 +15,8 @@ pub use toolbox::*;
 
 /// Crate utilities and type aliases
 mod utils;
+#[cfg(not(target_arch = "wasm32"))]
+pub use utils::write_atomic;
 pub use utils::{interval, maybe, EscalationPolicy};
 
 /// Errors
@@ -24,7 +26,12 @@ pub use errors::{MiddlewareError, ProviderError, RpcError};
 mod stream;
 pub use futures_util::StreamExt;
 pub use stream::{
-    tx_stream::TransactionStream, FilterWatcher, DEFAULT_LOCAL_POLL_INTERVAL, DEFAULT_POLL_INTERVAL,
+    reorg::{
+        BlockEvent, ReorgAwareBlockStream, ReorgAwareLogStream, ReorgStreamError,
+        DEFAULT_REORG_WINDOW,
+    },
+    tx_stream::TransactionStream,
+    FilterWatcher, DEFAULT_LOCAL_POLL_INTERVAL, DEFAULT_POLL_INTERVAL,
 };
 
 mod middleware;
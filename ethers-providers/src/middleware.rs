@@ -357,6 +357,45 @@ pub trait Middleware: Sync + Send + Debug {
         self.inner().call(tx, block).await.map_err(MiddlewareError::from_err)
     }
 
+    /// Like [`Middleware::estimate_gas`], but executes against the state and block overrides
+    /// instead of the unmodified chain state. Note that not all client implementations support
+    /// overrides.
+    async fn estimate_gas_with_overrides(
+        &self,
+        tx: &TypedTransaction,
+        block: Option<BlockId>,
+        overrides: &spoof::CallOverrides,
+    ) -> Result<U256, Self::Error> {
+        self.inner()
+            .estimate_gas_with_overrides(tx, block, overrides)
+            .await
+            .map_err(MiddlewareError::from_err)
+    }
+
+    /// Like [`Middleware::call`], but executes against the state and block overrides instead of
+    /// the unmodified chain state. Note that not all client implementations support overrides.
+    async fn call_with_overrides(
+        &self,
+        tx: &TypedTransaction,
+        block: Option<BlockId>,
+        overrides: &spoof::CallOverrides,
+    ) -> Result<Bytes, Self::Error> {
+        self.inner()
+            .call_with_overrides(tx, block, overrides)
+            .await
+            .map_err(MiddlewareError::from_err)
+    }
+
+    /// Simulates a sequence of blocks, each with its own calls, state and block overrides, on top
+    /// of `block` using `eth_simulateV1`. Returns the result, logs and gas usage of every call.
+    async fn simulate(
+        &self,
+        payload: &SimulatePayload,
+        block: Option<BlockId>,
+    ) -> Result<Vec<SimulatedBlock>, Self::Error> {
+        self.inner().simulate(payload, block).await.map_err(MiddlewareError::from_err)
+    }
+
     /// Return current client syncing status. If IsFalse sync is over.
     async fn syncing(&self) -> Result<SyncingStatus, Self::Error> {
         self.inner().syncing().await.map_err(MiddlewareError::from_err)
@@ -790,6 +829,80 @@ pub trait Middleware: Sync + Send + Debug {
             .map_err(MiddlewareError::from_err)
     }
 
+    /// Returns the storage slots of an account at the given transaction of a block, starting at
+    /// the slot with the hash `key_start`
+    /// Ref:
+    /// [Here](https://geth.ethereum.org/docs/interacting-with-geth/rpc/ns-debug#debugstoragerangeat)
+    async fn debug_storage_range_at(
+        &self,
+        block_hash: H256,
+        tx_index: u64,
+        address: Address,
+        key_start: H256,
+        max_result: u64,
+    ) -> Result<StorageRangeResult, Self::Error> {
+        self.inner()
+            .debug_storage_range_at(block_hash, tx_index, address, key_start, max_result)
+            .await
+            .map_err(MiddlewareError::from_err)
+    }
+
+    /// Returns the RLP encoded header of a block, see [`Block::decode_raw_header`]
+    async fn debug_get_raw_header<T: Into<BlockId> + Send + Sync>(
+        &self,
+        block: T,
+    ) -> Result<Bytes, Self::Error> {
+        self.inner().debug_get_raw_header(block).await.map_err(MiddlewareError::from_err)
+    }
+
+    /// Returns the RLP encoded block, see [`Block::decode_raw`]
+    async fn debug_get_raw_block<T: Into<BlockId> + Send + Sync>(
+        &self,
+        block: T,
+    ) -> Result<Bytes, Self::Error> {
+        self.inner().debug_get_raw_block(block).await.map_err(MiddlewareError::from_err)
+    }
+
+    /// Returns the consensus encoded receipts of a block, see [`TransactionReceipt::decode_raw`]
+    async fn debug_get_raw_receipts<T: Into<BlockId> + Send + Sync>(
+        &self,
+        block: T,
+    ) -> Result<Vec<Bytes>, Self::Error> {
+        self.inner().debug_get_raw_receipts(block).await.map_err(MiddlewareError::from_err)
+    }
+
+    /// Returns the signed and encoded bytes of a transaction, which decode into a [`Transaction`]
+    async fn debug_get_raw_transaction(&self, hash: TxHash) -> Result<Bytes, Self::Error> {
+        self.inner().debug_get_raw_transaction(hash).await.map_err(MiddlewareError::from_err)
+    }
+
+    /// Returns a range of accounts of the state at the given block.
+    ///
+    /// `start` is the key to start from, i.e. the `next` field of a previous result, or `None` to
+    /// start from the first account.
+    /// Ref:
+    /// [Here](https://geth.ethereum.org/docs/interacting-with-geth/rpc/ns-debug#debugaccountrange)
+    #[allow(clippy::too_many_arguments)]
+    async fn debug_account_range<T: Into<BlockId> + Send + Sync>(
+        &self,
+        block: T,
+        start: Option<Bytes>,
+        max_results: u64,
+        no_code: bool,
+        no_storage: bool,
+        incompletes: bool,
+    ) -> Result<AccountRangeResult, Self::Error> {
+        self.inner()
+            .debug_account_range(block, start, max_results, no_code, no_storage, incompletes)
+            .await
+            .map_err(MiddlewareError::from_err)
+    }
+
+    /// Returns the blocks which the node received but which failed validation
+    async fn debug_get_bad_blocks(&self) -> Result<Vec<BadBlock>, Self::Error> {
+        self.inner().debug_get_bad_blocks().await.map_err(MiddlewareError::from_err)
+    }
+
     // Parity `trace` support
 
     /// Executes the given call and returns a number of possible traces for it
@@ -0,0 +1,607 @@
+//! A client for the REST API of beacon nodes
+//!
+//! # Example
+//!
+//! ```no_run
+//! use ethers_providers::beacon::{BeaconClient, BeaconId};
+//!
+//! # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+//! let beacon = BeaconClient::new("http://localhost:5052".parse::<url::Url>()?);
+//! let checkpoints = beacon.finality_checkpoints(BeaconId::Head).await?;
+//! let finalized = beacon.header(BeaconId::Root(checkpoints.data.finalized.root)).await?;
+//! let sidecars = beacon.blob_sidecars(BeaconId::Slot(finalized.data.header.message.slot), &[]).await?;
+//! # Ok(()) }
+//! ```
+
+use ethers_core::{
+    k256::sha2::{Digest, Sha256},
+    types::{serde_helpers::deserialize_stringified_u64, Block, Bytes, H256},
+};
+use once_cell::sync::OnceCell;
+use reqwest::Client;
+use serde::{de::DeserializeOwned, Deserialize, Serialize, Serializer};
+use std::fmt;
+use thiserror::Error;
+use url::Url;
+
+/// The version byte of the versioned hash of a KZG commitment, see EIP-4844
+pub const VERSIONED_HASH_VERSION_KZG: u8 = 0x01;
+
+/// A client for the REST API of a beacon node
+#[derive(Debug, Clone)]
+pub struct BeaconClient {
+    client: Client,
+    url: Url,
+    timing: OnceCell<BeaconTiming>,
+}
+
+/// Error thrown when requesting a beacon node
+#[derive(Error, Debug)]
+pub enum BeaconError {
+    /// Thrown if the request failed
+    #[error(transparent)]
+    ReqwestError(#[from] reqwest::Error),
+
+    /// Thrown if the URL of the request could not be built
+    #[error(transparent)]
+    UrlError(#[from] url::ParseError),
+
+    /// Thrown if the beacon node returned an error
+    #[error("({code}) {message}")]
+    ApiError {
+        /// The HTTP status code
+        code: u16,
+        /// The error message
+        message: String,
+    },
+
+    /// Thrown if the response could not be deserialized
+    #[error("Deserialization Error: {err}. Response: {text}")]
+    SerdeJson {
+        /// Underlying error
+        err: serde_json::Error,
+        /// The contents of the response that could not be deserialized
+        text: String,
+    },
+
+    /// Thrown if the execution block is older than the beacon chain
+    #[error("timestamp {0} is before the beacon chain genesis")]
+    BeforeGenesis(u64),
+
+    /// Thrown if the beacon node reports a slot duration of zero
+    #[error("the beacon chain has a slot duration of zero")]
+    ZeroSlotDuration,
+}
+
+/// Identifies a block or state on the beacon chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BeaconId {
+    /// The canonical head
+    Head,
+    /// The genesis
+    Genesis,
+    /// The latest finalized checkpoint
+    Finalized,
+    /// The latest justified checkpoint, only valid for states
+    Justified,
+    /// The block or state at a slot
+    Slot(u64),
+    /// The block or state with a root
+    Root(H256),
+}
+
+impl fmt::Display for BeaconId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BeaconId::Head => f.write_str("head"),
+            BeaconId::Genesis => f.write_str("genesis"),
+            BeaconId::Finalized => f.write_str("finalized"),
+            BeaconId::Justified => f.write_str("justified"),
+            BeaconId::Slot(slot) => write!(f, "{slot}"),
+            BeaconId::Root(root) => write!(f, "{root:?}"),
+        }
+    }
+}
+
+impl From<u64> for BeaconId {
+    fn from(slot: u64) -> Self {
+        BeaconId::Slot(slot)
+    }
+}
+
+impl From<H256> for BeaconId {
+    fn from(root: H256) -> Self {
+        BeaconId::Root(root)
+    }
+}
+
+/// The envelope of the responses of a beacon node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BeaconResponse<T> {
+    /// The requested data
+    pub data: T,
+    /// Whether the response references a block which is not fully verified by the execution
+    /// client
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execution_optimistic: Option<bool>,
+    /// Whether the response references finalized history
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finalized: Option<bool>,
+}
+
+/// A beacon block header along with its root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BeaconHeader {
+    /// The root of the block
+    pub root: H256,
+    /// Whether the block is part of the canonical chain
+    pub canonical: bool,
+    /// The signed header
+    pub header: SignedBeaconBlockHeader,
+}
+
+/// A signed beacon block header
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedBeaconBlockHeader {
+    /// The header
+    pub message: BeaconBlockHeader,
+    /// The BLS signature of the proposer
+    pub signature: Bytes,
+}
+
+/// A beacon block header
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BeaconBlockHeader {
+    /// The slot of the block
+    #[serde(deserialize_with = "deserialize_stringified_u64", serialize_with = "serialize_quoted")]
+    pub slot: u64,
+    /// The index of the proposer
+    #[serde(deserialize_with = "deserialize_stringified_u64", serialize_with = "serialize_quoted")]
+    pub proposer_index: u64,
+    /// The root of the parent block
+    pub parent_root: H256,
+    /// The root of the state after the block
+    pub state_root: H256,
+    /// The root of the block body
+    pub body_root: H256,
+}
+
+/// A checkpoint of the beacon chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BeaconCheckpoint {
+    /// The epoch of the checkpoint
+    #[serde(deserialize_with = "deserialize_stringified_u64", serialize_with = "serialize_quoted")]
+    pub epoch: u64,
+    /// The root of the block at the start of the epoch
+    pub root: H256,
+}
+
+/// The finality checkpoints of a state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FinalityCheckpoints {
+    /// The justified checkpoint of the previous epoch
+    pub previous_justified: BeaconCheckpoint,
+    /// The justified checkpoint of the current epoch
+    pub current_justified: BeaconCheckpoint,
+    /// The finalized checkpoint
+    pub finalized: BeaconCheckpoint,
+}
+
+/// A blob along with its KZG commitment and proofs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobSidecar {
+    /// The index of the blob in the block
+    #[serde(deserialize_with = "deserialize_stringified_u64", serialize_with = "serialize_quoted")]
+    pub index: u64,
+    /// The blob
+    pub blob: Bytes,
+    /// The KZG commitment of the blob
+    pub kzg_commitment: Bytes,
+    /// The KZG proof of the blob
+    pub kzg_proof: Bytes,
+    /// The header of the block which includes the blob
+    pub signed_block_header: SignedBeaconBlockHeader,
+    /// The proof of inclusion of the commitment in the block body
+    pub kzg_commitment_inclusion_proof: Vec<H256>,
+}
+
+impl BlobSidecar {
+    /// Returns the versioned hash of the KZG commitment, which is referenced by the blob
+    /// transaction
+    pub fn versioned_hash(&self) -> H256 {
+        let mut hash: [u8; 32] = Sha256::digest(&self.kzg_commitment).into();
+        hash[0] = VERSIONED_HASH_VERSION_KZG;
+        H256(hash)
+    }
+}
+
+/// The balance of a validator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidatorBalance {
+    /// The index of the validator
+    #[serde(deserialize_with = "deserialize_stringified_u64", serialize_with = "serialize_quoted")]
+    pub index: u64,
+    /// The balance in gwei
+    #[serde(deserialize_with = "deserialize_stringified_u64", serialize_with = "serialize_quoted")]
+    pub balance: u64,
+}
+
+/// The timing parameters of a beacon chain, used to map timestamps to slots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BeaconTiming {
+    /// The timestamp of the genesis
+    pub genesis_time: u64,
+    /// The duration of a slot
+    pub seconds_per_slot: u64,
+}
+
+impl BeaconTiming {
+    /// Returns the slot at the given timestamp, `None` if it is before the genesis or the slot
+    /// duration is zero
+    pub fn slot_at(&self, timestamp: u64) -> Option<u64> {
+        let elapsed = timestamp.checked_sub(self.genesis_time)?;
+        elapsed.checked_div(self.seconds_per_slot)
+    }
+}
+
+#[derive(Deserialize)]
+struct Genesis {
+    #[serde(deserialize_with = "deserialize_stringified_u64")]
+    genesis_time: u64,
+}
+
+#[derive(Deserialize)]
+struct Spec {
+    #[serde(rename = "SECONDS_PER_SLOT", deserialize_with = "deserialize_stringified_u64")]
+    seconds_per_slot: u64,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    message: String,
+}
+
+/// Beacon nodes encode integers as strings
+fn serialize_quoted<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+impl BeaconClient {
+    /// Initializes a new client
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ethers_providers::beacon::BeaconClient;
+    /// use url::Url;
+    ///
+    /// let url = Url::parse("http://localhost:5052").unwrap();
+    /// let beacon = BeaconClient::new(url);
+    /// ```
+    pub fn new(url: impl Into<Url>) -> Self {
+        Self::new_with_client(url, Client::new())
+    }
+
+    /// Allows to customize the client by providing your own http client
+    pub fn new_with_client(url: impl Into<Url>, client: Client) -> Self {
+        let mut url = url.into();
+        // request paths are joined to the url, which would replace its last segment otherwise
+        if !url.path().ends_with('/') {
+            url.set_path(&format!("{}/", url.path()));
+        }
+        Self { client, url, timing: OnceCell::new() }
+    }
+
+    /// The Url to which requests are made
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Sends a GET request to the given path and deserializes the response
+    pub async fn get<R: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<R, BeaconError> {
+        let url = self.url.join(path)?;
+        let res = self.client.get(url).query(query).send().await?;
+        let status = res.status();
+        let body = res.bytes().await?;
+
+        if !status.is_success() {
+            let message = serde_json::from_slice::<ApiError>(&body)
+                .map(|err| err.message)
+                .unwrap_or_else(|_| String::from_utf8_lossy(&body).to_string());
+            return Err(BeaconError::ApiError { code: status.as_u16(), message })
+        }
+
+        serde_json::from_slice(&body).map_err(|err| BeaconError::SerdeJson {
+            err,
+            text: String::from_utf8_lossy(&body).to_string(),
+        })
+    }
+
+    /// Returns the header of a block
+    pub async fn header(
+        &self,
+        block: impl Into<BeaconId>,
+    ) -> Result<BeaconResponse<BeaconHeader>, BeaconError> {
+        self.get(&format!("eth/v1/beacon/headers/{}", block.into()), &[]).await
+    }
+
+    /// Returns the finality checkpoints of a state
+    pub async fn finality_checkpoints(
+        &self,
+        state: impl Into<BeaconId>,
+    ) -> Result<BeaconResponse<FinalityCheckpoints>, BeaconError> {
+        self.get(&format!("eth/v1/beacon/states/{}/finality_checkpoints", state.into()), &[]).await
+    }
+
+    /// Returns the blob sidecars of a block, filtered by the given indices if any
+    pub async fn blob_sidecars(
+        &self,
+        block: impl Into<BeaconId>,
+        indices: &[u64],
+    ) -> Result<BeaconResponse<Vec<BlobSidecar>>, BeaconError> {
+        let query: Vec<_> = indices.iter().map(|index| ("indices", index.to_string())).collect();
+        self.get(&format!("eth/v1/beacon/blob_sidecars/{}", block.into()), &query).await
+    }
+
+    /// Returns the balances of the given validators, or of all validators if `ids` is empty.
+    ///
+    /// Validators are identified by their index or their hex encoded public key.
+    pub async fn validator_balances<T: ToString>(
+        &self,
+        state: impl Into<BeaconId>,
+        ids: &[T],
+    ) -> Result<BeaconResponse<Vec<ValidatorBalance>>, BeaconError> {
+        let query: Vec<_> = ids.iter().map(|id| ("id", id.to_string())).collect();
+        self.get(&format!("eth/v1/beacon/states/{}/validator_balances", state.into()), &query).await
+    }
+
+    /// Returns the genesis time and slot duration of the beacon chain.
+    ///
+    /// These are fetched once and cached.
+    pub async fn timing(&self) -> Result<BeaconTiming, BeaconError> {
+        if let Some(timing) = self.timing.get() {
+            return Ok(*timing)
+        }
+        let genesis: BeaconResponse<Genesis> = self.get("eth/v1/beacon/genesis", &[]).await?;
+        let spec: BeaconResponse<Spec> = self.get("eth/v1/config/spec", &[]).await?;
+        let timing = BeaconTiming {
+            genesis_time: genesis.data.genesis_time,
+            seconds_per_slot: spec.data.seconds_per_slot,
+        };
+        let _ = self.timing.set(timing);
+        Ok(timing)
+    }
+
+    /// Returns the slot of the beacon block which includes the given execution block
+    pub async fn slot_of_block<TX>(&self, block: &Block<TX>) -> Result<u64, BeaconError> {
+        let timestamp = block.timestamp.low_u64();
+        let timing = self.timing().await?;
+        if timestamp < timing.genesis_time {
+            return Err(BeaconError::BeforeGenesis(timestamp))
+        }
+        timing.slot_at(timestamp).ok_or(BeaconError::ZeroSlotDuration)
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use std::{
+        collections::HashMap,
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        sync::{Arc, Mutex},
+    };
+
+    /// Serves the given responses by path and query, recording the requested targets
+    fn fixture_server(
+        routes: HashMap<&'static str, (u16, serde_json::Value)>,
+    ) -> (Url, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let target = line.split_whitespace().nth(1).unwrap_or_default().to_string();
+                // drain the headers
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break
+                    }
+                }
+                let (status, body) = routes
+                    .get(target.as_str())
+                    .cloned()
+                    .unwrap_or((404, serde_json::json!({ "code": 404, "message": "not found" })));
+                recorded.lock().unwrap().push(target);
+                let body = body.to_string();
+                let response = format!(
+                    "HTTP/1.1 {status} OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    fn header_json(slot: u64) -> serde_json::Value {
+        serde_json::json!({
+            "message": {
+                "slot": slot.to_string(),
+                "proposer_index": "7",
+                "parent_root": format!("{:?}", H256::repeat_byte(1)),
+                "state_root": format!("{:?}", H256::repeat_byte(2)),
+                "body_root": format!("{:?}", H256::repeat_byte(3)),
+            },
+            "signature": "0x01"
+        })
+    }
+
+    #[tokio::test]
+    async fn fetches_headers_and_checkpoints() {
+        let root = H256::repeat_byte(4);
+        let (url, _) = fixture_server(HashMap::from([
+            (
+                "/eth/v1/beacon/states/head/finality_checkpoints",
+                (
+                    200,
+                    serde_json::json!({
+                        "execution_optimistic": false,
+                        "finalized": false,
+                        "data": {
+                            "previous_justified": { "epoch": "9", "root": format!("{:?}", H256::zero()) },
+                            "current_justified": { "epoch": "10", "root": format!("{:?}", H256::zero()) },
+                            "finalized": { "epoch": "8", "root": format!("{root:?}") }
+                        }
+                    }),
+                ),
+            ),
+            (
+                "/eth/v1/beacon/headers/0x0404040404040404040404040404040404040404040404040404040404040404",
+                (
+                    200,
+                    serde_json::json!({
+                        "data": { "root": format!("{root:?}"), "canonical": true, "header": header_json(256) }
+                    }),
+                ),
+            ),
+        ]));
+        let beacon = BeaconClient::new(url);
+
+        let checkpoints = beacon.finality_checkpoints(BeaconId::Head).await.unwrap();
+        assert_eq!(checkpoints.data.finalized.epoch, 8);
+        assert_eq!(checkpoints.execution_optimistic, Some(false));
+
+        let header = beacon.header(checkpoints.data.finalized.root).await.unwrap();
+        assert_eq!(header.data.root, root);
+        assert_eq!(header.data.header.message.slot, 256);
+        assert_eq!(header.data.header.message.proposer_index, 7);
+
+        let err = beacon.header(BeaconId::Slot(1)).await.unwrap_err();
+        assert!(matches!(err, BeaconError::ApiError { code: 404, .. }));
+    }
+
+    #[tokio::test]
+    async fn fetches_blobs_and_balances() {
+        let commitment = Bytes::from(vec![0xc0; 48]);
+        let (url, requests) = fixture_server(HashMap::from([
+            (
+                "/eth/v1/beacon/blob_sidecars/finalized?indices=1",
+                (
+                    200,
+                    serde_json::json!({
+                        "data": [{
+                            "index": "1",
+                            "blob": "0x00",
+                            "kzg_commitment": commitment,
+                            "kzg_proof": "0x00",
+                            "signed_block_header": header_json(32),
+                            "kzg_commitment_inclusion_proof": [format!("{:?}", H256::zero())]
+                        }]
+                    }),
+                ),
+            ),
+            (
+                "/eth/v1/beacon/states/finalized/validator_balances?id=1&id=2",
+                (
+                    200,
+                    serde_json::json!({
+                        "data": [
+                            { "index": "1", "balance": "32000000000" },
+                            { "index": "2", "balance": "31999999999" }
+                        ]
+                    }),
+                ),
+            ),
+        ]));
+        let beacon = BeaconClient::new(url);
+
+        let sidecars = beacon.blob_sidecars(BeaconId::Finalized, &[1]).await.unwrap().data;
+        assert_eq!(sidecars.len(), 1);
+        assert_eq!(sidecars[0].index, 1);
+        assert_eq!(sidecars[0].signed_block_header.message.slot, 32);
+        let hash = sidecars[0].versioned_hash();
+        assert_eq!(hash[0], VERSIONED_HASH_VERSION_KZG);
+        assert_eq!(hash[1..], Sha256::digest(&commitment)[1..]);
+
+        let balances = beacon.validator_balances(BeaconId::Finalized, &[1, 2]).await.unwrap();
+        assert_eq!(balances.data[0].balance, 32_000_000_000);
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn maps_execution_blocks_to_slots() {
+        let (url, requests) = fixture_server(HashMap::from([
+            (
+                "/eth/v1/beacon/genesis",
+                (
+                    200,
+                    serde_json::json!({
+                        "data": {
+                            "genesis_time": "1606824023",
+                            "genesis_validators_root": format!("{:?}", H256::zero()),
+                            "genesis_fork_version": "0x00000000"
+                        }
+                    }),
+                ),
+            ),
+            (
+                "/eth/v1/config/spec",
+                (
+                    200,
+                    serde_json::json!({ "data": { "SECONDS_PER_SLOT": "12", "SLOTS_PER_EPOCH": "32" } }),
+                ),
+            ),
+        ]));
+        let beacon = BeaconClient::new(url);
+
+        let block =
+            Block::<H256> { timestamp: (1606824023u64 + 12 * 100).into(), ..Default::default() };
+        assert_eq!(beacon.slot_of_block(&block).await.unwrap(), 100);
+        let block = Block::<H256> { timestamp: 1606824022u64.into(), ..Default::default() };
+        assert!(matches!(beacon.slot_of_block(&block).await, Err(BeaconError::BeforeGenesis(_))));
+        // the timing is only fetched once
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn keeps_the_path_of_the_url() {
+        let (url, requests) = fixture_server(HashMap::from([(
+            "/beacon/eth/v1/beacon/headers/head",
+            (
+                200,
+                serde_json::json!({
+                    "data": { "root": format!("{:?}", H256::zero()), "canonical": true, "header": header_json(1) }
+                }),
+            ),
+        )]));
+        let beacon = BeaconClient::new(url.join("beacon").unwrap());
+        assert_eq!(beacon.url().path(), "/beacon/");
+
+        let header = beacon.header(BeaconId::Head).await.unwrap();
+        assert_eq!(header.data.header.message.slot, 1);
+        assert_eq!(*requests.lock().unwrap(), vec!["/beacon/eth/v1/beacon/headers/head"]);
+    }
+
+    #[test]
+    fn maps_timestamps_to_slots() {
+        let timing = BeaconTiming { genesis_time: 100, seconds_per_slot: 12 };
+        assert_eq!(timing.slot_at(99), None);
+        assert_eq!(timing.slot_at(100), Some(0));
+        assert_eq!(timing.slot_at(136), Some(3));
+        assert_eq!(BeaconTiming { seconds_per_slot: 0, ..timing }.slot_at(136), None);
+    }
+}
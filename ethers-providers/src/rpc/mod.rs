@@ -9,3 +9,5 @@ pub use connections::*;
 
 mod pubsub;
 pub use pubsub::{PubsubClient, SubscriptionStream};
+
+pub mod beacon;
@@ -24,10 +24,12 @@ use async_trait::async_trait;
 use ethers_core::{
     abi::{self, Detokenize, ParamType},
     types::{
+        spoof,
         transaction::{eip2718::TypedTransaction, eip2930::AccessListWithGasUsed},
-        Address, Block, BlockId, BlockNumber, BlockTrace, Bytes, Chain, EIP1186ProofResponse,
-        FeeHistory, Filter, FilterBlockOption, GethDebugTracingCallOptions,
-        GethDebugTracingOptions, GethTrace, Log, NameOrAddress, Selector, Signature, Trace,
+        AccountRangeResult, Address, BadBlock, Block, BlockId, BlockNumber, BlockTrace, Bytes,
+        Chain, EIP1186ProofResponse, FeeHistory, Filter, FilterBlockOption,
+        GethDebugTracingCallOptions, GethDebugTracingOptions, GethTrace, Log, NameOrAddress,
+        Selector, Signature, SimulatePayload, SimulatedBlock, StorageRangeResult, Trace,
         TraceFilter, TraceType, Transaction, TransactionReceipt, TransactionRequest, TxHash,
         TxpoolContent, TxpoolInspect, TxpoolStatus, H256, U256, U64,
     },
@@ -550,6 +552,40 @@ impl<P: JsonRpcClient> Middleware for Provider<P> {
         self.request("eth_estimateGas", params).await
     }
 
+    async fn estimate_gas_with_overrides(
+        &self,
+        tx: &TypedTransaction,
+        block: Option<BlockId>,
+        overrides: &spoof::CallOverrides,
+    ) -> Result<U256, ProviderError> {
+        if overrides.is_empty() {
+            return self.estimate_gas(tx, block).await
+        }
+        self.request("eth_estimateGas", override_params(tx, block, overrides)).await
+    }
+
+    async fn call_with_overrides(
+        &self,
+        tx: &TypedTransaction,
+        block: Option<BlockId>,
+        overrides: &spoof::CallOverrides,
+    ) -> Result<Bytes, ProviderError> {
+        if overrides.is_empty() {
+            return self.call(tx, block).await
+        }
+        self.request("eth_call", override_params(tx, block, overrides)).await
+    }
+
+    async fn simulate(
+        &self,
+        payload: &SimulatePayload,
+        block: Option<BlockId>,
+    ) -> Result<Vec<SimulatedBlock>, ProviderError> {
+        let payload = utils::serialize(payload);
+        let block = utils::serialize(&block.unwrap_or_else(|| BlockNumber::Latest.into()));
+        self.request("eth_simulateV1", [payload, block]).await
+    }
+
     async fn create_access_list(
         &self,
         tx: &TypedTransaction,
@@ -984,6 +1020,74 @@ impl<P: JsonRpcClient> Middleware for Provider<P> {
         self.request("debug_traceBlockByHash", [block, trace_options]).await
     }
 
+    async fn debug_storage_range_at(
+        &self,
+        block_hash: H256,
+        tx_index: u64,
+        address: Address,
+        key_start: H256,
+        max_result: u64,
+    ) -> Result<StorageRangeResult, ProviderError> {
+        let block_hash = utils::serialize(&block_hash);
+        let tx_index = utils::serialize(&tx_index);
+        let address = utils::serialize(&address);
+        let key_start = utils::serialize(&key_start);
+        let max_result = utils::serialize(&max_result);
+        self.request("debug_storageRangeAt", [block_hash, tx_index, address, key_start, max_result])
+            .await
+    }
+
+    async fn debug_get_raw_header<T: Into<BlockId> + Send + Sync>(
+        &self,
+        block: T,
+    ) -> Result<Bytes, ProviderError> {
+        self.request("debug_getRawHeader", [block.into()]).await
+    }
+
+    async fn debug_get_raw_block<T: Into<BlockId> + Send + Sync>(
+        &self,
+        block: T,
+    ) -> Result<Bytes, ProviderError> {
+        self.request("debug_getRawBlock", [block.into()]).await
+    }
+
+    async fn debug_get_raw_receipts<T: Into<BlockId> + Send + Sync>(
+        &self,
+        block: T,
+    ) -> Result<Vec<Bytes>, ProviderError> {
+        self.request("debug_getRawReceipts", [block.into()]).await
+    }
+
+    async fn debug_get_raw_transaction(&self, hash: TxHash) -> Result<Bytes, ProviderError> {
+        self.request("debug_getRawTransaction", [hash]).await
+    }
+
+    async fn debug_account_range<T: Into<BlockId> + Send + Sync>(
+        &self,
+        block: T,
+        start: Option<Bytes>,
+        max_results: u64,
+        no_code: bool,
+        no_storage: bool,
+        incompletes: bool,
+    ) -> Result<AccountRangeResult, ProviderError> {
+        let block = utils::serialize(&block.into());
+        let start = utils::serialize(&start.unwrap_or_default());
+        let max_results = utils::serialize(&max_results);
+        let no_code = utils::serialize(&no_code);
+        let no_storage = utils::serialize(&no_storage);
+        let incompletes = utils::serialize(&incompletes);
+        self.request(
+            "debug_accountRange",
+            [block, start, max_results, no_code, no_storage, incompletes],
+        )
+        .await
+    }
+
+    async fn debug_get_bad_blocks(&self) -> Result<Vec<BadBlock>, ProviderError> {
+        self.request("debug_getBadBlocks", ()).await
+    }
+
     async fn trace_call<T: Into<TypedTransaction> + Send + Sync>(
         &self,
         req: T,
@@ -1372,6 +1476,23 @@ impl Provider<MockProvider> {
     }
 }
 
+/// Serializes the params of an `eth_call` or `eth_estimateGas` with state and block overrides
+fn override_params(
+    tx: &TypedTransaction,
+    block: Option<BlockId>,
+    overrides: &spoof::CallOverrides,
+) -> Vec<serde_json::Value> {
+    let mut params = vec![
+        utils::serialize(tx),
+        utils::serialize(&block.unwrap_or_else(|| BlockNumber::Latest.into())),
+        utils::serialize(&overrides.state.clone().unwrap_or_default()),
+    ];
+    if let Some(block_overrides) = &overrides.block {
+        params.push(utils::serialize(block_overrides));
+    }
+    params
+}
+
 /// infallible conversion of Bytes to Address/String
 ///
 /// # Panics
@@ -1557,12 +1678,12 @@ pub fn is_local_endpoint(endpoint: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::Http;
+    use crate::{Http, MockResponse};
     use ethers_core::{
         types::{
             transaction::eip2930::AccessList, Eip1559TransactionRequest,
             GethDebugBuiltInTracerConfig, GethDebugBuiltInTracerType, GethDebugTracerConfig,
-            GethDebugTracerType, PreStateConfig, TransactionRequest, H256,
+            GethDebugTracerType, PreStateConfig, SimulateBlock, TransactionRequest, H256,
         },
         utils::{Anvil, Genesis, Geth, GethInstance},
     };
@@ -1943,6 +2064,185 @@ mod tests {
         assert!(tx.access_list().is_none());
     }
 
+    #[tokio::test]
+    async fn test_call_with_overrides() {
+        let (provider, mock) = Provider::mocked();
+        let adr: Address = "0x6fC21092DA55B392b045eD78F4732bff3C580e2c".parse().unwrap();
+        let tx: TypedTransaction = TransactionRequest::new().to(adr).into();
+
+        // --- without overrides the plain params are sent
+        mock.push::<Bytes, _>(Bytes::default()).unwrap();
+        provider.call_with_overrides(&tx, None, &Default::default()).await.unwrap();
+        mock.assert_request("eth_call", (&tx, BlockNumber::Latest)).unwrap();
+
+        // --- block overrides are sent after an empty state override set
+        let mut block = spoof::BlockOverrides::default();
+        block.number(100.into()).base_fee(7.into());
+        let overrides = spoof::CallOverrides::default().block(block.clone());
+        mock.push::<Bytes, _>(Bytes::default()).unwrap();
+        provider.call_with_overrides(&tx, None, &overrides).await.unwrap();
+        mock.assert_request(
+            "eth_call",
+            (
+                &tx,
+                BlockNumber::Latest,
+                serde_json::json!({}),
+                serde_json::json!({ "number": "0x64", "baseFee": "0x7" }),
+            ),
+        )
+        .unwrap();
+
+        // --- state overrides are sent without block overrides
+        let state = spoof::balance(adr, 1.into());
+        let overrides = spoof::CallOverrides::default().state(state.clone());
+        mock.push(U256::from(21000)).unwrap();
+        let gas =
+            provider.estimate_gas_with_overrides(&tx, Some(1.into()), &overrides).await.unwrap();
+        assert_eq!(gas, 21000.into());
+        mock.assert_request("eth_estimateGas", (&tx, BlockNumber::Number(1.into()), state))
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_simulate() {
+        let (provider, mock) = Provider::mocked();
+        let adr: Address = "0x6fC21092DA55B392b045eD78F4732bff3C580e2c".parse().unwrap();
+        let payload = SimulatePayload::default().block(
+            SimulateBlock::default()
+                .call(TransactionRequest::new().to(adr))
+                .state_overrides(spoof::balance(adr, 1.into())),
+        );
+
+        mock.on("eth_simulateV1", |_| {
+            MockResponse::Value(serde_json::json!([{
+                "number": "0x2",
+                "hash": "0x0000000000000000000000000000000000000000000000000000000000000002",
+                "timestamp": "0x64",
+                "gasLimit": "0x1c9c380",
+                "gasUsed": "0x5208",
+                "miner": "0x0000000000000000000000000000000000000000",
+                "calls": [{
+                    "returnData": "0x",
+                    "logs": [],
+                    "gasUsed": "0x5208",
+                    "status": "0x0",
+                    "error": { "code": 3, "message": "execution reverted", "data": "0xdeadbeef" }
+                }]
+            }]))
+        });
+        let blocks = provider.simulate(&payload, Some(1.into())).await.unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].gas_used, 21000.into());
+        let call = &blocks[0].calls[0];
+        assert!(!call.is_success());
+        assert_eq!(call.revert_data(), Some(&"0xdeadbeef".parse().unwrap()));
+
+        mock.assert_any_request("eth_simulateV1", (&payload, BlockNumber::Number(1.into())))
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_debug_raw_and_storage_range() {
+        let (provider, mock) = Provider::mocked();
+        let adr: Address = "0x6fC21092DA55B392b045eD78F4732bff3C580e2c".parse().unwrap();
+        let block_hash = H256::repeat_byte(1);
+
+        mock.on("debug_getRawReceipts", |_| {
+            MockResponse::Value(serde_json::json!([
+                "0xf9010801825208b9010000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000c0"
+            ]))
+        });
+        mock.on("debug_storageRangeAt", |_| {
+            MockResponse::Value(serde_json::json!({
+                "storage": {
+                    "0x290decd9548b62a8d60345a988386fc84ba6bc95484008f6362f93160ef3e563": {
+                        "key": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                        "value": "0x0000000000000000000000000000000000000000000000000000000000000001"
+                    }
+                },
+                "nextKey": null
+            }))
+        });
+
+        let receipts = provider.debug_get_raw_receipts(block_hash).await.unwrap();
+        let receipt = TransactionReceipt::decode_raw(&receipts[0]).unwrap();
+        assert_eq!(receipt.status, Some(1.into()));
+        assert_eq!(receipt.cumulative_gas_used, 21000.into());
+
+        let range =
+            provider.debug_storage_range_at(block_hash, 0, adr, H256::zero(), 10).await.unwrap();
+        assert_eq!(range.storage.len(), 1);
+        assert_eq!(range.next_key, None);
+
+        mock.assert_any_request("debug_getRawReceipts", [BlockId::Hash(block_hash)]).unwrap();
+        mock.assert_any_request("debug_storageRangeAt", (block_hash, 0, adr, H256::zero(), 10))
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_debug_account_range_pagination() {
+        let (provider, mock) = Provider::mocked();
+        let next: Bytes =
+            "0x1468288056310c82aa4c01a7e12a10f8111a0560e72b700555479031b86c357d".parse().unwrap();
+        let account = serde_json::json!({
+            "balance": "1",
+            "nonce": 0,
+            "root": H256::zero(),
+            "codeHash": H256::zero(),
+        });
+        let second_page = account.clone();
+        mock.on_match(
+            "debug_accountRange",
+            |params| params[1] == "0x",
+            move |_| {
+                MockResponse::Value(serde_json::json!({
+                    "root": H256::zero(),
+                    "accounts": { "0x0000000000000000000000000000000000000001": account },
+                    "next": "FGgogFYxDIKqTAGn4SoQ+BEaBWDnK3AFVUeQMbhsNX0=",
+                }))
+            },
+        );
+        mock.on_match(
+            "debug_accountRange",
+            |params| {
+                params[1] == "0x1468288056310c82aa4c01a7e12a10f8111a0560e72b700555479031b86c357d"
+            },
+            move |_| {
+                MockResponse::Value(serde_json::json!({
+                    "root": H256::zero(),
+                    "accounts": { "0x0000000000000000000000000000000000000002": second_page },
+                }))
+            },
+        );
+
+        let mut start = None;
+        let mut accounts = Vec::new();
+        loop {
+            let range = provider
+                .debug_account_range(BlockNumber::Latest, start, 1, true, true, false)
+                .await
+                .unwrap();
+            accounts.extend(range.accounts.into_keys());
+            match range.next {
+                Some(next) => start = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(
+            accounts,
+            vec![
+                "0x0000000000000000000000000000000000000001",
+                "0x0000000000000000000000000000000000000002"
+            ]
+        );
+        mock.assert_any_request(
+            "debug_accountRange",
+            (BlockNumber::Latest, next, 1, true, true, false),
+        )
+        .unwrap();
+        assert_eq!(mock.unmatched_count(), 0);
+    }
+
     #[tokio::test]
     async fn mainnet_lookup_address_invalid_resolver() {
         let provider = crate::MAINNET.provider();
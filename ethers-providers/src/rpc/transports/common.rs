@@ -313,6 +313,7 @@ impl JwtKey {
 }
 
 /// Contains the JWT secret and claims parameters.
+#[derive(Clone)]
 pub struct JwtAuth {
     key: EncodingKey,
     id: Option<String>,
@@ -360,6 +361,15 @@ impl JwtAuth {
     }
 }
 
+impl fmt::Debug for JwtAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtAuth")
+            .field("id", &self.id)
+            .field("clv", &self.clv)
+            .finish_non_exhaustive()
+    }
+}
+
 /// Claims struct as defined in <https://github.com/ethereum/execution-apis/blob/main/src/engine/authentication.md#jwt-claims>
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct Claims {
@@ -1,6 +1,6 @@
 // Code adapted from: https://github.com/althea-net/guac_rs/tree/master/web3/src/jsonrpc
 
-use super::common::{Authorization, JsonRpcError, Request, Response};
+use super::common::{Authorization, JsonRpcError, JwtAuth, Request, Response};
 use crate::{errors::ProviderError, JsonRpcClient};
 use async_trait::async_trait;
 use reqwest::{header::HeaderValue, Client, Error as ReqwestError};
@@ -32,6 +32,7 @@ pub struct Provider {
     id: AtomicU64,
     client: Client,
     url: Url,
+    jwt: Option<JwtAuth>,
 }
 
 #[derive(Error, Debug)]
@@ -52,6 +53,10 @@ pub enum ClientError {
         /// The contents of the HTTP response that could not be deserialized
         text: String,
     },
+
+    /// Thrown if the JWT token of the request could not be generated
+    #[error(transparent)]
+    JwtError(#[from] jsonwebtoken::errors::Error),
 }
 
 impl From<ClientError> for ProviderError {
@@ -93,7 +98,12 @@ impl JsonRpcClient for Provider {
         let next_id = self.id.fetch_add(1, Ordering::SeqCst);
         let payload = Request::new(next_id, method, params);
 
-        let res = self.client.post(self.url.as_ref()).json(&payload).send().await?;
+        let mut req = self.client.post(self.url.as_ref()).json(&payload);
+        if let Some(jwt) = &self.jwt {
+            // tokens are only valid for a short time, so a fresh one is issued for every request
+            req = req.bearer_auth(jwt.generate_token()?);
+        }
+        let res = req.send().await?;
         let body = res.bytes().await?;
 
         let raw = match serde_json::from_slice(&body) {
@@ -173,6 +183,23 @@ impl Provider {
         Ok(Self::new_with_client(url, client))
     }
 
+    /// Initializes a new HTTP Client which authenticates every request with a freshly issued JWT
+    /// token, as required by the Engine API of execution clients
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ethers_providers::{Http, JwtAuth, JwtKey};
+    /// use url::Url;
+    ///
+    /// let url = Url::parse("http://localhost:8551").unwrap();
+    /// let key = JwtKey::from_slice(&[0; 32]).unwrap();
+    /// let provider = Http::new_with_jwt(url, JwtAuth::new(key, None, None));
+    /// ```
+    pub fn new_with_jwt(url: impl Into<Url>, jwt: JwtAuth) -> Self {
+        Self { jwt: Some(jwt), ..Self::new(url) }
+    }
+
     /// Allows to customize the provider by providing your own http client
     ///
     /// # Example
@@ -186,7 +213,7 @@ impl Provider {
     /// let provider = Http::new_with_client(url, client);
     /// ```
     pub fn new_with_client(url: impl Into<Url>, client: reqwest::Client) -> Self {
-        Self { id: AtomicU64::new(1), client, url: url.into() }
+        Self { id: AtomicU64::new(1), client, url: url.into(), jwt: None }
     }
 }
 
@@ -201,7 +228,12 @@ impl FromStr for Provider {
 
 impl Clone for Provider {
     fn clone(&self) -> Self {
-        Self { id: AtomicU64::new(1), client: self.client.clone(), url: self.url.clone() }
+        Self {
+            id: AtomicU64::new(1),
+            client: self.client.clone(),
+            url: self.url.clone(),
+            jwt: self.jwt.clone(),
+        }
     }
 }
 
@@ -0,0 +1,513 @@
+//! A [JsonRpcClient] implementation that records per-method metrics for every request and
+//! instruments it with a `tracing` span.
+
+use crate::{errors::ProviderError, JsonRpcClient, RpcError};
+use async_trait::async_trait;
+use instant::Instant;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::value::RawValue;
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+use thiserror::Error;
+use tracing_futures::Instrument;
+
+/// The outcome of a single request, as reported to a [MetricsRecorder]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestOutcome {
+    /// The request succeeded and the response had the given size in bytes
+    Success {
+        /// Size of the serialized response in bytes
+        response_size: usize,
+    },
+    /// The request failed
+    Error {
+        /// The JSON-RPC error code, if the failure was a JSON-RPC error response
+        code: Option<i64>,
+    },
+    /// The request was dropped before it completed, e.g. by a timeout
+    Cancelled,
+}
+
+/// [MetricsRecorder] receives the measurements taken by a [MetricsClient].
+///
+/// Implement this to forward measurements to your metrics backend of choice, or use
+/// [InMemoryMetrics] to take snapshots locally.
+pub trait MetricsRecorder: Send + Sync + Debug {
+    /// Called before the request is sent, `request_size` is the size of the serialized params in
+    /// bytes
+    fn request_started(&self, method: &str, request_size: usize);
+
+    /// Called once the request completed, successfully or not, or was dropped before completing
+    fn request_finished(&self, method: &str, elapsed: Duration, outcome: RequestOutcome);
+}
+
+/// [MetricsClient] presents as a wrapper around [JsonRpcClient] that reports latency, error codes,
+/// request and response sizes and the number of in-flight requests per method to a
+/// [MetricsRecorder].
+///
+/// Every request is also wrapped in a `debug` level `rpc_request` span which carries the method
+/// and a client local request id.
+///
+/// # Example
+///
+/// ```
+/// use ethers_providers::{Http, InMemoryMetrics, MetricsClient, Provider};
+/// use std::sync::Arc;
+/// use url::Url;
+///
+/// let http = Http::new(Url::parse("http://localhost:8545").unwrap());
+/// let metrics = Arc::new(InMemoryMetrics::default());
+/// let provider = Provider::new(MetricsClient::new(http, metrics.clone()));
+///
+/// // ... make some requests
+///
+/// for (method, stats) in metrics.snapshot().methods {
+///     println!("{method}: {} requests, {:?} avg", stats.requests, stats.latency.mean());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct MetricsClient<T, M> {
+    inner: T,
+    recorder: M,
+    next_id: AtomicU64,
+}
+
+impl<T, M> MetricsClient<T, M>
+where
+    T: JsonRpcClient,
+    M: MetricsRecorder,
+{
+    /// Creates a new `MetricsClient` that reports to the given `recorder`
+    pub fn new(inner: T, recorder: M) -> Self {
+        Self { inner, recorder, next_id: AtomicU64::new(0) }
+    }
+
+    /// Returns the wrapped client
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns the recorder
+    pub fn recorder(&self) -> &M {
+        &self.recorder
+    }
+}
+
+/// Error thrown by the [MetricsClient]
+#[derive(Error, Debug)]
+pub enum MetricsClientError<E> {
+    /// Thrown if the request failed in the wrapped client
+    #[error(transparent)]
+    Inner(E),
+    /// (De)Serialization error
+    #[error(transparent)]
+    SerdeJson(serde_json::Error),
+}
+
+impl<E> RpcError for MetricsClientError<E>
+where
+    E: RpcError,
+{
+    fn as_error_response(&self) -> Option<&super::JsonRpcError> {
+        match self {
+            MetricsClientError::Inner(e) => e.as_error_response(),
+            MetricsClientError::SerdeJson(_) => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            MetricsClientError::Inner(e) => e.as_serde_error(),
+            MetricsClientError::SerdeJson(e) => Some(e),
+        }
+    }
+}
+
+impl<E> From<MetricsClientError<E>> for ProviderError
+where
+    E: Into<ProviderError>,
+{
+    fn from(src: MetricsClientError<E>) -> Self {
+        match src {
+            MetricsClientError::Inner(err) => err.into(),
+            MetricsClientError::SerdeJson(err) => err.into(),
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<T, M> JsonRpcClient for MetricsClient<T, M>
+where
+    T: JsonRpcClient,
+    M: MetricsRecorder,
+{
+    type Error = MetricsClientError<T::Error>;
+
+    async fn request<A, R>(&self, method: &str, params: A) -> Result<R, Self::Error>
+    where
+        A: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let span = tracing::debug_span!("rpc_request", method, id);
+
+        // zero sized params are skipped by the transports, see `crate::transports::common::Request`
+        let request_size = if std::mem::size_of::<A>() == 0 {
+            0
+        } else {
+            serde_json::to_vec(&params).map_err(MetricsClientError::SerdeJson)?.len()
+        };
+
+        async move {
+            let guard = InFlightGuard::start(&self.recorder, method, request_size);
+            let res: Result<Box<RawValue>, _> = self.inner.request(method, params).await;
+
+            match res {
+                Ok(raw) => {
+                    let response_size = raw.get().len();
+                    let elapsed = guard.finish(RequestOutcome::Success { response_size });
+                    tracing::trace!(?elapsed, response_size, "request succeeded");
+                    serde_json::from_str(raw.get()).map_err(MetricsClientError::SerdeJson)
+                }
+                Err(err) => {
+                    let code = err.as_error_response().map(|err| err.code);
+                    let elapsed = guard.finish(RequestOutcome::Error { code });
+                    tracing::debug!(?elapsed, ?code, %err, "request failed");
+                    Err(MetricsClientError::Inner(err))
+                }
+            }
+        }
+        .instrument(span)
+        .await
+    }
+}
+
+/// Reports a started request to the recorder as finished exactly once, as cancelled if it is
+/// dropped before [`finish`](Self::finish) is called
+struct InFlightGuard<'a, M: MetricsRecorder> {
+    recorder: &'a M,
+    method: &'a str,
+    start: Instant,
+    finished: bool,
+}
+
+impl<'a, M: MetricsRecorder> InFlightGuard<'a, M> {
+    fn start(recorder: &'a M, method: &'a str, request_size: usize) -> Self {
+        recorder.request_started(method, request_size);
+        Self { recorder, method, start: Instant::now(), finished: false }
+    }
+
+    /// Reports the outcome of the request and returns its latency
+    fn finish(mut self, outcome: RequestOutcome) -> Duration {
+        let elapsed = self.start.elapsed();
+        self.finished = true;
+        self.recorder.request_finished(self.method, elapsed, outcome);
+        elapsed
+    }
+}
+
+impl<M: MetricsRecorder> Drop for InFlightGuard<'_, M> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.recorder.request_finished(
+                self.method,
+                self.start.elapsed(),
+                RequestOutcome::Cancelled,
+            );
+        }
+    }
+}
+
+/// The default upper bounds of the [LatencyHistogram] buckets, in milliseconds
+pub const DEFAULT_LATENCY_BUCKETS_MS: &[u64] =
+    &[5, 10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000];
+
+/// A cumulative latency histogram with fixed bucket bounds
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    /// The upper bounds of the buckets in milliseconds
+    pub bounds: Vec<u64>,
+    /// The number of samples per bucket, the last entry counts all samples exceeding the largest
+    /// bound
+    pub counts: Vec<u64>,
+    /// The sum of all samples
+    pub sum: Duration,
+    /// The number of samples
+    pub count: u64,
+}
+
+impl LatencyHistogram {
+    /// Creates an empty histogram with the given bucket bounds in milliseconds
+    pub fn new(mut bounds: Vec<u64>) -> Self {
+        bounds.sort_unstable();
+        bounds.dedup();
+        let counts = vec![0; bounds.len() + 1];
+        Self { bounds, counts, sum: Duration::ZERO, count: 0 }
+    }
+
+    /// Records a sample
+    pub fn record(&mut self, sample: Duration) {
+        let millis = sample.as_millis();
+        let idx = self
+            .bounds
+            .iter()
+            .position(|bound| millis <= *bound as u128)
+            .unwrap_or(self.bounds.len());
+        self.counts[idx] += 1;
+        self.sum += sample;
+        self.count += 1;
+    }
+
+    /// Returns the mean of all samples
+    pub fn mean(&self) -> Option<Duration> {
+        (self.count > 0).then(|| self.sum.div_f64(self.count as f64))
+    }
+
+    /// Returns the upper bound in milliseconds of the bucket containing the given quantile, or
+    /// `None` if it lies in the overflow bucket or there are no samples.
+    pub fn quantile_upper_bound(&self, quantile: f64) -> Option<u64> {
+        if self.count == 0 {
+            return None
+        }
+        let rank = ((self.count as f64) * quantile.clamp(0.0, 1.0)).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (idx, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return self.bounds.get(idx).copied()
+            }
+        }
+        None
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new(DEFAULT_LATENCY_BUCKETS_MS.to_vec())
+    }
+}
+
+/// Metrics collected for a single method
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MethodMetrics {
+    /// Number of requests sent
+    pub requests: u64,
+    /// Number of requests currently awaiting a response
+    pub in_flight: u64,
+    /// Number of failed requests by JSON-RPC error code
+    pub errors_by_code: HashMap<i64, u64>,
+    /// Number of failed requests which did not carry a JSON-RPC error code, e.g. transport errors
+    pub transport_errors: u64,
+    /// Number of requests dropped before they completed
+    pub cancelled: u64,
+    /// Total size of the serialized params in bytes
+    pub request_bytes: u64,
+    /// Total size of the successful responses in bytes
+    pub response_bytes: u64,
+    /// Latency of all completed requests
+    pub latency: LatencyHistogram,
+}
+
+impl MethodMetrics {
+    /// Returns the total number of failed requests
+    pub fn errors(&self) -> u64 {
+        self.errors_by_code.values().sum::<u64>() + self.transport_errors
+    }
+}
+
+/// A point in time copy of the metrics collected by [InMemoryMetrics]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// Metrics keyed by method
+    pub methods: HashMap<String, MethodMetrics>,
+}
+
+/// A simple [MetricsRecorder] that aggregates all measurements in memory
+#[derive(Debug)]
+pub struct InMemoryMetrics {
+    buckets: Vec<u64>,
+    methods: Mutex<HashMap<String, MethodMetrics>>,
+}
+
+impl InMemoryMetrics {
+    /// Creates a recorder that uses the given latency bucket bounds in milliseconds
+    pub fn with_latency_buckets(buckets: Vec<u64>) -> Self {
+        Self { buckets, methods: Default::default() }
+    }
+
+    /// Returns a copy of all metrics collected so far
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot { methods: self.methods.lock().unwrap().clone() }
+    }
+
+    /// Clears all metrics collected so far, except for the in-flight counts
+    pub fn reset(&self) {
+        let mut methods = self.methods.lock().unwrap();
+        for metrics in methods.values_mut() {
+            *metrics = MethodMetrics {
+                in_flight: metrics.in_flight,
+                latency: LatencyHistogram::new(self.buckets.clone()),
+                ..Default::default()
+            };
+        }
+    }
+
+    fn with_method(&self, method: &str, f: impl FnOnce(&mut MethodMetrics)) {
+        let mut methods = self.methods.lock().unwrap();
+        let metrics = methods.entry(method.to_string()).or_insert_with(|| MethodMetrics {
+            latency: LatencyHistogram::new(self.buckets.clone()),
+            ..Default::default()
+        });
+        f(metrics)
+    }
+}
+
+impl Default for InMemoryMetrics {
+    fn default() -> Self {
+        Self::with_latency_buckets(DEFAULT_LATENCY_BUCKETS_MS.to_vec())
+    }
+}
+
+impl MetricsRecorder for InMemoryMetrics {
+    fn request_started(&self, method: &str, request_size: usize) {
+        self.with_method(method, |metrics| {
+            metrics.requests += 1;
+            metrics.in_flight += 1;
+            metrics.request_bytes += request_size as u64;
+        })
+    }
+
+    fn request_finished(&self, method: &str, elapsed: Duration, outcome: RequestOutcome) {
+        self.with_method(method, |metrics| {
+            metrics.in_flight = metrics.in_flight.saturating_sub(1);
+            if outcome == RequestOutcome::Cancelled {
+                metrics.cancelled += 1;
+                return
+            }
+            metrics.latency.record(elapsed);
+            match outcome {
+                RequestOutcome::Success { response_size } => {
+                    metrics.response_bytes += response_size as u64;
+                }
+                RequestOutcome::Error { code: Some(code) } => {
+                    *metrics.errors_by_code.entry(code).or_default() += 1;
+                }
+                RequestOutcome::Error { code: None } => metrics.transport_errors += 1,
+                RequestOutcome::Cancelled => {}
+            }
+        })
+    }
+}
+
+impl<R: MetricsRecorder + ?Sized> MetricsRecorder for std::sync::Arc<R> {
+    fn request_started(&self, method: &str, request_size: usize) {
+        (**self).request_started(method, request_size)
+    }
+
+    fn request_finished(&self, method: &str, elapsed: Duration, outcome: RequestOutcome) {
+        (**self).request_finished(method, elapsed, outcome)
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::{JsonRpcError, Middleware, MockProvider, MockResponse, Provider};
+    use ethers_core::types::U64;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn records_method_metrics() {
+        let mock = MockProvider::new();
+        mock.on("eth_blockNumber", |_| MockResponse::Value(serde_json::json!("0x10")));
+        mock.on("eth_chainId", |_| {
+            MockResponse::Error(JsonRpcError {
+                code: -32601,
+                message: "method not found".to_string(),
+                data: None,
+            })
+        });
+        let metrics = Arc::new(InMemoryMetrics::default());
+        let provider = Provider::new(MetricsClient::new(mock, metrics.clone()));
+
+        assert_eq!(provider.get_block_number().await.unwrap(), U64::from(16));
+        assert_eq!(provider.get_block_number().await.unwrap(), U64::from(16));
+        assert!(provider.get_chainid().await.is_err());
+        assert!(provider.get_balance(ethers_core::types::Address::zero(), None).await.is_err());
+
+        let snapshot = metrics.snapshot();
+        let block_number = &snapshot.methods["eth_blockNumber"];
+        assert_eq!(block_number.requests, 2);
+        assert_eq!(block_number.in_flight, 0);
+        assert_eq!(block_number.errors(), 0);
+        assert_eq!(block_number.request_bytes, 0);
+        assert_eq!(block_number.response_bytes, 2 * r#""0x10""#.len() as u64);
+        assert_eq!(block_number.latency.count, 2);
+
+        let chain_id = &snapshot.methods["eth_chainId"];
+        assert_eq!(chain_id.errors_by_code[&-32601], 1);
+
+        let balance = &snapshot.methods["eth_getBalance"];
+        assert_eq!(balance.transport_errors, 1);
+        assert!(balance.request_bytes > 0);
+
+        metrics.reset();
+        assert_eq!(metrics.snapshot().methods["eth_blockNumber"].requests, 0);
+    }
+
+    /// A client whose requests never complete
+    #[derive(Debug)]
+    struct Hanging;
+
+    #[async_trait]
+    impl JsonRpcClient for Hanging {
+        type Error = crate::MockError;
+
+        async fn request<A, R>(&self, _: &str, _: A) -> Result<R, Self::Error>
+        where
+            A: Debug + Serialize + Send + Sync,
+            R: DeserializeOwned + Send,
+        {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn records_cancelled_requests() {
+        let metrics = Arc::new(InMemoryMetrics::default());
+        let provider = Provider::new(MetricsClient::new(Hanging, metrics.clone()));
+
+        let request = provider.get_block_number();
+        tokio::time::timeout(Duration::from_millis(10), request).await.unwrap_err();
+
+        let block_number = &metrics.snapshot().methods["eth_blockNumber"];
+        assert_eq!(block_number.requests, 1);
+        assert_eq!(block_number.in_flight, 0);
+        assert_eq!(block_number.cancelled, 1);
+        assert_eq!(block_number.latency.count, 0);
+    }
+
+    #[test]
+    fn histogram_quantiles() {
+        let mut histogram = LatencyHistogram::new(vec![10, 100, 1000]);
+        assert_eq!(histogram.quantile_upper_bound(0.5), None);
+        for millis in [1, 2, 50, 500, 5000] {
+            histogram.record(Duration::from_millis(millis));
+        }
+        assert_eq!(histogram.counts, vec![2, 1, 1, 1]);
+        assert_eq!(histogram.quantile_upper_bound(0.4), Some(10));
+        assert_eq!(histogram.quantile_upper_bound(0.5), Some(100));
+        assert_eq!(histogram.quantile_upper_bound(0.8), Some(1000));
+        assert_eq!(histogram.quantile_upper_bound(1.0), None);
+        assert_eq!(histogram.mean(), Some(Duration::from_micros(1_110_600)));
+    }
+}
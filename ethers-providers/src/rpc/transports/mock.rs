@@ -29,11 +29,47 @@ pub enum MockResponse {
     Error(super::JsonRpcError),
 }
 
+type MockPredicate = Arc<dyn Fn(&Value) -> bool + Send + Sync>;
+type MockHandlerFn = Arc<Mutex<dyn FnMut(&Value) -> MockResponse + Send>>;
+
+/// A response handler registered for a single method, optionally guarded by a predicate over the
+/// request params.
+///
+/// Handlers are shared so that they can be called after releasing the lock on the registered
+/// handlers, which lets them call back into the mock.
+#[derive(Clone)]
+struct MockHandler {
+    method: String,
+    predicate: Option<MockPredicate>,
+    handler: MockHandlerFn,
+}
+
+impl MockHandler {
+    fn matches(&self, method: &str, params: &Value) -> bool {
+        self.method == method && self.predicate.as_ref().map(|p| p(params)).unwrap_or(true)
+    }
+}
+
+impl std::fmt::Debug for MockHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockHandler")
+            .field("method", &self.method)
+            .field("predicate", &self.predicate.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
 #[derive(Clone, Debug)]
 /// Mock transport used in test environments.
+///
+/// Responses can either be pushed to a queue with [`push`](Self::push), which are then returned
+/// regardless of the requested method, or registered per method with [`on`](Self::on) and
+/// [`on_match`](Self::on_match). Registered handlers take precedence over the queue.
 pub struct MockProvider {
     requests: Arc<Mutex<VecDeque<(String, MockParams)>>>,
     responses: Arc<Mutex<VecDeque<MockResponse>>>,
+    handlers: Arc<Mutex<Vec<MockHandler>>>,
+    unmatched: Arc<Mutex<Vec<(String, Value)>>>,
 }
 
 impl Default for MockProvider {
@@ -47,21 +83,30 @@ impl Default for MockProvider {
 impl JsonRpcClient for MockProvider {
     type Error = MockError;
 
-    /// Pushes the `(method, params)` to the back of the `requests` queue,
-    /// pops the responses from the back of the `responses` queue
+    /// Pushes the `(method, params)` to the back of the `requests` queue.
+    ///
+    /// The response is produced by the first registered handler matching the request, if any,
+    /// otherwise it is popped from the back of the `responses` queue.
     async fn request<T: Serialize + Send + Sync, R: DeserializeOwned>(
         &self,
         method: &str,
         params: T,
     ) -> Result<R, MockError> {
-        let params = if std::mem::size_of::<T>() == 0 {
-            MockParams::Zst
+        let (params, value) = if std::mem::size_of::<T>() == 0 {
+            (MockParams::Zst, Value::Null)
         } else {
-            MockParams::Value(serde_json::to_value(params)?)
+            let value = serde_json::to_value(params)?;
+            (MockParams::Value(value.clone()), value)
         };
         self.requests.lock().unwrap().push_back((method.to_owned(), params));
-        let mut data = self.responses.lock().unwrap();
-        let element = data.pop_back().ok_or(MockError::EmptyResponses)?;
+
+        let element = match self.handle(method, &value) {
+            Some(element) => element,
+            None => match self.responses.lock().unwrap().pop_back() {
+                Some(element) => element,
+                None => return Err(self.unmatched_error(method, value)),
+            },
+        };
         match element {
             MockResponse::Value(value) => {
                 let res: R = serde_json::from_value(value)?;
@@ -73,6 +118,24 @@ impl JsonRpcClient for MockProvider {
 }
 
 impl MockProvider {
+    /// Runs the first handler matching the request, if any.
+    fn handle(&self, method: &str, params: &Value) -> Option<MockResponse> {
+        let handlers = self.handlers.lock().unwrap().clone();
+        let handler = handlers.into_iter().find(|h| h.matches(method, params))?;
+        let mut handler = handler.handler.lock().unwrap();
+        Some((*handler)(params))
+    }
+
+    /// Records a request that could not be answered and returns the corresponding error.
+    fn unmatched_error(&self, method: &str, params: Value) -> MockError {
+        self.unmatched.lock().unwrap().push((method.to_owned(), params.clone()));
+        if self.handlers.lock().unwrap().is_empty() {
+            MockError::EmptyResponses
+        } else {
+            MockError::UnmatchedRequest { method: method.to_owned(), params }
+        }
+    }
+
     /// Checks that the provided request was submitted by the client
     pub fn assert_request<T: Serialize + Send + Sync>(
         &self,
@@ -93,14 +156,129 @@ impl MockProvider {
         Ok(())
     }
 
+    /// Checks that the provided request was submitted by the client, regardless of the order in
+    /// which requests were made.
+    ///
+    /// The first matching request is removed from the recorded requests.
+    pub fn assert_any_request<T: Serialize + Send + Sync>(
+        &self,
+        method: &str,
+        data: T,
+    ) -> Result<(), MockError> {
+        let expected =
+            if std::mem::size_of::<T>() == 0 { None } else { Some(serde_json::to_value(data)?) };
+        let mut requests = self.requests.lock().unwrap();
+        let pos = requests
+            .iter()
+            .position(|(m, inp)| {
+                m == method &&
+                    match (inp, &expected) {
+                        (MockParams::Zst, None) => true,
+                        (MockParams::Value(inp), Some(expected)) => inp == expected,
+                        _ => false,
+                    }
+            })
+            .ok_or_else(|| MockError::RequestNotFound(method.to_owned()))?;
+        requests.remove(pos);
+        Ok(())
+    }
+
     /// Instantiates a mock transport
     pub fn new() -> Self {
         Self {
             requests: Arc::new(Mutex::new(VecDeque::new())),
             responses: Arc::new(Mutex::new(VecDeque::new())),
+            handlers: Arc::new(Mutex::new(Vec::new())),
+            unmatched: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
+    /// Registers a handler which answers every request to `method`.
+    ///
+    /// The handler receives the request params, or `Value::Null` if the request has none, and may
+    /// hold state across calls. Handlers are tried in registration order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ethers_providers::{MockProvider, MockResponse};
+    /// use serde_json::json;
+    ///
+    /// let mock = MockProvider::new();
+    /// let mut block = 0u64;
+    /// mock.on("eth_blockNumber", move |_| {
+    ///     block += 1;
+    ///     MockResponse::Value(json!(format!("{block:#x}")))
+    /// });
+    /// ```
+    pub fn on<F>(&self, method: impl Into<String>, handler: F)
+    where
+        F: FnMut(&Value) -> MockResponse + Send + 'static,
+    {
+        self.handlers.lock().unwrap().push(MockHandler {
+            method: method.into(),
+            predicate: None,
+            handler: Arc::new(Mutex::new(handler)),
+        });
+    }
+
+    /// Registers a handler which answers requests to `method` whose params satisfy `predicate`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ethers_providers::{MockProvider, MockResponse};
+    /// use serde_json::json;
+    ///
+    /// let mock = MockProvider::new();
+    /// mock.on_match(
+    ///     "eth_call",
+    ///     |params| params[0]["to"] == "0x0000000000000000000000000000000000000001",
+    ///     |_| MockResponse::Value(json!("0x01")),
+    /// );
+    /// ```
+    pub fn on_match<P, F>(&self, method: impl Into<String>, predicate: P, handler: F)
+    where
+        P: Fn(&Value) -> bool + Send + Sync + 'static,
+        F: FnMut(&Value) -> MockResponse + Send + 'static,
+    {
+        self.handlers.lock().unwrap().push(MockHandler {
+            method: method.into(),
+            predicate: Some(Arc::new(predicate)),
+            handler: Arc::new(Mutex::new(handler)),
+        });
+    }
+
+    /// Returns all `(method, params)` requests made so far, in order, except for those removed by
+    /// [`assert_request`](Self::assert_request) and
+    /// [`assert_any_request`](Self::assert_any_request). Requests without params have
+    /// `Value::Null` params.
+    pub fn requests(&self) -> Vec<(String, Value)> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(method, params)| {
+                let params = match params {
+                    MockParams::Value(params) => params.clone(),
+                    MockParams::Zst => Value::Null,
+                };
+                (method.clone(), params)
+            })
+            .collect()
+    }
+
+    /// Returns all `(method, params)` requests which could neither be answered by a handler nor
+    /// by the responses queue.
+    pub fn unmatched_requests(&self) -> Vec<(String, Value)> {
+        self.unmatched.lock().unwrap().clone()
+    }
+
+    /// Returns the number of requests which could not be answered.
+    pub fn unmatched_count(&self) -> usize {
+        self.unmatched.lock().unwrap().len()
+    }
+
     /// Pushes the data to the responses
     pub fn push<T: Serialize + Send + Sync, K: Borrow<T>>(&self, data: K) -> Result<(), MockError> {
         let value = serde_json::to_value(data.borrow())?;
@@ -129,6 +307,19 @@ pub enum MockError {
     #[error("empty responses array, please push some responses")]
     EmptyResponses,
 
+    /// No registered handler matched the request and the responses array is empty
+    #[error("no mock handler matched `{method}` with params {params}, please register a handler or push a response")]
+    UnmatchedRequest {
+        /// The requested method
+        method: String,
+        /// The request params
+        params: Value,
+    },
+
+    /// No request matching the assertion was submitted
+    #[error("no `{0}` request with the given params was submitted")]
+    RequestNotFound(String),
+
     /// Custom JsonRpcError
     #[error("JSON-RPC error: {0}")]
     JsonRpcError(super::JsonRpcError),
@@ -161,7 +352,7 @@ impl From<MockError> for ProviderError {
 mod tests {
     use super::*;
     use crate::{JsonRpcError, Middleware};
-    use ethers_core::types::U64;
+    use ethers_core::types::{U256, U64};
 
     #[tokio::test]
     async fn pushes_request_and_response() {
@@ -215,6 +406,104 @@ mod tests {
         };
     }
 
+    #[tokio::test]
+    async fn dispatches_to_method_handlers() {
+        let mock = MockProvider::new();
+        let mut calls = 0u64;
+        mock.on("eth_blockNumber", move |_| {
+            calls += 1;
+            MockResponse::Value(serde_json::to_value(U64::from(calls)).unwrap())
+        });
+        mock.on_match(
+            "eth_getBalance",
+            |params| params[0] == "0x01",
+            |_| MockResponse::Value(serde_json::json!("0x1")),
+        );
+        mock.on_match(
+            "eth_getBalance",
+            |params| params[0] == "0x02",
+            |_| MockResponse::Value(serde_json::json!("0x2")),
+        );
+
+        let b: U64 = mock.request("eth_getBalance", ["0x02"]).await.unwrap();
+        assert_eq!(b.as_u64(), 2);
+        let b: U64 = mock.request("eth_getBalance", ["0x01"]).await.unwrap();
+        assert_eq!(b.as_u64(), 1);
+        let block: U64 = mock.request("eth_blockNumber", ()).await.unwrap();
+        assert_eq!(block.as_u64(), 1);
+        let block: U64 = mock.request("eth_blockNumber", ()).await.unwrap();
+        assert_eq!(block.as_u64(), 2);
+
+        mock.assert_any_request("eth_getBalance", ["0x01"]).unwrap();
+        mock.assert_any_request("eth_blockNumber", ()).unwrap();
+        mock.assert_request("eth_getBalance", ["0x02"]).unwrap();
+        assert!(matches!(
+            mock.assert_any_request("eth_getBalance", ["0x01"]).unwrap_err(),
+            MockError::RequestNotFound(_)
+        ));
+        assert_eq!(mock.unmatched_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn counts_unmatched_requests() {
+        let mock = MockProvider::new();
+        mock.on_match(
+            "eth_getBalance",
+            |params| params[0] == "0x01",
+            |_| MockResponse::Value(serde_json::json!("0x1")),
+        );
+        // the responses queue is used as fallback
+        mock.push(U64::from(7)).unwrap();
+        let b: U64 = mock.request("eth_getBalance", ["0x03"]).await.unwrap();
+        assert_eq!(b.as_u64(), 7);
+
+        let err = mock.request::<_, U64>("eth_getBalance", ["0x03"]).await.unwrap_err();
+        match err {
+            MockError::UnmatchedRequest { method, params } => {
+                assert_eq!(method, "eth_getBalance");
+                assert_eq!(params, serde_json::json!(["0x03"]));
+            }
+            _ => panic!("expected unmatched request"),
+        }
+        assert_eq!(mock.unmatched_count(), 1);
+        assert_eq!(mock.unmatched_requests()[0].0, "eth_getBalance");
+    }
+
+    #[tokio::test]
+    async fn handlers_can_call_back_into_the_mock() {
+        let mock = MockProvider::new();
+        let inner = mock.clone();
+        mock.on("eth_blockNumber", move |_| {
+            inner.on("eth_chainId", |_| MockResponse::Value(serde_json::json!("0x1")));
+            MockResponse::Value(serde_json::json!(U64::from(inner.unmatched_count())))
+        });
+
+        let block: U64 = mock.request("eth_blockNumber", ()).await.unwrap();
+        assert_eq!(block.as_u64(), 0);
+        let chain_id: U64 = mock.request("eth_chainId", ()).await.unwrap();
+        assert_eq!(chain_id.as_u64(), 1);
+    }
+
+    #[tokio::test]
+    async fn records_requests() {
+        let mock = MockProvider::new();
+        mock.on("eth_getBalance", |_| MockResponse::Value(serde_json::json!("0x1")));
+        mock.on("eth_blockNumber", |_| MockResponse::Value(serde_json::json!("0x2")));
+
+        let _: U256 = mock.request("eth_getBalance", ["0x01", "latest"]).await.unwrap();
+        let _: U64 = mock.request("eth_blockNumber", ()).await.unwrap();
+        assert_eq!(
+            mock.requests(),
+            vec![
+                ("eth_getBalance".to_string(), serde_json::json!(["0x01", "latest"])),
+                ("eth_blockNumber".to_string(), Value::Null),
+            ]
+        );
+
+        mock.assert_any_request("eth_getBalance", ["0x01", "latest"]).unwrap();
+        assert_eq!(mock.requests().len(), 1);
+    }
+
     #[tokio::test]
     async fn composes_with_provider() {
         let (provider, mock) = crate::Provider::mocked();
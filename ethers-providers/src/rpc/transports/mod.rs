@@ -18,6 +18,15 @@ pub use rw::{RwClient, RwClientError};
 mod retry;
 pub use retry::*;
 
+mod rate_limit;
+pub use rate_limit::{ComputeUnitCosts, RateLimitedClient, RateLimitedClientBuilder};
+
+mod metrics;
+pub use metrics::{
+    InMemoryMetrics, LatencyHistogram, MethodMetrics, MetricsClient, MetricsClientError,
+    MetricsRecorder, MetricsSnapshot, RequestOutcome, DEFAULT_LATENCY_BUCKETS_MS,
+};
+
 #[cfg(all(feature = "ws", not(feature = "legacy-ws")))]
 mod ws;
 #[cfg(all(feature = "ws", not(feature = "legacy-ws")))]
@@ -29,5 +38,10 @@ pub mod legacy_ws;
 #[cfg(feature = "legacy-ws")]
 pub use legacy_ws::{ClientError as WsClientError, Ws};
 
+#[cfg(not(target_arch = "wasm32"))]
+mod polling;
+#[cfg(not(target_arch = "wasm32"))]
+pub use polling::PollingPubsub;
+
 mod mock;
 pub use mock::{MockError, MockProvider, MockResponse};
@@ -0,0 +1,379 @@
+//! A [PubsubClient] implementation which emulates subscriptions over any [JsonRpcClient] by
+//! polling filters
+
+use crate::{errors::ProviderError, JsonRpcClient, PubsubClient, DEFAULT_POLL_INTERVAL};
+use async_trait::async_trait;
+use ethers_core::types::{H256, U256};
+use futures_timer::Delay;
+use futures_util::stream::{self, Stream};
+use instant::Duration;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{value::RawValue, Value};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// A client which emulates `eth_subscribe` over transports without push notifications, such as
+/// [`Http`](crate::Http), so that
+/// [`Middleware::subscribe_blocks`](crate::Middleware::subscribe_blocks) and friends work unchanged
+/// across transports.
+///
+/// `newHeads`, `logs` and `newPendingTransactions` subscriptions are backed by the matching
+/// filter, which is polled with `eth_getFilterChanges` at the configured interval. All other
+/// requests are forwarded to the inner client.
+///
+/// **Note**: filters which expired on the node are reinstalled, which may miss the notifications
+/// in between. Dropping a subscription stream without calling `unsubscribe` leaves its filter
+/// to expire on the node.
+///
+/// # Example
+///
+/// ```no_run
+/// use ethers_providers::{Http, Middleware, PollingPubsub, Provider, StreamExt};
+/// use std::time::Duration;
+///
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let http: Http = "http://localhost:8545".parse()?;
+/// let provider = Provider::new(PollingPubsub::new(http).with_interval(Duration::from_secs(1)));
+///
+/// let mut blocks = provider.subscribe_blocks().await?;
+/// while let Some(block) = blocks.next().await {
+///     println!("new block {:?}", block.number);
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Clone)]
+pub struct PollingPubsub<C> {
+    inner: Arc<C>,
+    interval: Duration,
+    next_id: Arc<Mutex<U256>>,
+    subscriptions: Arc<Mutex<HashMap<U256, Subscription>>>,
+}
+
+/// A subscription backed by a filter
+#[derive(Clone)]
+struct Subscription {
+    kind: SubscriptionKind,
+    /// the filter request, kept to reinstall the filter if it expires
+    install: (&'static str, Vec<Value>),
+    filter_id: Arc<Mutex<U256>>,
+    active: Arc<AtomicBool>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SubscriptionKind {
+    NewHeads,
+    Logs,
+    NewPendingTransactions,
+}
+
+impl<C> PollingPubsub<C> {
+    /// Creates a new client which polls the filters of the subscriptions every
+    /// [`DEFAULT_POLL_INTERVAL`]
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            interval: DEFAULT_POLL_INTERVAL,
+            next_id: Arc::new(Mutex::new(U256::one())),
+            subscriptions: Default::default(),
+        }
+    }
+
+    /// Sets the interval at which the filters of the subscriptions are polled
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Returns the inner client
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+}
+
+impl<C: JsonRpcClient> PollingPubsub<C> {
+    async fn install(&self, method: &str, params: &[Value]) -> Result<U256, ProviderError> {
+        self.inner.request(method, params).await.map_err(Into::into)
+    }
+
+    async fn subscribe_filter(&self, params: Value) -> Result<U256, ProviderError> {
+        let params = match params {
+            Value::Array(params) => params,
+            params => vec![params],
+        };
+        let (kind, install) = match params.first().and_then(Value::as_str) {
+            Some("newHeads") => (SubscriptionKind::NewHeads, ("eth_newBlockFilter", vec![])),
+            Some("logs") => {
+                let filter =
+                    params.get(1).cloned().unwrap_or_else(|| Value::Object(Default::default()));
+                (SubscriptionKind::Logs, ("eth_newFilter", vec![filter]))
+            }
+            Some("newPendingTransactions") if params.get(1).map_or(true, |full| full == false) => (
+                SubscriptionKind::NewPendingTransactions,
+                ("eth_newPendingTransactionFilter", vec![]),
+            ),
+            _ => {
+                return Err(ProviderError::CustomError(format!(
+                    "unsupported polling subscription: {}",
+                    Value::Array(params)
+                )))
+            }
+        };
+
+        self.uninstall_inactive().await;
+        let filter_id = self.install(install.0, &install.1).await?;
+        let id = {
+            let mut next_id = self.next_id.lock().unwrap();
+            let id = *next_id;
+            *next_id += U256::one();
+            id
+        };
+        let subscription = Subscription {
+            kind,
+            install,
+            filter_id: Arc::new(Mutex::new(filter_id)),
+            active: Arc::new(AtomicBool::new(true)),
+        };
+        self.subscriptions.lock().unwrap().insert(id, subscription);
+        Ok(id)
+    }
+
+    /// Uninstalls the filters of the subscriptions whose streams were dropped
+    async fn uninstall_inactive(&self) {
+        let inactive: Vec<_> = {
+            let mut subscriptions = self.subscriptions.lock().unwrap();
+            let ids: Vec<_> = subscriptions
+                .iter()
+                .filter(|(_, sub)| !sub.active.load(Ordering::SeqCst))
+                .map(|(id, _)| *id)
+                .collect();
+            ids.into_iter().filter_map(|id| subscriptions.remove(&id)).collect()
+        };
+        for sub in inactive {
+            let filter_id = *sub.filter_id.lock().unwrap();
+            let res: Result<bool, _> = self.inner.request("eth_uninstallFilter", [filter_id]).await;
+            if let Err(err) = res {
+                tracing::debug!(?err, ?filter_id, "failed to uninstall subscription filter");
+            }
+        }
+    }
+
+    async fn unsubscribe_filter(&self, params: Value) -> Result<bool, ProviderError> {
+        let id: U256 = match params {
+            Value::Array(mut params) if !params.is_empty() => {
+                serde_json::from_value(params.remove(0))?
+            }
+            params => serde_json::from_value(params)?,
+        };
+        let Some(subscription) = self.subscriptions.lock().unwrap().remove(&id) else {
+            return Ok(false)
+        };
+        subscription.active.store(false, Ordering::SeqCst);
+        let filter_id = *subscription.filter_id.lock().unwrap();
+        self.inner.request("eth_uninstallFilter", [filter_id]).await.map_err(Into::into)
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<C> JsonRpcClient for PollingPubsub<C>
+where
+    C: JsonRpcClient + 'static,
+{
+    type Error = ProviderError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: fmt::Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        match method {
+            "eth_subscribe" => {
+                let id = self.subscribe_filter(serde_json::to_value(params)?).await?;
+                Ok(serde_json::from_value(serde_json::to_value(id)?)?)
+            }
+            "eth_unsubscribe" => {
+                let removed = self.unsubscribe_filter(serde_json::to_value(params)?).await?;
+                Ok(serde_json::from_value(Value::Bool(removed))?)
+            }
+            _ => self.inner.request(method, params).await.map_err(Into::into),
+        }
+    }
+}
+
+impl<C> PubsubClient for PollingPubsub<C>
+where
+    C: JsonRpcClient + 'static,
+{
+    type NotificationStream = Pin<Box<dyn Stream<Item = Box<RawValue>> + Send>>;
+
+    fn subscribe<T: Into<U256>>(&self, id: T) -> Result<Self::NotificationStream, Self::Error> {
+        let id = id.into();
+        let subscription =
+            self.subscriptions.lock().unwrap().get(&id).cloned().ok_or_else(|| {
+                ProviderError::CustomError(format!("unknown subscription id: {id:?}"))
+            })?;
+
+        let state = (self.inner.clone(), subscription, self.interval, VecDeque::new());
+        let stream = stream::unfold(state, |(inner, sub, interval, mut buffered)| async move {
+            loop {
+                if let Some(item) = buffered.pop_front() {
+                    return Some((item, (inner, sub, interval, buffered)))
+                }
+                Delay::new(interval).await;
+                if !sub.active.load(Ordering::SeqCst) {
+                    return None
+                }
+                match poll_changes(&*inner, &sub).await {
+                    Ok(items) => buffered.extend(items),
+                    Err(err) => tracing::warn!(?err, "failed to poll subscription filter"),
+                }
+            }
+        });
+        Ok(Box::pin(stream))
+    }
+
+    fn unsubscribe<T: Into<U256>>(&self, id: T) -> Result<(), Self::Error> {
+        // the filter is uninstalled by `eth_unsubscribe` or the next `eth_subscribe`
+        if let Some(subscription) = self.subscriptions.lock().unwrap().get(&id.into()) {
+            subscription.active.store(false, Ordering::SeqCst);
+        }
+        Ok(())
+    }
+}
+
+/// Fetches the changes of the filter of a subscription, in the shape of the notifications of the
+/// subscription
+async fn poll_changes<C: JsonRpcClient>(
+    inner: &C,
+    sub: &Subscription,
+) -> Result<Vec<Box<RawValue>>, ProviderError> {
+    let filter_id = *sub.filter_id.lock().unwrap();
+    let changes: Vec<Box<RawValue>> =
+        match inner.request("eth_getFilterChanges", [filter_id]).await.map_err(Into::into) {
+            Ok(changes) => changes,
+            Err(err) if is_filter_not_found(&err) => {
+                tracing::debug!(?filter_id, "reinstalling expired subscription filter");
+                let (method, params) = &sub.install;
+                let filter_id: U256 = inner.request(method, params).await.map_err(Into::into)?;
+                *sub.filter_id.lock().unwrap() = filter_id;
+                return Ok(vec![])
+            }
+            Err(err) => return Err(err),
+        };
+
+    if sub.kind != SubscriptionKind::NewHeads {
+        return Ok(changes)
+    }
+    // block filters return hashes, but `newHeads` notifications are headers
+    let mut headers = Vec::with_capacity(changes.len());
+    for hash in changes {
+        let hash: H256 = serde_json::from_str(hash.get())?;
+        let header: Option<Box<RawValue>> =
+            inner.request("eth_getBlockByHash", (hash, false)).await.map_err(Into::into)?;
+        headers.extend(header);
+    }
+    Ok(headers)
+}
+
+fn is_filter_not_found(err: &ProviderError) -> bool {
+    use crate::RpcError;
+    err.as_error_response().map_or(false, |err| err.message.contains("filter not found"))
+}
+
+impl<C: fmt::Debug> fmt::Debug for PollingPubsub<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PollingPubsub")
+            .field("inner", &self.inner)
+            .field("interval", &self.interval)
+            .field("subscriptions", &self.subscriptions.lock().unwrap().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::{Middleware, MockProvider, MockResponse, Provider, StreamExt};
+    use ethers_core::types::{Block, Filter, Log, TxHash};
+
+    fn polling(mock: MockProvider) -> Provider<PollingPubsub<MockProvider>> {
+        Provider::new(PollingPubsub::new(mock).with_interval(Duration::from_millis(1)))
+    }
+
+    #[tokio::test]
+    async fn emulates_new_heads() {
+        let mock = MockProvider::new();
+        mock.on("eth_newBlockFilter", |_| MockResponse::Value(serde_json::json!("0x10")));
+        let mut polls = 0u64;
+        mock.on("eth_getFilterChanges", move |_| {
+            polls += 1;
+            let hashes =
+                if polls == 2 { vec![H256::repeat_byte(1), H256::repeat_byte(2)] } else { vec![] };
+            MockResponse::Value(serde_json::to_value(hashes).unwrap())
+        });
+        mock.on("eth_getBlockByHash", |params| {
+            let hash: H256 = serde_json::from_value(params[0].clone()).unwrap();
+            let block = Block::<TxHash> { hash: Some(hash), ..Default::default() };
+            MockResponse::Value(serde_json::to_value(block).unwrap())
+        });
+        mock.on("eth_uninstallFilter", |_| MockResponse::Value(serde_json::json!(true)));
+        let provider = polling(mock.clone());
+
+        let stream = provider.subscribe_blocks().await.unwrap();
+        let blocks: Vec<_> = stream.take(2).collect().await;
+        assert_eq!(blocks[0].hash, Some(H256::repeat_byte(1)));
+        assert_eq!(blocks[1].hash, Some(H256::repeat_byte(2)));
+
+        assert!(provider.unsubscribe(1u64).await.unwrap());
+        mock.assert_any_request("eth_uninstallFilter", [U256::from(0x10)]).unwrap();
+    }
+
+    #[tokio::test]
+    async fn emulates_logs_and_reinstalls_expired_filters() {
+        let mock = MockProvider::new();
+        let mut installs = 0u64;
+        mock.on("eth_newFilter", move |_| {
+            installs += 1;
+            MockResponse::Value(serde_json::to_value(U256::from(installs)).unwrap())
+        });
+        mock.on("eth_getFilterChanges", |params| {
+            // the first filter expired
+            if params[0] == "0x1" {
+                return MockResponse::Error(crate::JsonRpcError {
+                    code: -32000,
+                    message: "filter not found".to_string(),
+                    data: None,
+                })
+            }
+            let log = Log { log_index: Some(7.into()), ..Default::default() };
+            MockResponse::Value(serde_json::to_value(vec![log]).unwrap())
+        });
+        let provider = polling(mock.clone());
+
+        let filter = Filter::new().address(ethers_core::types::Address::repeat_byte(1));
+        let mut logs = provider.subscribe_logs(&filter).await.unwrap();
+        let log = logs.next().await.unwrap();
+        assert_eq!(log.log_index, Some(7.into()));
+        mock.assert_any_request("eth_newFilter", [&filter]).unwrap();
+    }
+
+    #[tokio::test]
+    async fn forwards_other_requests() {
+        let mock = MockProvider::new();
+        mock.on("eth_blockNumber", |_| MockResponse::Value(serde_json::json!("0x2a")));
+        let provider = polling(mock);
+        assert_eq!(provider.get_block_number().await.unwrap(), 42.into());
+        assert!(provider
+            .subscribe::<_, TxHash>(serde_json::json!(["newPendingTransactions", true]))
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,514 @@
+//! A [JsonRpcClient] implementation that proactively enforces a compute unit budget before
+//! requests are sent.
+
+use crate::JsonRpcClient;
+use async_trait::async_trait;
+#[cfg(target_arch = "wasm32")]
+use instant::Instant;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{collections::HashMap, fmt::Debug, sync::Mutex, time::Duration};
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::time::Instant;
+use tracing::trace;
+
+/// The compute unit cost of every JSON-RPC method
+///
+/// Methods which are not part of the table are charged the `default_cost`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComputeUnitCosts {
+    costs: HashMap<String, u64>,
+    default_cost: u64,
+}
+
+impl ComputeUnitCosts {
+    /// Creates a table which charges every method the same `cost`
+    pub fn uniform(cost: u64) -> Self {
+        Self { costs: HashMap::new(), default_cost: cost }
+    }
+
+    /// Returns Alchemy's compute unit schedule
+    ///
+    /// See also <https://docs.alchemy.com/reference/compute-units>
+    pub fn alchemy() -> Self {
+        const COSTS: &[(&str, u64)] = &[
+            ("net_version", 0),
+            ("eth_chainId", 0),
+            ("eth_syncing", 0),
+            ("eth_protocolVersion", 0),
+            ("net_listening", 0),
+            ("eth_uninstallFilter", 10),
+            ("eth_accounts", 10),
+            ("eth_blockNumber", 10),
+            ("eth_subscribe", 10),
+            ("eth_unsubscribe", 10),
+            ("eth_feeHistory", 10),
+            ("eth_maxPriorityFeePerGas", 10),
+            ("eth_createAccessList", 10),
+            ("eth_getTransactionReceipt", 15),
+            ("eth_getUncleByBlockHashAndIndex", 15),
+            ("eth_getUncleByBlockNumberAndIndex", 15),
+            ("eth_getTransactionByBlockHashAndIndex", 15),
+            ("eth_getTransactionByBlockNumberAndIndex", 15),
+            ("eth_getUncleCountByBlockHash", 15),
+            ("eth_getUncleCountByBlockNumber", 15),
+            ("web3_clientVersion", 15),
+            ("web3_sha3", 15),
+            ("eth_getBlockByNumber", 16),
+            ("eth_getStorageAt", 17),
+            ("eth_getTransactionByHash", 17),
+            ("eth_gasPrice", 19),
+            ("eth_getBalance", 19),
+            ("eth_getCode", 19),
+            ("eth_getFilterChanges", 20),
+            ("eth_newBlockFilter", 20),
+            ("eth_newFilter", 20),
+            ("eth_newPendingTransactionFilter", 20),
+            ("eth_getBlockTransactionCountByHash", 20),
+            ("eth_getBlockTransactionCountByNumber", 20),
+            ("eth_getProof", 21),
+            ("eth_getBlockByHash", 21),
+            ("eth_call", 26),
+            ("eth_getTransactionCount", 26),
+            ("eth_getFilterLogs", 75),
+            ("eth_getLogs", 75),
+            ("eth_estimateGas", 87),
+            ("eth_sendRawTransaction", 250),
+            ("trace_get", 17),
+            ("trace_block", 24),
+            ("trace_transaction", 26),
+            ("trace_call", 75),
+            ("trace_rawTransaction", 75),
+            ("trace_filter", 75),
+            ("trace_replayTransaction", 2983),
+            ("trace_replayBlockTransactions", 2983),
+            ("debug_traceTransaction", 309),
+            ("debug_traceCall", 309),
+            ("debug_traceBlockByHash", 497),
+            ("debug_traceBlockByNumber", 497),
+        ];
+
+        let costs = COSTS.iter().map(|(method, cost)| (method.to_string(), *cost)).collect();
+        // the same average cost the `RetryClient` assumes
+        Self { costs, default_cost: 17 }
+    }
+
+    /// Sets the cost of `method`
+    pub fn set(&mut self, method: impl Into<String>, cost: u64) -> &mut Self {
+        self.costs.insert(method.into(), cost);
+        self
+    }
+
+    /// Sets the cost of methods which are not part of the table
+    pub fn set_default(&mut self, cost: u64) -> &mut Self {
+        self.default_cost = cost;
+        self
+    }
+
+    /// Returns the cost of `method`
+    pub fn cost(&self, method: &str) -> u64 {
+        self.costs.get(method).copied().unwrap_or(self.default_cost)
+    }
+}
+
+impl Default for ComputeUnitCosts {
+    fn default() -> Self {
+        Self::alchemy()
+    }
+}
+
+/// A token bucket which hands out reservations in arrival order.
+///
+/// The balance is allowed to go negative: a request that can not be served immediately reserves
+/// its tokens anyway and waits until the debt has been refilled. Since every request is charged
+/// against the balance left by all previous ones, waiting requests are served first in, first out.
+#[derive(Debug)]
+struct TokenBucket {
+    /// Tokens added per second
+    rate: f64,
+    /// Maximum number of tokens that can be accumulated
+    capacity: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u64, capacity: u64) -> Self {
+        let capacity = capacity.max(1) as f64;
+        Self { rate: rate.max(1) as f64, capacity, tokens: capacity, last_refill: Instant::now() }
+    }
+
+    /// Reserves `cost` tokens and returns how long the caller has to wait until they are
+    /// available
+    fn reserve(&mut self, cost: u64, now: Instant) -> Duration {
+        if now > self.last_refill {
+            let elapsed = (now - self.last_refill).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+            self.last_refill = now;
+        }
+        self.tokens -= cost as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+
+    /// Gives back `cost` tokens of a reservation which was not used
+    fn refund(&mut self, cost: u64) {
+        self.tokens = (self.tokens + cost as f64).min(self.capacity);
+    }
+}
+
+#[derive(Debug)]
+struct Buckets {
+    global: TokenBucket,
+    methods: HashMap<String, TokenBucket>,
+}
+
+impl Buckets {
+    /// Returns the bucket of `method`, or the global bucket
+    fn bucket(&mut self, method: Option<&str>) -> Option<&mut TokenBucket> {
+        match method {
+            Some(method) => self.methods.get_mut(method),
+            None => Some(&mut self.global),
+        }
+    }
+}
+
+/// Tokens reserved in a bucket, which are refunded if the request is dropped while waiting for
+/// them, so that cancelled requests do not leave debt behind
+struct Reservation<'a> {
+    buckets: &'a Mutex<Buckets>,
+    /// The method of the method bucket, `None` for the global bucket
+    method: Option<&'a str>,
+    cost: u64,
+    delay: Duration,
+}
+
+impl Reservation<'_> {
+    /// Waits until the reserved tokens are available
+    async fn wait(mut self, method: &str) {
+        delay(method, self.delay).await;
+        // the tokens are spent
+        self.cost = 0;
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        if self.cost > 0 {
+            if let Some(bucket) = self.buckets.lock().unwrap().bucket(self.method) {
+                bucket.refund(self.cost);
+            }
+        }
+    }
+}
+
+/// [RateLimitedClient] presents as a wrapper around [JsonRpcClient] that delays requests until
+/// they fit into a compute units per second budget, instead of waiting for the endpoint to respond
+/// with a rate limit error.
+///
+/// Every method is weighted by its [ComputeUnitCosts]. Requests which exceed the budget are
+/// queued and sent in the order they were issued. In addition to the global budget, single methods
+/// can be limited to a number of requests per second. Requests of a limited method are queued in
+/// the order they were issued for their method first, and only join the queue of the global
+/// budget once their method limit lets them through, so that they do not hold back other methods.
+/// Requests which are dropped while queued give their reservation back.
+///
+/// # Example
+///
+/// ```
+/// use ethers_providers::{ComputeUnitCosts, Http, Provider, RateLimitedClientBuilder};
+/// use url::Url;
+///
+/// let http = Http::new(Url::parse("http://localhost:8545").unwrap());
+/// let client = RateLimitedClientBuilder::default()
+///     .compute_units_per_second(330)
+///     .costs(ComputeUnitCosts::alchemy())
+///     .method_requests_per_second("eth_getLogs", 2)
+///     .build(http);
+/// let provider = Provider::new(client);
+/// ```
+#[derive(Debug)]
+pub struct RateLimitedClient<T> {
+    inner: T,
+    costs: ComputeUnitCosts,
+    buckets: Mutex<Buckets>,
+}
+
+impl<T> RateLimitedClient<T>
+where
+    T: JsonRpcClient,
+{
+    /// Creates a new `RateLimitedClient` that enforces the given compute units per second budget
+    /// with Alchemy's compute unit costs.
+    pub fn new(inner: T, compute_units_per_second: u64) -> Self {
+        RateLimitedClientBuilder::default()
+            .compute_units_per_second(compute_units_per_second)
+            .build(inner)
+    }
+
+    /// Returns the wrapped client
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns the compute unit costs
+    pub fn costs(&self) -> &ComputeUnitCosts {
+        &self.costs
+    }
+
+    /// Reserves a request to `method` in its method bucket, if it has one
+    fn reserve_method<'a>(&'a self, method: &'a str) -> Reservation<'a> {
+        self.reserve(Some(method), 1)
+    }
+
+    /// Reserves the compute units of a request to `method` in the global bucket
+    fn reserve_global(&self, method: &str) -> Reservation<'_> {
+        self.reserve(None, self.costs.cost(method))
+    }
+
+    fn reserve<'a>(&'a self, method: Option<&'a str>, cost: u64) -> Reservation<'a> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let (cost, delay) = match buckets.bucket(method) {
+            Some(bucket) => (cost, bucket.reserve(cost, Instant::now())),
+            None => (0, Duration::ZERO),
+        };
+        Reservation { buckets: &self.buckets, method, cost, delay }
+    }
+}
+
+async fn delay(method: &str, delay: Duration) {
+    if !delay.is_zero() {
+        trace!(method, ?delay, "delaying request to stay within compute unit budget");
+
+        #[cfg(target_arch = "wasm32")]
+        futures_timer::Delay::new(delay).await;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Builder for a [`RateLimitedClient`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimitedClientBuilder {
+    /// available compute units per second
+    compute_units_per_second: u64,
+    /// maximum number of compute units that can be spent at once
+    burst: Option<u64>,
+    /// compute unit cost per method
+    costs: ComputeUnitCosts,
+    /// requests per second limits for single methods
+    method_limits: HashMap<String, u64>,
+}
+
+// === impl RateLimitedClientBuilder ===
+
+impl RateLimitedClientBuilder {
+    /// Sets the number of available compute units per second
+    ///
+    /// See also, <https://github.com/alchemyplatform/alchemy-docs/blob/master/documentation/compute-units.md#rate-limits-cups>
+    pub fn compute_units_per_second(mut self, compute_units_per_second: u64) -> Self {
+        self.compute_units_per_second = compute_units_per_second;
+        self
+    }
+
+    /// Limits the number of requests per second regardless of the method, this charges every
+    /// method a cost of `1`
+    pub fn requests_per_second(mut self, requests_per_second: u64) -> Self {
+        self.compute_units_per_second = requests_per_second;
+        self.costs = ComputeUnitCosts::uniform(1);
+        self
+    }
+
+    /// Sets the compute unit cost table
+    pub fn costs(mut self, costs: ComputeUnitCosts) -> Self {
+        self.costs = costs;
+        self
+    }
+
+    /// Sets the maximum number of compute units that can be spent at once after the client was
+    /// idle, defaults to the budget of one second
+    pub fn burst(mut self, burst: u64) -> Self {
+        self.burst = Some(burst);
+        self
+    }
+
+    /// Additionally limits `method` to `requests_per_second`
+    pub fn method_requests_per_second(
+        mut self,
+        method: impl Into<String>,
+        requests_per_second: u64,
+    ) -> Self {
+        self.method_limits.insert(method.into(), requests_per_second);
+        self
+    }
+
+    /// Creates the `RateLimitedClient` with the configured settings
+    pub fn build<T>(self, client: T) -> RateLimitedClient<T>
+    where
+        T: JsonRpcClient,
+    {
+        let RateLimitedClientBuilder { compute_units_per_second, burst, costs, method_limits } =
+            self;
+        let global =
+            TokenBucket::new(compute_units_per_second, burst.unwrap_or(compute_units_per_second));
+        let methods = method_limits
+            .into_iter()
+            .map(|(method, rps)| (method, TokenBucket::new(rps, rps)))
+            .collect();
+        RateLimitedClient { inner: client, costs, buckets: Mutex::new(Buckets { global, methods }) }
+    }
+}
+
+// Some sensible defaults
+impl Default for RateLimitedClientBuilder {
+    fn default() -> Self {
+        Self {
+            // alchemy max cpus <https://github.com/alchemyplatform/alchemy-docs/blob/master/documentation/compute-units.md#rate-limits-cups>
+            compute_units_per_second: 330,
+            burst: None,
+            costs: ComputeUnitCosts::alchemy(),
+            method_limits: HashMap::new(),
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<T> JsonRpcClient for RateLimitedClient<T>
+where
+    T: JsonRpcClient,
+{
+    type Error = T::Error;
+
+    async fn request<A, R>(&self, method: &str, params: A) -> Result<R, Self::Error>
+    where
+        A: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        // the global budget is only charged once the method limit lets the request through, so
+        // that requests waiting on their method do not hold back other methods
+        self.reserve_method(method).wait(method).await;
+        self.reserve_global(method).wait(method).await;
+        self.inner.request(method, params).await
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::{MockProvider, MockResponse};
+    use ethers_core::types::U64;
+    use futures_util::future::{join, join_all};
+
+    #[test]
+    fn token_bucket_reservations() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(10, 20);
+        bucket.last_refill = now;
+
+        assert_eq!(bucket.reserve(15, now), Duration::ZERO);
+        assert_eq!(bucket.reserve(5, now), Duration::ZERO);
+        // 10 tokens short
+        assert_eq!(bucket.reserve(10, now), Duration::from_secs(1));
+        // queued behind the previous reservation
+        assert_eq!(bucket.reserve(10, now), Duration::from_secs(2));
+        // refilling pays off the debt first
+        assert_eq!(bucket.reserve(10, now + Duration::from_secs(2)), Duration::from_secs(1));
+        // never exceeds the capacity after being idle
+        assert_eq!(bucket.reserve(20, now + Duration::from_secs(100)), Duration::ZERO);
+        assert_eq!(bucket.reserve(1, now + Duration::from_secs(100)), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn compute_unit_costs() {
+        let mut costs = ComputeUnitCosts::alchemy();
+        assert_eq!(costs.cost("eth_getLogs"), 75);
+        assert_eq!(costs.cost("eth_chainId"), 0);
+        assert_eq!(costs.cost("unknown_method"), 17);
+        costs.set("eth_getLogs", 100).set_default(1);
+        assert_eq!(costs.cost("eth_getLogs"), 100);
+        assert_eq!(costs.cost("unknown_method"), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn delays_requests_over_budget() {
+        let mock = MockProvider::new();
+        mock.on("eth_blockNumber", |_| MockResponse::Value(serde_json::json!("0x1")));
+        mock.on("eth_chainId", |_| MockResponse::Value(serde_json::json!("0x1")));
+        let client = RateLimitedClientBuilder::default()
+            .requests_per_second(100)
+            .burst(1)
+            .build(mock.clone());
+
+        let start = Instant::now();
+        for _ in 0..5 {
+            let _: U64 = client.request("eth_blockNumber", ()).await.unwrap();
+        }
+        // 4 requests had to wait 10ms each
+        assert_eq!(start.elapsed(), Duration::from_millis(40));
+
+        let client = RateLimitedClientBuilder::default()
+            .requests_per_second(10_000)
+            .method_requests_per_second("eth_chainId", 10)
+            .build(mock);
+
+        let start = Instant::now();
+        for _ in 0..10 {
+            let _: U64 = client.request("eth_chainId", ()).await.unwrap();
+            let _: U64 = client.request("eth_blockNumber", ()).await.unwrap();
+        }
+        // the method bucket allows 10 requests at once
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        let _: U64 = client.request("eth_chainId", ()).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_millis(100));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn method_limits_do_not_hold_back_other_methods() {
+        let mock = MockProvider::new();
+        mock.on("eth_blockNumber", |_| MockResponse::Value(serde_json::json!("0x1")));
+        mock.on("eth_chainId", |_| MockResponse::Value(serde_json::json!("0x1")));
+        let client = RateLimitedClientBuilder::default()
+            .requests_per_second(10)
+            .method_requests_per_second("eth_chainId", 1)
+            .build(mock);
+
+        let start = Instant::now();
+        let chain_ids = async {
+            join_all((0..5).map(|_| client.request::<_, U64>("eth_chainId", ()))).await;
+            start.elapsed()
+        };
+        let blocks = async {
+            for _ in 0..9 {
+                let _: U64 = client.request("eth_blockNumber", ()).await.unwrap();
+            }
+            start.elapsed()
+        };
+        let (chain_ids, blocks) = join(chain_ids, blocks).await;
+
+        // only the first `eth_chainId` request was charged against the global budget
+        assert_eq!(blocks, Duration::ZERO);
+        assert_eq!(chain_ids, Duration::from_secs(4));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn refunds_dropped_requests() {
+        let mock = MockProvider::new();
+        mock.on("eth_blockNumber", |_| MockResponse::Value(serde_json::json!("0x1")));
+        let client = RateLimitedClientBuilder::default().requests_per_second(1).build(mock);
+
+        let start = Instant::now();
+        let _: U64 = client.request("eth_blockNumber", ()).await.unwrap();
+        // gives up while waiting for its reservation
+        let request = client.request::<_, U64>("eth_blockNumber", ());
+        tokio::time::timeout(Duration::from_millis(500), request).await.unwrap_err();
+
+        // the next request is not queued behind the dropped one
+        let _: U64 = client.request("eth_blockNumber", ()).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,443 @@
+//! Recovery of `newHeads` and `logs` notifications which were emitted while the websocket was
+//! disconnected.
+//!
+//! The `SubscriptionManager` remembers the position of the last item it delivered to each
+//! subscription. After a reconnect, live notifications of the re-issued subscription are buffered
+//! while a backfill task fetches the missed headers via `eth_getBlockByNumber` or the missed logs
+//! via `eth_getLogs`. Once the task completes, the backfilled items are delivered first, followed
+//! by the buffered items. Items which were already delivered, either shortly before the disconnect
+//! or as part of the backfill, are skipped.
+//!
+//! If the missed items can not be fetched, the subscription is ended rather than silently
+//! skipping the gap, so that the subscriber can resubscribe and recover the missed items itself.
+
+use super::{Instruction, WsClientError};
+use ethers_core::types::{Filter, FilterBlockOption, H256, U256, U64};
+use futures_channel::{mpsc, oneshot};
+use futures_util::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{
+    value::{to_raw_value, RawValue},
+    Value,
+};
+use std::collections::HashSet;
+
+/// The maximum number of blocks which are backfilled after a reconnect. If more blocks were missed,
+/// only the most recent ones are recovered.
+pub(super) const MAX_BACKFILL_BLOCKS: u64 = 1024;
+
+/// The number of headers fetched at once while backfilling, to spare a node which likely just
+/// recovered
+const BACKFILL_CONCURRENCY: usize = 8;
+
+/// The number of most recently delivered items remembered per subscription to skip duplicates
+/// after a reconnect
+pub(super) const RECENT_ITEMS: usize = 1024;
+
+/// The kind of a subscription, derived from its `eth_subscribe` params
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum SubKind {
+    NewHeads,
+    Logs(Value),
+    Other,
+}
+
+impl SubKind {
+    pub(super) fn from_params(params: &RawValue) -> Self {
+        let params: Vec<Value> = match serde_json::from_str(params.get()) {
+            Ok(params) => params,
+            Err(_) => return SubKind::Other,
+        };
+        match params.first().and_then(Value::as_str) {
+            Some("newHeads") => SubKind::NewHeads,
+            Some("logs") => SubKind::Logs(
+                params.get(1).cloned().unwrap_or_else(|| Value::Object(Default::default())),
+            ),
+            _ => SubKind::Other,
+        }
+    }
+
+    pub(super) fn supports_backfill(&self) -> bool {
+        !matches!(self, SubKind::Other)
+    }
+}
+
+/// The position of a header or log in the chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) struct Cursor {
+    pub block: U64,
+    pub log_index: U256,
+}
+
+/// The fields of a header or log notification required for backfilling
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ItemMeta {
+    number: Option<U64>,
+    hash: Option<H256>,
+    block_number: Option<U64>,
+    block_hash: Option<H256>,
+    log_index: Option<U256>,
+    #[serde(default)]
+    removed: bool,
+}
+
+/// Uniquely identifies a delivered item
+pub(super) type ItemId = (Option<H256>, Option<U256>, bool);
+
+/// A notification together with the metadata used for deduplication
+#[derive(Debug)]
+pub(super) struct Item {
+    pub cursor: Option<Cursor>,
+    pub id: ItemId,
+}
+
+impl Item {
+    pub(super) fn parse(kind: &SubKind, raw: &RawValue) -> Option<Self> {
+        if !kind.supports_backfill() {
+            return None
+        }
+        let meta: ItemMeta = serde_json::from_str(raw.get()).ok()?;
+        let item = match kind {
+            SubKind::NewHeads => Item {
+                cursor: meta.number.map(|block| Cursor { block, log_index: U256::zero() }),
+                id: (meta.hash, None, false),
+            },
+            _ => Item {
+                cursor: meta
+                    .block_number
+                    .zip(meta.log_index)
+                    .map(|(block, log_index)| Cursor { block, log_index }),
+                id: (meta.block_hash, meta.log_index, meta.removed),
+            },
+        };
+        Some(item)
+    }
+}
+
+/// Backfill progress of a single subscription
+#[derive(Debug, Default)]
+pub(super) enum BackfillState {
+    /// Notifications are delivered as they arrive
+    #[default]
+    Idle,
+    /// A backfill task is running, notifications are buffered until it completes
+    Running {
+        /// Whether another reconnect happened while the task was running, in which case a new
+        /// backfill is started once this one completes
+        rerun: bool,
+        buffer: Vec<Box<RawValue>>,
+    },
+}
+
+/// Returns the items which were not delivered yet, and adds them to the `delivered` ones
+pub(super) fn dedup(
+    kind: &SubKind,
+    delivered: &mut HashSet<ItemId>,
+    items: Vec<Box<RawValue>>,
+) -> Vec<Box<RawValue>> {
+    items
+        .into_iter()
+        .filter(|raw| Item::parse(kind, raw).map(|item| delivered.insert(item.id)).unwrap_or(true))
+        .collect()
+}
+
+/// Spawns a task which fetches everything after `cursor` and reports back to the manager via
+/// [`Instruction::Backfilled`]
+pub(super) fn spawn_backfill(
+    requests: mpsc::UnboundedSender<Instruction>,
+    id: u64,
+    kind: SubKind,
+    cursor: Cursor,
+) {
+    let fut = async move {
+        let items = fetch_missed(&requests, &kind, cursor).await;
+        match &items {
+            Ok(items) => tracing::debug!(id, count = items.len(), "Backfill complete"),
+            Err(err) => {
+                tracing::error!(id, %err, "Failed to backfill subscription after reconnect")
+            }
+        }
+        let _ = requests.unbounded_send(Instruction::Backfilled { id, items });
+    };
+
+    #[cfg(target_arch = "wasm32")]
+    super::spawn_local(fut);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    tokio::spawn(fut);
+}
+
+async fn request<P: Serialize>(
+    requests: &mpsc::UnboundedSender<Instruction>,
+    method: &str,
+    params: P,
+) -> Result<Box<RawValue>, WsClientError> {
+    let (sender, rx) = oneshot::channel();
+    let params = to_raw_value(&params)?;
+    requests
+        .unbounded_send(Instruction::Request { method: method.to_string(), params, sender })
+        .map_err(|_| WsClientError::DeadChannel)?;
+    Ok(rx.await.map_err(|_| WsClientError::UnexpectedClose)??)
+}
+
+/// Fetches all headers or logs after the `cursor`
+async fn fetch_missed(
+    requests: &mpsc::UnboundedSender<Instruction>,
+    kind: &SubKind,
+    cursor: Cursor,
+) -> Result<Vec<Box<RawValue>>, WsClientError> {
+    let head: U64 = serde_json::from_str(request(requests, "eth_blockNumber", ()).await?.get())?;
+    let from = backfill_start(kind, cursor, head);
+    if from > head {
+        return Ok(Vec::new())
+    }
+
+    match kind {
+        SubKind::NewHeads => {
+            let mut blocks = stream::iter(from.as_u64()..=head.as_u64())
+                .map(|number| request(requests, "eth_getBlockByNumber", (U64::from(number), false)))
+                .buffered(BACKFILL_CONCURRENCY);
+            let mut headers = Vec::new();
+            while let Some(block) = blocks.next().await {
+                let block = block?;
+                if block.get() != "null" {
+                    headers.push(block);
+                }
+            }
+            Ok(headers)
+        }
+        SubKind::Logs(filter) => {
+            let mut filter = filter.clone();
+            // a filter for the logs of a single block is passed through as is
+            if !is_at_block_hash(&filter) {
+                if let Value::Object(ref mut filter) = filter {
+                    filter.insert("fromBlock".to_string(), serde_json::to_value(from)?);
+                    filter.insert("toBlock".to_string(), serde_json::to_value(head)?);
+                }
+            }
+            let logs = request(requests, "eth_getLogs", [filter]).await?;
+            let logs: Vec<Box<RawValue>> = serde_json::from_str(logs.get())?;
+            Ok(logs
+                .into_iter()
+                .filter(|raw| {
+                    Item::parse(kind, raw)
+                        .and_then(|item| item.cursor)
+                        .map(|pos| pos > cursor)
+                        .unwrap_or(true)
+                })
+                .collect())
+        }
+        SubKind::Other => Ok(Vec::new()),
+    }
+}
+
+/// Returns whether the log `filter` selects a block by its hash, which can not be combined with a
+/// block range
+fn is_at_block_hash(filter: &Value) -> bool {
+    matches!(
+        serde_json::from_value::<Filter>(filter.clone()),
+        Ok(Filter { block_option: FilterBlockOption::AtBlockHash(_), .. })
+    )
+}
+
+/// Returns the first block to backfill.
+///
+/// Headers are fetched starting with the block after the cursor. The block of the cursor is
+/// included for logs since it may have been delivered only partially.
+fn backfill_start(kind: &SubKind, cursor: Cursor, head: U64) -> U64 {
+    let from = match kind {
+        SubKind::NewHeads => cursor.block + 1,
+        _ => cursor.block,
+    };
+    let earliest = (head + 1).saturating_sub(U64::from(MAX_BACKFILL_BLOCKS));
+    from.max(earliest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(s: &str) -> Box<RawValue> {
+        RawValue::from_string(s.to_string()).unwrap()
+    }
+
+    #[test]
+    fn parses_sub_kind() {
+        assert_eq!(SubKind::from_params(&raw(r#"["newHeads"]"#)), SubKind::NewHeads);
+        assert_eq!(
+            SubKind::from_params(&raw(r#"["logs",{"address":"0x01"}]"#)),
+            SubKind::Logs(serde_json::json!({"address": "0x01"}))
+        );
+        assert_eq!(SubKind::from_params(&raw(r#"["logs"]"#)), SubKind::Logs(serde_json::json!({})));
+        assert_eq!(SubKind::from_params(&raw(r#"["newPendingTransactions"]"#)), SubKind::Other);
+    }
+
+    #[test]
+    fn parses_items() {
+        let header = Item::parse(
+            &SubKind::NewHeads,
+            &raw(r#"{"number":"0x10","hash":"0x0000000000000000000000000000000000000000000000000000000000000001"}"#),
+        )
+        .unwrap();
+        assert_eq!(header.cursor, Some(Cursor { block: 16.into(), log_index: U256::zero() }));
+        assert_eq!(header.id, (Some(H256::from_low_u64_be(1)), None, false));
+
+        let log = Item::parse(
+            &SubKind::Logs(Value::Null),
+            &raw(r#"{"blockNumber":"0x10","blockHash":"0x0000000000000000000000000000000000000000000000000000000000000001","logIndex":"0x2","removed":true}"#),
+        )
+        .unwrap();
+        assert_eq!(log.cursor, Some(Cursor { block: 16.into(), log_index: 2.into() }));
+        assert_eq!(log.id, (Some(H256::from_low_u64_be(1)), Some(2.into()), true));
+
+        assert!(Item::parse(&SubKind::Other, &raw(r#""0x01""#)).is_none());
+    }
+
+    #[test]
+    fn detects_block_hash_filters() {
+        assert!(is_at_block_hash(&serde_json::json!({
+            "blockHash": H256::from_low_u64_be(1),
+            "address": "0x0000000000000000000000000000000000000001"
+        })));
+        assert!(!is_at_block_hash(&serde_json::json!({
+            "address": "0x0000000000000000000000000000000000000001"
+        })));
+        assert!(!is_at_block_hash(&serde_json::json!({})));
+    }
+
+    #[test]
+    fn computes_backfill_start() {
+        let cursor = Cursor { block: 100.into(), log_index: 3.into() };
+        assert_eq!(backfill_start(&SubKind::NewHeads, cursor, 110.into()), 101.into());
+        assert_eq!(backfill_start(&SubKind::Logs(Value::Null), cursor, 110.into()), 100.into());
+        assert_eq!(
+            backfill_start(&SubKind::NewHeads, cursor, (100 + 2 * MAX_BACKFILL_BLOCKS).into()),
+            (101 + MAX_BACKFILL_BLOCKS).into()
+        );
+    }
+
+    #[test]
+    fn dedups_delivered_items() {
+        let header = |n: u64| {
+            raw(&format!(r#"{{"number":"{:#x}","hash":"{:?}"}}"#, n, H256::from_low_u64_be(n)))
+        };
+        let mut delivered: HashSet<_> =
+            [Item::parse(&SubKind::NewHeads, &header(1)).unwrap().id].into_iter().collect();
+        let items = dedup(&SubKind::NewHeads, &mut delivered, vec![header(1), header(2)]);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].get(), header(2).get());
+        let buffered =
+            dedup(&SubKind::NewHeads, &mut delivered, vec![header(2), header(3), header(3)]);
+        assert_eq!(buffered.len(), 1);
+        assert_eq!(buffered[0].get(), header(3).get());
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    mod reconnect {
+        use super::*;
+        use crate::{Middleware, Provider};
+        use futures_util::{SinkExt, StreamExt};
+        use serde_json::json;
+        use std::time::Duration;
+        use tokio::net::{TcpListener, TcpStream};
+        use tokio_tungstenite::{accept_async, tungstenite::Message, WebSocketStream};
+
+        type Server = WebSocketStream<TcpStream>;
+
+        fn head(n: u64) -> Value {
+            json!({ "number": format!("{n:#x}"), "hash": H256::from_low_u64_be(n) })
+        }
+
+        async fn next_request(ws: &mut Server) -> Option<Value> {
+            while let Some(Ok(msg)) = ws.next().await {
+                if let Message::Text(text) = msg {
+                    return serde_json::from_str(&text).ok()
+                }
+            }
+            None
+        }
+
+        async fn send(ws: &mut Server, msg: Value) {
+            ws.send(Message::Text(msg.to_string())).await.unwrap();
+        }
+
+        async fn respond(ws: &mut Server, req: &Value, result: Value) {
+            send(ws, json!({ "jsonrpc": "2.0", "id": req["id"], "result": result })).await;
+        }
+
+        async fn notify(ws: &mut Server, sub: &str, result: Value) {
+            let params = json!({ "subscription": sub, "result": result });
+            send(ws, json!({ "jsonrpc": "2.0", "method": "eth_subscription", "params": params }))
+                .await;
+        }
+
+        /// Serves a connection which delivers block 1 and goes away, then a connection on which
+        /// blocks 2 and 3 were missed and which replays block 1 when subscribing. If `fail` is
+        /// set, the missed blocks can not be fetched.
+        async fn serve(fail: bool) -> String {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+
+            tokio::spawn(async move {
+                let mut ws = accept_async(listener.accept().await.unwrap().0).await.unwrap();
+                let req = next_request(&mut ws).await.unwrap();
+                assert_eq!(req["method"], "eth_subscribe");
+                respond(&mut ws, &req, json!("0x1")).await;
+                notify(&mut ws, "0x1", head(1)).await;
+                ws.close(None).await.unwrap();
+
+                let mut ws = accept_async(listener.accept().await.unwrap().0).await.unwrap();
+                while let Some(req) = next_request(&mut ws).await {
+                    match req["method"].as_str().unwrap() {
+                        "eth_subscribe" => {
+                            respond(&mut ws, &req, json!("0x2")).await;
+                            notify(&mut ws, "0x2", head(1)).await;
+                            notify(&mut ws, "0x2", head(3)).await;
+                            notify(&mut ws, "0x2", head(4)).await;
+                        }
+                        "eth_blockNumber" if fail => {
+                            let error = json!({ "code": -32000, "message": "unavailable" });
+                            send(
+                                &mut ws,
+                                json!({ "jsonrpc": "2.0", "id": req["id"], "error": error }),
+                            )
+                            .await
+                        }
+                        "eth_blockNumber" => respond(&mut ws, &req, json!("0x3")).await,
+                        "eth_getBlockByNumber" => {
+                            let number: U64 =
+                                serde_json::from_value(req["params"][0].clone()).unwrap();
+                            respond(&mut ws, &req, head(number.as_u64())).await
+                        }
+                        _ => respond(&mut ws, &req, json!(true)).await,
+                    }
+                }
+            });
+
+            format!("ws://{addr}")
+        }
+
+        #[tokio::test]
+        async fn backfills_missed_heads_after_reconnect() {
+            let provider = Provider::connect(serve(false).await).await.unwrap();
+            let stream = provider.subscribe::<_, Value>(["newHeads"]).await.unwrap();
+            let heads: Vec<Value> =
+                tokio::time::timeout(Duration::from_secs(10), stream.take(4).collect())
+                    .await
+                    .unwrap();
+            // the replayed block 1 is skipped
+            assert_eq!(heads, vec![head(1), head(2), head(3), head(4)]);
+        }
+
+        #[tokio::test]
+        async fn ends_subscriptions_which_can_not_be_backfilled() {
+            let provider = Provider::connect(serve(true).await).await.unwrap();
+            let stream = provider.subscribe::<_, Value>(["newHeads"]).await.unwrap();
+            let heads: Vec<Value> =
+                tokio::time::timeout(Duration::from_secs(10), stream.collect()).await.unwrap();
+            // the stream ends instead of skipping blocks 2 and 3
+            assert_eq!(heads, vec![head(1)]);
+        }
+    }
+}
@@ -2,6 +2,7 @@
 use super::WebSocketConfig;
 use super::{
     backend::{BackendDriver, WsBackend},
+    backfill::{dedup, spawn_backfill, BackfillState},
     ActiveSub, ConnectionDetails, InFlight, Instruction, Notification, PubSubItem, Response, SubId,
     WsClient, WsClientError,
 };
@@ -11,7 +12,7 @@ use futures_channel::{mpsc, oneshot};
 use futures_util::{select_biased, StreamExt};
 use serde_json::value::{to_raw_value, RawValue};
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, HashMap, HashSet},
     sync::{
         atomic::{AtomicU64, Ordering},
         Arc, Mutex,
@@ -103,16 +104,22 @@ impl SubscriptionManager {
         let id = id_opt.unwrap();
 
         // alias exists, or should be dropped from alias table
-        let sub_opt = self.subs.get(&id);
-        if sub_opt.is_none() {
+        let Some(active) = self.subs.get_mut(&id) else {
             tracing::trace!(id, "Aliased subscription found, but not active");
             self.aliases.remove(&server_id);
+            return
+        };
+
+        // hold back live notifications until the missed ones have been backfilled
+        if let BackfillState::Running { buffer, .. } = &mut active.backfill {
+            tracing::trace!(id, "Buffering notification during backfill");
+            buffer.push(notification.result);
+            return
         }
-        let active = sub_opt.unwrap();
 
         tracing::debug!(id, "Forwarding notification to listener");
         // send the notification over the channel
-        let send_res = active.channel.unbounded_send(notification.result);
+        let send_res = active.deliver(notification.result);
 
         // receiver has dropped, so we drop the sub
         if send_res.is_err() {
@@ -149,7 +156,7 @@ impl SubscriptionManager {
     ) -> Result<Box<RawValue>, WsClientError> {
         let (tx, rx) = mpsc::unbounded();
 
-        let active_sub = ActiveSub { params, channel: tx, current_server_id: None };
+        let active_sub = ActiveSub::new(params, tx);
         let req = active_sub.serialize_raw(id)?;
 
         // Explicit scope for the lock
@@ -162,6 +169,75 @@ impl SubscriptionManager {
 
         Ok(req)
     }
+
+    /// Starts backfilling all subscriptions which have delivered at least one item, so that items
+    /// emitted while disconnected are not lost
+    fn start_backfills(&mut self, requests: &mpsc::UnboundedSender<Instruction>) {
+        let ids: Vec<u64> = self.subs.keys().copied().collect();
+        for id in ids {
+            self.start_backfill(id, requests);
+        }
+    }
+
+    fn start_backfill(&mut self, id: u64, requests: &mpsc::UnboundedSender<Instruction>) {
+        let Some(sub) = self.subs.get_mut(&id) else { return };
+        // only `newHeads` and `logs` subscriptions track a cursor
+        let Some(cursor) = sub.cursor else { return };
+        match &mut sub.backfill {
+            BackfillState::Running { rerun, .. } => *rerun = true,
+            state @ BackfillState::Idle => {
+                tracing::debug!(id, ?cursor, "Backfilling subscription");
+                *state = BackfillState::Running { rerun: false, buffer: Vec::new() };
+                spawn_backfill(requests.clone(), id, sub.kind.clone(), cursor);
+            }
+        }
+    }
+
+    /// Delivers the backfilled items followed by the notifications buffered in the meantime.
+    ///
+    /// If the backfill failed, the subscription is ended so that its listener notices the gap, and
+    /// the unsubscribe request to dispatch is returned.
+    #[tracing::instrument(skip(self, items, requests))]
+    fn complete_backfill(
+        &mut self,
+        id: u64,
+        items: Result<Vec<Box<RawValue>>, WsClientError>,
+        requests: &mpsc::UnboundedSender<Instruction>,
+    ) -> Option<Box<RawValue>> {
+        let Ok(items) = items else {
+            tracing::debug!(id, "Ending subscription which could not be backfilled");
+            return self.end_subscription(id)
+        };
+        let sub = self.subs.get_mut(&id)?;
+        let BackfillState::Running { rerun, buffer } = std::mem::take(&mut sub.backfill) else {
+            return None
+        };
+
+        let mut delivered: HashSet<_> = sub.recent.iter().copied().collect();
+        let items = dedup(&sub.kind, &mut delivered, items);
+        let buffered = dedup(&sub.kind, &mut delivered, buffer);
+        tracing::debug!(
+            backfilled = items.len(),
+            buffered = buffered.len(),
+            "Resuming subscription"
+        );
+        for item in items.into_iter().chain(buffered) {
+            if sub.deliver(item).is_err() {
+                tracing::debug!(id, "Listener dropped. Dropping alias and subs");
+                if let Some(server_id) = sub.current_server_id {
+                    self.aliases.remove(&server_id);
+                }
+                self.subs.remove(&id);
+                return None
+            }
+        }
+
+        if rerun {
+            // reconnected again while backfilling, the new gap needs to be filled as well
+            self.start_backfill(id, requests);
+        }
+        None
+    }
 }
 
 /// The `RequestManager` holds copies of all pending requests (as `InFlight`),
@@ -203,6 +279,8 @@ pub struct RequestManager {
     config: Option<WebSocketConfig>,
     // Instructions from the user-facing providers
     instructions: mpsc::UnboundedReceiver<Instruction>,
+    // Instructions from the backfill tasks spawned after reconnects
+    internal: (mpsc::UnboundedSender<Instruction>, mpsc::UnboundedReceiver<Instruction>),
 }
 
 impl RequestManager {
@@ -248,6 +326,7 @@ impl RequestManager {
                 backend,
                 conn,
                 instructions: instructions_rx,
+                internal: mpsc::unbounded(),
             },
             WsClient { instructions: instructions_tx, channel_map },
         ))
@@ -271,6 +350,7 @@ impl RequestManager {
                 conn,
                 config: None,
                 instructions: instructions_rx,
+                internal: mpsc::unbounded(),
             },
             WsClient { instructions: instructions_tx, channel_map },
         ))
@@ -303,6 +383,7 @@ impl RequestManager {
                 conn,
                 config: Some(config),
                 instructions: instructions_rx,
+                internal: mpsc::unbounded(),
             },
             WsClient { instructions: instructions_tx, channel_map },
         ))
@@ -375,6 +456,9 @@ impl RequestManager {
         }
         tracing::info!(subs = self.subs.count(), reqs = req_cnt, "Re-connection complete");
 
+        // recover notifications emitted while we were disconnected
+        self.subs.start_backfills(&self.internal.0);
+
         Ok(())
     }
 
@@ -448,6 +532,14 @@ impl RequestManager {
                         .map_err(|_| WsClientError::DeadChannel)?;
                 }
             }
+            Instruction::Backfilled { id, items } => {
+                if let Some(req) = self.subs.complete_backfill(id, items, &self.internal.0) {
+                    self.backend
+                        .dispatcher
+                        .unbounded_send(req)
+                        .map_err(|_| WsClientError::DeadChannel)?;
+                }
+            }
         }
         Ok(())
     }
@@ -473,6 +565,12 @@ impl RequestManager {
                             break Err(e);
                         }
                     },
+                    // we hold a sender, so this never terminates
+                    inst_opt = self.internal.1.next() => {
+                        if let Some(instruction) = inst_opt {
+                            if let Err(e) = self.service_instruction(instruction) { break Err(e) }
+                        }
+                    },
                     inst_opt = self.instructions.next() => {
                         match inst_opt {
                             Some(instruction) => if let Err(e) = self.service_instruction(instruction) { break Err(e)},
@@ -2,6 +2,8 @@
 
 mod backend;
 
+mod backfill;
+
 mod manager;
 
 use manager::{RequestManager, SharedChannelMap};
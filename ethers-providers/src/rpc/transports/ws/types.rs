@@ -1,10 +1,14 @@
-use std::fmt;
+use std::{collections::VecDeque, fmt};
 
 use ethers_core::types::U256;
 use futures_channel::{mpsc, oneshot};
 use serde::{de, Deserialize};
 use serde_json::value::{to_raw_value, RawValue};
 
+use super::{
+    backfill::{BackfillState, Cursor, Item, ItemId, SubKind, RECENT_ITEMS},
+    WsClientError,
+};
 use crate::{common::Request, JsonRpcError};
 
 // Normal JSON-RPC response
@@ -224,9 +228,49 @@ pub(super) struct ActiveSub {
     pub params: Box<RawValue>,
     pub channel: mpsc::UnboundedSender<Box<RawValue>>,
     pub current_server_id: Option<U256>,
+    pub kind: SubKind,
+    // Position of the last delivered item, used to backfill after reconnects
+    pub cursor: Option<Cursor>,
+    // Ids of the most recently delivered items, so that they are not delivered again after
+    // reconnects
+    pub recent: VecDeque<ItemId>,
+    pub backfill: BackfillState,
 }
 
 impl ActiveSub {
+    pub(super) fn new(
+        params: Box<RawValue>,
+        channel: mpsc::UnboundedSender<Box<RawValue>>,
+    ) -> Self {
+        let kind = SubKind::from_params(&params);
+        Self {
+            params,
+            channel,
+            current_server_id: None,
+            kind,
+            cursor: None,
+            recent: VecDeque::new(),
+            backfill: BackfillState::Idle,
+        }
+    }
+
+    /// Forwards the item to the listener and advances the cursor
+    pub(super) fn deliver(
+        &mut self,
+        item: Box<RawValue>,
+    ) -> Result<(), mpsc::TrySendError<Box<RawValue>>> {
+        if let Some(parsed) = Item::parse(&self.kind, &item) {
+            if let Some(pos) = parsed.cursor {
+                self.cursor = Some(self.cursor.map_or(pos, |cursor| cursor.max(pos)));
+            }
+            if self.recent.len() == RECENT_ITEMS {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(parsed.id);
+        }
+        self.channel.unbounded_send(item)
+    }
+
     pub(super) fn to_request(&self, id: u64) -> Request<'static, Box<RawValue>> {
         Request::new(id, "eth_subscribe", self.params.clone())
     }
@@ -242,6 +286,9 @@ pub enum Instruction {
     Request { method: String, params: Box<RawValue>, sender: oneshot::Sender<Response> },
     /// Cancel an existing subscription
     Unsubscribe { id: U256 },
+    /// Items missed by a subscription while disconnected, or the error which prevented fetching
+    /// them
+    Backfilled { id: u64, items: Result<Vec<Box<RawValue>>, WsClientError> },
 }
 
 #[cfg(target_arch = "wasm32")]
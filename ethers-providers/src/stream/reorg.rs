@@ -0,0 +1,431 @@
+use crate::{JsonRpcClient, Middleware, Provider, ProviderError};
+use ethers_core::types::{Block, Filter, Log, TxHash, H256};
+use futures_core::stream::Stream;
+use futures_util::{stream, StreamExt};
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// The default number of recent blocks a [`ReorgAwareBlockStream`] keeps track of
+pub const DEFAULT_REORG_WINDOW: usize = 64;
+
+#[cfg(target_arch = "wasm32")]
+type BoxEventStream<'a, T> = Pin<Box<dyn Stream<Item = Result<T, ReorgStreamError>> + 'a>>;
+#[cfg(not(target_arch = "wasm32"))]
+type BoxEventStream<'a, T> = Pin<Box<dyn Stream<Item = Result<T, ReorgStreamError>> + Send + 'a>>;
+
+/// A change of the canonical chain
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BlockEvent {
+    /// The block was added to the canonical chain
+    New(Block<TxHash>),
+    /// The block was removed from the canonical chain by a reorg
+    Reverted(Block<TxHash>),
+}
+
+impl BlockEvent {
+    /// Returns the block of the event
+    pub fn block(&self) -> &Block<TxHash> {
+        match self {
+            BlockEvent::New(block) | BlockEvent::Reverted(block) => block,
+        }
+    }
+}
+
+/// Errors `ReorgAwareBlockStream` and `ReorgAwareLogStream` can throw
+#[derive(Debug, thiserror::Error)]
+pub enum ReorgStreamError {
+    /// Failed to fetch a block or logs
+    #[error(transparent)]
+    ProviderError(#[from] ProviderError),
+    /// An ancestor of a new block could not be found
+    #[error("Block `{0:?}` not found")]
+    BlockNotFound(H256),
+    /// The common ancestor of the old and the new chain is older than the tracked window. The
+    /// stream continues on the new chain without emitting [`BlockEvent::Reverted`] events: the
+    /// blocks of the new chain fetched back to the tracked window follow this error as
+    /// [`BlockEvent::New`] events.
+    #[error("Reorg is deeper than the tracked window of {0} blocks")]
+    TooDeep(usize),
+}
+
+type EventQueue = VecDeque<Result<BlockEvent, ReorgStreamError>>;
+
+/// The most recent blocks of the canonical chain, oldest first
+#[derive(Debug)]
+struct ChainWindow {
+    blocks: VecDeque<Block<TxHash>>,
+    depth: usize,
+}
+
+impl ChainWindow {
+    fn new(depth: usize) -> Self {
+        Self { blocks: VecDeque::new(), depth: depth.max(1) }
+    }
+
+    fn position(&self, hash: H256) -> Option<usize> {
+        self.blocks.iter().position(|block| block.hash == Some(hash))
+    }
+
+    fn push(&mut self, block: Block<TxHash>) {
+        self.blocks.push_back(block);
+        while self.blocks.len() > self.depth {
+            self.blocks.pop_front();
+        }
+    }
+
+    /// Appends the block to the window and queues the resulting chain events.
+    ///
+    /// If the block does not extend the current tip, its ancestors are fetched until a block of the
+    /// window is found. Blocks of the window after this common ancestor are reverted. If no common
+    /// ancestor is found within the window, a [`ReorgStreamError::TooDeep`] error is queued
+    /// before the blocks of the new chain.
+    async fn advance<P: JsonRpcClient>(
+        &mut self,
+        provider: &Provider<P>,
+        block: Block<TxHash>,
+        events: &mut EventQueue,
+    ) -> Result<(), ReorgStreamError> {
+        // pending blocks are not part of the chain yet
+        let (Some(hash), Some(_)) = (block.hash, block.number) else { return Ok(()) };
+        if self.position(hash).is_some() {
+            return Ok(())
+        }
+        let Some(oldest) = self.blocks.front().and_then(|block| block.number) else {
+            self.push(block.clone());
+            events.push_back(Ok(BlockEvent::New(block)));
+            return Ok(())
+        };
+
+        // walk back until we find the common ancestor, which also fills gaps in the stream
+        let mut chain = vec![block];
+        let ancestor = loop {
+            let last = chain.last().expect("not empty");
+            if let Some(pos) = self.position(last.parent_hash) {
+                break Some(pos)
+            }
+            if last.number.map_or(true, |number| number <= oldest) {
+                break None
+            }
+            let parent_hash = last.parent_hash;
+            let parent = provider
+                .get_block(parent_hash)
+                .await?
+                .ok_or(ReorgStreamError::BlockNotFound(parent_hash))?;
+            chain.push(parent);
+        };
+        chain.reverse();
+
+        match ancestor {
+            Some(pos) => {
+                events.extend(self.blocks.drain(pos + 1..).rev().map(BlockEvent::Reverted).map(Ok))
+            }
+            None => {
+                // start over on the new chain
+                self.blocks.clear();
+                events.push_back(Err(ReorgStreamError::TooDeep(self.depth)));
+            }
+        }
+        for block in chain {
+            self.push(block.clone());
+            events.push_back(Ok(BlockEvent::New(block)));
+        }
+        Ok(())
+    }
+}
+
+/// Turns a stream of blocks, e.g. from [`Middleware::subscribe_blocks`], into a stream of
+/// [`BlockEvent`]s.
+///
+/// The stream keeps a window of recent blocks. If a new block's parent is not the current tip, its
+/// ancestors are fetched until the common ancestor is found: the blocks of the old chain are
+/// emitted as [`BlockEvent::Reverted`] (newest first), followed by the blocks of the new chain as
+/// [`BlockEvent::New`] (oldest first). Blocks which were skipped by the inner stream are fetched
+/// and emitted as well.
+///
+/// # Example
+///
+/// ```no_run
+/// use ethers_providers::{BlockEvent, Middleware, Provider, ReorgAwareBlockStream, StreamExt, Ws};
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let provider = Provider::<Ws>::connect("ws://localhost:8546").await?;
+/// let blocks = provider.subscribe_blocks().await?;
+/// let mut stream = ReorgAwareBlockStream::new(&provider, blocks);
+/// while let Some(event) = stream.next().await {
+///     match event? {
+///         BlockEvent::New(block) => println!("new block {:?}", block.number),
+///         BlockEvent::Reverted(block) => println!("reverted block {:?}", block.number),
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[must_use = "streams do nothing unless polled"]
+pub struct ReorgAwareBlockStream<'a, P> {
+    provider: &'a Provider<P>,
+    depth: usize,
+    inner: BoxEventStream<'a, BlockEvent>,
+}
+
+impl<'a, P> ReorgAwareBlockStream<'a, P>
+where
+    P: JsonRpcClient,
+{
+    /// Creates a new stream which tracks the last [`DEFAULT_REORG_WINDOW`] blocks
+    pub fn new<St>(provider: &'a Provider<P>, blocks: St) -> Self
+    where
+        St: Stream<Item = Block<TxHash>> + Send + Unpin + 'a,
+    {
+        Self::with_window(provider, blocks, DEFAULT_REORG_WINDOW)
+    }
+
+    /// Creates a new stream which tracks the last `depth` blocks, reorgs deeper than that result
+    /// in a [`ReorgStreamError::TooDeep`] error
+    pub fn with_window<St>(provider: &'a Provider<P>, blocks: St, depth: usize) -> Self
+    where
+        St: Stream<Item = Block<TxHash>> + Send + Unpin + 'a,
+    {
+        Self { provider, depth, inner: Box::pin(block_events(provider, blocks, depth)) }
+    }
+
+    /// Returns a stream of the logs matching `filter` in the canonical chain, see
+    /// [`ReorgAwareLogStream`]
+    pub fn logs(self, filter: &Filter) -> ReorgAwareLogStream<'a> {
+        ReorgAwareLogStream::new(self, filter)
+    }
+}
+
+impl<'a, P> Stream for ReorgAwareBlockStream<'a, P> {
+    type Item = Result<BlockEvent, ReorgStreamError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+fn block_events<'a, P, St>(
+    provider: &'a Provider<P>,
+    blocks: St,
+    depth: usize,
+) -> impl Stream<Item = Result<BlockEvent, ReorgStreamError>> + 'a
+where
+    P: JsonRpcClient,
+    St: Stream<Item = Block<TxHash>> + Unpin + 'a,
+{
+    let state = (blocks, ChainWindow::new(depth), VecDeque::new());
+    stream::unfold(state, move |(mut blocks, mut window, mut pending)| async move {
+        loop {
+            if let Some(event) = pending.pop_front() {
+                return Some((event, (blocks, window, pending)))
+            }
+            let block = blocks.next().await?;
+            if let Err(err) = window.advance(provider, block, &mut pending).await {
+                return Some((Err(err), (blocks, window, pending)))
+            }
+        }
+    })
+}
+
+/// A stream of the logs matching a filter in the canonical chain, driven by a
+/// [`ReorgAwareBlockStream`].
+///
+/// The logs of every new block are fetched via `eth_getLogs`. If a block is reverted, the logs
+/// previously emitted for it are emitted again in reverse order, with `removed` set to `true`.
+#[must_use = "streams do nothing unless polled"]
+pub struct ReorgAwareLogStream<'a> {
+    inner: BoxEventStream<'a, Log>,
+}
+
+impl<'a> ReorgAwareLogStream<'a> {
+    /// Creates a new log stream on top of the given block stream
+    pub fn new<P: JsonRpcClient>(blocks: ReorgAwareBlockStream<'a, P>, filter: &Filter) -> Self {
+        let provider = blocks.provider;
+        let depth = blocks.depth;
+        let filter = filter.clone();
+        // logs of the blocks in the window, oldest first
+        let emitted: VecDeque<(H256, Vec<Log>)> = VecDeque::new();
+        let state = (blocks, emitted, VecDeque::new());
+        let inner = stream::unfold(state, move |(mut blocks, mut emitted, mut pending)| {
+            let filter = filter.clone();
+            async move {
+                loop {
+                    if let Some(log) = pending.pop_front() {
+                        return Some((Ok(log), (blocks, emitted, pending)))
+                    }
+                    let event = match blocks.next().await? {
+                        Ok(event) => event,
+                        Err(err) => return Some((Err(err), (blocks, emitted, pending))),
+                    };
+                    let Some(hash) = event.block().hash else { continue };
+                    match event {
+                        BlockEvent::New(_) => {
+                            let logs = match provider
+                                .get_logs(&filter.clone().at_block_hash(hash))
+                                .await
+                            {
+                                Ok(logs) => logs,
+                                Err(err) => {
+                                    return Some((Err(err.into()), (blocks, emitted, pending)))
+                                }
+                            };
+                            pending.extend(logs.iter().cloned());
+                            emitted.push_back((hash, logs));
+                            while emitted.len() > depth {
+                                emitted.pop_front();
+                            }
+                        }
+                        BlockEvent::Reverted(_) => {
+                            if let Some(pos) = emitted.iter().position(|(h, _)| *h == hash) {
+                                let (_, logs) = emitted.remove(pos).expect("exists");
+                                pending.extend(logs.into_iter().rev().map(|mut log| {
+                                    log.removed = Some(true);
+                                    log
+                                }));
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        Self { inner: Box::pin(inner) }
+    }
+}
+
+impl<'a> Stream for ReorgAwareLogStream<'a> {
+    type Item = Result<Log, ReorgStreamError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::{MockProvider, MockResponse};
+    use ethers_core::types::U64;
+
+    fn block(number: u64, fork: u64) -> Block<TxHash> {
+        let hash = |number: u64, fork: u64| H256::from_low_u64_be(number * 100 + fork);
+        // forks share their history up to block 2
+        let parent_fork = if number <= 3 { 0 } else { fork };
+        Block {
+            hash: Some(hash(number, fork)),
+            parent_hash: hash(number - 1, parent_fork),
+            number: Some(U64::from(number)),
+            ..Default::default()
+        }
+    }
+
+    fn serve_blocks(mock: &MockProvider, blocks: Vec<Block<TxHash>>) {
+        for block in blocks {
+            let hash = block.hash.unwrap();
+            mock.on_match(
+                "eth_getBlockByHash",
+                move |params| params[0] == serde_json::json!(hash),
+                move |_| MockResponse::Value(serde_json::to_value(&block).unwrap()),
+            );
+        }
+    }
+
+    fn number(event: &BlockEvent) -> (bool, u64, u64) {
+        let block = event.block();
+        let hash = block.hash.unwrap().to_low_u64_be();
+        (matches!(event, BlockEvent::New(_)), block.number.unwrap().as_u64(), hash % 100)
+    }
+
+    #[tokio::test]
+    async fn emits_reverted_blocks() {
+        let (provider, mock) = Provider::mocked();
+        serve_blocks(&mock, vec![block(3, 1), block(5, 1)]);
+
+        let blocks = stream::iter(vec![
+            block(1, 0),
+            block(2, 0),
+            block(3, 0),
+            // reorg replacing block 3
+            block(4, 1),
+            // duplicate
+            block(4, 1),
+            // skips block 5
+            block(6, 1),
+        ]);
+        let events: Vec<_> = ReorgAwareBlockStream::new(&provider, blocks)
+            .map(|event| number(&event.unwrap()))
+            .collect()
+            .await;
+        assert_eq!(
+            events,
+            vec![
+                (true, 1, 0),
+                (true, 2, 0),
+                (true, 3, 0),
+                (false, 3, 0),
+                (true, 3, 1),
+                (true, 4, 1),
+                (true, 5, 1),
+                (true, 6, 1),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn errors_on_deep_reorgs() {
+        let (provider, mock) = Provider::mocked();
+        serve_blocks(&mock, vec![block(3, 1)]);
+
+        let blocks = stream::iter(vec![block(2, 0), block(3, 0), block(4, 1), block(5, 1)]);
+        let events: Vec<_> =
+            ReorgAwareBlockStream::with_window(&provider, blocks, 1).collect().await;
+        assert_eq!(events.len(), 6);
+        assert!(matches!(events[2], Err(ReorgStreamError::TooDeep(1))));
+        // continues on the new chain, including the blocks fetched while looking for the ancestor
+        let events: Vec<_> =
+            events[3..].iter().map(|event| number(event.as_ref().unwrap())).collect();
+        assert_eq!(events, vec![(true, 3, 1), (true, 4, 1), (true, 5, 1)]);
+    }
+
+    #[tokio::test]
+    async fn fetches_logs_of_the_new_chain_after_deep_reorgs() {
+        let (provider, mock) = Provider::mocked();
+        serve_blocks(&mock, vec![block(3, 1)]);
+        mock.on("eth_getLogs", |params| {
+            let block_hash: H256 = serde_json::from_value(params[0]["blockHash"].clone()).unwrap();
+            let log = Log { block_hash: Some(block_hash), ..Default::default() };
+            MockResponse::Value(serde_json::to_value(vec![log]).unwrap())
+        });
+
+        let blocks = stream::iter(vec![block(2, 0), block(3, 0), block(4, 1), block(5, 1)]);
+        let logs: Vec<_> = ReorgAwareBlockStream::with_window(&provider, blocks, 1)
+            .logs(&Filter::new())
+            .map(|log| log.map(|log| log.block_hash.unwrap().to_low_u64_be()).ok())
+            .collect()
+            .await;
+        assert_eq!(logs, vec![Some(200), Some(300), None, Some(301), Some(401), Some(501)]);
+    }
+
+    #[tokio::test]
+    async fn re_emits_removed_logs() {
+        let (provider, mock) = Provider::mocked();
+        serve_blocks(&mock, vec![block(3, 1)]);
+        mock.on("eth_getLogs", |params| {
+            let block_hash: H256 = serde_json::from_value(params[0]["blockHash"].clone()).unwrap();
+            let log = Log { block_hash: Some(block_hash), ..Default::default() };
+            MockResponse::Value(serde_json::to_value(vec![log]).unwrap())
+        });
+
+        let blocks = stream::iter(vec![block(2, 0), block(3, 0), block(4, 1)]);
+        let logs: Vec<_> = ReorgAwareBlockStream::new(&provider, blocks)
+            .logs(&Filter::new())
+            .map(|log| {
+                let log = log.unwrap();
+                (log.block_hash.unwrap().to_low_u64_be(), log.removed.unwrap_or_default())
+            })
+            .collect()
+            .await;
+        assert_eq!(logs, vec![(200, false), (300, false), (300, true), (301, false), (401, false)]);
+    }
+}
@@ -23,6 +23,18 @@ pub trait RawCall<'a> {
     /// Sets the [state override set](https://geth.ethereum.org/docs/rpc/ns-eth#3-object---state-override-set).
     /// Note that not all client implementations will support this as a parameter.
     fn state(self, state: &'a spoof::State) -> Self;
+    /// Sets the block overrides, e.g. the block number, timestamp or base fee.
+    /// Note that not all client implementations will support this as a parameter.
+    ///
+    /// The default implementation ignores the overrides, implementors which can pass them on to
+    /// the node should override it.
+    fn block_overrides(self, overrides: &'a spoof::BlockOverrides) -> Self
+    where
+        Self: Sized,
+    {
+        let _ = overrides;
+        self
+    }
 
     /// Maps a closure `f` over the result of `.await`ing this call
     fn map<F>(self, f: F) -> Map<Self, F>
@@ -95,6 +107,11 @@ impl<'a, P> RawCall<'a> for CallBuilder<'a, P> {
     fn state(self, state: &'a spoof::State) -> Self {
         self.map_input(|call| call.input.state = Some(state))
     }
+    /// Sets the block overrides, e.g. the block number, timestamp or base fee.
+    /// Note that not all client implementations will support this as a parameter.
+    fn block_overrides(self, overrides: &'a spoof::BlockOverrides) -> Self {
+        self.map_input(|call| call.input.block_overrides = Some(overrides))
+    }
 }
 
 impl<'a, P: JsonRpcClient> Future for CallBuilder<'a, P> {
@@ -142,11 +159,12 @@ struct CallInput<'a> {
     tx: &'a TypedTransaction,
     block: Option<BlockId>,
     state: Option<&'a spoof::State>,
+    block_overrides: Option<&'a spoof::BlockOverrides>,
 }
 
 impl<'a> CallInput<'a> {
     fn new(tx: &'a TypedTransaction) -> Self {
-        Self { tx, block: None, state: None }
+        Self { tx, block: None, state: None, block_overrides: None }
     }
 }
 
@@ -155,7 +173,8 @@ impl<'a> Serialize for CallInput<'a> {
     where
         S: serde::ser::Serializer,
     {
-        let len = 2 + self.state.is_some() as usize;
+        let overrides = self.block_overrides.is_some();
+        let len = 2 + (self.state.is_some() || overrides) as usize + overrides as usize;
 
         let mut tup = serializer.serialize_tuple(len)?;
         tup.serialize_element(self.tx)?;
@@ -163,8 +182,14 @@ impl<'a> Serialize for CallInput<'a> {
         let block = self.block.unwrap_or_else(|| BlockNumber::Latest.into());
         tup.serialize_element(&block)?;
 
-        if let Some(state) = self.state {
-            tup.serialize_element(state)?;
+        // block overrides follow the state override set, which may be empty
+        match self.state {
+            Some(state) => tup.serialize_element(state)?,
+            None if overrides => tup.serialize_element(&spoof::State::default())?,
+            None => {}
+        }
+        if let Some(block_overrides) = self.block_overrides {
+            tup.serialize_element(block_overrides)?;
         }
         tup.end()
     }
@@ -211,6 +236,12 @@ where
     fn state(self, state: &'a spoof::State) -> Self {
         Self { inner: self.inner.state(state), f: self.f }
     }
+
+    /// Sets the block overrides, e.g. the block number, timestamp or base fee.
+    /// Note that not all client implementations will support this as a parameter.
+    fn block_overrides(self, overrides: &'a spoof::BlockOverrides) -> Self {
+        Self { inner: self.inner.block_overrides(overrides), f: self.f }
+    }
 }
 
 impl<T, F, Y> Future for Map<T, F>
@@ -244,10 +275,16 @@ mod tests {
         TypedTransaction,
         Option<BlockId>,
         #[serde(default)] Option<spoof::State>,
+        #[serde(default)] Option<spoof::BlockOverrides>,
     );
     impl<'a> From<&'a CallInputOwned> for CallInput<'a> {
         fn from(src: &'a CallInputOwned) -> Self {
-            Self { tx: &src.0, block: src.1, state: src.2.as_ref() }
+            Self {
+                tx: &src.0,
+                block: src.1,
+                state: src.2.as_ref(),
+                block_overrides: src.3.as_ref(),
+            }
         }
     }
 
@@ -259,7 +296,8 @@ mod tests {
         let de = CallInput::from(&de);
 
         assert_eq!(input.tx, de.tx);
-        assert_eq!(input.state, de.state);
+        assert_eq!(input.state.cloned().unwrap_or_default(), de.state.cloned().unwrap_or_default());
+        assert_eq!(input.block_overrides, de.block_overrides);
 
         let block = input.block.or_else(|| Some(BlockNumber::Latest.into()));
         assert_eq!(block, de.block);
@@ -296,6 +334,14 @@ mod tests {
         state.account(adr1);
         let call = provider.call_raw(&tx).state(&state);
         test_encode(call);
+
+        let mut overrides = spoof::BlockOverrides::default();
+        overrides.number(100.into()).time(1_700_000_000.into()).coinbase(adr2);
+        let call = provider.call_raw(&tx).block_overrides(&overrides);
+        test_encode(call);
+
+        let call = provider.call_raw(&tx).state(&state).block_overrides(&overrides);
+        test_encode(call);
     }
 
     #[tokio::test]
@@ -0,0 +1,536 @@
+use super::log_query::load_page;
+use crate::Middleware;
+use async_trait::async_trait;
+use ethers_core::types::{BlockNumber, Filter, Log, H256, U64};
+use serde::{Deserialize, Serialize};
+use std::{
+    convert::Infallible,
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+use thiserror::Error;
+
+/// The last block a [`LogBackfill`] completed, identified by its number and hash
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The block number
+    pub block: U64,
+    /// The block hash, used to detect reorgs when resuming
+    pub hash: H256,
+}
+
+/// Persists the [`Checkpoint`] of a [`LogBackfill`]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait CheckpointStore: Send + Sync + Debug {
+    /// The error returned by the store
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Loads the last saved checkpoint
+    async fn load(&self) -> Result<Option<Checkpoint>, Self::Error>;
+
+    /// Saves the checkpoint, replacing the previous one
+    async fn save(&self, checkpoint: &Checkpoint) -> Result<(), Self::Error>;
+
+    /// Removes the saved checkpoint
+    async fn clear(&self) -> Result<(), Self::Error>;
+}
+
+/// Keeps the checkpoint in memory. Clones share the same checkpoint.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryCheckpointStore {
+    checkpoint: Arc<Mutex<Option<Checkpoint>>>,
+}
+
+impl MemoryCheckpointStore {
+    /// Creates an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the current checkpoint
+    pub fn checkpoint(&self) -> Option<Checkpoint> {
+        *self.checkpoint.lock().unwrap()
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl CheckpointStore for MemoryCheckpointStore {
+    type Error = Infallible;
+
+    async fn load(&self) -> Result<Option<Checkpoint>, Self::Error> {
+        Ok(self.checkpoint())
+    }
+
+    async fn save(&self, checkpoint: &Checkpoint) -> Result<(), Self::Error> {
+        *self.checkpoint.lock().unwrap() = Some(*checkpoint);
+        Ok(())
+    }
+
+    async fn clear(&self) -> Result<(), Self::Error> {
+        *self.checkpoint.lock().unwrap() = None;
+        Ok(())
+    }
+}
+
+/// Stores the checkpoint as JSON in a file.
+///
+/// The file is replaced atomically by writing to a temporary file next to it first.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Debug)]
+pub struct FileCheckpointStore {
+    path: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileCheckpointStore {
+    /// Creates a store which saves the checkpoint at `path`
+    pub fn new(path: impl Into<std::path::PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Returns the path of the checkpoint file
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl CheckpointStore for FileCheckpointStore {
+    type Error = std::io::Error;
+
+    async fn load(&self) -> Result<Option<Checkpoint>, Self::Error> {
+        match tokio::fs::read(&self.path).await {
+            Ok(content) => Ok(Some(serde_json::from_slice(&content)?)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn save(&self, checkpoint: &Checkpoint) -> Result<(), Self::Error> {
+        crate::write_atomic(&self.path, serde_json::to_vec(checkpoint)?).await
+    }
+
+    async fn clear(&self) -> Result<(), Self::Error> {
+        match tokio::fs::remove_file(&self.path).await {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Errors of a [`LogBackfill`]
+#[derive(Debug, Error)]
+pub enum LogBackfillError<M: Middleware, E> {
+    /// Failed to load blocks or logs
+    #[error("{0}")]
+    MiddlewareError(M::Error),
+    /// Failed to load or save the checkpoint
+    #[error("checkpoint store error: {0}")]
+    Store(E),
+    /// The filter's `fromBlock` is not a block number
+    #[error("backfill filters require a numeric `fromBlock`")]
+    InvalidFromBlock,
+    /// A block the backfill depends on could not be found
+    #[error("block {0} not found")]
+    BlockNotFound(U64),
+}
+
+/// A range of logs loaded by a [`LogBackfill`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BackfillBatch {
+    /// The first block of the range
+    pub from_block: U64,
+    /// The last block of the range
+    pub to_block: U64,
+    /// All logs matching the filter in `from_block..=to_block`
+    pub logs: Vec<Log>,
+    /// The checkpoint to save once the logs are processed, see [`LogBackfill::commit`]
+    pub checkpoint: Checkpoint,
+}
+
+/// The result of a [`LogBackfill::next`] step
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BackfillEvent {
+    /// New logs to process
+    Logs(BackfillBatch),
+    /// The saved checkpoint is no longer part of the canonical chain. Data derived from blocks
+    /// after `resume_from` must be discarded, the backfill continues after it, or from the start
+    /// of the filter if `resume_from` is `None`.
+    Rollback {
+        /// The checkpoint that was reorged out
+        invalidated: Checkpoint,
+        /// The checkpoint the backfill was rolled back to
+        resume_from: Option<Checkpoint>,
+    },
+}
+
+/// Resumable backfill of historical logs.
+///
+/// Logs are loaded in batches of blocks and handed out with [`LogBackfill::next`]. Once a batch is
+/// processed, [`LogBackfill::commit`] saves its [`Checkpoint`] to the [`CheckpointStore`], so that
+/// a restarted backfill resumes after the last committed batch. Before each batch, the hash of the
+/// checkpoint block is compared with the canonical chain, if it changed the backfill rolls back
+/// by the configured reorg depth and emits a [`BackfillEvent::Rollback`]. A batch is loaded again
+/// if the hash of its last block changed while its logs were loaded.
+///
+/// # Example
+///
+/// ```no_run
+/// use ethers_core::types::{Address, Filter};
+/// use ethers_providers::{
+///     BackfillEvent, FileCheckpointStore, Http, LogBackfill, Middleware, Provider,
+/// };
+/// # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
+/// let provider = Provider::<Http>::try_from("http://localhost:8545")?;
+/// let filter = Filter::new().address(Address::zero()).from_block(12_000_000u64);
+/// let store = FileCheckpointStore::new("checkpoint.json");
+/// let mut backfill = LogBackfill::new(&provider, &filter, store).with_confirmations(12);
+/// while let Some(event) = backfill.next().await? {
+///     match event {
+///         BackfillEvent::Logs(batch) => {
+///             // process batch.logs
+///             backfill.commit(&batch).await?;
+///         }
+///         BackfillEvent::Rollback { resume_from, .. } => {
+///             // discard data after resume_from
+///         }
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct LogBackfill<'a, M, S> {
+    provider: &'a M,
+    filter: Filter,
+    store: S,
+    batch_size: u64,
+    confirmations: u64,
+    reorg_depth: u64,
+    /// The last block handed out, `None` until the checkpoint was loaded
+    cursor: Option<Option<Checkpoint>>,
+}
+
+impl<'a, M, S> LogBackfill<'a, M, S>
+where
+    M: Middleware,
+    S: CheckpointStore,
+{
+    /// Creates a new backfill of the logs matching `filter`, starting at its `fromBlock` or after
+    /// the checkpoint saved in `store`
+    pub fn new(provider: &'a M, filter: &Filter, store: S) -> Self {
+        Self {
+            provider,
+            filter: filter.clone(),
+            store,
+            batch_size: 10_000,
+            confirmations: 0,
+            reorg_depth: 64,
+            cursor: None,
+        }
+    }
+
+    /// set the number of blocks per [`BackfillBatch`], the range is bisected if the provider
+    /// rejects it
+    pub fn with_batch_size(mut self, batch_size: u64) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// set the number of confirmations a block needs before it is backfilled
+    pub fn with_confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations;
+        self
+    }
+
+    /// set the number of blocks to roll back when the checkpoint was reorged out
+    pub fn with_reorg_depth(mut self, reorg_depth: u64) -> Self {
+        self.reorg_depth = reorg_depth.max(1);
+        self
+    }
+
+    /// Returns the checkpoint store
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Loads the next batch of logs, returns `None` once the backfill caught up with the
+    /// confirmed head of the chain
+    pub async fn next(&mut self) -> Result<Option<BackfillEvent>, LogBackfillError<M, S::Error>> {
+        let cursor = match self.cursor {
+            Some(cursor) => cursor,
+            None => {
+                let checkpoint = self.store.load().await.map_err(LogBackfillError::Store)?;
+                self.cursor = Some(checkpoint);
+                checkpoint
+            }
+        };
+
+        loop {
+            if let Some(checkpoint) = cursor {
+                if self.block_hash(checkpoint.block).await? != checkpoint.hash {
+                    return self.rollback(checkpoint).await.map(Some)
+                }
+            }
+
+            let start = self.start_block()?;
+            let from_block = cursor.map_or(start, |checkpoint| checkpoint.block + 1);
+            let head = self
+                .provider
+                .get_block_number()
+                .await
+                .map_err(LogBackfillError::MiddlewareError)?;
+            let Some(confirmed) = head.checked_sub(self.confirmations.into()) else {
+                return Ok(None)
+            };
+            let mut to_block = (from_block + self.batch_size - 1).min(confirmed);
+            if let Some(end) = self.filter.get_to_block() {
+                to_block = to_block.min(end);
+            }
+            if from_block > to_block {
+                return Ok(None)
+            }
+
+            // the hash is fetched before and after the logs, so that logs of a reorged chain are
+            // never paired with the hash of the new chain
+            let hash = self.block_hash(to_block).await?;
+            let page = load_page(
+                self.provider,
+                self.filter.clone(),
+                from_block.as_u64(),
+                to_block.as_u64(),
+                true,
+            )
+            .await
+            .map_err(LogBackfillError::MiddlewareError)?;
+            if self.block_hash(to_block).await? != hash {
+                tracing::debug!(block = %to_block, "reorg while loading logs, retrying batch");
+                continue
+            }
+
+            let checkpoint = Checkpoint { block: to_block, hash };
+            self.cursor = Some(Some(checkpoint));
+            return Ok(Some(BackfillEvent::Logs(BackfillBatch {
+                from_block,
+                to_block,
+                logs: page.logs,
+                checkpoint,
+            })))
+        }
+    }
+
+    /// Saves the checkpoint of a processed batch
+    pub async fn commit(
+        &mut self,
+        batch: &BackfillBatch,
+    ) -> Result<(), LogBackfillError<M, S::Error>> {
+        self.store.save(&batch.checkpoint).await.map_err(LogBackfillError::Store)
+    }
+
+    fn start_block(&self) -> Result<U64, LogBackfillError<M, S::Error>> {
+        if self.filter.get_block_hash().is_some() {
+            return Err(LogBackfillError::InvalidFromBlock)
+        }
+        match self.filter.block_option.get_from_block() {
+            None | Some(BlockNumber::Earliest) => Ok(U64::zero()),
+            Some(BlockNumber::Number(number)) => Ok(*number),
+            Some(_) => Err(LogBackfillError::InvalidFromBlock),
+        }
+    }
+
+    async fn block_hash(&self, number: U64) -> Result<H256, LogBackfillError<M, S::Error>> {
+        self.provider
+            .get_block(number)
+            .await
+            .map_err(LogBackfillError::MiddlewareError)?
+            .and_then(|block| block.hash)
+            .ok_or(LogBackfillError::BlockNotFound(number))
+    }
+
+    /// Rolls the checkpoint back by `reorg_depth` blocks, or to the start of the filter
+    async fn rollback(
+        &mut self,
+        invalidated: Checkpoint,
+    ) -> Result<BackfillEvent, LogBackfillError<M, S::Error>> {
+        let start = self.start_block()?;
+        let resume_from = match invalidated.block.checked_sub(self.reorg_depth.into()) {
+            Some(block) if block >= start => {
+                Some(Checkpoint { block, hash: self.block_hash(block).await? })
+            }
+            _ => None,
+        };
+        match resume_from {
+            Some(checkpoint) => self.store.save(&checkpoint).await,
+            None => self.store.clear().await,
+        }
+        .map_err(LogBackfillError::Store)?;
+        self.cursor = Some(resume_from);
+        Ok(BackfillEvent::Rollback { invalidated, resume_from })
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::{MockProvider, MockResponse, Provider};
+    use ethers_core::types::Block;
+    use serde_json::{json, Value};
+
+    fn block_number(value: &Value) -> u64 {
+        u64::from_str_radix(value.as_str().unwrap().trim_start_matches("0x"), 16).unwrap()
+    }
+
+    /// A chain of `head + 1` blocks whose hashes depend on `fork` for blocks after `fork_block`
+    fn mock_chain(
+        head: u64,
+        fork: Arc<Mutex<Option<u64>>>,
+    ) -> (Provider<MockProvider>, MockProvider) {
+        let (provider, mock) = Provider::mocked();
+        serve_chain(&mock, head, fork);
+        (provider, mock)
+    }
+
+    fn serve_chain(mock: &MockProvider, head: u64, fork: Arc<Mutex<Option<u64>>>) {
+        mock.on("eth_blockNumber", move |_| MockResponse::Value(json!(U64::from(head))));
+        mock.on("eth_getBlockByNumber", move |params| {
+            let number = block_number(&params[0]);
+            let forked = fork.lock().unwrap().map_or(false, |fork| number > fork);
+            let hash = H256::from_low_u64_be(number + if forked { 1000 } else { 0 });
+            let block = Block::<H256> {
+                number: Some(number.into()),
+                hash: Some(hash),
+                ..Default::default()
+            };
+            MockResponse::Value(serde_json::to_value(block).unwrap())
+        });
+        mock.on("eth_getLogs", |params| {
+            let from = block_number(&params[0]["fromBlock"]);
+            let to = block_number(&params[0]["toBlock"]);
+            let logs: Vec<Log> = (from..=to)
+                .map(|block| Log { block_number: Some(block.into()), ..Default::default() })
+                .collect();
+            MockResponse::Value(serde_json::to_value(logs).unwrap())
+        });
+    }
+
+    fn blocks(event: &BackfillEvent) -> (u64, u64) {
+        match event {
+            BackfillEvent::Logs(batch) => (batch.from_block.as_u64(), batch.to_block.as_u64()),
+            event => panic!("unexpected event {event:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn resumes_from_checkpoint() {
+        let (provider, _mock) = mock_chain(30, Default::default());
+        let filter = Filter::new().from_block(5u64);
+        let store = MemoryCheckpointStore::new();
+
+        let mut backfill = LogBackfill::new(&provider, &filter, store.clone()).with_batch_size(10);
+        let event = backfill.next().await.unwrap().unwrap();
+        assert_eq!(blocks(&event), (5, 14));
+        let BackfillEvent::Logs(batch) = event else { unreachable!() };
+        assert_eq!(batch.logs.len(), 10);
+        backfill.commit(&batch).await.unwrap();
+        assert_eq!(blocks(&backfill.next().await.unwrap().unwrap()), (15, 24));
+
+        // the second batch was not committed, a new backfill starts after block 14 again
+        let mut backfill = LogBackfill::new(&provider, &filter, store.clone())
+            .with_batch_size(10)
+            .with_confirmations(2);
+        assert_eq!(blocks(&backfill.next().await.unwrap().unwrap()), (15, 24));
+        assert_eq!(blocks(&backfill.next().await.unwrap().unwrap()), (25, 28));
+        assert!(backfill.next().await.unwrap().is_none());
+        assert_eq!(store.checkpoint().unwrap().block, 14.into());
+    }
+
+    #[tokio::test]
+    async fn rolls_back_reorged_checkpoint() {
+        let fork = Arc::new(Mutex::new(None));
+        let (provider, _mock) = mock_chain(100, fork.clone());
+        let filter = Filter::new().from_block(0u64);
+        let store = MemoryCheckpointStore::new();
+
+        let mut backfill = LogBackfill::new(&provider, &filter, store.clone())
+            .with_batch_size(50)
+            .with_reorg_depth(10);
+        let BackfillEvent::Logs(batch) = backfill.next().await.unwrap().unwrap() else {
+            panic!("expected logs")
+        };
+        backfill.commit(&batch).await.unwrap();
+        assert_eq!(store.checkpoint().unwrap().hash, H256::from_low_u64_be(49));
+
+        *fork.lock().unwrap() = Some(45);
+        let event = backfill.next().await.unwrap().unwrap();
+        assert_eq!(
+            event,
+            BackfillEvent::Rollback {
+                invalidated: batch.checkpoint,
+                resume_from: Some(Checkpoint { block: 39.into(), hash: H256::from_low_u64_be(39) }),
+            }
+        );
+        assert_eq!(store.checkpoint().unwrap().block, 39.into());
+        assert_eq!(blocks(&backfill.next().await.unwrap().unwrap()), (40, 89));
+
+        // rolling back beyond the start of the filter starts over
+        let filter = Filter::new().from_block(40u64);
+        let store = MemoryCheckpointStore::new();
+        store
+            .save(&Checkpoint { block: 49.into(), hash: H256::from_low_u64_be(49) })
+            .await
+            .unwrap();
+        let mut backfill = LogBackfill::new(&provider, &filter, store.clone()).with_reorg_depth(10);
+        assert!(matches!(
+            backfill.next().await.unwrap().unwrap(),
+            BackfillEvent::Rollback { resume_from: None, .. }
+        ));
+        assert!(store.checkpoint().is_none());
+        assert_eq!(blocks(&backfill.next().await.unwrap().unwrap()), (40, 100));
+    }
+
+    #[tokio::test]
+    async fn reloads_batches_reorged_while_loading() {
+        let fork = Arc::new(Mutex::new(None));
+        let (provider, mock) = Provider::mocked();
+        let requests = Arc::new(Mutex::new(0));
+        let (counter, reorg) = (requests.clone(), fork.clone());
+        mock.on("eth_getLogs", move |_| {
+            *counter.lock().unwrap() += 1;
+            // the chain reorgs while the first batch is loaded
+            reorg.lock().unwrap().get_or_insert(5);
+            MockResponse::Value(json!([]))
+        });
+        serve_chain(&mock, 20, fork);
+
+        let filter = Filter::new().from_block(0u64);
+        let mut backfill = LogBackfill::new(&provider, &filter, MemoryCheckpointStore::new());
+        let BackfillEvent::Logs(batch) = backfill.next().await.unwrap().unwrap() else {
+            panic!("expected logs")
+        };
+        assert_eq!(*requests.lock().unwrap(), 2);
+        assert_eq!(
+            batch.checkpoint,
+            Checkpoint { block: 20.into(), hash: H256::from_low_u64_be(1020) }
+        );
+    }
+
+    #[tokio::test]
+    async fn file_store_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileCheckpointStore::new(dir.path().join("checkpoint.json"));
+        assert_eq!(store.load().await.unwrap(), None);
+
+        let checkpoint = Checkpoint { block: 42.into(), hash: H256::repeat_byte(1) };
+        store.save(&checkpoint).await.unwrap();
+        assert_eq!(store.load().await.unwrap(), Some(checkpoint));
+        assert_eq!(FileCheckpointStore::new(store.path()).load().await.unwrap(), Some(checkpoint));
+
+        store.clear().await.unwrap();
+        assert_eq!(store.load().await.unwrap(), None);
+        store.clear().await.unwrap();
+    }
+}
@@ -1,6 +1,9 @@
-use crate::{utils::PinBoxFut, JsonRpcClient, Middleware, Provider, ProviderError};
+use crate::{
+    utils::PinBoxFut, JsonRpcClient, Middleware, MiddlewareError, Provider, ProviderError,
+};
 use ethers_core::types::{Filter, Log, U64};
 use futures_core::stream::Stream;
+use futures_util::{stream::FuturesOrdered, StreamExt};
 use std::{
     collections::VecDeque,
     pin::Pin,
@@ -8,14 +11,57 @@ use std::{
 };
 use thiserror::Error;
 
+/// Error messages used by providers to reject `eth_getLogs` requests which span too many blocks or
+/// match too many logs
+const RANGE_ERROR_PATTERNS: &[&str] = &[
+    // geth, infura
+    "query returned more than",
+    "query timeout exceeded",
+    // alchemy
+    "log response size exceeded",
+    // bnb chain, nodereal
+    "response size should not greater than",
+    "exceed maximum block range",
+    // ankr
+    "block range is too wide",
+    // op-geth, base
+    "block range greater than",
+    // erigon
+    "query exceeds max results",
+    // quicknode, chainstack
+    "eth_getlogs is limited to a",
+    "eth_getlogs and eth_newfilter are limited to a",
+    // llamarpc, polygon
+    "block range too large",
+];
+
+/// Returns `true` if the error indicates that the requested block range should be narrowed
+fn is_range_error(err: &impl MiddlewareError) -> bool {
+    err.as_error_response().map_or(false, |err| {
+        let message = err.message.to_lowercase();
+        RANGE_ERROR_PATTERNS.iter().any(|pattern| message.contains(pattern))
+    })
+}
+
 /// A log query provides streaming access to historical logs via a paginated
 /// request. For streaming access to future logs, use [`Middleware::watch`] or
 /// [`Middleware::subscribe_logs`]
+///
+/// By default pages are loaded one after another and an error fails the query. With
+/// [`LogQuery::adaptive`] the block range of a page is bisected when the provider rejects it for
+/// returning too many results, and the page size grows again after successful pages. With
+/// [`LogQuery::with_concurrency`] several pages are loaded at once, logs are still yielded in
+/// order.
 pub struct LogQuery<'a, P> {
     provider: &'a Provider<P>,
     filter: Filter,
     from_block: Option<U64>,
     page_size: u64,
+    adaptive: bool,
+    min_page_size: u64,
+    max_page_size: u64,
+    concurrency: usize,
+    pages: FuturesOrdered<PinBoxFut<'a, Page>>,
     current_logs: VecDeque<Log>,
     last_block: Option<U64>,
     state: LogQueryState<'a>,
@@ -26,6 +72,51 @@ enum LogQueryState<'a> {
     LoadLastBlock(PinBoxFut<'a, U64>),
     LoadLogs(PinBoxFut<'a, Vec<Log>>),
     Consume,
+    LoadPages,
+    Done,
+}
+
+/// The logs of a block range loaded by [`load_page`]
+pub(super) struct Page {
+    pub(super) logs: Vec<Log>,
+    /// The smallest span of blocks that had to be requested, if the range was split
+    split_span: Option<u64>,
+}
+
+/// Loads the logs of all blocks in `from..=to`.
+///
+/// If `adaptive` is set, ranges which are rejected by the provider are bisected until they succeed
+/// or only a single block is left.
+pub(super) async fn load_page<M: Middleware>(
+    provider: &M,
+    filter: Filter,
+    from: u64,
+    to: u64,
+    adaptive: bool,
+) -> Result<Page, M::Error> {
+    let mut ranges = VecDeque::from([(from, to)]);
+    let mut page = Page { logs: Vec::new(), split_span: None };
+    while let Some((from, to)) = ranges.pop_front() {
+        let filter = filter.clone().from_block(from).to_block(to);
+        match provider.get_logs(&filter).await {
+            Ok(logs) => {
+                page.logs.extend(logs);
+                if page.split_span.is_some() {
+                    let span = to - from + 1;
+                    page.split_span = page.split_span.map(|min| min.min(span));
+                }
+            }
+            Err(err) if adaptive && from < to && is_range_error(&err) => {
+                let mid = from + (to - from) / 2;
+                ranges.push_front((mid + 1, to));
+                ranges.push_front((from, mid));
+                page.split_span =
+                    Some(page.split_span.map_or(mid - from + 1, |min| min.min(mid - from + 1)));
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(page)
 }
 
 impl<'a, P> LogQuery<'a, P>
@@ -39,6 +130,11 @@ where
             filter: filter.clone(),
             from_block: filter.get_from_block(),
             page_size: 10000,
+            adaptive: false,
+            min_page_size: 1,
+            max_page_size: 10000,
+            concurrency: 1,
+            pages: FuturesOrdered::new(),
             current_logs: VecDeque::new(),
             last_block: None,
             state: LogQueryState::Initial,
@@ -48,8 +144,75 @@ where
     /// set page size for pagination
     pub fn with_page_size(mut self, page_size: u64) -> Self {
         self.page_size = page_size;
+        self.max_page_size = self.max_page_size.max(page_size);
+        self
+    }
+
+    /// Bisect the block range of a page if the provider rejects it, e.g. with a "query returned
+    /// more than 10000 results" error, and double the page size again after successful pages
+    pub fn adaptive(mut self) -> Self {
+        self.adaptive = true;
         self
     }
+
+    /// set the smallest page size an adaptive query shrinks to after a page had to be split
+    pub fn with_min_page_size(mut self, min_page_size: u64) -> Self {
+        self.min_page_size = min_page_size.max(1);
+        self
+    }
+
+    /// set the largest page size an adaptive query grows to
+    pub fn with_max_page_size(mut self, max_page_size: u64) -> Self {
+        self.max_page_size = max_page_size.max(1);
+        self
+    }
+
+    /// set the number of pages which are loaded concurrently
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Whether pages are loaded by the adaptive, concurrent loader instead of one after another
+    fn uses_pages(&self) -> bool {
+        self.adaptive || self.concurrency > 1
+    }
+
+    /// Returns the last block of the page starting at `from_block`
+    fn page_end(&self, from_block: u64) -> u64 {
+        from_block.saturating_add(self.page_size.max(1) - 1)
+    }
+
+    /// Queues new pages until `concurrency` pages are in flight or the last block is reached
+    fn queue_pages(&mut self) {
+        // can safely assume these will always be set in this state
+        let last_block = self.last_block.unwrap().as_u64();
+        let mut from_block = self.from_block.unwrap().as_u64();
+        while self.pages.len() < self.concurrency && from_block <= last_block {
+            let to_block = self.page_end(from_block).min(last_block);
+            let fut = Box::pin(load_page(
+                self.provider,
+                self.filter.clone(),
+                from_block,
+                to_block,
+                self.adaptive,
+            ));
+            self.pages.push_back(fut);
+            from_block = to_block + 1;
+        }
+        self.from_block = Some(from_block.into());
+    }
+
+    /// Adjusts the page size after a page was loaded
+    fn adapt_page_size(&mut self, page: &Page) {
+        if !self.adaptive {
+            return
+        }
+        self.page_size = match page.split_span {
+            Some(span) => span.max(self.min_page_size),
+            None => self.page_size.saturating_mul(2).min(self.max_page_size),
+        };
+    }
 }
 
 macro_rules! rewake_with_new_state {
@@ -98,10 +261,15 @@ where
                     Ok(last_block) => {
                         self.last_block = Some(last_block);
 
+                        if self.uses_pages() {
+                            self.from_block = self.filter.get_from_block();
+                            rewake_with_new_state!(ctx, self, LogQueryState::LoadPages);
+                        }
+
                         // this is okay because we will only enter this state when the filter is
                         // paginatable i.e. from block is set
                         let from_block = self.filter.get_from_block().unwrap();
-                        let to_block = from_block + self.page_size;
+                        let to_block = U64::from(self.page_end(from_block.as_u64()));
                         self.from_block = Some(to_block + 1);
 
                         let filter = self.filter.clone().from_block(from_block).to_block(to_block);
@@ -131,7 +299,7 @@ where
                         // load new logs if there are still more pages to go through
                         // can safely assume this will always be set in this state
                         let from_block = self.from_block.unwrap();
-                        let to_block = from_block + self.page_size;
+                        let to_block = U64::from(self.page_end(from_block.as_u64()));
 
                         // no more pages to load, and everything is consumed
                         // can safely assume this will always be set in this state
@@ -151,6 +319,220 @@ where
                     Poll::Ready(log.map(Ok))
                 }
             }
+            LogQueryState::LoadPages => {
+                if let Some(log) = self.current_logs.pop_front() {
+                    return Poll::Ready(Some(Ok(log)))
+                }
+                self.queue_pages();
+                match futures_util::ready!(self.pages.poll_next_unpin(ctx)) {
+                    Some(Ok(page)) => {
+                        self.adapt_page_size(&page);
+                        self.current_logs = VecDeque::from(page.logs);
+                        ctx.waker().wake_by_ref();
+                        Poll::Pending
+                    }
+                    Some(Err(err)) => {
+                        self.pages = FuturesOrdered::new();
+                        self.state = LogQueryState::Done;
+                        Poll::Ready(Some(Err(LogQueryError::LoadLogsError(err))))
+                    }
+                    None => {
+                        self.state = LogQueryState::Done;
+                        Poll::Ready(None)
+                    }
+                }
+            }
+            LogQueryState::Done => Poll::Ready(None),
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::{JsonRpcError, MockResponse};
+    use ethers_core::types::{BlockNumber, H256};
+    use futures_util::TryStreamExt;
+    use serde_json::Value;
+    use std::sync::{Arc, Mutex};
+
+    fn block_range(params: &Value) -> (u64, u64) {
+        let filter = &params[0];
+        let parse = |key: &str| {
+            u64::from_str_radix(filter[key].as_str().unwrap().trim_start_matches("0x"), 16).unwrap()
+        };
+        (parse("fromBlock"), parse("toBlock"))
+    }
+
+    fn log(block: u64) -> Log {
+        Log {
+            block_number: Some(block.into()),
+            block_hash: Some(H256::from_low_u64_be(block)),
+            ..Default::default()
+        }
+    }
+
+    fn too_many_results() -> MockResponse {
+        MockResponse::Error(JsonRpcError {
+            code: -32005,
+            message: "query returned more than 10000 results".to_string(),
+            data: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn bisects_rejected_ranges() {
+        let (provider, mock) = Provider::mocked();
+        mock.push(U64::from(99)).unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        mock.on("eth_getLogs", move |params| {
+            let (from, to) = block_range(params);
+            seen.lock().unwrap().push((from, to));
+            // at most 10 blocks per request
+            if to - from >= 10 {
+                return too_many_results()
+            }
+            MockResponse::Value(
+                serde_json::to_value((from..=to).map(log).collect::<Vec<_>>()).unwrap(),
+            )
+        });
+
+        let filter = Filter::new().from_block(BlockNumber::Number(0.into()));
+        let logs: Vec<Log> = LogQuery::new(&provider, &filter)
+            .with_page_size(64)
+            .adaptive()
+            .try_collect()
+            .await
+            .unwrap();
+
+        let blocks: Vec<u64> = logs.iter().map(|log| log.block_number.unwrap().as_u64()).collect();
+        assert_eq!(blocks, (0..100).collect::<Vec<_>>());
+
+        let requests = requests.lock().unwrap();
+        // the first page is split until it succeeds, following pages start with the smaller size
+        assert_eq!(requests[..4], [(0, 63), (0, 31), (0, 15), (0, 7)]);
+        assert!(requests
+            .iter()
+            .skip_while(|(from, _)| *from < 64)
+            .all(|(from, to)| to - from < 16));
+    }
+
+    #[tokio::test]
+    async fn grows_page_size_after_success() {
+        let (provider, mock) = Provider::mocked();
+        mock.push(U64::from(69)).unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        mock.on("eth_getLogs", move |params| {
+            seen.lock().unwrap().push(block_range(params));
+            MockResponse::Value(serde_json::json!([]))
+        });
+
+        let filter = Filter::new().from_block(BlockNumber::Number(0.into()));
+        let logs: Vec<Log> = LogQuery::new(&provider, &filter)
+            .with_page_size(10)
+            .with_max_page_size(40)
+            .adaptive()
+            .try_collect()
+            .await
+            .unwrap();
+        assert!(logs.is_empty());
+
+        assert_eq!(*requests.lock().unwrap(), vec![(0, 9), (10, 29), (30, 69)]);
+    }
+
+    #[tokio::test]
+    async fn yields_concurrent_pages_in_order() {
+        let (provider, mock) = Provider::mocked();
+        mock.push(U64::from(49)).unwrap();
+        mock.on("eth_getLogs", move |params| {
+            let (from, to) = block_range(params);
+            MockResponse::Value(
+                serde_json::to_value((from..=to).map(log).collect::<Vec<_>>()).unwrap(),
+            )
+        });
+
+        let filter = Filter::new().from_block(BlockNumber::Number(0.into()));
+        let logs: Vec<Log> = LogQuery::new(&provider, &filter)
+            .with_page_size(5)
+            .with_concurrency(4)
+            .try_collect()
+            .await
+            .unwrap();
+
+        let blocks: Vec<u64> = logs.iter().map(|log| log.block_number.unwrap().as_u64()).collect();
+        assert_eq!(blocks, (0..50).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn pages_span_page_size_blocks() {
+        for adaptive in [false, true] {
+            let (provider, mock) = Provider::mocked();
+            mock.push(U64::from(24)).unwrap();
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let seen = requests.clone();
+            mock.on("eth_getLogs", move |params| {
+                seen.lock().unwrap().push(block_range(params));
+                MockResponse::Value(serde_json::json!([]))
+            });
+
+            let filter = Filter::new().from_block(BlockNumber::Number(0.into()));
+            let mut query = LogQuery::new(&provider, &filter).with_page_size(10);
+            if adaptive {
+                query = query.adaptive().with_max_page_size(10);
+            }
+            let logs: Vec<Log> = query.try_collect().await.unwrap();
+            assert!(logs.is_empty());
+
+            // only the adaptive loader stops at the last block
+            let last = if adaptive { (20, 24) } else { (20, 29) };
+            assert_eq!(*requests.lock().unwrap(), vec![(0, 9), (10, 19), last]);
+        }
+    }
+
+    #[tokio::test]
+    async fn fails_on_other_errors() {
+        for message in ["header not found", "invalid block range params"] {
+            let (provider, mock) = Provider::mocked();
+            mock.push(U64::from(49)).unwrap();
+            mock.on("eth_getLogs", move |_| {
+                MockResponse::Error(JsonRpcError {
+                    code: -32000,
+                    message: message.to_string(),
+                    data: None,
+                })
+            });
+
+            let filter = Filter::new().from_block(BlockNumber::Number(0.into()));
+            let mut query = LogQuery::new(&provider, &filter).adaptive();
+            assert!(matches!(query.next().await, Some(Err(LogQueryError::LoadLogsError(_)))));
+            assert!(query.next().await.is_none());
+            assert_eq!(mock.unmatched_count(), 0);
         }
     }
+
+    #[tokio::test]
+    async fn fails_on_rate_limits() {
+        let (provider, mock) = Provider::mocked();
+        mock.push(U64::from(49)).unwrap();
+        let requests = Arc::new(Mutex::new(0));
+        let counter = requests.clone();
+        mock.on("eth_getLogs", move |_| {
+            *counter.lock().unwrap() += 1;
+            MockResponse::Error(JsonRpcError {
+                code: -32005,
+                message: "daily request count limit exceeded".to_string(),
+                data: None,
+            })
+        });
+
+        let filter = Filter::new().from_block(BlockNumber::Number(0.into()));
+        let mut query = LogQuery::new(&provider, &filter).adaptive();
+        assert!(matches!(query.next().await, Some(Err(LogQueryError::LoadLogsError(_)))));
+        assert!(query.next().await.is_none());
+        // the range is not bisected
+        assert_eq!(*requests.lock().unwrap(), 1);
+    }
 }
@@ -1,5 +1,7 @@
 mod pending_transaction;
-pub use pending_transaction::PendingTransaction;
+pub use pending_transaction::{
+    ConfirmationPolicy, DropReason, PendingTransaction, ReorgBehavior, REPLACEMENT_SEARCH_DEPTH,
+};
 
 mod pending_escalator;
 pub use pending_escalator::EscalatingPending;
@@ -7,5 +9,13 @@ pub use pending_escalator::EscalatingPending;
 mod log_query;
 pub use log_query::{LogQuery, LogQueryError};
 
+mod log_backfill;
+#[cfg(not(target_arch = "wasm32"))]
+pub use log_backfill::FileCheckpointStore;
+pub use log_backfill::{
+    BackfillBatch, BackfillEvent, Checkpoint, CheckpointStore, LogBackfill, LogBackfillError,
+    MemoryCheckpointStore,
+};
+
 pub mod call_raw;
 pub use call_raw::*;
@@ -1,11 +1,13 @@
 use crate::{
     utils::{interval, PinBoxFut},
-    JsonRpcClient, Middleware, Provider, ProviderError,
+    JsonRpcClient, Middleware, Provider, ProviderError, RpcError,
+};
+use ethers_core::types::{
+    Address, BlockNumber, Transaction, TransactionReceipt, TxHash, U256, U64,
 };
-use ethers_core::types::{Transaction, TransactionReceipt, TxHash, U64};
 use futures_core::stream::Stream;
 use futures_timer::Delay;
-use futures_util::stream::StreamExt;
+use futures_util::{stream::StreamExt, try_join};
 use instant::Duration;
 use pin_project::pin_project;
 use std::{
@@ -13,15 +15,190 @@ use std::{
     future::Future,
     ops::Deref,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
 };
 
+/// The condition a mined transaction has to meet for a [`PendingTransaction`] to resolve.
+///
+/// # Example
+///
+/// ```ignore
+/// use ethers_providers::ConfirmationPolicy;
+///
+/// // only credit the deposit once it can no longer be reorged
+/// let receipt = client
+///     .send_transaction(tx, None)
+///     .await?
+///     .confirmation_policy(ConfirmationPolicy::Finalized)
+///     .await?;
+/// ```
+#[derive(Clone)]
+pub enum ConfirmationPolicy {
+    /// The block of the transaction and `n - 1` blocks on top of it have been mined
+    Confirmations(usize),
+    /// The block of the transaction is at or below the `safe` head. Falls back to the latest block
+    /// on chains which do not support the `safe` tag.
+    Safe,
+    /// The block of the transaction is at or below the `finalized` head. Falls back to the latest
+    /// block on chains which do not support the `finalized` tag.
+    Finalized,
+    /// A predicate of the receipt and the current block number
+    Custom(Arc<dyn Fn(&TransactionReceipt, U64) -> bool + Send + Sync>),
+}
+
+impl ConfirmationPolicy {
+    /// Creates a policy from a predicate of the receipt and the current block number
+    pub fn custom<F>(predicate: F) -> Self
+    where
+        F: Fn(&TransactionReceipt, U64) -> bool + Send + Sync + 'static,
+    {
+        ConfirmationPolicy::Custom(Arc::new(predicate))
+    }
+
+    /// Returns `true` if the receipt alone satisfies the policy
+    fn is_immediate(&self) -> bool {
+        matches!(self, ConfirmationPolicy::Confirmations(confs) if *confs <= 1)
+    }
+
+    /// The head the block of the transaction is compared against
+    fn head(&self) -> BlockNumber {
+        match self {
+            ConfirmationPolicy::Safe => BlockNumber::Safe,
+            ConfirmationPolicy::Finalized => BlockNumber::Finalized,
+            _ => BlockNumber::Latest,
+        }
+    }
+
+    /// Returns `true` if the receipt is confirmed given the number of the [`Self::head`] block
+    fn is_confirmed(&self, receipt: &TransactionReceipt, head: U64) -> bool {
+        let Some(inclusion_block) = receipt.block_number else { return false };
+        match self {
+            // the transaction already has 1 confirmation when it's mined
+            ConfirmationPolicy::Confirmations(confs) => head + 1 >= inclusion_block + *confs as u64,
+            ConfirmationPolicy::Safe | ConfirmationPolicy::Finalized => head >= inclusion_block,
+            ConfirmationPolicy::Custom(predicate) => predicate(receipt, head),
+        }
+    }
+}
+
+impl Default for ConfirmationPolicy {
+    fn default() -> Self {
+        ConfirmationPolicy::Confirmations(1)
+    }
+}
+
+impl fmt::Debug for ConfirmationPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfirmationPolicy::Confirmations(confs) => {
+                f.debug_tuple("Confirmations").field(confs).finish()
+            }
+            ConfirmationPolicy::Safe => f.write_str("Safe"),
+            ConfirmationPolicy::Finalized => f.write_str("Finalized"),
+            ConfirmationPolicy::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+impl From<usize> for ConfirmationPolicy {
+    fn from(confs: usize) -> Self {
+        ConfirmationPolicy::Confirmations(confs)
+    }
+}
+
+/// What a [`PendingTransaction`] does when the receipt of its transaction disappears through a
+/// reorg while waiting for confirmations
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReorgBehavior {
+    /// Wait for the transaction to be mined again
+    #[default]
+    Rewait,
+    /// Resolve to [`ProviderError::TransactionReorged`]
+    Error,
+}
+
+/// The number of blocks which are searched for the transaction replacing a dropped transaction
+pub const REPLACEMENT_SEARCH_DEPTH: u64 = 64;
+
+/// The reason a pending transaction was removed from the mempool without being mined, see
+/// [`PendingTransaction::report_drop_reason`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DropReason {
+    /// Another transaction of the sender with the same nonce was mined, i.e. the transaction was
+    /// sped up or cancelled. The replacement is `None` if it was not found within the last
+    /// [`REPLACEMENT_SEARCH_DEPTH`] blocks.
+    Replaced(Option<Box<Transaction>>),
+    /// The max fee of the transaction is below the current base fee
+    Underpriced {
+        /// The max fee per gas of the transaction
+        max_fee: U256,
+        /// The base fee per gas of the latest block
+        base_fee: U256,
+    },
+    /// The transaction was dropped for an unknown reason, e.g. evicted from a full mempool
+    Dropped,
+}
+
+impl DropReason {
+    /// Returns the hash of the replacement transaction, if known
+    pub fn replacement_hash(&self) -> Option<TxHash> {
+        match self {
+            DropReason::Replaced(Some(tx)) => Some(tx.hash),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the transaction was replaced by one which sends nothing to the sender
+    /// itself, the common way of cancelling a transaction
+    pub fn is_cancellation(&self) -> bool {
+        match self {
+            DropReason::Replaced(Some(tx)) => tx.to == Some(tx.from) && tx.value.is_zero(),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for DropReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DropReason::Replaced(Some(tx)) => write!(f, "replaced by {:?}", tx.hash),
+            DropReason::Replaced(None) => f.write_str("replaced"),
+            DropReason::Underpriced { max_fee, base_fee } => {
+                write!(f, "underpriced, max fee {max_fee} is below base fee {base_fee}")
+            }
+            DropReason::Dropped => f.write_str("dropped"),
+        }
+    }
+}
+
+/// The fields of a pending transaction needed to tell why it was dropped
+#[derive(Clone, Copy, Debug)]
+struct SeenTx {
+    from: Address,
+    nonce: U256,
+    max_fee: Option<U256>,
+}
+
+impl From<&Transaction> for SeenTx {
+    fn from(tx: &Transaction) -> Self {
+        Self { from: tx.from, nonce: tx.nonce, max_fee: tx.max_fee_per_gas.or(tx.gas_price) }
+    }
+}
+
 /// A pending transaction is a transaction which has been submitted but is not yet mined.
 /// `await`'ing on a pending transaction will resolve to a transaction receipt
 /// once the transaction has enough `confirmations`. The default number of confirmations
-/// is 1, but may be adjusted with the `confirmations` method. If the transaction does not
+/// is 1, but may be adjusted with the `confirmations` method, or replaced by waiting for the
+/// `safe` or `finalized` head with the `confirmation_policy` method. If the transaction does not
 /// have enough confirmations or is not mined, the future will stay in the pending state.
 ///
+/// While waiting for confirmations the receipt is re-fetched, so that a transaction which is
+/// removed from the chain by a reorg is detected, see [`ReorgBehavior`].
+///
+/// If the transaction disappears from the mempool, the future resolves to `None`, or, with
+/// [`PendingTransaction::report_drop_reason`], to an error which tells why, see [`DropReason`].
+///
 /// # Example
 ///
 /// ```ignore
@@ -37,11 +214,14 @@ use std::{
 #[pin_project]
 pub struct PendingTransaction<'a, P> {
     tx_hash: TxHash,
-    confirmations: usize,
+    policy: ConfirmationPolicy,
+    on_reorg: ReorgBehavior,
     provider: &'a Provider<P>,
     state: PendingTxState<'a>,
     interval: Box<dyn Stream<Item = ()> + Send + Unpin>,
     retries_remaining: usize,
+    report_drop_reason: bool,
+    seen: Option<SeenTx>,
 }
 
 const DEFAULT_RETRIES: usize = 3;
@@ -53,11 +233,14 @@ impl<'a, P: JsonRpcClient> PendingTransaction<'a, P> {
 
         Self {
             tx_hash,
-            confirmations: 1,
+            policy: ConfirmationPolicy::default(),
+            on_reorg: ReorgBehavior::default(),
             provider,
             state: PendingTxState::InitialDelay(delay),
             interval: Box::new(interval(provider.get_interval())),
             retries_remaining: DEFAULT_RETRIES,
+            report_drop_reason: false,
+            seen: None,
         }
     }
 
@@ -78,7 +261,23 @@ impl<'a, P: JsonRpcClient> PendingTransaction<'a, P> {
     /// to a receipt
     #[must_use]
     pub fn confirmations(mut self, confs: usize) -> Self {
-        self.confirmations = confs;
+        self.policy = ConfirmationPolicy::Confirmations(confs);
+        self
+    }
+
+    /// Sets the condition the mined transaction has to meet for the pending transaction to
+    /// resolve to a receipt
+    #[must_use]
+    pub fn confirmation_policy(mut self, policy: ConfirmationPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets what happens when the receipt disappears through a reorg while waiting for
+    /// confirmations
+    #[must_use]
+    pub fn on_reorg(mut self, behavior: ReorgBehavior) -> Self {
+        self.on_reorg = behavior;
         self
     }
 
@@ -102,6 +301,15 @@ impl<'a, P: JsonRpcClient> PendingTransaction<'a, P> {
         self.retries_remaining = retries;
         self
     }
+
+    /// Resolves to [`ProviderError::TransactionDropped`] instead of `None` if the transaction
+    /// disappears from the mempool, telling whether it was replaced by another transaction with
+    /// the same nonce, is underpriced or was dropped
+    #[must_use]
+    pub fn report_drop_reason(mut self) -> Self {
+        self.report_drop_reason = true;
+        self
+    }
 }
 
 impl<'a, P> PendingTransaction<'a, P> {
@@ -182,6 +390,14 @@ impl<'a, P: JsonRpcClient> Future for PendingTransaction<'a, P> {
                 if tx_opt.is_none() {
                     if *this.retries_remaining == 0 {
                         tracing::debug!("Dropped from mempool, pending tx {:?}", *this.tx_hash);
+                        if *this.report_drop_reason {
+                            let fut = drop_reason_fut(this.provider, *this.tx_hash, *this.seen);
+                            rewake_with_new_state!(
+                                ctx,
+                                this,
+                                PendingTxState::GettingDropReason(fut)
+                            );
+                        }
                         *this.state = PendingTxState::Completed;
                         return Poll::Ready(Ok(None))
                     }
@@ -192,6 +408,7 @@ impl<'a, P: JsonRpcClient> Future for PendingTransaction<'a, P> {
 
                 // If it hasn't confirmed yet, poll again later
                 let tx = tx_opt.unwrap();
+                *this.seen = Some(SeenTx::from(&tx));
                 rewake_with_new_state_if!(
                     tx.block_number.is_none(),
                     ctx,
@@ -229,13 +446,13 @@ impl<'a, P: JsonRpcClient> Future for PendingTransaction<'a, P> {
                     PendingTxState::PausedGettingReceipt
                 );
 
-                // If we requested more than 1 confirmation, we need to compare the receipt's
-                // block number and the current block
-                if *this.confirmations > 1 {
+                // Unless the receipt is enough, we need to compare the receipt's block number
+                // and the head of the policy
+                if !this.policy.is_immediate() {
                     tracing::debug!("Waiting on confirmations for pending tx {:?}", *this.tx_hash);
 
-                    let fut = Box::pin(this.provider.get_block_number());
-                    *this.state = PendingTxState::GettingBlockNumber(fut, receipt.take());
+                    let fut = confirmations_fut(this.provider, *this.tx_hash, this.policy.head());
+                    *this.state = PendingTxState::GettingConfirmations(fut, receipt.take());
 
                     // Schedule the waker to poll again
                     ctx.waker().wake_by_ref();
@@ -245,41 +462,63 @@ impl<'a, P: JsonRpcClient> Future for PendingTransaction<'a, P> {
                     return Poll::Ready(Ok(receipt))
                 }
             }
-            PendingTxState::PausedGettingBlockNumber(receipt) => {
+            PendingTxState::PausedGettingConfirmations(receipt) => {
                 // Wait the polling period so that we do not spam the chain when no
                 // new block has been mined
                 let _ready = futures_util::ready!(this.interval.poll_next_unpin(ctx));
 
-                // we need to re-instantiate the get_block_number future so that
-                // we poll again
-                let fut = Box::pin(this.provider.get_block_number());
-                *this.state = PendingTxState::GettingBlockNumber(fut, receipt.take());
+                // we need to re-instantiate the future so that we poll again
+                let fut = confirmations_fut(this.provider, *this.tx_hash, this.policy.head());
+                *this.state = PendingTxState::GettingConfirmations(fut, receipt.take());
                 ctx.waker().wake_by_ref();
             }
-            PendingTxState::GettingBlockNumber(fut, receipt) => {
-                let current_block = futures_util::ready!(fut.as_mut().poll(ctx))?;
+            PendingTxState::GettingConfirmations(fut, receipt) => {
+                let (latest_receipt, head) = futures_util::ready!(fut.as_mut().poll(ctx))?;
 
-                // This is safe so long as we only enter the `GettingBlock`
+                // This is safe so long as we only enter the `GettingConfirmations`
                 // loop from `CheckingReceipt`, which contains an explicit
                 // `is_none` check
-                let receipt = receipt.take().expect("GettingBlockNumber without receipt");
-
-                // Wait for the interval
-                let inclusion_block = receipt
-                    .block_number
-                    .expect("Receipt did not have a block number. This should never happen");
-                // if the transaction has at least K confirmations, return the receipt
-                // (subtract 1 since the tx already has 1 conf when it's mined)
-                if current_block > inclusion_block + *this.confirmations - 1 {
-                    let receipt = Some(receipt);
+                let receipt = receipt.take().expect("GettingConfirmations without receipt");
+
+                // the block of the transaction is no longer part of the chain
+                let Some(latest_receipt) = latest_receipt else {
+                    tracing::debug!("Receipt disappeared, pending tx {:?}", *this.tx_hash);
+                    if *this.on_reorg == ReorgBehavior::Error {
+                        *this.state = PendingTxState::Completed;
+                        return Poll::Ready(Err(ProviderError::TransactionReorged(*this.tx_hash)))
+                    }
+                    rewake_with_new_state!(ctx, this, PendingTxState::PausedGettingTx);
+                };
+                if latest_receipt.block_hash != receipt.block_hash {
+                    tracing::debug!(
+                        "Pending tx {:?} was reorged into block {:?}",
+                        *this.tx_hash,
+                        latest_receipt.block_hash
+                    );
+                }
+
+                if this.policy.is_confirmed(&latest_receipt, head) {
                     *this.state = PendingTxState::Completed;
-                    return Poll::Ready(Ok(receipt))
+                    return Poll::Ready(Ok(Some(latest_receipt)))
                 } else {
-                    tracing::trace!(tx_hash = ?this.tx_hash, "confirmations {}/{}", current_block - inclusion_block + 1, this.confirmations);
-                    *this.state = PendingTxState::PausedGettingBlockNumber(Some(receipt));
+                    tracing::trace!(tx_hash = ?this.tx_hash, ?head, policy = ?this.policy, "waiting on confirmations");
+                    *this.state = PendingTxState::PausedGettingConfirmations(Some(latest_receipt));
                     ctx.waker().wake_by_ref();
                 }
             }
+            PendingTxState::GettingDropReason(fut) => {
+                let Some(reason) = futures_util::ready!(fut.as_mut().poll(ctx))? else {
+                    tracing::debug!("Pending tx {:?} was mined, getting receipt", *this.tx_hash);
+                    let fut = Box::pin(this.provider.get_transaction_receipt(*this.tx_hash));
+                    rewake_with_new_state!(ctx, this, PendingTxState::GettingReceipt(fut));
+                };
+                tracing::debug!("Pending tx {:?} was {}", *this.tx_hash, reason);
+                *this.state = PendingTxState::Completed;
+                return Poll::Ready(Err(ProviderError::TransactionDropped {
+                    tx_hash: *this.tx_hash,
+                    reason,
+                }))
+            }
             PendingTxState::Completed => {
                 panic!("polled pending transaction future after completion")
             }
@@ -293,7 +532,8 @@ impl<'a, P> fmt::Debug for PendingTransaction<'a, P> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("PendingTransaction")
             .field("tx_hash", &self.tx_hash)
-            .field("confirmations", &self.confirmations)
+            .field("policy", &self.policy)
+            .field("on_reorg", &self.on_reorg)
             .field("state", &self.state)
             .finish()
     }
@@ -321,6 +561,84 @@ impl<'a, P> Deref for PendingTransaction<'a, P> {
     }
 }
 
+/// Fetches the receipt of the transaction along with the number of the given head block
+fn confirmations_fut<'a, P: JsonRpcClient>(
+    provider: &'a Provider<P>,
+    tx_hash: TxHash,
+    head: BlockNumber,
+) -> PinBoxFut<'a, (Option<TransactionReceipt>, U64)> {
+    Box::pin(async move {
+        let receipt = provider.get_transaction_receipt(tx_hash);
+        let head = async {
+            if head == BlockNumber::Latest {
+                return provider.get_block_number().await
+            }
+            // there is no safe or finalized block on chains which are not proof of stake, nodes
+            // either return no block or reject the tag
+            let number = match provider.get_block(head).await {
+                Ok(block) => block.and_then(|block| block.number),
+                Err(err) if err.as_error_response().is_some() => None,
+                Err(err) => return Err(err),
+            };
+            match number {
+                Some(number) => Ok(number),
+                None => provider.get_block_number().await,
+            }
+        };
+        try_join!(receipt, head)
+    })
+}
+
+/// The number of blocks fetched at once while searching for a replacement
+const REPLACEMENT_SEARCH_CONCURRENCY: usize = 8;
+
+/// Tells why the transaction disappeared from the mempool, `None` if it was mined after all
+fn drop_reason_fut<P: JsonRpcClient>(
+    provider: &Provider<P>,
+    tx_hash: TxHash,
+    seen: Option<SeenTx>,
+) -> PinBoxFut<'_, Option<DropReason>> {
+    Box::pin(async move {
+        // without the sender and nonce there is nothing to go by
+        let Some(seen) = seen else { return Ok(Some(DropReason::Dropped)) };
+
+        let nonce = provider.get_transaction_count(seen.from, None).await?;
+        if nonce > seen.nonce {
+            // a transaction with the same nonce was mined, search for it in the latest blocks
+            let latest = provider.get_block_number().await?;
+            let mut blocks = futures_util::stream::iter(
+                (0..=latest.as_u64()).rev().take(REPLACEMENT_SEARCH_DEPTH as usize),
+            )
+            .map(|number| provider.get_block_with_txs(number))
+            .buffered(REPLACEMENT_SEARCH_CONCURRENCY);
+            while let Some(block) = blocks.next().await {
+                let Some(block) = block? else { continue };
+                if let Some(tx) = block
+                    .transactions
+                    .into_iter()
+                    .find(|tx| tx.from == seen.from && tx.nonce == seen.nonce)
+                {
+                    // the transaction itself was mined after it was last looked up
+                    if tx.hash == tx_hash {
+                        return Ok(None)
+                    }
+                    return Ok(Some(DropReason::Replaced(Some(Box::new(tx)))))
+                }
+            }
+            return Ok(Some(DropReason::Replaced(None)))
+        }
+
+        let base_fee =
+            provider.get_block(BlockNumber::Latest).await?.and_then(|block| block.base_fee_per_gas);
+        match (seen.max_fee, base_fee) {
+            (Some(max_fee), Some(base_fee)) if max_fee < base_fee => {
+                Ok(Some(DropReason::Underpriced { max_fee, base_fee }))
+            }
+            _ => Ok(Some(DropReason::Dropped)),
+        }
+    })
+}
+
 // We box the TransactionReceipts to keep the enum small.
 enum PendingTxState<'a> {
     /// Initial delay to ensure the GettingTx loop doesn't immediately fail
@@ -344,10 +662,16 @@ enum PendingTxState<'a> {
     CheckingReceipt(Option<TransactionReceipt>),
 
     /// Waiting for interval to elapse before calling API again
-    PausedGettingBlockNumber(Option<TransactionReceipt>),
+    PausedGettingConfirmations(Option<TransactionReceipt>),
+
+    /// Polling the blockchain for the receipt and the head block number of the policy
+    GettingConfirmations(
+        PinBoxFut<'a, (Option<TransactionReceipt>, U64)>,
+        Option<TransactionReceipt>,
+    ),
 
-    /// Polling the blockchain for the current block number
-    GettingBlockNumber(PinBoxFut<'a, U64>, Option<TransactionReceipt>),
+    /// Looking up why the transaction was dropped
+    GettingDropReason(PinBoxFut<'a, Option<DropReason>>),
 
     /// Future has completed and should panic if polled again
     Completed,
@@ -361,12 +685,250 @@ impl<'a> fmt::Debug for PendingTxState<'a> {
             PendingTxState::GettingTx(_) => "GettingTx",
             PendingTxState::PausedGettingReceipt => "PausedGettingReceipt",
             PendingTxState::GettingReceipt(_) => "GettingReceipt",
-            PendingTxState::GettingBlockNumber(_, _) => "GettingBlockNumber",
-            PendingTxState::PausedGettingBlockNumber(_) => "PausedGettingBlockNumber",
+            PendingTxState::GettingConfirmations(_, _) => "GettingConfirmations",
+            PendingTxState::PausedGettingConfirmations(_) => "PausedGettingConfirmations",
             PendingTxState::CheckingReceipt(_) => "CheckingReceipt",
+            PendingTxState::GettingDropReason(_) => "GettingDropReason",
             PendingTxState::Completed => "Completed",
         };
 
         f.debug_struct("PendingTxState").field("state", &state).finish()
     }
 }
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::{JsonRpcError, MockResponse};
+    use ethers_core::types::{Block, H256};
+
+    fn mined(block_number: u64) -> (Transaction, TransactionReceipt) {
+        let tx = Transaction { block_number: Some(block_number.into()), ..Default::default() };
+        let receipt = TransactionReceipt {
+            block_number: Some(block_number.into()),
+            block_hash: Some(H256::repeat_byte(block_number as u8)),
+            ..Default::default()
+        };
+        (tx, receipt)
+    }
+
+    #[tokio::test]
+    async fn waits_until_finalized() {
+        let (provider, mock) = Provider::mocked();
+        let (tx, receipt) = mined(5);
+        mock.on("eth_getTransactionByHash", move |_| {
+            MockResponse::Value(serde_json::to_value(&tx).unwrap())
+        });
+        let res = receipt.clone();
+        mock.on("eth_getTransactionReceipt", move |_| {
+            MockResponse::Value(serde_json::to_value(&res).unwrap())
+        });
+        let mut finalized = 2u64;
+        mock.on("eth_getBlockByNumber", move |_| {
+            finalized += 2;
+            let block = Block::<TxHash> { number: Some(finalized.into()), ..Default::default() };
+            MockResponse::Value(serde_json::to_value(block).unwrap())
+        });
+
+        let confirmed = PendingTransaction::new(H256::zero(), &provider)
+            .interval(Duration::from_millis(1))
+            .confirmation_policy(ConfirmationPolicy::Finalized)
+            .await
+            .unwrap();
+        assert_eq!(confirmed, Some(receipt));
+        mock.assert_any_request("eth_getBlockByNumber", (BlockNumber::Finalized, false)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn finalized_falls_back_to_latest() {
+        let (provider, mock) = Provider::mocked();
+        let (tx, receipt) = mined(5);
+        mock.on("eth_getTransactionByHash", move |_| {
+            MockResponse::Value(serde_json::to_value(&tx).unwrap())
+        });
+        let res = receipt.clone();
+        mock.on("eth_getTransactionReceipt", move |_| {
+            MockResponse::Value(serde_json::to_value(&res).unwrap())
+        });
+        // the chain does not know the `finalized` tag
+        mock.on("eth_getBlockByNumber", |_| {
+            MockResponse::Error(JsonRpcError {
+                code: -32000,
+                message: "finalized block not found".to_string(),
+                data: None,
+            })
+        });
+        mock.on("eth_blockNumber", |_| MockResponse::Value(serde_json::json!(U64::from(6))));
+
+        let confirmed = PendingTransaction::new(H256::zero(), &provider)
+            .interval(Duration::from_millis(1))
+            .confirmation_policy(ConfirmationPolicy::Finalized)
+            .await
+            .unwrap();
+        assert_eq!(confirmed, Some(receipt));
+    }
+
+    #[tokio::test]
+    async fn custom_policy() {
+        let (provider, mock) = Provider::mocked();
+        let (tx, receipt) = mined(5);
+        mock.on("eth_getTransactionByHash", move |_| {
+            MockResponse::Value(serde_json::to_value(&tx).unwrap())
+        });
+        mock.on("eth_getTransactionReceipt", move |_| {
+            MockResponse::Value(serde_json::to_value(&receipt).unwrap())
+        });
+        let mut head = 5u64;
+        mock.on("eth_blockNumber", move |_| {
+            head += 1;
+            MockResponse::Value(serde_json::to_value(U64::from(head)).unwrap())
+        });
+
+        let confirmed = PendingTransaction::new(H256::zero(), &provider)
+            .interval(Duration::from_millis(1))
+            .confirmation_policy(ConfirmationPolicy::custom(|receipt, head| {
+                head >= receipt.block_number.unwrap() + 3
+            }))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(confirmed.block_number, Some(5.into()));
+    }
+
+    #[tokio::test]
+    async fn detects_reorged_receipt() {
+        let (provider, mock) = Provider::mocked();
+        let (tx, receipt) = mined(5);
+        mock.on("eth_getTransactionByHash", move |_| {
+            MockResponse::Value(serde_json::to_value(&tx).unwrap())
+        });
+        let mut calls = 0;
+        mock.on("eth_getTransactionReceipt", move |_| {
+            calls += 1;
+            // the receipt disappears after it was first seen
+            let receipt = (calls == 1).then(|| receipt.clone());
+            MockResponse::Value(serde_json::to_value(receipt).unwrap())
+        });
+        mock.on("eth_blockNumber", |_| {
+            MockResponse::Value(serde_json::to_value(U64::from(5)).unwrap())
+        });
+
+        let err = PendingTransaction::new(H256::zero(), &provider)
+            .interval(Duration::from_millis(1))
+            .confirmations(3)
+            .on_reorg(ReorgBehavior::Error)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProviderError::TransactionReorged(hash) if hash == H256::zero()));
+    }
+
+    /// A sender whose transaction was seen pending once and then disappeared
+    fn vanishing_tx(mock: &crate::MockProvider) -> Transaction {
+        let tx = Transaction {
+            hash: H256::zero(),
+            from: Address::repeat_byte(1),
+            nonce: 3.into(),
+            max_fee_per_gas: Some(10.into()),
+            ..Default::default()
+        };
+        let mut seen = Some(tx.clone());
+        mock.on("eth_getTransactionByHash", move |_| {
+            MockResponse::Value(serde_json::to_value(seen.take()).unwrap())
+        });
+        tx
+    }
+
+    #[tokio::test]
+    async fn reports_replacement() {
+        let (provider, mock) = Provider::mocked();
+        let tx = vanishing_tx(&mock);
+        mock.on("eth_getTransactionCount", |_| MockResponse::Value(serde_json::json!("0x4")));
+        mock.on("eth_blockNumber", |_| MockResponse::Value(serde_json::json!("0xa")));
+        let replacement =
+            Transaction { hash: H256::repeat_byte(2), to: Some(tx.from), ..tx.clone() };
+        let included = replacement.clone();
+        mock.on("eth_getBlockByNumber", move |params| {
+            let number = params[0].as_str().unwrap();
+            let transactions = if number == "0x9" { vec![included.clone()] } else { vec![] };
+            let block = Block { transactions, ..Default::default() };
+            MockResponse::Value(serde_json::to_value(block).unwrap())
+        });
+
+        let err = PendingTransaction::new(tx.hash, &provider)
+            .interval(Duration::from_millis(1))
+            .retries(0)
+            .report_drop_reason()
+            .await
+            .unwrap_err();
+        let ProviderError::TransactionDropped { tx_hash, reason } = err else { panic!("{err}") };
+        assert_eq!(tx_hash, tx.hash);
+        assert_eq!(reason.replacement_hash(), Some(replacement.hash));
+        assert!(reason.is_cancellation());
+        mock.assert_any_request("eth_getBlockByNumber", ("0xa", true)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn resolves_transactions_mined_while_looking_for_a_replacement() {
+        let (provider, mock) = Provider::mocked();
+        let tx = vanishing_tx(&mock);
+        mock.on("eth_getTransactionCount", |_| MockResponse::Value(serde_json::json!("0x4")));
+        mock.on("eth_blockNumber", |_| MockResponse::Value(serde_json::json!("0xa")));
+        let mined = Transaction { block_number: Some(9.into()), ..tx.clone() };
+        mock.on("eth_getBlockByNumber", move |params| {
+            let number = params[0].as_str().unwrap();
+            let transactions = if number == "0x9" { vec![mined.clone()] } else { vec![] };
+            let block = Block { transactions, ..Default::default() };
+            MockResponse::Value(serde_json::to_value(block).unwrap())
+        });
+        let receipt = TransactionReceipt {
+            transaction_hash: tx.hash,
+            block_number: Some(9.into()),
+            ..Default::default()
+        };
+        let res = receipt.clone();
+        mock.on("eth_getTransactionReceipt", move |_| {
+            MockResponse::Value(serde_json::to_value(&res).unwrap())
+        });
+
+        let confirmed = PendingTransaction::new(tx.hash, &provider)
+            .interval(Duration::from_millis(1))
+            .retries(0)
+            .report_drop_reason()
+            .await
+            .unwrap();
+        assert_eq!(confirmed, Some(receipt));
+    }
+
+    #[tokio::test]
+    async fn reports_underpriced() {
+        let (provider, mock) = Provider::mocked();
+        let tx = vanishing_tx(&mock);
+        mock.on("eth_getTransactionCount", |_| MockResponse::Value(serde_json::json!("0x3")));
+        mock.on("eth_getBlockByNumber", |_| {
+            let block = Block::<TxHash> { base_fee_per_gas: Some(20.into()), ..Default::default() };
+            MockResponse::Value(serde_json::to_value(block).unwrap())
+        });
+
+        let err = PendingTransaction::new(tx.hash, &provider)
+            .interval(Duration::from_millis(1))
+            .retries(0)
+            .report_drop_reason()
+            .await
+            .unwrap_err();
+        let ProviderError::TransactionDropped { reason, .. } = err else { panic!("{err}") };
+        assert_eq!(reason, DropReason::Underpriced { max_fee: 10.into(), base_fee: 20.into() });
+    }
+
+    #[tokio::test]
+    async fn dropped_resolves_to_none_by_default() {
+        let (provider, mock) = Provider::mocked();
+        let tx = vanishing_tx(&mock);
+        let res = PendingTransaction::new(tx.hash, &provider)
+            .interval(Duration::from_millis(1))
+            .retries(0)
+            .await
+            .unwrap();
+        assert_eq!(res, None);
+    }
+}
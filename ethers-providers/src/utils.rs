@@ -14,6 +14,19 @@ pub(crate) type PinBoxFut<'a, T> = Pin<Box<dyn Future<Output = Result<T, Provide
 pub(crate) type PinBoxFut<'a, T> =
     Pin<Box<dyn Future<Output = Result<T, ProviderError>> + Send + 'a>>;
 
+/// Replaces the file at `path` with `contents` atomically, by writing to a temporary file next to
+/// it first and renaming that over it
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn write_atomic(
+    path: impl AsRef<std::path::Path>,
+    contents: impl AsRef<[u8]>,
+) -> std::io::Result<()> {
+    let path = path.as_ref();
+    let tmp = path.with_extension("tmp");
+    tokio::fs::write(&tmp, contents).await?;
+    tokio::fs::rename(tmp, path).await
+}
+
 /// Calls the future if `item` is None, otherwise returns a `futures::ok`
 pub async fn maybe<F, T, E>(item: Option<T>, f: F) -> Result<T, E>
 where
@@ -1,11 +1,15 @@
+use async_trait::async_trait;
 use ethers::{
-    core::types::Chain,
-    etherscan::Client,
+    core::types::U256,
     middleware::gas_oracle::{
-        BlockNative, Etherscan, GasCategory, GasNow, GasOracle, Polygon, ProviderOracle,
+        BlockMedian, Cache, FeeHistoryOracle, FeedEstimate, FeedOracle, GasCategory, GasFeed,
+        GasOracle, ProviderOracle, Result,
     },
     providers::{Http, Provider},
 };
+use std::time::Duration;
+
+const RPC_URL: &str = "https://eth.llamarpc.com";
 
 /// In Ethereum, the "gas" of a transaction refers to the amount of computation required to execute
 /// the transaction on the blockchain. Gas is typically measured in units of "gas," and the cost of
@@ -20,66 +24,65 @@ use ethers::{
 /// behavior of the library when it comes to determining the gas cost of transactions.
 #[tokio::main]
 async fn main() {
-    blocknative().await;
-    etherscan().await;
-    gas_now().await;
-    polygon().await;
-    provider_oracle().await;
-    //etherchain().await; // FIXME: Etherchain URL is broken (Http 404)
+    let provider = Provider::<Http>::try_from(RPC_URL).unwrap();
+
+    fee_history(provider.clone()).await;
+    block_median(provider.clone()).await;
+    provider_oracle(provider.clone()).await;
+    feed(provider).await;
 }
 
-async fn blocknative() {
-    let api_key: Option<String> = std::env::var("BLOCK_NATIVE_API_KEY").ok();
-    let oracle = BlockNative::new(api_key).category(GasCategory::Fastest);
-    match oracle.fetch().await {
-        Ok(gas_price) => println!("[Blocknative]: Gas price is {gas_price:?}"),
-        Err(e) => panic!("[Blocknative]: Cannot estimate gas: {e:?}"),
+async fn fee_history(provider: Provider<Http>) {
+    let oracle = FeeHistoryOracle::new(provider).category(GasCategory::Fast);
+    match oracle.estimate_eip1559_fees().await {
+        Ok((max_fee, priority_fee)) => {
+            println!("[Fee history]: Max fee is {max_fee:?}, priority fee is {priority_fee:?}")
+        }
+        Err(e) => panic!("[Fee history]: Cannot estimate gas: {e:?}"),
     }
 }
 
-async fn etherscan() {
-    let client = Client::new_from_opt_env(Chain::Mainnet).unwrap();
-    let oracle = Etherscan::new(client).category(GasCategory::Fast);
+async fn block_median(provider: Provider<Http>) {
+    let oracle = BlockMedian::new(provider).block_count(5);
     match oracle.fetch().await {
-        Ok(gas_price) => println!("[Etherscan]: Gas price is {gas_price:?}"),
-        Err(e) => panic!("[Etherscan]: Cannot estimate gas: {e:?}"),
+        Ok(gas_price) => println!("[Block median]: Gas price is {gas_price:?}"),
+        Err(e) => panic!("[Block median]: Cannot estimate gas: {e:?}"),
     }
 }
 
-async fn gas_now() {
-    let oracle = GasNow::new().category(GasCategory::Fast);
+async fn provider_oracle(provider: Provider<Http>) {
+    // cache the node's estimates for a block
+    let oracle = Cache::new(Duration::from_secs(12), ProviderOracle::new(provider));
     match oracle.fetch().await {
-        Ok(gas_price) => println!("[GasNow]: Gas price is {gas_price:?}"),
-        Err(e) => panic!("[GasNow]: Cannot estimate gas: {e:?}"),
+        Ok(gas_price) => println!("[Provider oracle]: Gas price is {gas_price:?}"),
+        Err(e) => panic!("[Provider oracle]: Cannot estimate gas: {e:?}"),
     }
 }
 
-async fn polygon() {
-    let chain = Chain::Polygon;
-    if let Ok(oracle) = Polygon::new(chain) {
-        match oracle.category(GasCategory::SafeLow).fetch().await {
-            Ok(gas_price) => println!("[Polygon]: Gas price is {gas_price:?}"),
-            Err(e) => panic!("[Polygon]: Cannot estimate gas: {e:?}"),
-        }
-    }
-}
+/// An external fee service, e.g. shared by all the services of a team
+#[derive(Debug)]
+struct FeeService;
 
-async fn provider_oracle() {
-    const RPC_URL: &str = "https://eth.llamarpc.com";
-    let provider = Provider::<Http>::try_from(RPC_URL).unwrap();
-    let oracle = ProviderOracle::new(provider);
-    match oracle.fetch().await {
-        Ok(gas_price) => println!("[Provider oracle]: Gas price is {gas_price:?}"),
-        Err(e) => panic!("[Provider oracle]: Cannot estimate gas: {e:?}"),
+#[async_trait]
+impl GasFeed for FeeService {
+    async fn estimate(&self, _category: GasCategory) -> Result<FeedEstimate> {
+        // query the service here
+        let gwei = U256::exp10(9);
+        Ok(FeedEstimate {
+            gas_price: Some(gwei * 30),
+            max_fee_per_gas: Some(gwei * 60),
+            max_priority_fee_per_gas: Some(gwei),
+        })
     }
 }
 
-/*
-// FIXME: Etherchain URL is broken (Http 404)
-async fn etherchain() {
-    let oracle = Etherchain::new().category(GasCategory::Standard);
+async fn feed(provider: Provider<Http>) {
+    let oracle = FeedOracle::new(FeeService)
+        .category(GasCategory::Fast)
+        .max_fee_per_gas(U256::exp10(9) * 200)
+        .fallback(FeeHistoryOracle::new(provider));
     match oracle.fetch().await {
-        Ok(gas_price) => println!("[Etherchain]: Gas price is {gas_price:?}"),
-        Err(e) => panic!("[Etherchain]: Cannot estimate gas: {e:?}"),
+        Ok(gas_price) => println!("[Feed]: Gas price is {gas_price:?}"),
+        Err(e) => panic!("[Feed]: Cannot estimate gas: {e:?}"),
     }
-}*/
+}